@@ -0,0 +1,98 @@
+use std::fmt::Write;
+
+use scut_core::{
+    audit::{audit, Issue},
+    interface::{index::Query, RemoteStorage, UserInteraction},
+    ledger::overwrites,
+    signature::verify_signatures,
+    Config,
+};
+use tracing::instrument;
+
+/// Walk the whole history of the game in remote storage and print a table of any holes or irregularities
+///
+/// Saves that were uploaded by a substitute, saves that were overwritten, and saves with a bad signature, are listed afterwards
+#[instrument(skip_all, ret, err)]
+pub fn run(
+    config: Config,
+    mut remote: Box<dyn RemoteStorage>,
+    mut ui: Box<dyn UserInteraction>,
+) -> anyhow::Result<()> {
+    let mut audit = audit(remote.index(), &config.roster)?;
+
+    // with a shared folder, the enemy team's player saves are in their private folder
+    if config.shared_folder.is_some() {
+        audit.issues.retain(|issue| {
+            !matches!(issue, Issue::MissingPlayerSave { turn, .. } if turn.side != config.side)
+        });
+    }
+
+    let substitutions = remote.substitutions()?;
+    if !substitutions.is_empty() {
+        let mut list = String::from("Uploaded by substitutes:\n");
+        for substitution in substitutions.iter() {
+            writeln!(list, "  {substitution}")?;
+        }
+        ui.message(&list);
+    }
+
+    let ledger = remote.ledger()?;
+    let overwritten = overwrites(&ledger);
+    if !overwritten.is_empty() {
+        let mut list = String::from("Overwritten saves:\n");
+        for (save, uploads) in overwritten.iter() {
+            writeln!(list, "  {save}")?;
+            for upload in uploads {
+                writeln!(
+                    list,
+                    "    uploaded by {} at {} ({} bytes)",
+                    upload.uploader, upload.timestamp, upload.size
+                )?;
+            }
+        }
+        ui.message(&list);
+    }
+
+    let saves = remote.index().search(&Query::new())?;
+    let problems = verify_signatures(&mut *remote, &saves, &config.roster)?;
+    if !problems.is_empty() {
+        let mut list = String::from("Signature problems:\n");
+        for problem in problems.iter() {
+            writeln!(list, "  ⚠️ {problem}")?;
+        }
+        ui.message(&list);
+    }
+
+    if audit.issues.is_empty() {
+        ui.message(&format!(
+            "No holes or irregularities found up to {} ✔️",
+            audit.current_turn
+        ));
+        ui.wait_for_user_before_close("");
+        return Ok(());
+    }
+
+    let mut table = String::new();
+    writeln!(table, "Audited saves up to {}\n", audit.current_turn)?;
+    writeln!(table, "{:<12} {:<22} Save", "Turn", "Issue")?;
+    for issue in audit.issues.iter() {
+        writeln!(
+            table,
+            "{:<12} {:<22} {}",
+            issue.turn().to_string(),
+            issue.kind(),
+            issue.save()
+        )?;
+    }
+
+    let holes = audit.issues.iter().filter(|issue| issue.is_hole()).count();
+    writeln!(
+        table,
+        "\nFound {holes} holes and {} irregularities",
+        audit.issues.len() - holes
+    )?;
+
+    ui.message(&table);
+    ui.wait_for_user_before_close("");
+    Ok(())
+}
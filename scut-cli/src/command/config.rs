@@ -59,7 +59,7 @@ pub fn run(
                 "Config is located at {}",
                 config_service.location()?
             ));
-            ui.message(&config_service.serialize(&config)?);
+            ui.message(&config_service.serialize(&config.redacted())?);
         }
         ConfigSubcommand::Get { key } => {
             debug!(subcommand = "get");
@@ -67,7 +67,8 @@ pub fn run(
             ui.message(&format!("{value}"));
         }
         ConfigSubcommand::Set { key, value } => {
-            debug!(subcommand = "set", %key, %value);
+            // the value is left out, it may be a password
+            debug!(subcommand = "set", %key);
             let value = normalise(value);
             let setting = Setting::new(key, value)
                 .with_context(|| format!("Invalid value for config.{key}"))?;
@@ -0,0 +1,104 @@
+use std::path::Path;
+
+use scut_core::{
+    interface::{
+        file_system::local_file_system::LocalFileSystem, FileSystem, LocalStorage, Predict,
+        RemoteStorage, UserInteraction,
+    },
+    snapshot::{parse_snapshots, Snapshot},
+    Config,
+};
+use tracing::instrument;
+
+/// Capture the saves in local and remote storage, and what scut predicts for them, in the format used by scut's tests.
+///
+/// The snapshot is written to `output` if given, otherwise it is printed.
+#[instrument(skip_all, ret, err)]
+pub fn snapshot(
+    config: Config,
+    output: Option<&Path>,
+    mut local: Box<dyn LocalStorage>,
+    mut remote: Box<dyn RemoteStorage>,
+    predictor: Box<dyn Predict>,
+    mut ui: Box<dyn UserInteraction>,
+) -> anyhow::Result<()> {
+    let player = config.acting_player()?;
+    let playing_solo = config.solo.unwrap_or_default();
+
+    let prediction = predictor.predict(
+        config.side,
+        player,
+        None,
+        playing_solo,
+        &mut *local,
+        &mut *remote,
+    )?;
+
+    let snapshot = Snapshot::capture(
+        config.side,
+        player,
+        playing_solo,
+        config.observer.unwrap_or_default(),
+        &*local,
+        &*remote,
+        prediction,
+    )?;
+
+    match output {
+        Some(path) => {
+            LocalFileSystem::new().write_string_to_file(&snapshot.to_string(), path)?;
+            ui.wait_for_user_before_close(&format!(
+                "Wrote a snapshot to {} ✔️ You can attach it to an issue at <https://github.com/drmason13/scut/issues/new>",
+                path.display()
+            ));
+        }
+        None => {
+            ui.message(&snapshot.to_string());
+            ui.wait_for_user_before_close("");
+        }
+    }
+
+    Ok(())
+}
+
+/// Predict again from each snapshot in `file`, and show any that are now predicted differently
+#[instrument(skip_all, ret, err)]
+pub fn replay(
+    file: &Path,
+    predictor: impl Fn(&Snapshot) -> Box<dyn Predict>,
+    mut ui: Box<dyn UserInteraction>,
+) -> anyhow::Result<()> {
+    let content = LocalFileSystem::new().read_file_to_string(file)?;
+    let snapshots = parse_snapshots(&content)?;
+
+    let mut changed = 0;
+    for (idx, snapshot) in snapshots.iter().enumerate() {
+        let prediction = snapshot.replay(&*predictor(snapshot))?;
+
+        if prediction == snapshot.prediction {
+            ui.message(&format!("Snapshot {}: prediction is unchanged ✔️", idx + 1));
+            continue;
+        }
+
+        changed += 1;
+        let replayed = Snapshot {
+            side: snapshot.side,
+            player: snapshot.player.clone(),
+            playing_solo: snapshot.playing_solo,
+            observer: snapshot.observer,
+            local: snapshot.local.clone(),
+            remote: snapshot.remote.clone(),
+            prediction,
+        };
+        ui.message(&format!(
+            "Snapshot {}: prediction has changed ⚠️\n\nCaptured:\n{snapshot}\nNow:\n{replayed}",
+            idx + 1
+        ));
+    }
+
+    ui.wait_for_user_before_close(&format!(
+        "Replayed {} snapshots, {changed} predicted differently",
+        snapshots.len()
+    ));
+    Ok(())
+}
@@ -0,0 +1,61 @@
+use std::fmt::Write;
+
+use scut_core::{
+    interface::{storage::mirrored_remote::MirroredRemote, UserInteraction},
+    Config,
+};
+use tracing::instrument;
+
+/// List the saves that each mirror is missing, and copy them over if `resync` is set, after confirming with the user
+#[instrument(skip_all, ret, err)]
+pub fn run(
+    config: Config,
+    resync: bool,
+    mut mirrored: MirroredRemote,
+    skipped: Vec<String>,
+    mut ui: Box<dyn UserInteraction>,
+) -> anyhow::Result<()> {
+    if config.mirrors.is_empty() {
+        ui.wait_for_user_before_close(
+            "You haven't configured any mirrors, use `scut config edit` to add some",
+        );
+        return Ok(());
+    }
+
+    for name in skipped.iter() {
+        ui.message(&format!(
+            "⚠️ Couldn't connect to {name}, see the log for why"
+        ));
+    }
+
+    let divergence = mirrored.divergence()?;
+    if divergence.is_empty() {
+        ui.wait_for_user_before_close("Every mirror has every save 🪞");
+        return Ok(());
+    }
+
+    let mut list = String::from("Missing saves:\n");
+    for lagging in divergence.iter() {
+        writeln!(list, "  ⚠️ {lagging}")?;
+    }
+    if !resync {
+        writeln!(
+            list,
+            "\nUse `scut mirrors --resync` to copy them from the mirrors that have them"
+        )?;
+        ui.message(&list);
+        ui.wait_for_user_before_close("");
+        return Ok(());
+    }
+
+    ui.message(&list);
+    if !ui.confirm("Copy them from the mirrors that have them?", Some(true)) {
+        ui.wait_for_user_before_close("User cancelled. Stopping.");
+        return Ok(());
+    }
+
+    mirrored.resync(&divergence)?;
+
+    ui.wait_for_user_before_close("Done ✔️");
+    Ok(())
+}
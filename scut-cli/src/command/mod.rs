@@ -1,13 +1,27 @@
 use std::fmt::Write;
 
+pub mod audit;
 pub mod config;
+pub mod debug;
+pub mod mirrors;
+pub mod prune;
+pub mod public_key;
+pub mod rollback;
+pub mod versions;
 
 use scut_core::{
     download_predicted_saves,
     interface::{
-        predict::{AutosavePrediction, AutosavePredictionReason, Predict},
+        predict::{
+            handoff::Handoff, warnings::PredictionWarning, AutosavePrediction,
+            AutosavePredictionReason, Predict,
+        },
         LocalStorage, RemoteStorage, UserInteraction,
     },
+    ledger::{find_uploads, record_uploads},
+    record_synced_saves,
+    signature::{sign_uploads, verify_signatures, SigningKey},
+    substitute::record_substitute_uploads,
     upload_predicted_autosave, upload_predicted_saves, Config,
 };
 
@@ -19,16 +33,78 @@ pub fn run(
     mut local: Box<dyn LocalStorage>,
     mut remote: Box<dyn RemoteStorage>,
     predictor: Box<dyn Predict>,
+    signing_key: &SigningKey,
     mut ui: Box<dyn UserInteraction>,
 ) -> anyhow::Result<()> {
     let local = &mut *local;
     let remote = &mut *remote;
 
     let side = config.side;
-    let player = config.player.as_str();
+    let player = config.acting_player()?;
+    let substitute = config.substitute();
+    if let Some(substitute) = substitute {
+        ui.message(&format!("{substitute} is playing as {player}"));
+    }
 
     let playing_solo = config.solo.unwrap_or_default();
-    let prediction = predictor.predict(side, player, turn_override, playing_solo, local, remote)?;
+    let mut prediction =
+        predictor.predict(side, player, turn_override, playing_solo, local, remote)?;
+
+    match &prediction.handoff {
+        Some(Handoff::YourPart { load, save_as }) => ui.message(&format!(
+            "🤝 It's your part of the turn: load {load} and save your part as {save_as}"
+        )),
+        Some(Handoff::WaitingOn { save }) => {
+            ui.message(&format!("🤝 Waiting for {save} to be uploaded"))
+        }
+        Some(Handoff::Complete) | None => {}
+    }
+
+    let mut stale_autosave = false;
+    for warning in prediction.warnings.iter() {
+        match warning {
+            PredictionWarning::RemoteDiffers(save) => {
+                if ui.confirm(
+                    &format!(
+                        "⚠️ {save} has changed in remote storage since you last synced it. \
+                        Do you want to download it again?"
+                    ),
+                    Some(true),
+                ) {
+                    prediction.downloads.push(save.clone());
+                }
+            }
+            PredictionWarning::LocalDiffers(save) => {
+                if ui.confirm(
+                    &format!(
+                        "⚠️ Your copy of {save} has changed since you last synced it. \
+                        Do you want to upload it again, overwriting the remote copy? ⚠️"
+                    ),
+                    Some(false),
+                ) {
+                    prediction.uploads.push(save.clone());
+                }
+            }
+            PredictionWarning::AutosaveOlderThanLastUpload(save) => {
+                stale_autosave = true;
+                ui.message(&format!(
+                    "⚠️ Your autosave hasn't changed since you uploaded {save}. \
+                    It might be left over from an earlier turn."
+                ));
+            }
+        }
+    }
+
+    // signatures are checked before downloading, so that a forged save is never extracted over your own
+    let signature_problems = verify_signatures(remote, &prediction.downloads, &config.roster)?;
+    for problem in signature_problems.iter() {
+        if !ui.confirm(
+            &format!("⚠️ {problem}. Do you want to download it anyway? ⚠️"),
+            Some(false),
+        ) {
+            prediction.downloads.retain(|save| save != problem.save());
+        }
+    }
 
     let mut confirmation_prompt = String::new();
 
@@ -55,6 +131,9 @@ pub fn run(
         }
     }
 
+    let mut uploaded = prediction.uploads.clone();
+    let downloaded = prediction.downloads.clone();
+    let mut uploaded_autosave = None;
     let mut uploads_handle = None;
     let mut downloads_handle = None;
 
@@ -85,7 +164,7 @@ pub fn run(
         AutosavePrediction::Ready(autosave) => {
             if ui.confirm(
                 &format!("Do you want to upload your autosave as: {autosave}?",),
-                Some(true),
+                Some(!stale_autosave),
             ) {
                 Some(autosave)
             } else {
@@ -96,7 +175,8 @@ pub fn run(
             AutosavePredictionReason::AutosaveAlreadyUploaded => {
                 if ui.confirm(
                     &format!(
-                        "⚠️ {autosave} has already been uploaded. \
+                        "⚠️ {autosave} has already been uploaded, \
+                        it will be kept as a version, see `scut versions \"{autosave}\"`. \
                         Do you want to overwrite it with your autosave? ⚠️",
                     ),
                     Some(false),
@@ -123,11 +203,33 @@ pub fn run(
                     None
                 }
             }
+            AutosavePredictionReason::StaleAutosave(save) => {
+                let stale = if save.player.is_some() {
+                    format!("was written before you saved {save}")
+                } else {
+                    format!("is the same one you already uploaded as {save}")
+                };
+                if ui.confirm(
+                    &format!(
+                        "⚠️ Your autosave {stale}, so it might be left over from an earlier turn. \
+                        Do you want to upload it as {autosave} anyway? ⚠️",
+                    ),
+                    Some(false),
+                ) {
+                    Some(autosave)
+                } else {
+                    None
+                }
+            }
             AutosavePredictionReason::AutosaveNotAvailable => None,
+            AutosavePredictionReason::Observing => None,
+            AutosavePredictionReason::HandoffNotFinished(_) => None,
         },
     } {
         ui.message(&format!("Uploading autosave as '{autosave}' 🚀"));
-        upload_predicted_autosave(&mut *local, &mut *remote, autosave)?;
+        upload_predicted_autosave(&mut *local, &mut *remote, autosave.clone())?;
+        uploaded.push(autosave.clone());
+        uploaded_autosave = Some(autosave);
     } else if confirmation_prompt.is_empty() {
         ui.message("Your local saves folder is synced with remote.");
         ui.wait_for_user_before_close("Nothing to do 💤");
@@ -158,6 +260,37 @@ pub fn run(
         }
     }
 
+    record_synced_saves(
+        local,
+        remote,
+        &uploaded,
+        &downloaded,
+        uploaded_autosave.as_ref(),
+    )?;
+
+    if let Some(substitute) = substitute {
+        record_substitute_uploads(remote, &uploaded, substitute)?;
+    }
+
+    // uploads are signed and recorded in the ledger by whoever actually uploaded them, even when playing as someone else
+    let uploads = find_uploads(remote, &uploaded)?;
+    record_uploads(remote, &uploads, &config.player)?;
+    sign_uploads(remote, &uploads, &config.player, signing_key)?;
+    remote.publish()?;
+
+    for mirror in remote.lagging_mirrors() {
+        ui.message(&format!(
+            "⚠️ {mirror} missed the upload, use `scut mirrors --resync` to catch it up"
+        ));
+    }
+
+    // and again afterwards, in case a save changed while it was downloading
+    for problem in verify_signatures(remote, &downloaded, &config.roster)? {
+        if !signature_problems.contains(&problem) {
+            ui.message(&format!("⚠️ {problem}"));
+        }
+    }
+
     ui.wait_for_user_before_close("Done ✔️");
     Ok(())
 }
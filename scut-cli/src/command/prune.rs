@@ -0,0 +1,67 @@
+use std::fmt::Write;
+
+use scut_core::{
+    interface::{RemoteStorage, UserInteraction},
+    prune::plan_prune,
+    Config,
+};
+use tracing::instrument;
+
+/// Bundle the saves that fall outside the retention policy into archives, after confirming with the user, or only list them if `dry_run` is set
+#[instrument(skip_all, ret, err)]
+pub fn run(
+    config: Config,
+    dry_run: bool,
+    mut remote: Box<dyn RemoteStorage>,
+    mut ui: Box<dyn UserInteraction>,
+) -> anyhow::Result<()> {
+    let Some(retention) = &config.retention else {
+        ui.wait_for_user_before_close(
+            "You haven't configured a retention policy, use `scut config edit` to add one",
+        );
+        return Ok(());
+    };
+
+    let archives = remote.archives()?;
+    let prune = plan_prune(retention, &mut *remote)?;
+
+    let mut list = String::new();
+    if !archives.is_empty() {
+        writeln!(list, "Already archived:")?;
+        for archive in archives.iter() {
+            writeln!(list, "  🗃️ {}: {} saves", archive.name, archive.saves.len())?;
+        }
+    }
+
+    if prune.bundles.is_empty() {
+        ui.message(&list);
+        ui.wait_for_user_before_close("Nothing to prune 💤");
+        return Ok(());
+    }
+
+    writeln!(list, "Will archive:")?;
+    for bundle in prune.bundles.iter() {
+        writeln!(list, "  🗜️ {bundle}")?;
+    }
+
+    if dry_run {
+        writeln!(list, "\nUse `scut prune` without --dry-run to archive them")?;
+        ui.message(&list);
+        ui.wait_for_user_before_close("");
+        return Ok(());
+    }
+
+    ui.message(&list);
+    if !ui.confirm(
+        "Archived saves can still be downloaded, but only by scut. Is that OK?",
+        Some(true),
+    ) {
+        ui.wait_for_user_before_close("User cancelled. Stopping.");
+        return Ok(());
+    }
+
+    prune.execute(&mut *remote)?;
+
+    ui.wait_for_user_before_close("Done ✔️ Archived saves were moved into the `archive` folder.");
+    Ok(())
+}
@@ -0,0 +1,24 @@
+use scut_core::{
+    interface::UserInteraction,
+    signature::{public_key, SigningKey},
+    Config,
+};
+use tracing::instrument;
+
+/// Print your public key, in the form your teammates need to add it to the roster in their config
+#[instrument(skip_all, ret, err)]
+pub fn run(
+    config: Config,
+    signing_key: &SigningKey,
+    mut ui: Box<dyn UserInteraction>,
+) -> anyhow::Result<()> {
+    ui.message(&format!(
+        "Your public key is listed in the roster like this:\n\n\
+        [roster.keys]\n\
+        {} = \"{}\"",
+        config.player,
+        public_key(signing_key)
+    ));
+    ui.wait_for_user_before_close("");
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use std::fmt::Write;
+
+use scut_core::{
+    interface::{LocalStorage, RemoteStorage, UserInteraction},
+    rollback::plan_rollback,
+    Turn,
+};
+use tracing::instrument;
+
+/// Supersede every save after the start of `turn` so that it can be replayed, after confirming with the user
+#[instrument(skip_all, ret, err)]
+pub fn run(
+    turn: Turn,
+    mut local: Box<dyn LocalStorage>,
+    mut remote: Box<dyn RemoteStorage>,
+    mut ui: Box<dyn UserInteraction>,
+) -> anyhow::Result<()> {
+    let rollback = plan_rollback(turn, &*local, &*remote)?;
+
+    let mut confirmation_prompt = String::new();
+    writeln!(confirmation_prompt, "Rolling back to replay {turn}")?;
+
+    if !rollback.remote.is_empty() {
+        writeln!(confirmation_prompt, "Will supersede in remote storage:")?;
+        for save in rollback.remote.iter() {
+            writeln!(confirmation_prompt, "  🗄️ {save}")?;
+        }
+    }
+
+    if !rollback.local.is_empty() {
+        writeln!(confirmation_prompt, "Will supersede in local storage:")?;
+        for save in rollback.local.iter() {
+            writeln!(confirmation_prompt, "  🗄️ {save}")?;
+        }
+    }
+
+    writeln!(confirmation_prompt, "Will restore:")?;
+    writeln!(
+        confirmation_prompt,
+        "  ⬇️ {} (autosave)",
+        rollback.turn_start
+    )?;
+
+    ui.message(&confirmation_prompt);
+
+    if !ui.confirm(
+        "⚠️ Everyone in the game should agree to this. Is that OK? ⚠️",
+        Some(false),
+    ) {
+        ui.wait_for_user_before_close("User cancelled. Stopping.");
+        return Ok(());
+    }
+
+    rollback.execute(&mut *local, &mut *remote)?;
+
+    ui.wait_for_user_before_close(&format!(
+        "Rolled back to {turn} ✔️ Superseded saves were moved into the `superseded` folders."
+    ));
+    Ok(())
+}
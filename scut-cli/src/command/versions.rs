@@ -0,0 +1,65 @@
+use std::fmt::Write;
+
+use scut_core::{
+    interface::{RemoteStorage, UserInteraction},
+    signature::SigningKey,
+    versions::restore_version,
+    Config, Save,
+};
+use tracing::instrument;
+
+/// List the previous versions of a save that were kept when it was overwritten
+#[instrument(skip_all, ret, err)]
+pub fn list(
+    save: Save,
+    mut remote: Box<dyn RemoteStorage>,
+    mut ui: Box<dyn UserInteraction>,
+) -> anyhow::Result<()> {
+    let versions = remote.versions(&save)?;
+
+    if versions.is_empty() {
+        ui.message(&format!("{save} has never been overwritten"));
+        ui.wait_for_user_before_close("");
+        return Ok(());
+    }
+
+    let mut list = format!("Previous versions of {save}:\n");
+    for version in versions.iter() {
+        writeln!(list, "  🗄️ {version}")?;
+    }
+    writeln!(
+        list,
+        "\nUse `scut restore \"{save}\" <version>` to put a version back"
+    )?;
+
+    ui.message(&list);
+    ui.wait_for_user_before_close("");
+    Ok(())
+}
+
+/// Put a previous version of a save back, after confirming with the user
+#[instrument(skip_all, ret, err)]
+pub fn restore(
+    save: Save,
+    version: u64,
+    config: Config,
+    mut remote: Box<dyn RemoteStorage>,
+    signing_key: &SigningKey,
+    mut ui: Box<dyn UserInteraction>,
+) -> anyhow::Result<()> {
+    if !ui.confirm(
+        &format!(
+            "⚠️ This replaces {save} in remote storage with version {version}, \
+            the current copy will be kept as a version. Is that OK? ⚠️",
+        ),
+        Some(false),
+    ) {
+        ui.wait_for_user_before_close("User cancelled. Stopping.");
+        return Ok(());
+    }
+
+    restore_version(&mut *remote, &save, version, &config.player, signing_key)?;
+
+    ui.wait_for_user_before_close(&format!("Restored {save} version {version} ✔️"));
+    Ok(())
+}
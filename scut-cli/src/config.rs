@@ -7,6 +7,7 @@ use scut_core::{
         file_system::local_file_system::LocalFileSystem,
         Terminal,
     },
+    signature::{load_or_create_signing_key, SigningKey, SIGNING_KEY_FILE},
     Config,
 };
 
@@ -31,3 +32,14 @@ pub(crate) fn ready_config(
     let mut config_service = TomlFileConfig::new(config_location, file_system, user_interaction);
     Ok((get_config(&mut config_service)?, Box::new(config_service)))
 }
+
+/// Load your signing key from beside the config, creating a new one if you don't have one yet
+pub(crate) fn ready_signing_key(config_option: Option<PathBuf>) -> anyhow::Result<SigningKey> {
+    let config_location = config_option.unwrap_or(TomlFileConfig::default_location()?);
+
+    load_or_create_signing_key(
+        &config_location.with_file_name(SIGNING_KEY_FILE),
+        &mut LocalFileSystem::new(),
+    )
+    .context("failed to load your signing key")
+}
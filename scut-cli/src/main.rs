@@ -20,12 +20,21 @@
 //!
 //! Commands:
 //!   config
-//!   download  Ready a turn to be played
-//!   upload    Share a turn that you've finished playing
-//!   help      Print this message or the help of the given subcommand(s)
+//!   audit       Check the history of the game in remote storage for missing or irregular saves
+//!   download    Ready a turn to be played
+//!   upload      Share a turn that you've finished playing
+//!   rollback    Replay an earlier turn, superseding every save made after it started
+//!   debug       Capture or replay a snapshot of your saves, to report a bug
+//!   public-key  Print your public key, for your teammates to add to the roster
+//!   versions    List the previous versions of a save that were kept when it was overwritten
+//!   restore     Put a previous version of a save back in remote storage
+//!   mirrors     List the saves that each mirror is missing, and copy them over
+//!   prune       Bundle old turns in remote storage into archives, following the retention policy in the config
+//!   help        Print this message or the help of the given subcommand(s)
 //!
 //! Options:
 //!   -c, --config <CONFIG>  Load config from PATH instead of the default config path
+//!       --as <PLAYER>      Play as another player on the roster, e.g. while covering for a teammate
 //!   -h, --help             Print help information
 //!   -V, --version          Print version information
 //! ```
@@ -40,7 +49,11 @@ use clap::{Parser, Subcommand, ValueHint};
 use command::config::ConfigArgs;
 use scut_core::{
     error::Report,
-    interface::{predict::simple_predict::SimplePredict, Terminal},
+    interface::{
+        predict::{observer_predict::ObserverPredict, simple_predict::SimplePredict},
+        Predict, Terminal,
+    },
+    Config, Save, Side, Turn,
 };
 use tracing::{debug, info, instrument};
 use tracing_appender::non_blocking::WorkerGuard;
@@ -68,11 +81,84 @@ pub(crate) struct Cli {
     /// Override the log path set in the config.
     #[arg(short, long, value_hint=ValueHint::FilePath)]
     pub(crate) log_path: Option<PathBuf>,
+
+    /// Play as another player on the roster, e.g. while covering for a teammate.
+    ///
+    /// Saves are signed as PLAYER, and remote storage records that you uploaded them.
+    #[arg(long = "as", value_name = "PLAYER")]
+    pub(crate) acting_as: Option<String>,
 }
 
 #[derive(Debug, Subcommand)]
 pub(crate) enum CliSubcommand {
     Config(ConfigArgs),
+
+    /// Check the history of the game in remote storage for missing or irregular saves
+    Audit,
+
+    /// Replay an earlier turn, superseding every save made after it started
+    Rollback {
+        /// The side of the turn to replay, e.g. Axis
+        side: Side,
+
+        /// The number of the turn to replay
+        number: u32,
+    },
+
+    /// Capture or replay a snapshot of your saves, to report a bug
+    Debug {
+        #[command(subcommand)]
+        command: DebugCommand,
+    },
+
+    /// Print your public key, for your teammates to add to the roster
+    PublicKey,
+
+    /// List the previous versions of a save that were kept when it was overwritten
+    Versions {
+        /// The save, e.g. "Allies 5"
+        save: Save,
+    },
+
+    /// Put a previous version of a save back in remote storage
+    Restore {
+        /// The save, e.g. "Allies 5"
+        save: Save,
+
+        /// The version to put back, as listed by `scut versions`
+        version: u64,
+    },
+
+    /// List the saves that each mirror is missing, and copy them over
+    Mirrors {
+        /// Copy the missing saves from the mirrors that have them
+        #[arg(long)]
+        resync: bool,
+    },
+
+    /// Bundle old turns in remote storage into archives, following the retention policy in the config
+    Prune {
+        /// List what would be archived, without archiving anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub(crate) enum DebugCommand {
+    /// Capture the names of the saves in your local and remote folders, and what scut predicts for them
+    Snapshot {
+        /// Write the snapshot to PATH instead of printing it
+        #[arg(short, long, value_name = "PATH", value_hint=ValueHint::FilePath)]
+        output: Option<PathBuf>,
+    },
+
+    /// Predict again from a snapshot, and show whether the prediction has changed
+    Replay {
+        /// The snapshot file to replay
+        #[arg(value_hint=ValueHint::FilePath)]
+        file: PathBuf,
+    },
 }
 
 fn main() -> Result<(), Report> {
@@ -81,13 +167,14 @@ fn main() -> Result<(), Report> {
         config,
         turn,
         log_path,
+        acting_as,
     } = Cli::parse();
 
     let _guard = setup_tracing(log_path)?;
 
     debug!("starting scut");
 
-    Ok(run(sub_cmd, config, turn)?)
+    Ok(run(sub_cmd, config, turn, acting_as)?)
 }
 
 #[instrument(skip_all, level = "INFO")]
@@ -95,10 +182,15 @@ pub(crate) fn run(
     sub_cmd: Option<CliSubcommand>,
     config: Option<PathBuf>,
     turn: Option<u32>,
+    acting_as: Option<String>,
 ) -> anyhow::Result<()> {
     info!(config_path = ?config.as_ref().map(|p| p.display()));
 
-    let (config, config_service) = config::ready_config(config)?;
+    let config_path = config.clone();
+    let (mut config, config_service) = config::ready_config(config)?;
+    if acting_as.is_some() {
+        config.acting_as = acting_as;
+    }
     let command_user_interaction = Box::new(Terminal::new());
 
     match sub_cmd {
@@ -108,9 +200,72 @@ pub(crate) fn run(
             config_service,
             command_user_interaction,
         ),
+        Some(CliSubcommand::Audit) => {
+            let (_, remote_storage, config) = storage::ready_storage(config)?;
+            command::audit::run(config, remote_storage, command_user_interaction)
+        }
+        Some(CliSubcommand::Rollback { side, number }) => {
+            let (local_storage, remote_storage, _) = storage::ready_storage(config)?;
+            command::rollback::run(
+                Turn::new(side, number),
+                local_storage,
+                remote_storage,
+                command_user_interaction,
+            )
+        }
+        Some(CliSubcommand::Debug {
+            command: DebugCommand::Snapshot { output },
+        }) => {
+            let (local_storage, remote_storage, config) = storage::ready_storage(config)?;
+            let predictor = predictor(&config, config.observer.unwrap_or_default());
+            command::debug::snapshot(
+                config,
+                output.as_deref(),
+                local_storage,
+                remote_storage,
+                predictor,
+                command_user_interaction,
+            )
+        }
+        Some(CliSubcommand::Debug {
+            command: DebugCommand::Replay { file },
+        }) => command::debug::replay(
+            &file,
+            |snapshot| predictor(&config, snapshot.observer),
+            command_user_interaction,
+        ),
+        Some(CliSubcommand::PublicKey) => {
+            let signing_key = config::ready_signing_key(config_path)?;
+            command::public_key::run(config, &signing_key, command_user_interaction)
+        }
+        Some(CliSubcommand::Versions { save }) => {
+            let (_, remote_storage, _) = storage::ready_storage(config)?;
+            command::versions::list(save, remote_storage, command_user_interaction)
+        }
+        Some(CliSubcommand::Restore { save, version }) => {
+            let (_, remote_storage, config) = storage::ready_storage(config)?;
+            let signing_key = config::ready_signing_key(config_path)?;
+            command::versions::restore(
+                save,
+                version,
+                config,
+                remote_storage,
+                &signing_key,
+                command_user_interaction,
+            )
+        }
+        Some(CliSubcommand::Mirrors { resync }) => {
+            let (mirrored, skipped) = storage::ready_mirrors(&config)?;
+            command::mirrors::run(config, resync, mirrored, skipped, command_user_interaction)
+        }
+        Some(CliSubcommand::Prune { dry_run }) => {
+            let (_, remote_storage, config) = storage::ready_storage(config)?;
+            command::prune::run(config, dry_run, remote_storage, command_user_interaction)
+        }
         None => {
             let (local_storage, remote_storage, mut config) = storage::ready_storage(config)?;
-            let predictor = Box::<SimplePredict>::default();
+            let predictor = predictor(&config, config.observer.unwrap_or_default());
+            let signing_key = config::ready_signing_key(config_path)?;
 
             command::run(
                 turn,
@@ -118,12 +273,27 @@ pub(crate) fn run(
                 local_storage,
                 remote_storage,
                 predictor,
+                &signing_key,
                 command_user_interaction,
             )
         }
     }
 }
 
+/// Choose how to predict from the config: observers never play, everyone else uses [`SimplePredict`]
+fn predictor(config: &Config, observer: bool) -> Box<dyn Predict> {
+    if observer {
+        Box::new(ObserverPredict::new(
+            config.observe_player_saves.unwrap_or_default(),
+        ))
+    } else {
+        Box::new(
+            SimplePredict::new(config.roster.aliases.clone())
+                .handoff(config.roster.handoff.clone()),
+        )
+    }
+}
+
 fn setup_tracing(log_path: Option<PathBuf>) -> anyhow::Result<WorkerGuard> {
     let log_path = match log_path {
         Some(path) => path,
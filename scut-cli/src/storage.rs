@@ -2,31 +2,19 @@ use anyhow::Context;
 use scut_core::{
     error::ErrorSuggestions,
     interface::{
-        compression::SevenZipCompression,
         file_system::local_file_system::LocalFileSystem,
-        storage::{dropbox_folder::DropboxFolder, game_saves_folder::GameSavesFolder},
+        storage::{connect, game_saves_folder::GameSavesFolder, mirrored_remote::MirroredRemote},
         LocalStorage, RemoteStorage,
     },
     Config,
 };
 
 pub type ReadiedStorage = (Box<dyn LocalStorage>, Box<dyn RemoteStorage>, Config);
+pub type ReadiedMirrors = (MirroredRemote, Vec<String>);
 
 pub(crate) fn ready_storage(config: Config) -> anyhow::Result<ReadiedStorage> {
-    let compression = SevenZipCompression::new(&config.seven_zip_path);
-
-    let remote_storage = DropboxFolder::new(
-        config.dropbox.clone(),
-        Box::new(LocalFileSystem::new()),
-        Box::new(compression),
-    )
-    .with_context(|| {
-        format!(
-            "failed to load dropbox folder with path '{}'",
-            config.dropbox.display()
-        )
-    })
-    .suggest("Use `scut config edit` to review and update your config")?;
+    let remote_storage = connect::remote_storage(&config)
+        .suggest("Use `scut config edit` to review and update your config")?;
 
     let local_storage =
         GameSavesFolder::new(config.saves.clone(), Box::new(LocalFileSystem::new()))
@@ -38,5 +26,10 @@ pub(crate) fn ready_storage(config: Config) -> anyhow::Result<ReadiedStorage> {
             })
             .suggest("Use `scut config edit` to review and update your config")?;
 
-    Ok((Box::new(local_storage), Box::new(remote_storage), config))
+    Ok((Box::new(local_storage), remote_storage, config))
+}
+
+/// Connect to each mirror, to check whether they have diverged, returning the names of those that can't be reached
+pub(crate) fn ready_mirrors(config: &Config) -> anyhow::Result<ReadiedMirrors> {
+    connect::mirrors(config).suggest("Use `scut config edit` to review and update your config")
 }
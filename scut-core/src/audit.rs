@@ -0,0 +1,351 @@
+//! Auditing walks the history of a game in remote storage, from the first turn to the current turn,
+//! looking for holes (saves that should exist but don't) and irregularities (saves that shouldn't exist).
+//!
+//! This replaces manually inspecting the remote folder after a dispute.
+
+use std::fmt;
+
+use crate::{interface::index::Query, interface::Index, Roster, Save, Side, Turn};
+
+/// The result of auditing the saves in an [`Index`]
+#[derive(Debug, PartialEq, Eq)]
+pub struct Audit {
+    /// The latest turn with a turn start save, saves after this turn are irregular
+    pub current_turn: Turn,
+
+    /// Everything found to be wrong, in turn order
+    pub issues: Vec<Issue>,
+}
+
+/// A hole or irregularity found when auditing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Issue {
+    /// The save that starts this turn is missing
+    MissingTurnStart(Turn),
+    /// A player on the roster did not upload a save for a turn that has finished
+    MissingPlayerSave { turn: Turn, player: String },
+    /// A save for part of a player's turn
+    ExtraPart(Save),
+    /// A save from a player who isn't on the roster for that side
+    OrphanSave(Save),
+    /// A save for a turn after the current turn
+    BeyondCurrentTurn(Save),
+}
+
+impl Issue {
+    /// The turn that this issue was found in
+    pub fn turn(&self) -> Turn {
+        match self {
+            Issue::MissingTurnStart(turn) => *turn,
+            Issue::MissingPlayerSave { turn, .. } => *turn,
+            Issue::ExtraPart(save) | Issue::OrphanSave(save) | Issue::BeyondCurrentTurn(save) => {
+                save.turn
+            }
+        }
+    }
+
+    /// Returns true if this issue is a save that should exist but doesn't.
+    ///
+    /// Otherwise the issue is an irregular save that exists but perhaps shouldn't.
+    pub fn is_hole(&self) -> bool {
+        matches!(
+            self,
+            Issue::MissingTurnStart(_) | Issue::MissingPlayerSave { .. }
+        )
+    }
+
+    /// A short description of the kind of issue
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Issue::MissingTurnStart(_) => "missing turn start",
+            Issue::MissingPlayerSave { .. } => "missing player save",
+            Issue::ExtraPart(_) => "extra part",
+            Issue::OrphanSave(_) => "orphan save",
+            Issue::BeyondCurrentTurn(_) => "beyond current turn",
+        }
+    }
+
+    /// The save that is missing or irregular
+    pub fn save(&self) -> Save {
+        match self {
+            Issue::MissingTurnStart(turn) => Save::new(*turn),
+            Issue::MissingPlayerSave { turn, player } => Save::new(*turn).player(player),
+            Issue::ExtraPart(save) | Issue::OrphanSave(save) | Issue::BeyondCurrentTurn(save) => {
+                save.clone()
+            }
+        }
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.kind(), self.save())
+    }
+}
+
+/// Audit the saves in an index, from Axis 1 to the current turn.
+///
+/// For every turn this checks that the turn start save exists, and that every player on the roster for the active side uploaded a save.
+/// Player saves for the current turn aren't expected until the turn is finished, so they are not reported as missing.
+///
+/// If the roster doesn't list any players for a side, the players who have uploaded saves for that side are used instead.
+///
+/// Saves signed with one of a player's aliases count as that player's saves.
+/// If a side hands off the turn between players, each player's part of the turn counts as their save.
+pub fn audit<'a>(index: &'a dyn Index<'a>, roster: &Roster) -> anyhow::Result<Audit> {
+    let current_turn = index
+        .latest(&Query::new().player(None))?
+        .map(|save| save.turn)
+        .unwrap_or(Turn::new(Side::first(), 1));
+
+    let axis = roster_or_uploaders(index, roster, Side::Axis)?;
+    let allies = roster_or_uploaders(index, roster, Side::Allies)?;
+
+    let mut issues = Vec::new();
+    let mut turn = Turn::new(Side::first(), 1);
+
+    while turn <= current_turn {
+        let saves = index.search(&Query::new().turn(turn))?;
+        let players = match turn.side {
+            Side::Axis => &axis,
+            Side::Allies => &allies,
+        };
+
+        if !saves.iter().any(|save| save.player.is_none()) {
+            issues.push(Issue::MissingTurnStart(turn));
+        }
+
+        // the part of the turn each save's player plays in the handoff order, if their side hands off
+        let handoff_part = |player: &str| roster.handoff.part(turn.side, player, &roster.aliases);
+
+        if turn < current_turn {
+            for player in players {
+                let uploaded = saves.iter().any(|save| {
+                    save.player
+                        .as_ref()
+                        .is_some_and(|p| roster.aliases.same_player(p, player))
+                        && (save.part.is_none() || save.part == handoff_part(player))
+                });
+
+                if !uploaded {
+                    issues.push(Issue::MissingPlayerSave {
+                        turn,
+                        player: player.clone(),
+                    });
+                }
+            }
+        }
+
+        for save in saves.into_iter().filter(|save| save.player.is_some()) {
+            if !players.iter().any(|player| {
+                save.player
+                    .as_ref()
+                    .is_some_and(|p| roster.aliases.same_player(p, player))
+            }) {
+                issues.push(Issue::OrphanSave(save));
+            } else if save.part.is_some()
+                && save.part != handoff_part(save.player.as_deref().unwrap_or_default())
+            {
+                issues.push(Issue::ExtraPart(save));
+            }
+        }
+
+        turn = turn.next();
+    }
+
+    let mut beyond = index.search(&Query::new())?;
+    beyond.retain(|save| save.turn > current_turn);
+    beyond.sort();
+    issues.extend(beyond.into_iter().map(Issue::BeyondCurrentTurn));
+
+    Ok(Audit {
+        current_turn,
+        issues,
+    })
+}
+
+/// The roster players for a side, or if none are listed, everyone who has uploaded a save for that side
+fn roster_or_uploaders<'a>(
+    index: &'a dyn Index<'a>,
+    roster: &Roster,
+    side: Side,
+) -> anyhow::Result<Vec<String>> {
+    let players = roster.players(side);
+    if !players.is_empty() {
+        return Ok(players.to_vec());
+    }
+
+    let mut uploaders: Vec<String> = index
+        .search(&Query::new().side(side).not_player(None))?
+        .into_iter()
+        .filter_map(|save| save.player)
+        .map(|player| roster.aliases.canonical(&player).to_string())
+        .collect();
+    uploaders.sort();
+    uploaders.dedup();
+
+    Ok(uploaders)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{interface::index::mock_index::MockIndex, Aliases, HandoffOrder};
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn roster() -> Roster {
+        Roster {
+            axis: vec!["DM".to_string(), "DG".to_string()],
+            allies: vec!["GM".to_string(), "TG".to_string()],
+            aliases: Aliases::default(),
+            handoff: HandoffOrder::default(),
+            keys: Default::default(),
+        }
+    }
+
+    #[test]
+    fn complete_history_has_no_issues() -> anyhow::Result<()> {
+        let saves = &[
+            Save::from_parts(Side::Axis, 1),
+            Save::from_parts(Side::Axis, 1).player("DM"),
+            Save::from_parts(Side::Axis, 1).player("DG"),
+            Save::from_parts(Side::Allies, 1),
+            Save::from_parts(Side::Allies, 1).player("GM"),
+            Save::from_parts(Side::Allies, 1).player("TG"),
+            Save::from_parts(Side::Axis, 2),
+            Save::from_parts(Side::Axis, 2).player("DM"),
+        ];
+        let index = MockIndex::new(saves);
+
+        assert_eq!(
+            audit(&index, &roster())?,
+            Audit {
+                current_turn: Turn::new(Side::Axis, 2),
+                issues: Vec::new(),
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn audit_finds_holes() -> anyhow::Result<()> {
+        let saves = &[
+            Save::from_parts(Side::Axis, 1),
+            Save::from_parts(Side::Axis, 1).player("DM"),
+            Save::from_parts(Side::Allies, 1).player("GM"),
+            Save::from_parts(Side::Allies, 1).player("TG"),
+            Save::from_parts(Side::Axis, 2),
+        ];
+        let index = MockIndex::new(saves);
+
+        assert_eq!(
+            audit(&index, &roster())?.issues,
+            vec![
+                Issue::MissingPlayerSave {
+                    turn: Turn::new(Side::Axis, 1),
+                    player: "DG".to_string()
+                },
+                Issue::MissingTurnStart(Turn::new(Side::Allies, 1)),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn audit_finds_irregularities() -> anyhow::Result<()> {
+        let saves = &[
+            Save::from_parts(Side::Axis, 1),
+            Save::from_parts(Side::Axis, 1).player("DM"),
+            Save::from_parts(Side::Axis, 1).player("DM").part("A"),
+            Save::from_parts(Side::Axis, 1).player("DG"),
+            Save::from_parts(Side::Axis, 1).player("XX"),
+            Save::from_parts(Side::Allies, 1),
+            Save::from_parts(Side::Allies, 2).player("GM"),
+        ];
+        let index = MockIndex::new(saves);
+
+        assert_eq!(
+            audit(&index, &roster())?.issues,
+            vec![
+                Issue::ExtraPart(Save::from_parts(Side::Axis, 1).player("DM").part("A")),
+                Issue::OrphanSave(Save::from_parts(Side::Axis, 1).player("XX")),
+                Issue::BeyondCurrentTurn(Save::from_parts(Side::Allies, 2).player("GM")),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn audit_without_roster_uses_uploaders() -> anyhow::Result<()> {
+        let saves = &[
+            Save::from_parts(Side::Axis, 1),
+            Save::from_parts(Side::Axis, 1).player("DM"),
+            Save::from_parts(Side::Allies, 1),
+            Save::from_parts(Side::Allies, 1).player("GM"),
+            Save::from_parts(Side::Axis, 2),
+            Save::from_parts(Side::Axis, 2).player("DM"),
+            Save::from_parts(Side::Axis, 2).player("DG"),
+            Save::from_parts(Side::Allies, 2),
+        ];
+        let index = MockIndex::new(saves);
+
+        assert_eq!(
+            audit(&index, &Roster::default())?.issues,
+            vec![Issue::MissingPlayerSave {
+                turn: Turn::new(Side::Axis, 1),
+                player: "DG".to_string()
+            }]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn audit_accepts_aliases() -> anyhow::Result<()> {
+        let saves = &[
+            Save::from_parts(Side::Axis, 1),
+            Save::from_parts(Side::Axis, 1).player("DM"),
+            Save::from_parts(Side::Axis, 1).player("DG"),
+            Save::from_parts(Side::Allies, 1),
+            Save::from_parts(Side::Allies, 1).player("Gm"),
+            Save::from_parts(Side::Allies, 1).player("TG"),
+            Save::from_parts(Side::Axis, 2),
+        ];
+        let index = MockIndex::new(saves);
+
+        let mut roster = roster();
+        roster.aliases.insert("GM", "Gm");
+
+        assert_eq!(audit(&index, &roster)?.issues, Vec::new());
+
+        Ok(())
+    }
+
+    #[test]
+    fn audit_accepts_handoff_parts() -> anyhow::Result<()> {
+        let saves = &[
+            Save::from_parts(Side::Axis, 1),
+            Save::from_parts(Side::Axis, 1).player("DM").part("A"),
+            Save::from_parts(Side::Axis, 1).player("DG").part("B"),
+            Save::from_parts(Side::Axis, 1).player("DG").part("C"),
+            Save::from_parts(Side::Allies, 1),
+        ];
+        let index = MockIndex::new(saves);
+
+        let mut roster = roster();
+        roster.handoff.axis = vec!["DM".to_string(), "DG".to_string()];
+
+        assert_eq!(
+            audit(&index, &roster)?.issues,
+            vec![Issue::ExtraPart(
+                Save::from_parts(Side::Axis, 1).player("DG").part("C")
+            )]
+        );
+
+        Ok(())
+    }
+}
@@ -4,25 +4,51 @@ use serde::{Deserialize, Serialize};
 
 #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum Key {
-    Dropbox,
+    Folder,
+    Provider,
+    RemoteUrl,
+    RemoteUsername,
+    RemotePassword,
+    RemoteKey,
+    RemoteSmtp,
+    RemoteMailingList,
+    SharedFolder,
+    TeamPassphrase,
+    GamePassphrase,
     Saves,
     SevenZipPath,
     Side,
     Player,
     Turn,
     Solo,
+    ActingAs,
+    Observer,
+    ObservePlayerSaves,
 }
 
 impl fmt::Display for Key {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Key::Dropbox => write!(f, "dropbox"),
+            Key::Folder => write!(f, "folder"),
+            Key::Provider => write!(f, "remote.provider"),
+            Key::RemoteUrl => write!(f, "remote.url"),
+            Key::RemoteUsername => write!(f, "remote.username"),
+            Key::RemotePassword => write!(f, "remote.password"),
+            Key::RemoteKey => write!(f, "remote.key"),
+            Key::RemoteSmtp => write!(f, "remote.smtp"),
+            Key::RemoteMailingList => write!(f, "remote.mailing_list"),
+            Key::SharedFolder => write!(f, "shared_folder"),
+            Key::TeamPassphrase => write!(f, "team_passphrase"),
+            Key::GamePassphrase => write!(f, "game_passphrase"),
             Key::Saves => write!(f, "saves"),
             Key::SevenZipPath => write!(f, "seven_zip_path"),
             Key::Side => write!(f, "side"),
             Key::Player => write!(f, "player"),
             Key::Turn => write!(f, "turn"),
             Key::Solo => write!(f, "solo"),
+            Key::ActingAs => write!(f, "acting_as"),
+            Key::Observer => write!(f, "observer"),
+            Key::ObservePlayerSaves => write!(f, "observe_player_saves"),
         }
     }
 }
@@ -32,7 +58,32 @@ impl FromStr for Key {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.to_lowercase().as_ref() {
-            "dropbox" => Ok(Self::Dropbox),
+            "folder" | "dropbox" => Ok(Self::Folder),
+            "provider" | "remote.provider" | "remote provider" | "remote" => Ok(Self::Provider),
+            "remote.url" | "remote url" | "remote_url" | "url" => Ok(Self::RemoteUrl),
+            "remote.username" | "remote username" | "remote_username" | "username" => {
+                Ok(Self::RemoteUsername)
+            }
+            "remote.password" | "remote password" | "remote_password" | "password" => {
+                Ok(Self::RemotePassword)
+            }
+            "remote.key" | "remote key" | "remote_key" | "key" | "ssh key" => Ok(Self::RemoteKey),
+            "remote.smtp" | "remote smtp" | "remote_smtp" | "smtp" => Ok(Self::RemoteSmtp),
+            "remote.mailing_list"
+            | "remote mailing list"
+            | "remote_mailing_list"
+            | "mailing_list"
+            | "mailing list" => Ok(Self::RemoteMailingList),
+            "shared_folder" | "sharedfolder" | "shared-folder" | "shared folder" | "shared"
+            | "shared_dropbox" | "shareddropbox" | "shared-dropbox" | "shared dropbox" => {
+                Ok(Self::SharedFolder)
+            }
+            "team_passphrase" | "teampassphrase" | "team-passphrase" | "team passphrase" => {
+                Ok(Self::TeamPassphrase)
+            }
+            "game_passphrase" | "gamepassphrase" | "game-passphrase" | "game passphrase" => {
+                Ok(Self::GamePassphrase)
+            }
             "saves" | "save" => Ok(Self::Saves),
             "seven_zip_path" | "sevenzippath" | "seven-zip-path" | "seven zip path"
             | "sevenzip path" | "sevenzip-path" | "7zpath" | "7z path" | "7z-path" | "7z_path" => {
@@ -42,6 +93,12 @@ impl FromStr for Key {
             "player" | "name" => Ok(Self::Player),
             "turn" => Ok(Self::Turn),
             "solo" => Ok(Self::Solo),
+            "acting_as" | "actingas" | "acting-as" | "acting as" | "as" => Ok(Self::ActingAs),
+            "observer" | "observe" | "spectator" => Ok(Self::Observer),
+            "observe_player_saves"
+            | "observeplayersaves"
+            | "observe-player-saves"
+            | "observe player saves" => Ok(Self::ObservePlayerSaves),
             key => anyhow::bail!("invalid key: {key}"),
         }
     }
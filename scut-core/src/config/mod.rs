@@ -1,16 +1,22 @@
-use std::path::PathBuf;
+use std::{fmt, path::PathBuf};
 
 use serde::{Deserialize, Serialize};
 
-use crate::Side;
+use crate::{error::ErrorSuggestions, Side};
 
 mod key;
+mod remote;
+mod retention;
+mod roster;
 mod setting;
 
 pub use key::Key;
+pub use remote::{Mirror, Provider, Remote, RemoteKind};
+pub use retention::Retention;
+pub use roster::{part_letter, Aliases, HandoffOrder, Roster};
 pub use setting::Setting;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Serialize, Deserialize)]
 pub struct Config {
     pub saves: PathBuf,
     pub side: Side,
@@ -20,27 +26,158 @@ pub struct Config {
     #[serde(default)]
     pub solo: Option<bool>,
 
-    pub dropbox: PathBuf,
+    /// The folder that saves are shared through, synced by the provider in `remote`
+    #[serde(alias = "dropbox")]
+    pub folder: PathBuf,
     pub seven_zip_path: PathBuf,
+
+    /// How the `folder` is synced, e.g. by Dropbox or OneDrive, or the server that saves are uploaded to instead
+    #[serde(default, skip_serializing_if = "Remote::is_default")]
+    pub remote: Remote,
+
+    /// Mirror every upload to these remotes too, reading from them if `remote` can't be
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mirrors: Vec<Mirror>,
+
+    /// Which saves to keep when old turns are pruned from remote storage, see [`prune`](crate::prune)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retention: Option<Retention>,
+
+    /// Exchange turn start saves with the enemy team in this folder, keeping every other save private in `folder`
+    #[serde(
+        default,
+        alias = "shared_dropbox",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub shared_folder: Option<PathBuf>,
+
+    /// Encrypt saves that only your team should see with this passphrase
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub team_passphrase: Option<String>,
+
+    /// Encrypt turn start saves, which are shared with the enemy team, with this passphrase
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub game_passphrase: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Roster::is_empty")]
+    pub roster: Roster,
+
+    /// Play as this player on the roster instead of `player`, e.g. while covering for a teammate
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acting_as: Option<String>,
+
+    /// Follow the game without playing: download every turn start save and never upload
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub observer: Option<bool>,
+
+    /// When observing, also download every player's saves
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub observe_player_saves: Option<bool>,
+}
+
+/// Passphrases are left out, e.g. of logs, only whether they are set is shown
+impl fmt::Debug for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Config")
+            .field("saves", &self.saves)
+            .field("side", &self.side)
+            .field("player", &self.player)
+            .field("turn", &self.turn)
+            .field("solo", &self.solo)
+            .field("folder", &self.folder)
+            .field("seven_zip_path", &self.seven_zip_path)
+            .field("remote", &self.remote)
+            .field("mirrors", &self.mirrors)
+            .field("retention", &self.retention)
+            .field("shared_folder", &self.shared_folder)
+            .field("team_passphrase", &redacted(&self.team_passphrase))
+            .field("game_passphrase", &redacted(&self.game_passphrase))
+            .field("roster", &self.roster)
+            .field("acting_as", &self.acting_as)
+            .field("observer", &self.observer)
+            .field("observe_player_saves", &self.observe_player_saves)
+            .finish()
+    }
+}
+
+/// Stands in for a secret in debug output
+pub(crate) fn redacted(secret: &Option<String>) -> Option<&'static str> {
+    secret.as_ref().map(|_| "***")
 }
 
 impl Config {
+    /// A copy to show on screen, with every password and passphrase that is set replaced by `***`
+    pub fn redacted(mut self) -> Config {
+        let redact = |secret: &Option<String>| redacted(secret).map(String::from);
+
+        self.team_passphrase = redact(&self.team_passphrase);
+        self.game_passphrase = redact(&self.game_passphrase);
+        self.remote.password = redact(&self.remote.password);
+        for mirror in &mut self.mirrors {
+            mirror.remote.password = redact(&mirror.remote.password);
+        }
+        self
+    }
+
     pub fn get(&self, key: Key) -> Setting {
         match key {
-            Key::Dropbox => Setting::Dropbox(self.dropbox.clone()),
+            Key::Folder => Setting::Folder(self.folder.clone()),
+            Key::Provider => Setting::Provider(self.remote.provider),
+            Key::RemoteUrl => Setting::RemoteUrl(self.remote.url.clone()),
+            Key::RemoteUsername => Setting::RemoteUsername(self.remote.username.clone()),
+            Key::RemotePassword => Setting::RemotePassword(self.remote.password.clone()),
+            Key::RemoteKey => Setting::RemoteKey(self.remote.key.clone()),
+            Key::RemoteSmtp => Setting::RemoteSmtp(self.remote.smtp.clone()),
+            Key::RemoteMailingList => Setting::RemoteMailingList(self.remote.mailing_list.clone()),
+            Key::SharedFolder => Setting::SharedFolder(self.shared_folder.clone()),
+            Key::TeamPassphrase => Setting::TeamPassphrase(self.team_passphrase.clone()),
+            Key::GamePassphrase => Setting::GamePassphrase(self.game_passphrase.clone()),
             Key::Saves => Setting::Saves(self.saves.clone()),
             Key::SevenZipPath => Setting::SevenZipPath(self.seven_zip_path.clone()),
             Key::Side => Setting::Side(self.side),
             Key::Player => Setting::Player(self.player.clone()),
             Key::Turn => Setting::Turn(self.turn),
             Key::Solo => Setting::Solo(self.solo),
+            Key::ActingAs => Setting::ActingAs(self.acting_as.clone()),
+            Key::Observer => Setting::Observer(self.observer),
+            Key::ObservePlayerSaves => Setting::ObservePlayerSaves(self.observe_player_saves),
         }
     }
 
     pub fn set(mut self, setting: Setting) -> Config {
         match setting {
-            Setting::Dropbox(value) => {
-                self.dropbox = value;
+            Setting::Folder(value) => {
+                self.folder = value;
+            }
+            Setting::Provider(value) => {
+                self.remote.provider = value;
+            }
+            Setting::RemoteUrl(value) => {
+                self.remote.url = value;
+            }
+            Setting::RemoteUsername(value) => {
+                self.remote.username = value;
+            }
+            Setting::RemotePassword(value) => {
+                self.remote.password = value;
+            }
+            Setting::RemoteKey(value) => {
+                self.remote.key = value;
+            }
+            Setting::RemoteSmtp(value) => {
+                self.remote.smtp = value;
+            }
+            Setting::RemoteMailingList(value) => {
+                self.remote.mailing_list = value;
+            }
+            Setting::SharedFolder(value) => {
+                self.shared_folder = value;
+            }
+            Setting::TeamPassphrase(value) => {
+                self.team_passphrase = value;
+            }
+            Setting::GamePassphrase(value) => {
+                self.game_passphrase = value;
             }
             Setting::Saves(value) => {
                 self.saves = value;
@@ -60,8 +197,146 @@ impl Config {
             Setting::Solo(value) => {
                 self.solo = value;
             }
+            Setting::ActingAs(value) => {
+                self.acting_as = value;
+            }
+            Setting::Observer(value) => {
+                self.observer = value;
+            }
+            Setting::ObservePlayerSaves(value) => {
+                self.observe_player_saves = value;
+            }
         }
 
         self
     }
+
+    /// The player whose saves are predicted and uploaded: `acting_as` if set, otherwise `player`.
+    ///
+    /// A substitute can only act as another player on the roster for their side.
+    pub fn acting_player(&self) -> anyhow::Result<&str> {
+        let Some(acting_as) = self.acting_as.as_deref() else {
+            return Ok(self.player.as_str());
+        };
+
+        let on_roster = self
+            .roster
+            .players(self.side)
+            .iter()
+            .any(|p| self.roster.aliases.same_player(p, acting_as));
+
+        if acting_as != self.player && !on_roster {
+            Err(anyhow::anyhow!(
+                "cannot act as {acting_as}: they are not on the roster for {}",
+                self.side
+            ))
+            .suggest(format!(
+                "Check the spelling, or add {acting_as} to config.roster.{}",
+                self.side.to_string().to_lowercase()
+            ))?;
+        }
+
+        Ok(acting_as)
+    }
+
+    /// The player who actually uploaded saves, if they are acting as someone else
+    pub fn substitute(&self) -> Option<&str> {
+        match self.acting_as.as_deref() {
+            Some(acting_as) if acting_as != self.player => Some(self.player.as_str()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn config(acting_as: Option<&str>) -> Config {
+        Config {
+            saves: PathBuf::from("saves"),
+            side: Side::Axis,
+            player: "DM".to_string(),
+            turn: None,
+            solo: None,
+            folder: PathBuf::from("dropbox"),
+            seven_zip_path: PathBuf::from("7z"),
+            remote: Remote::default(),
+            mirrors: Vec::new(),
+            retention: None,
+            shared_folder: None,
+            team_passphrase: None,
+            game_passphrase: None,
+            roster: Roster {
+                axis: vec!["DM".to_string(), "DG".to_string()],
+                allies: vec!["GM".to_string()],
+                aliases: Aliases::default(),
+                handoff: HandoffOrder::default(),
+                keys: Default::default(),
+            },
+            acting_as: acting_as.map(String::from),
+            observer: None,
+            observe_player_saves: None,
+        }
+    }
+
+    #[test]
+    fn acting_player_defaults_to_player() -> anyhow::Result<()> {
+        let config = config(None);
+        assert_eq!(config.acting_player()?, "DM");
+        assert_eq!(config.substitute(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn acting_as_teammate() -> anyhow::Result<()> {
+        let config = config(Some("DG"));
+        assert_eq!(config.acting_player()?, "DG");
+        assert_eq!(config.substitute(), Some("DM"));
+        Ok(())
+    }
+
+    #[test]
+    fn acting_as_must_be_on_roster_for_our_side() {
+        assert!(config(Some("GM")).acting_player().is_err());
+        assert!(config(Some("XX")).acting_player().is_err());
+    }
+
+    #[test]
+    fn debug_leaves_out_secrets() {
+        let mut config = config(None);
+        config.team_passphrase = Some("team secret".to_string());
+        config.game_passphrase = Some("game secret".to_string());
+        config.remote.password = Some("app password".to_string());
+
+        let debug = format!("{config:?}");
+        assert!(!debug.contains("secret") && !debug.contains("app password"));
+        assert!(debug.contains(r#"team_passphrase: Some("***")"#));
+    }
+
+    #[test]
+    fn settings_and_redacted_copies_leave_out_secrets() {
+        let mut config = config(None);
+        config.team_passphrase = Some("team secret".to_string());
+        config.remote.password = Some("app password".to_string());
+        config.mirrors.push(Mirror {
+            folder: None,
+            remote: Remote {
+                password: Some("mirror secret".to_string()),
+                ..Remote::default()
+            },
+        });
+
+        assert_eq!(config.get(Key::TeamPassphrase).to_string(), "***");
+        assert_eq!(config.get(Key::GamePassphrase).to_string(), "None");
+        assert_eq!(config.get(Key::RemotePassword).to_string(), "***");
+        assert_eq!(config.get(Key::Player).to_string(), "DM");
+
+        let redacted = config.redacted();
+        assert_eq!(redacted.team_passphrase.as_deref(), Some("***"));
+        assert_eq!(redacted.game_passphrase, None);
+        assert_eq!(redacted.remote.password.as_deref(), Some("***"));
+        assert_eq!(redacted.mirrors[0].remote.password.as_deref(), Some("***"));
+    }
 }
@@ -0,0 +1,494 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::ErrorSuggestions;
+
+/// How saves are shared with the other players, the `[remote]` section of the config.
+///
+/// ```toml
+/// [remote]
+/// provider = "onedrive"
+/// ```
+///
+/// Providers that are reached over the network, rather than syncing the `dropbox` folder, also need to know where and who you are:
+///
+/// ```toml
+/// [remote]
+/// provider = "webdav"
+/// url = "https://cloud.example.com/remote.php/dav/files/DM/scut/"
+/// username = "DM"
+/// password = "app password"
+/// ```
+///
+/// For S3 the url is the endpoint, bucket and prefix, and the username and password are the access key id and secret access key:
+///
+/// ```toml
+/// [remote]
+/// provider = "s3"
+/// url = "http://nas.local:9000/campaigns/ww1/"
+/// username = "access key id"
+/// password = "secret access key"
+/// ```
+///
+/// For a scut server the url is the game on the server, and the password is the token the server's admin gave you:
+///
+/// ```toml
+/// [remote]
+/// provider = "server"
+/// url = "https://scut.example.com/games/ww1/"
+/// password = "token"
+/// ```
+///
+/// For play by email the url is the IMAP folder that the mailing list delivers to, and you log in with your email address:
+///
+/// ```toml
+/// [remote]
+/// provider = "email"
+/// url = "imaps://imap.example.com/scut-ww1"
+/// smtp = "smtps://smtp.example.com"
+/// mailing_list = "ww1@groups.example.com"
+/// username = "dm@example.com"
+/// password = "app password"
+/// ```
+///
+/// For SFTP the url is the server and folder, and you log in with an SSH key:
+///
+/// ```toml
+/// [remote]
+/// provider = "sftp"
+/// url = "sftp://vps.example.com/srv/scut/ww1"
+/// username = "scut"
+/// key = 'C:\Users\masond\.ssh\id_ed25519'
+/// ```
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Remote {
+    /// The program that syncs the `folder` with the other players, or the kind of server that saves are uploaded to
+    #[serde(default)]
+    pub provider: RemoteKind,
+
+    /// Where saves are uploaded to, for providers that are reached over the network
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+
+    /// The password, for SFTP the passphrase of the SSH key if it has one, or for a scut server your token
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+
+    /// The SSH private key to log in with, for SFTP
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<PathBuf>,
+
+    /// The server that emails are sent through, for play by email
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub smtp: Option<String>,
+
+    /// The address of the game's mailing list, for play by email
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mailing_list: Option<String>,
+}
+
+/// The password is left out, only whether it is set is shown
+impl fmt::Debug for Remote {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Remote")
+            .field("provider", &self.provider)
+            .field("url", &self.url)
+            .field("username", &self.username)
+            .field("password", &super::redacted(&self.password))
+            .field("key", &self.key)
+            .field("smtp", &self.smtp)
+            .field("mailing_list", &self.mailing_list)
+            .finish()
+    }
+}
+
+impl Remote {
+    pub fn is_default(&self) -> bool {
+        self == &Remote::default()
+    }
+
+    /// The url of the remote folder, which must be set for providers that are reached over the network
+    pub fn url(&self) -> anyhow::Result<&str> {
+        Ok(self
+            .url
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("remote.url must be set to use {}", self.provider))
+            .suggest(format!(
+                "Use `scut config set remote.url <URL>` to set the url of your {}",
+                self.provider.folder_name()
+            ))?)
+    }
+
+    /// The SSH private key to log in with, which must be set for SFTP
+    pub fn key(&self) -> anyhow::Result<&Path> {
+        Ok(self
+            .key
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("remote.key must be set to use {}", self.provider))
+            .suggest(
+                "Use `scut config set remote.key <PATH>` to set the path of your SSH private key",
+            )?)
+    }
+
+    /// The server that emails are sent through, which must be set for play by email
+    pub fn smtp(&self) -> anyhow::Result<&str> {
+        Ok(self
+            .smtp
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("remote.smtp must be set to use {}", self.provider))
+            .suggest("Use `scut config set remote.smtp <URL>` to set the url of your email provider's SMTP server")?)
+    }
+
+    /// The address that saves are emailed to, which must be set for play by email
+    pub fn mailing_list(&self) -> anyhow::Result<&str> {
+        Ok(self
+            .mailing_list
+            .as_deref()
+            .ok_or_else(|| {
+                anyhow::anyhow!("remote.mailing_list must be set to use {}", self.provider)
+            })
+            .suggest("Use `scut config set remote.mailing_list <ADDRESS>` to set the address of your game's mailing list")?)
+    }
+
+    /// The username and password to log in with, if a username is set
+    pub fn credentials(&self) -> Option<(&str, Option<&str>)> {
+        self.username
+            .as_deref()
+            .map(|username| (username, self.password.as_deref()))
+    }
+
+    /// Names the remote for the user, by its url if it is reached over the network, otherwise by its folder at `location`
+    pub fn describe(&self, location: &Path) -> String {
+        match &self.url {
+            Some(url) if !self.provider.is_synced_folder() => format!("{} '{url}'", self.provider),
+            _ => format!("{} '{}'", self.provider, location.display()),
+        }
+    }
+}
+
+/// Another remote that saves are mirrored to, one of the `[[mirrors]]` in the config.
+///
+/// Each mirror is configured like `[remote]`, with the folder it keeps saves in as its `folder`:
+///
+/// ```toml
+/// [[mirrors]]
+/// provider = "folder"
+/// folder = '\\nas\scut\ww1'
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Mirror {
+    /// The folder that the mirror keeps saves in, for providers that sync a folder and for git
+    #[serde(default, alias = "dropbox", skip_serializing_if = "Option::is_none")]
+    pub folder: Option<PathBuf>,
+
+    #[serde(flatten)]
+    pub remote: Remote,
+}
+
+impl Mirror {
+    /// The folder that the mirror keeps saves in, which must be set unless the mirror is reached over the network
+    pub fn folder(&self) -> anyhow::Result<&Path> {
+        let provider = self.remote.provider;
+        match &self.folder {
+            Some(folder) => Ok(folder),
+            None if !provider.is_synced_folder() && provider != RemoteKind::Git => {
+                Ok(Path::new(""))
+            }
+            None => Ok(Err(anyhow::anyhow!(
+                "each mirror using {provider} must set the folder it keeps saves in"
+            ))
+            .suggest("Use `scut config edit` to add `folder = '<PATH>'` to the mirror")?),
+        }
+    }
+
+    /// Names the mirror for the user, see [`Remote::describe`]
+    pub fn describe(&self) -> String {
+        self.remote
+            .describe(self.folder.as_deref().unwrap_or(Path::new("")))
+    }
+}
+
+/// A program that syncs a folder on your computer with the other players' computers.
+///
+/// scut only reads and writes files in the folder, the provider decides where the folder usually is
+/// and how it names the copies it makes when two computers change the same file at once.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    #[default]
+    Dropbox,
+    OneDrive,
+    GoogleDrive,
+    Syncthing,
+    /// Any other folder that is shared somehow, e.g. on a NAS
+    Folder,
+}
+
+impl Provider {
+    /// How to refer to the synced folder in messages, e.g. "your OneDrive folder"
+    pub fn folder_name(&self) -> String {
+        match self {
+            Provider::Folder => String::from("synced folder"),
+            provider => format!("{provider} folder"),
+        }
+    }
+
+    /// What to check when a save that should be in the synced folder isn't there
+    pub fn sync_suggestion(&self) -> String {
+        match self {
+            Provider::Folder => String::from("Is the synced folder available on this computer?"),
+            provider => format!("Is your {provider} client synced?"),
+        }
+    }
+}
+
+impl fmt::Display for Provider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Provider::Dropbox => write!(f, "Dropbox"),
+            Provider::OneDrive => write!(f, "OneDrive"),
+            Provider::GoogleDrive => write!(f, "Google Drive"),
+            Provider::Syncthing => write!(f, "Syncthing"),
+            Provider::Folder => write!(f, "folder"),
+        }
+    }
+}
+
+impl FromStr for Provider {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match normalize(s).as_str() {
+            "dropbox" => Ok(Provider::Dropbox),
+            "onedrive" => Ok(Provider::OneDrive),
+            "googledrive" | "gdrive" | "google" => Ok(Provider::GoogleDrive),
+            "syncthing" => Ok(Provider::Syncthing),
+            "folder" | "nas" | "network" | "other" => Ok(Provider::Folder),
+            _ => anyhow::bail!(
+                "unknown provider {s}, expected one of Dropbox, OneDrive, Google Drive, Syncthing or folder"
+            ),
+        }
+    }
+}
+
+/// How saves reach the other players: through a folder on your computer that a [`Provider`] syncs,
+/// or uploaded by scut itself over the network.
+///
+/// It is the `provider` of the remote in the config, e.g. `provider = "onedrive"` or `provider = "webdav"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum RemoteKind {
+    /// A folder on your computer, synced by its provider
+    Synced(Provider),
+    /// A folder on a WebDAV server, e.g. Nextcloud, uploaded to directly without a sync client
+    WebDav,
+    /// A bucket in S3 or compatible object storage, e.g. MinIO, uploaded to directly without a sync client
+    S3,
+    /// A folder on a server reachable over SSH, e.g. a VPS, uploaded to directly without a sync client
+    Sftp,
+    /// A clone of a git repository, committed to and pushed by scut
+    Git,
+    /// A game on a scut server, see `scut-server`
+    Server,
+    /// A mailing list, that saves are emailed to and read back from an IMAP folder
+    Email,
+}
+
+impl Default for RemoteKind {
+    fn default() -> Self {
+        RemoteKind::Synced(Provider::default())
+    }
+}
+
+impl RemoteKind {
+    /// Returns true if a provider syncs the `folder` on this computer, false if scut uploads to it over the network
+    ///
+    /// A git clone is kept in the `folder`, but scut is the one syncing it with `remote.url`.
+    pub fn is_synced_folder(&self) -> bool {
+        matches!(self, RemoteKind::Synced(_))
+    }
+
+    /// How to refer to where saves are kept in messages, e.g. "your OneDrive folder" or "your S3 bucket"
+    pub fn folder_name(&self) -> String {
+        match self {
+            RemoteKind::Synced(provider) => provider.folder_name(),
+            RemoteKind::S3 => String::from("S3 bucket"),
+            RemoteKind::Git => String::from("git clone"),
+            RemoteKind::Server => String::from("game on the scut server"),
+            kind => format!("{kind} folder"),
+        }
+    }
+
+    /// What to check when a save that should be in remote storage isn't there
+    pub fn sync_suggestion(&self) -> String {
+        match self {
+            RemoteKind::Synced(provider) => provider.sync_suggestion(),
+            RemoteKind::WebDav | RemoteKind::S3 | RemoteKind::Sftp => {
+                format!("Is the {self} server online, and remote.url correct?")
+            }
+            RemoteKind::Server => {
+                String::from("Is the scut server online, and remote.url correct?")
+            }
+            RemoteKind::Email => {
+                String::from("Has the email arrived, and are remote.url and remote.smtp correct?")
+            }
+            RemoteKind::Git => {
+                String::from("Can you reach the repository, and is remote.url correct?")
+            }
+        }
+    }
+}
+
+impl From<Provider> for RemoteKind {
+    fn from(provider: Provider) -> Self {
+        RemoteKind::Synced(provider)
+    }
+}
+
+impl fmt::Display for RemoteKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RemoteKind::Synced(provider) => provider.fmt(f),
+            RemoteKind::WebDav => write!(f, "WebDAV"),
+            RemoteKind::S3 => write!(f, "S3"),
+            RemoteKind::Sftp => write!(f, "SFTP"),
+            RemoteKind::Git => write!(f, "git"),
+            RemoteKind::Server => write!(f, "scut server"),
+            RemoteKind::Email => write!(f, "email"),
+        }
+    }
+}
+
+impl FromStr for RemoteKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match normalize(s).as_str() {
+            "webdav" | "nextcloud" | "owncloud" => Ok(RemoteKind::WebDav),
+            "s3" | "minio" | "aws" => Ok(RemoteKind::S3),
+            "sftp" | "ssh" => Ok(RemoteKind::Sftp),
+            "git" | "github" | "gitlab" => Ok(RemoteKind::Git),
+            "server" | "scutserver" => Ok(RemoteKind::Server),
+            "email" | "mail" | "pbem" | "imap" => Ok(RemoteKind::Email),
+            _ => s.parse().map(RemoteKind::Synced).map_err(|_| {
+                anyhow::anyhow!(
+                    "unknown provider {s}, expected one of Dropbox, OneDrive, Google Drive, Syncthing, folder, WebDAV, S3, SFTP, git, scut server or email"
+                )
+            }),
+        }
+    }
+}
+
+impl TryFrom<String> for RemoteKind {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+/// How the kind is written in the config file
+impl From<RemoteKind> for String {
+    fn from(kind: RemoteKind) -> Self {
+        match kind {
+            RemoteKind::Synced(Provider::Dropbox) => "dropbox",
+            RemoteKind::Synced(Provider::OneDrive) => "onedrive",
+            RemoteKind::Synced(Provider::GoogleDrive) => "googledrive",
+            RemoteKind::Synced(Provider::Syncthing) => "syncthing",
+            RemoteKind::Synced(Provider::Folder) => "folder",
+            RemoteKind::WebDav => "webdav",
+            RemoteKind::S3 => "s3",
+            RemoteKind::Sftp => "sftp",
+            RemoteKind::Git => "git",
+            RemoteKind::Server => "server",
+            RemoteKind::Email => "email",
+        }
+        .to_string()
+    }
+}
+
+/// Lowercase, without spaces, dashes or underscores, so that e.g. `One Drive` and `one-drive` are the same
+fn normalize(s: &str) -> String {
+    s.to_lowercase()
+        .replace(|c: char| c.is_whitespace() || c == '-' || c == '_', "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn provider_round_trip() -> anyhow::Result<()> {
+        let kinds = [
+            RemoteKind::Synced(Provider::Dropbox),
+            RemoteKind::Synced(Provider::OneDrive),
+            RemoteKind::Synced(Provider::GoogleDrive),
+            RemoteKind::Synced(Provider::Syncthing),
+            RemoteKind::Synced(Provider::Folder),
+            RemoteKind::WebDav,
+            RemoteKind::S3,
+            RemoteKind::Sftp,
+            RemoteKind::Git,
+            RemoteKind::Server,
+            RemoteKind::Email,
+        ];
+        for kind in kinds {
+            assert_eq!(kind.to_string().parse::<RemoteKind>()?, kind);
+            assert_eq!(String::from(kind).parse::<RemoteKind>()?, kind);
+        }
+        assert_eq!(
+            "one-drive".parse::<RemoteKind>()?,
+            RemoteKind::Synced(Provider::OneDrive)
+        );
+        assert_eq!("NAS".parse::<Provider>()?, Provider::Folder);
+        assert!("webdav".parse::<Provider>().is_err());
+        assert!("carrier pigeon".parse::<RemoteKind>().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn mirrors_are_configured_like_remote() -> anyhow::Result<()> {
+        #[derive(Deserialize)]
+        struct Mirrors {
+            mirrors: Vec<Mirror>,
+        }
+
+        let Mirrors { mirrors } = toml::from_str(
+            r#"
+            [[mirrors]]
+            provider = "folder"
+            folder = "/mnt/nas/scut"
+
+            [[mirrors]]
+            provider = "onedrive"
+            dropbox = "/home/dm/OneDrive/scut"
+
+            [[mirrors]]
+            provider = "webdav"
+            url = "https://cloud.example.com/scut/"
+            "#,
+        )?;
+
+        assert_eq!(mirrors[0].folder()?, Path::new("/mnt/nas/scut"));
+        // configs written before the folder was called `folder` still work
+        assert_eq!(mirrors[1].folder()?, Path::new("/home/dm/OneDrive/scut"));
+        assert_eq!(mirrors[2].remote.provider, RemoteKind::WebDav);
+        assert_eq!(
+            mirrors[2].describe(),
+            "WebDAV 'https://cloud.example.com/scut/'"
+        );
+        assert!(Mirror::default().folder().is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+
+/// Which saves to keep in remote storage when old turns are pruned, the `[retention]` section of the config.
+///
+/// ```toml
+/// [retention]
+/// keep_turns = 20
+/// keep_every = 10
+/// ```
+///
+/// Every save from the last 20 turns is kept, along with the turn start of every 10th turn.
+/// Older saves are bundled into one archive per 10 turns, or per `bundle_turns`, see [`prune`](crate::prune).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Retention {
+    /// Keep every save from this many of the latest turns
+    pub keep_turns: u32,
+
+    /// Also keep the turn start of every turn that is a multiple of this, e.g. turns 10, 20, 30...
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_every: Option<u32>,
+
+    /// How many turns of older saves to bundle into each archive
+    #[serde(default = "default_bundle_turns")]
+    pub bundle_turns: u32,
+}
+
+fn default_bundle_turns() -> u32 {
+    10
+}
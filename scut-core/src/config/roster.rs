@@ -0,0 +1,204 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Side;
+
+/// The players on each side of the game, identified by how they sign their saves.
+///
+/// Used to work out whose saves should exist for each turn.
+/// A side with no players listed is treated as unknown.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Roster {
+    #[serde(default)]
+    pub axis: Vec<String>,
+    #[serde(default)]
+    pub allies: Vec<String>,
+
+    /// Other names that players have signed their saves with, e.g. before they changed their signature
+    #[serde(default, skip_serializing_if = "Aliases::is_empty")]
+    pub aliases: Aliases,
+
+    /// The order that players on each side play their parts of a turn, when they play it sequentially
+    #[serde(default, skip_serializing_if = "HandoffOrder::is_empty")]
+    pub handoff: HandoffOrder,
+
+    /// The public key each player signs their uploads with, in hex, see [`signature`](crate::signature)
+    ///
+    /// ```toml
+    /// [roster.keys]
+    /// DM = "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c"
+    /// ```
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub keys: BTreeMap<String, String>,
+}
+
+impl Roster {
+    /// The players listed for a side
+    pub fn players(&self, side: Side) -> &[String] {
+        match side {
+            Side::Axis => &self.axis,
+            Side::Allies => &self.allies,
+        }
+    }
+
+    /// Returns true if no players are listed for either side
+    pub fn is_empty(&self) -> bool {
+        self.axis.is_empty()
+            && self.allies.is_empty()
+            && self.aliases.is_empty()
+            && self.handoff.is_empty()
+            && self.keys.is_empty()
+    }
+
+    /// The public key listed for `player`, under any of their names
+    pub fn public_key(&self, player: &str) -> Option<&str> {
+        self.keys
+            .iter()
+            .find(|(name, _)| self.aliases.same_player(name, player))
+            .map(|(_, key)| key.as_str())
+    }
+}
+
+/// The order that players on each side hand off a turn to each other, e.g. DM plays the navy, then DG plays the army.
+///
+/// Each player saves their part of the turn with the part letter for their place in the order, so
+/// the first player saves part A, the second player loads part A and saves part B, and so on.
+///
+/// ```toml
+/// [roster.handoff]
+/// axis = ["DM", "DG"]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HandoffOrder {
+    #[serde(default)]
+    pub axis: Vec<String>,
+    #[serde(default)]
+    pub allies: Vec<String>,
+}
+
+impl HandoffOrder {
+    /// The order that players on a side play their parts, empty if the side doesn't hand off
+    pub fn order(&self, side: Side) -> &[String] {
+        match side {
+            Side::Axis => &self.axis,
+            Side::Allies => &self.allies,
+        }
+    }
+
+    /// The part letter that `player` saves their part of the turn as, if they are in the handoff order for `side`
+    pub fn part(&self, side: Side, player: &str, aliases: &Aliases) -> Option<String> {
+        self.order(side)
+            .iter()
+            .position(|p| aliases.same_player(p, player))
+            .map(part_letter)
+    }
+
+    /// Returns true if neither side hands off
+    pub fn is_empty(&self) -> bool {
+        self.axis.is_empty() && self.allies.is_empty()
+    }
+}
+
+/// The part letter for a step in the handoff order: A, B, C... Z, then AA, AB and so on, like spreadsheet columns
+pub fn part_letter(step: usize) -> String {
+    let mut letters = Vec::new();
+    let mut remaining = step + 1;
+    while remaining > 0 {
+        remaining -= 1;
+        letters.push(char::from(b'A' + (remaining % 26) as u8));
+        remaining /= 26;
+    }
+    letters.into_iter().rev().collect()
+}
+
+/// The other names each player has signed their saves with, keyed by their current name.
+///
+/// ```toml
+/// [roster.aliases]
+/// GMa = ["GM"]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Aliases(BTreeMap<String, Vec<String>>);
+
+impl Aliases {
+    /// Record that `player` has also signed their saves as `alias`
+    pub fn insert<S, T>(&mut self, player: S, alias: T)
+    where
+        S: Into<String>,
+        T: Into<String>,
+    {
+        self.0.entry(player.into()).or_default().push(alias.into());
+    }
+
+    /// The current name of the player who signed their saves as `name`.
+    ///
+    /// Names that aren't an alias are returned unchanged.
+    pub fn canonical<'a>(&'a self, name: &'a str) -> &'a str {
+        self.0
+            .iter()
+            .find(|(_, aliases)| aliases.iter().any(|alias| alias == name))
+            .map(|(player, _)| player.as_str())
+            .unwrap_or(name)
+    }
+
+    /// Returns true if `a` and `b` are names of the same player
+    pub fn same_player(&self, a: &str, b: &str) -> bool {
+        self.canonical(a) == self.canonical(b)
+    }
+
+    /// Returns true if no aliases are listed
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aliases_resolve_to_current_name() {
+        let mut aliases = Aliases::default();
+        aliases.insert("GMa", "GM");
+        aliases.insert("GMa", "Gm");
+
+        assert_eq!(aliases.canonical("GM"), "GMa");
+        assert_eq!(aliases.canonical("Gm"), "GMa");
+        assert_eq!(aliases.canonical("GMa"), "GMa");
+        assert_eq!(aliases.canonical("TG"), "TG");
+
+        assert!(aliases.same_player("GM", "Gm"));
+        assert!(!aliases.same_player("GM", "TG"));
+    }
+
+    #[test]
+    fn handoff_parts_follow_the_order() {
+        let handoff = HandoffOrder {
+            axis: vec!["DM".to_string(), "DG".to_string()],
+            allies: Vec::new(),
+        };
+        let aliases = Aliases::default();
+
+        assert_eq!(
+            handoff.part(Side::Axis, "DM", &aliases),
+            Some("A".to_string())
+        );
+        assert_eq!(
+            handoff.part(Side::Axis, "DG", &aliases),
+            Some("B".to_string())
+        );
+        assert_eq!(handoff.part(Side::Axis, "XX", &aliases), None);
+        assert_eq!(handoff.part(Side::Allies, "DM", &aliases), None);
+    }
+
+    #[test]
+    fn part_letters_go_past_z() {
+        assert_eq!(part_letter(0), "A");
+        assert_eq!(part_letter(25), "Z");
+        assert_eq!(part_letter(26), "AA");
+        assert_eq!(part_letter(27), "AB");
+        assert_eq!(part_letter(26 + 26 * 26), "AAA");
+    }
+}
@@ -3,23 +3,51 @@ use std::{fmt, path::PathBuf};
 use parsely::{switch, token, Parse};
 use serde::{Deserialize, Serialize};
 
-use crate::{error::ErrorSuggestions, Key, Side};
+use crate::{error::ErrorSuggestions, Key, RemoteKind, Side};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Setting {
-    Dropbox(PathBuf),
+    Folder(PathBuf),
+    Provider(RemoteKind),
+    RemoteUrl(Option<String>),
+    RemoteUsername(Option<String>),
+    RemotePassword(Option<String>),
+    RemoteKey(Option<PathBuf>),
+    RemoteSmtp(Option<String>),
+    RemoteMailingList(Option<String>),
+    SharedFolder(Option<PathBuf>),
+    TeamPassphrase(Option<String>),
+    GamePassphrase(Option<String>),
     Saves(PathBuf),
     SevenZipPath(PathBuf),
     Side(Side),
     Player(String),
     Turn(Option<u32>),
     Solo(Option<bool>),
+    ActingAs(Option<String>),
+    Observer(Option<bool>),
+    ObservePlayerSaves(Option<bool>),
 }
 
+/// Passwords and passphrases are shown as `***` if they are set, like in debug output, so that they don't end up on screen
 impl fmt::Display for Setting {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Setting::Dropbox(value) => value.display().fmt(f),
+            Setting::RemotePassword(value)
+            | Setting::TeamPassphrase(value)
+            | Setting::GamePassphrase(value) => match super::redacted(value) {
+                Some(redacted) => redacted.fmt(f),
+                None => write!(f, "None"),
+            },
+            Setting::Folder(value) => value.display().fmt(f),
+            Setting::Provider(value) => value.fmt(f),
+            Setting::RemoteKey(value) | Setting::SharedFolder(value) => {
+                if let Some(path) = value {
+                    path.display().fmt(f)
+                } else {
+                    write!(f, "None")
+                }
+            }
             Setting::Saves(value) => value.display().fmt(f),
             Setting::SevenZipPath(value) => value.display().fmt(f),
             Setting::Side(value) => value.fmt(f),
@@ -32,6 +60,19 @@ impl fmt::Display for Setting {
                 }
             }
             Setting::Solo(value) => value.unwrap_or_default().fmt(f),
+            Setting::Observer(value) => value.unwrap_or_default().fmt(f),
+            Setting::ObservePlayerSaves(value) => value.unwrap_or_default().fmt(f),
+            Setting::ActingAs(value)
+            | Setting::RemoteUrl(value)
+            | Setting::RemoteUsername(value)
+            | Setting::RemoteSmtp(value)
+            | Setting::RemoteMailingList(value) => {
+                if let Some(text) = value {
+                    text.fmt(f)
+                } else {
+                    write!(f, "None")
+                }
+            }
         }
     }
 }
@@ -39,7 +80,15 @@ impl fmt::Display for Setting {
 impl Setting {
     pub fn new(key: Key, value: String) -> anyhow::Result<Self> {
         match key {
-            Key::Dropbox => Ok(Setting::Dropbox(value.into())),
+            Key::Folder => Ok(Setting::Folder(value.into())),
+            Key::Provider => Ok(Setting::Provider(value.parse()?)),
+            Key::RemoteUrl => Ok(Setting::RemoteUrl(optional(value))),
+            Key::RemoteUsername => Ok(Setting::RemoteUsername(optional(value))),
+            Key::RemotePassword => Ok(Setting::RemotePassword(optional(value))),
+            Key::RemoteKey => Ok(Setting::RemoteKey(optional(value).map(PathBuf::from))),
+            Key::RemoteSmtp => Ok(Setting::RemoteSmtp(optional(value))),
+            Key::RemoteMailingList => Ok(Setting::RemoteMailingList(optional(value))),
+            Key::SharedFolder => Ok(Setting::SharedFolder(optional(value).map(PathBuf::from))),
             Key::Saves => Ok(Setting::Saves(value.into())),
             Key::SevenZipPath => Ok(Setting::SevenZipPath(value.into())),
             Key::Side => Ok(Setting::Side(value.parse()?)),
@@ -53,28 +102,47 @@ impl Setting {
                         u32::MAX
                     ))?,
             ))),
-            Key::Solo => Ok(Setting::Solo(Some({
-                switch([
-                    (token("true").any_case(), true),
-                    (token("yes").any_case(), true),
-                    (token("y").any_case(), true),
-                    (token("1").any_case(), true),
-                    (token("false").any_case(), false),
-                    (token("no").any_case(), false),
-                    (token("n").any_case(), false),
-                    (token("0").any_case(), false),
-                ])
-                .parse(&value)
-                .map_err(|_| anyhow::anyhow!("`{value}` is not a valid boolean"))
-                .and_then(|(parsed, remaining)| {
-                    if remaining.is_empty() {
-                        Ok(parsed)
-                    } else {
-                        Err(anyhow::anyhow!("`{value}` is not a valid boolean"))
-                    }
-                })
-                .suggest("config.solo should be set to 'true' or 'false'")?
-            }))),
+            Key::Solo => Ok(Setting::Solo(Some(parse_bool(&value, key)?))),
+            Key::Observer => Ok(Setting::Observer(Some(parse_bool(&value, key)?))),
+            Key::ObservePlayerSaves => {
+                Ok(Setting::ObservePlayerSaves(Some(parse_bool(&value, key)?)))
+            }
+            Key::ActingAs => Ok(Setting::ActingAs(optional(value))),
+            Key::TeamPassphrase => Ok(Setting::TeamPassphrase(optional(value))),
+            Key::GamePassphrase => Ok(Setting::GamePassphrase(optional(value))),
         }
     }
 }
+
+/// Parse an optional setting, where an empty value or `none` unsets it
+fn optional(value: String) -> Option<String> {
+    match value.as_str() {
+        "" => None,
+        none if none.eq_ignore_ascii_case("none") => None,
+        _ => Some(value),
+    }
+}
+
+/// Parse a boolean setting, accepting true/false, yes/no, y/n and 1/0 in any case
+fn parse_bool(value: &str, key: Key) -> anyhow::Result<bool> {
+    Ok(switch([
+        (token("true").any_case(), true),
+        (token("yes").any_case(), true),
+        (token("y").any_case(), true),
+        (token("1").any_case(), true),
+        (token("false").any_case(), false),
+        (token("no").any_case(), false),
+        (token("n").any_case(), false),
+        (token("0").any_case(), false),
+    ])
+    .parse(value)
+    .map_err(|_| anyhow::anyhow!("`{value}` is not a valid boolean"))
+    .and_then(|(parsed, remaining)| {
+        if remaining.is_empty() {
+            Ok(parsed)
+        } else {
+            Err(anyhow::anyhow!("`{value}` is not a valid boolean"))
+        }
+    })
+    .suggest(format!("config.{key} should be set to 'true' or 'false'"))?)
+}
@@ -0,0 +1,45 @@
+use std::{fs, path::Path};
+
+use super::Compression;
+
+/// Copies files instead of compressing them, so that tests can check what arrives in remote storage
+///
+/// Folders are bundled into a `.copies` file, which lists the name and content of each file on a line each.
+#[derive(Debug, Clone)]
+pub struct CopyCompression;
+
+impl Compression for CopyCompression {
+    fn compress(&self, from: &Path, to: &Path) -> anyhow::Result<()> {
+        if !from.is_dir() {
+            fs::copy(from, to.with_extension("copy"))?;
+            return Ok(());
+        }
+
+        let mut bundle = String::new();
+        for entry in fs::read_dir(from)? {
+            let path = entry?.path();
+            let name = path.file_name().expect("file has a name").to_string_lossy();
+            bundle.push_str(&format!("{name}\n{}\n", fs::read_to_string(&path)?));
+        }
+        fs::write(to.with_extension("copies"), bundle)?;
+        Ok(())
+    }
+
+    fn decompress(&self, from: &Path, to: &Path) -> anyhow::Result<()> {
+        if from
+            .extension()
+            .is_some_and(|extension| extension == "copies")
+        {
+            let bundle = fs::read_to_string(from)?;
+            let mut lines = bundle.lines();
+            while let (Some(name), Some(content)) = (lines.next(), lines.next()) {
+                fs::write(to.join(name), content)?;
+            }
+            return Ok(());
+        }
+
+        let name = from.with_extension("sav");
+        fs::copy(from, to.join(name.file_name().expect("archive has a name")))?;
+        Ok(())
+    }
+}
@@ -9,6 +9,8 @@ pub use seven_zip::SevenZipCompression;
 
 use dyn_clone::DynClone;
 
+#[cfg(any(test, feature = "test-support"))]
+pub mod copy_compression;
 #[cfg(test)]
 pub mod mock_compression;
 
@@ -17,7 +19,7 @@ use std::path::Path;
 pub trait Compression: DynClone + Send + Sync {
     /// Compress the file located at `from` and save the resulting compressed file at `to`.
     ///
-    /// * `from` must be a full path including filename and extension.
+    /// * `from` must be a full path including filename and extension, or a folder to bundle every file in it into one archive.
     /// * `to` must include the filename and must not include the extension.
     ///
     /// The implementation will add the specific extension if needed.
@@ -30,6 +32,13 @@ pub trait Compression: DynClone + Send + Sync {
     ///
     /// The implementation will add the specific extension if needed.
     fn decompress(&self, from: &Path, to: &Path) -> anyhow::Result<()>;
+
+    /// Check that the archive at `archive` is complete, e.g. that it wasn't cut short while it was still being written or synced.
+    ///
+    /// The default implementation trusts every archive.
+    fn check(&self, _archive: &Path) -> anyhow::Result<()> {
+        Ok(())
+    }
 }
 
 impl Clone for Box<dyn Compression> {
@@ -1,30 +1,140 @@
+//! Saves are compressed into 7z archives by the 7-Zip command line program.
+//!
+//! If a passphrase is set, archives are encrypted using 7-Zip's own encryption, so that they can be checked offline with 7-Zip,
+//! e.g. `7z t "Axis DM 5.7z"`, which asks for the passphrase. Turn starts, which are shared with the enemy team,
+//! are encrypted with a passphrase of their own:
+//!
+//! * the content and the names of the files in the archive are encrypted with AES-256 in CBC mode (`-mhe=on`)
+//! * the key is derived from the passphrase by hashing a salt and the passphrase, encoded as UTF-16LE, with SHA-256 2^19 times
+//!
+//! The passphrase is typed in to 7-Zip when it asks for it, rather than passed on the command line where other users could see it.
+//! 7-Zip reports "Wrong password" if an archive can't be decrypted with the passphrase.
+//!
+//! Before an archive is decompressed, its start header is checked to make sure the archive is complete:
+//! 7-Zip writes the header last, and it records where the archive should end.
+
 use std::{
-    os::windows::process::CommandExt,
+    fmt,
+    fs::File,
+    io::{Read, Write},
     path::{Path, PathBuf},
-    process::Command,
+    process::{Command, Output, Stdio},
 };
 
-use crate::error::{output_error, path::ErrorPaths};
+use crate::{
+    error::{output_error, path::ErrorPaths, ErrorSuggestions},
+    interface::{process::background, storage::split_remote::is_turn_start},
+    save::path_to_save,
+    Key,
+};
 
 use anyhow::Context;
 use tracing::{debug, instrument};
 
-const CREATE_NO_WINDOW: u32 = 0x08000000;
-
 use super::Compression;
 
+/// The signature that every 7z archive starts with
+const SIGNATURE: [u8; 6] = [b'7', b'z', 0xBC, 0xAF, 0x27, 0x1C];
+
+/// The size of the start header: the signature, version and CRC, then the offset, size and CRC of the header at the end of the archive
+const START_HEADER_SIZE: u64 = 32;
+
 /// An implementation of Compression using 7z
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct SevenZipCompression {
     seven_zip_path: PathBuf,
+    passphrase: Option<(String, Key)>,
+    turn_start_passphrase: Option<(String, Key)>,
 }
 
 impl SevenZipCompression {
     pub fn new(seven_zip_path: &Path) -> Self {
         SevenZipCompression {
             seven_zip_path: seven_zip_path.to_path_buf(),
+            passphrase: None,
+            turn_start_passphrase: None,
         }
     }
+
+    /// Builder method to encrypt archives with a passphrase, read from the config setting `key`
+    pub fn passphrase(mut self, passphrase: Option<String>, key: Key) -> Self {
+        self.passphrase = passphrase.map(|passphrase| (passphrase, key));
+        self
+    }
+
+    /// Builder method to encrypt turn starts, which are shared with the enemy team, with a passphrase of their own,
+    /// read from the config setting `key`
+    pub fn turn_start_passphrase(mut self, passphrase: Option<String>, key: Key) -> Self {
+        self.turn_start_passphrase = passphrase.map(|passphrase| (passphrase, key));
+        self
+    }
+
+    /// The passphrase for the save in the archive at `path`, which is named after the save
+    fn passphrase_for(&self, path: &Path) -> Option<&(String, Key)> {
+        match path_to_save(path) {
+            Some(save) if is_turn_start(&save) => self.turn_start_passphrase.as_ref(),
+            _ => self.passphrase.as_ref(),
+        }
+    }
+
+    /// Run 7-Zip, typing in the passphrase if there is one
+    fn run(
+        &self,
+        command: &mut Command,
+        passphrase: Option<&(String, Key)>,
+    ) -> std::io::Result<Output> {
+        let Some((passphrase, _)) = passphrase else {
+            return command.output();
+        };
+
+        // `-p` on its own makes 7-Zip ask for the passphrase, then ask again to verify it when compressing
+        let mut child = command
+            .arg("-p")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            // 7-Zip might exit without asking, e.g. if the archive doesn't exist, which its output explains
+            let _ = write!(stdin, "{passphrase}\n{passphrase}\n");
+        }
+        child.wait_with_output()
+    }
+
+    /// Suggest how to fix a passphrase that 7-Zip couldn't decrypt an archive with
+    fn passphrase_suggestion(passphrase: Option<&(String, Key)>) -> String {
+        match passphrase {
+            Some((_, key)) => {
+                format!("Check that config.{key} is the same passphrase that everyone else uses")
+            }
+            None => format!(
+                "This save is encrypted, ask for the passphrase and set config.{} or config.{}",
+                Key::TeamPassphrase,
+                Key::GamePassphrase
+            ),
+        }
+    }
+}
+
+/// Shows which setting the passphrase is read from, never the passphrase itself
+impl fmt::Debug for SevenZipCompression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SevenZipCompression")
+            .field("seven_zip_path", &self.seven_zip_path)
+            .field("passphrase", &self.passphrase.as_ref().map(|(_, key)| key))
+            .field(
+                "turn_start_passphrase",
+                &self.turn_start_passphrase.as_ref().map(|(_, key)| key),
+            )
+            .finish()
+    }
+}
+
+/// Returns true if 7-Zip failed because an archive was encrypted with a different passphrase, or none was given
+fn is_wrong_passphrase(output: &Output) -> bool {
+    [&output.stdout, &output.stderr]
+        .into_iter()
+        .any(|text| String::from_utf8_lossy(text).contains("Wrong password"))
 }
 
 impl Compression for SevenZipCompression {
@@ -34,16 +144,20 @@ impl Compression for SevenZipCompression {
 
         debug!(?from, ?to, PATH = path.to_str(), "compressing");
 
-        let mut command = Command::new("7z");
+        let mut command = background("7z");
         command
-            .creation_flags(CREATE_NO_WINDOW)
             .env("PATH", path.as_os_str())
             .arg("a")
             .arg(to)
             .arg(from);
 
-        let output = command
-            .output()
+        let passphrase = self.passphrase_for(to);
+        if passphrase.is_some() {
+            command.arg("-mhe=on");
+        }
+
+        let output = self
+            .run(&mut command, passphrase)
             // Assumption: user is running windows and should have a 7z.exe file
             .path(path.join("7z.exe"))
             .with_context(|| "failed to run 7zip")?;
@@ -70,16 +184,23 @@ impl Compression for SevenZipCompression {
 
         debug!(?from, ?to, PATH = path.to_str(), "decompressing");
 
-        let mut command = Command::new("7z");
+        let mut command = background("7z");
         command
-            .creation_flags(CREATE_NO_WINDOW)
             .env("PATH", path.as_os_str())
             .arg("e")
             .arg(from)
             .arg(format!("-o{}", to.display()))
             .arg("-y");
 
-        let output = command.output().with_context(|| "failed to run 7zip")?;
+        let passphrase = self.passphrase_for(from);
+        let output = self
+            .run(&mut command, passphrase)
+            .with_context(|| "failed to run 7zip")?;
+
+        if !output.status.success() && is_wrong_passphrase(&output) {
+            return Err(anyhow::anyhow!("failed to decrypt '{}'", from.display()))
+                .suggest(Self::passphrase_suggestion(passphrase))?;
+        }
 
         output_error(&output)
             .with_context(|| {
@@ -98,4 +219,219 @@ impl Compression for SevenZipCompression {
 
         Ok(())
     }
+
+    #[instrument(skip_all, ret, err)]
+    fn check(&self, archive: &Path) -> anyhow::Result<()> {
+        debug!(?archive, "checking");
+
+        let mut file = File::open(archive)
+            .with_context(|| format!("failed to open '{}'", archive.display()))?;
+        let length = file.metadata()?.len();
+
+        let mut header = [0; START_HEADER_SIZE as usize];
+        if file.read_exact(&mut header).is_err() {
+            return incomplete(archive, length);
+        }
+        if header[..SIGNATURE.len()] != SIGNATURE {
+            anyhow::bail!("'{}' is not a 7z archive", archive.display());
+        }
+
+        let offset = u64::from_le_bytes(header[12..20].try_into()?);
+        let size = u64::from_le_bytes(header[20..28].try_into()?);
+
+        // the header is zeroed until 7-Zip finishes writing, only an empty archive has no header
+        let unfinished = size == 0 && length > START_HEADER_SIZE;
+        let end = START_HEADER_SIZE
+            .saturating_add(offset)
+            .saturating_add(size);
+        if unfinished || length < end {
+            return incomplete(archive, length);
+        }
+
+        Ok(())
+    }
+}
+
+/// The error for an archive that was cut short, e.g. because it is still being written or synced
+fn incomplete(archive: &Path, length: u64) -> anyhow::Result<()> {
+    Err(anyhow::anyhow!(
+        "'{}' is incomplete, only {length} bytes have arrived",
+        archive.display()
+    ))
+    .suggest("It might still be uploading or syncing, wait a moment then run scut again")?
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    /// Stands in for 7-Zip, "encrypting" an archive by writing the passphrase it is given before the content,
+    /// and recording its arguments in `args` beside it. It only uses shell builtins, as PATH is the folder it is in
+    #[cfg(unix)]
+    const FAKE_SEVEN_ZIP: &str = r#"#!/bin/sh
+echo "$*" >> "${0%/*}/args"
+IFS= read -r passphrase
+case $1 in
+a)
+    { echo "$passphrase"; while IFS= read -r line; do echo "$line"; done < "$3"; } > "$2"
+    ;;
+e)
+    name=${2##*/}
+    {
+        IFS= read -r expected
+        if [ "$passphrase" != "$expected" ]; then
+            echo "ERROR: $2 : Wrong password"
+            exit 2
+        fi
+        while IFS= read -r line; do echo "$line"; done > "${3#-o}/${name%.7z}.sav"
+    } < "$2"
+    ;;
+esac
+"#;
+
+    /// A folder with the fake 7-Zip in it
+    #[cfg(unix)]
+    fn fake_seven_zip() -> anyhow::Result<tempfile::TempDir> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("7z");
+        fs::write(&path, FAKE_SEVEN_ZIP)?;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+        Ok(dir)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn seven_zip_types_in_the_passphrase() -> anyhow::Result<()> {
+        let seven_zip = fake_seven_zip()?;
+        let dir = tempfile::tempdir()?;
+        let save = dir.path().join("Axis DM 5.sav");
+        let archive = dir.path().join("Axis DM 5.7z");
+        fs::write(&save, "DM's turn\n")?;
+
+        let compression = SevenZipCompression::new(seven_zip.path())
+            .passphrase(Some("hunter2".to_string()), Key::TeamPassphrase);
+        compression.compress(&save, &archive)?;
+        fs::remove_file(&save)?;
+        compression.decompress(&archive, dir.path())?;
+        assert_eq!(fs::read_to_string(&save)?, "DM's turn\n");
+
+        let args = fs::read_to_string(seven_zip.path().join("args"))?;
+        assert!(args.contains("-mhe=on"));
+        assert!(!args.contains("hunter2"));
+        assert!(!format!("{compression:?}").contains("hunter2"));
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn seven_zip_encrypts_turn_starts_with_their_own_passphrase() -> anyhow::Result<()> {
+        let seven_zip = fake_seven_zip()?;
+        let dir = tempfile::tempdir()?;
+        let save = dir.path().join("Allies 5.sav");
+        let archive = dir.path().join("Allies 5.7z");
+        fs::write(&save, "end of Axis 5\n")?;
+
+        SevenZipCompression::new(seven_zip.path())
+            .passphrase(Some("hunter2".to_string()), Key::TeamPassphrase)
+            .turn_start_passphrase(Some("everyone".to_string()), Key::GamePassphrase)
+            .compress(&save, &archive)?;
+        fs::remove_file(&save)?;
+
+        // the enemy team only knows the game passphrase
+        SevenZipCompression::new(seven_zip.path())
+            .turn_start_passphrase(Some("everyone".to_string()), Key::GamePassphrase)
+            .decompress(&archive, dir.path())?;
+        assert_eq!(fs::read_to_string(&save)?, "end of Axis 5\n");
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn seven_zip_reports_a_wrong_passphrase() -> anyhow::Result<()> {
+        let seven_zip = fake_seven_zip()?;
+        let dir = tempfile::tempdir()?;
+        let save = dir.path().join("Axis DM 5.sav");
+        let archive = dir.path().join("Axis DM 5.7z");
+        fs::write(&save, "DM's turn\n")?;
+
+        SevenZipCompression::new(seven_zip.path())
+            .passphrase(Some("hunter2".to_string()), Key::GamePassphrase)
+            .compress(&save, &archive)?;
+
+        for compression in [
+            SevenZipCompression::new(seven_zip.path())
+                .passphrase(Some("hunter3".to_string()), Key::GamePassphrase),
+            SevenZipCompression::new(seven_zip.path()),
+        ] {
+            let error = compression
+                .decompress(&archive, dir.path())
+                .expect_err("the passphrase is wrong");
+            assert!(format!("{error:?}").contains("failed to decrypt"));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn wrong_passwords_are_recognised() {
+        let output = |stderr: &str| Output {
+            status: Default::default(),
+            stdout: b"7-Zip 23.01 (x64)".to_vec(),
+            stderr: stderr.as_bytes().to_vec(),
+        };
+
+        assert!(is_wrong_passphrase(&output(
+            "ERROR: Axis DM 5.7z : Wrong password"
+        )));
+        assert!(!is_wrong_passphrase(&output(
+            "ERROR: Axis DM 5.7z : Can not open the file as archive"
+        )));
+    }
+
+    /// A start header for an archive whose header is `size` bytes long, `offset` bytes after the start header
+    fn start_header(offset: u64, size: u64) -> Vec<u8> {
+        let mut header = SIGNATURE.to_vec();
+        header.extend([0, 4, 0, 0, 0, 0]);
+        header.extend(offset.to_le_bytes());
+        header.extend(size.to_le_bytes());
+        header.extend([0; 4]);
+        header
+    }
+
+    #[test]
+    fn seven_zip_checks_archives_are_complete() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let archive = dir.path().join("Axis DM 1.7z");
+        let compression = SevenZipCompression::new(Path::new("7z"));
+
+        let mut complete = start_header(10, 5);
+        complete.extend([1; 15]);
+        fs::write(&archive, &complete)?;
+        compression.check(&archive)?;
+
+        // cut short while syncing
+        fs::write(&archive, &complete[..40])?;
+        assert!(compression.check(&archive).is_err());
+
+        // still being written by 7-Zip
+        let mut unfinished = start_header(0, 0);
+        unfinished.extend([1; 15]);
+        fs::write(&archive, &unfinished)?;
+        assert!(compression.check(&archive).is_err());
+
+        // empty
+        fs::write(&archive, start_header(0, 0))?;
+        compression.check(&archive)?;
+
+        fs::write(&archive, "not an archive at all, but long enough")?;
+        assert!(compression.check(&archive).is_err());
+
+        Ok(())
+    }
 }
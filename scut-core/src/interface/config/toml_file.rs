@@ -9,6 +9,7 @@ use crate::interface::file_system::is_not_found_err;
 use crate::interface::{
     user_interaction::query_and_parse, ConfigPersistence, FileSystem, UserInteraction,
 };
+use crate::{Provider, Remote, RemoteKind, Roster};
 
 use super::{Config, ConfigInit, ConfigService};
 
@@ -79,14 +80,89 @@ impl TomlFileConfig {
     fn init_config(&mut self) -> anyhow::Result<Config> {
         let ui = &mut *self.user_interaction;
 
-        let dropbox_result = dropbox_dir::personal_dir();
+        // Dropbox is used unless it isn't installed, then the player chooses what else syncs their saves
+        let (provider, sync_root) = match detect_sync_root(Provider::Dropbox) {
+            Some(sync_root) => (RemoteKind::Synced(Provider::Dropbox), Some(sync_root)),
+            None => {
+                let provider = query_and_parse(
+                    "Which program shares your saves with the other players? \
+                    (Dropbox, OneDrive, Google Drive, Syncthing or folder)",
+                    ui,
+                )
+                .unwrap_or_default();
+                let sync_root = match provider {
+                    RemoteKind::Synced(provider) => detect_sync_root(provider),
+                    _ => None,
+                };
+                (provider, sync_root)
+            }
+        };
 
-        let dropbox = match dropbox_result {
-            Ok(dropbox) => dropbox,
-            Err(_) => ask_player_for_dropbox_folder(ui)
-                .context("Dropbox folder configuration is missing")?,
-        }
-        .into();
+        let mut remote = Remote {
+            provider,
+            ..Remote::default()
+        };
+
+        let folder = match sync_root {
+            Some(sync_root) => sync_root,
+            // there's no folder to sync, saves are uploaded straight to the server
+            None if !provider.is_synced_folder() => {
+                let url = match provider {
+                    RemoteKind::Git => "What is the url of the git repository?".to_string(),
+                    RemoteKind::Email => {
+                        "What is the url of the IMAP folder that the game's emails arrive in?"
+                            .to_string()
+                    }
+                    provider => format!("What is the url of your {}?", provider.folder_name()),
+                };
+                remote.url = Some(ui.query(&url));
+                match provider {
+                    // git logs in however it is set up to, and the saves are kept in a clone on this computer
+                    RemoteKind::Git => ui
+                        .query("Which folder should the repository be cloned into?")
+                        .into(),
+                    // the server knows who you are from your token
+                    RemoteKind::Server => {
+                        remote.password =
+                            Some(ui.query("What token did the server's admin give you?"));
+                        PathBuf::new()
+                    }
+                    // emails are sent through SMTP and read back over IMAP, logging in to both as you
+                    RemoteKind::Email => {
+                        remote.smtp =
+                            Some(ui.query("What is the url of your email provider's SMTP server?"));
+                        remote.mailing_list =
+                            Some(ui.query("What is the address of the game's mailing list?"));
+                        remote.username = Some(ui.query("What email address do you log in with?"));
+                        remote.password = Some(ui.query("What password do you log in with?"));
+                        PathBuf::new()
+                    }
+                    RemoteKind::S3 => {
+                        remote.username = Some(ui.query("What access key id do you log in with?"));
+                        remote.password =
+                            Some(ui.query("What secret access key do you log in with?"));
+                        PathBuf::new()
+                    }
+                    // SSH logs in with a key rather than a password
+                    RemoteKind::Sftp => {
+                        remote.username = Some(ui.query("What username do you log in with?"));
+                        remote.key = Some(
+                            ui.query("Where is the SSH private key you log in with?")
+                                .into(),
+                        );
+                        PathBuf::new()
+                    }
+                    _ => {
+                        remote.username = Some(ui.query("What username do you log in with?"));
+                        remote.password = Some(ui.query("What password do you log in with?"));
+                        PathBuf::new()
+                    }
+                }
+            }
+            None => ask_player_for_synced_folder(provider, ui)
+                .with_context(|| format!("{} configuration is missing", provider.folder_name()))?
+                .into(),
+        };
 
         let home = dirs::home_dir().context("Unable to find your documents folder")?;
         let saves = home
@@ -103,13 +179,23 @@ impl TomlFileConfig {
         let solo = Some(false);
 
         Ok(Config {
-            dropbox,
+            folder,
             saves,
             seven_zip_path,
+            remote,
+            mirrors: Vec::new(),
+            retention: None,
+            shared_folder: None,
+            team_passphrase: None,
+            game_passphrase: None,
             side,
             player,
             turn,
             solo,
+            roster: Roster::default(),
+            acting_as: None,
+            observer: None,
+            observe_player_saves: None,
         })
     }
 }
@@ -164,16 +250,59 @@ impl ConfigInit for TomlFileConfig {
 
 impl ConfigService for TomlFileConfig {}
 
+/// Find the folder that `provider` syncs on this computer, if it is where the provider usually puts it
+#[instrument(ret)]
+fn detect_sync_root(provider: Provider) -> Option<PathBuf> {
+    let home = dirs::home_dir();
+
+    let candidates: Vec<PathBuf> = match provider {
+        Provider::Dropbox => dropbox_dir::personal_dir()
+            .ok()
+            .map(PathBuf::from)
+            .into_iter()
+            .collect(),
+        // the OneDrive client sets this on Windows
+        Provider::OneDrive => std::env::var_os("OneDrive")
+            .map(PathBuf::from)
+            .into_iter()
+            .chain(home.map(|home| home.join("OneDrive")))
+            .collect(),
+        // Google Drive for desktop mounts itself as a drive
+        Provider::GoogleDrive => vec![PathBuf::from(r"G:\My Drive")]
+            .into_iter()
+            .chain(home.map(|home| home.join("Google Drive")))
+            .collect(),
+        Provider::Syncthing => home.map(|home| home.join("Sync")).into_iter().collect(),
+        Provider::Folder => Vec::new(),
+    };
+
+    candidates.into_iter().find(|path| path.is_dir())
+}
+
 #[instrument(skip_all, ret)]
-fn ask_player_for_dropbox_folder(ui: &mut dyn UserInteraction) -> Option<String> {
-    ui.message("Unable to find your dropbox folder");
-    ui.message("You may not have the dropbox client installed. This is required to use scut.");
-    ui.message("If you have installed the dropbox client, then you can enter your dropbox folder to continue.");
-    if !ui.confirm("Would you like to enter your dropbox folder?", Some(true)) {
+fn ask_player_for_synced_folder(
+    provider: RemoteKind,
+    ui: &mut dyn UserInteraction,
+) -> Option<String> {
+    let folder = provider.folder_name();
+
+    ui.message(&format!("Unable to find your {folder}"));
+    if provider != RemoteKind::Synced(Provider::Folder) {
+        ui.message(&format!(
+            "You may not have the {provider} client installed."
+        ));
+    }
+    ui.message(&format!(
+        "If you know where your {folder} is, then you can enter it to continue."
+    ));
+    if !ui.confirm(
+        &format!("Would you like to enter your {folder}?"),
+        Some(true),
+    ) {
         return None;
     }
     loop {
-        let dropbox = ui.query("Please enter the absolute path to your dropbox folder");
+        let dropbox = ui.query(&format!("Please enter the absolute path to your {folder}"));
 
         if dropbox.is_empty() {
             ui.message("That's not a valid path");
@@ -1,13 +1,14 @@
 use std::{
     fs::{self},
-    io,
+    io::{self, Write},
     path::{Path, PathBuf},
+    time::UNIX_EPOCH,
 };
 
 use anyhow::Context;
 use tracing::instrument;
 
-use super::FileSystem;
+use super::{FileMetadata, FileStat, FileSystem};
 
 #[derive(Debug, Clone)]
 pub struct LocalFileSystem;
@@ -53,6 +54,72 @@ impl FileSystem for LocalFileSystem {
         fs::read_to_string(path)
             .with_context(|| format!("failed to read from file: '{}'", path.display()))
     }
+
+    #[instrument(level = "TRACE", skip(self, content), err)]
+    fn write_file(&mut self, content: &[u8], path: &Path) -> anyhow::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).with_context(|| {
+                format!("failed to create parent directory: '{}'", dir.display())
+            })?;
+        }
+
+        fs::File::create(path)
+            .and_then(|mut file| {
+                file.write_all(content)?;
+                file.sync_all()
+            })
+            .with_context(|| format!("failed to write to file: '{}'", path.display()))
+    }
+
+    #[instrument(level = "TRACE", skip(self), ret, err)]
+    fn move_file(&mut self, from: &Path, to: &Path) -> anyhow::Result<()> {
+        if let Some(dir) = to.parent() {
+            fs::create_dir_all(dir).with_context(|| {
+                format!("failed to create parent directory: '{}'", dir.display())
+            })?;
+        }
+
+        fs::rename(from, to).with_context(|| {
+            format!(
+                "failed to move file: '{}' to '{}'",
+                from.display(),
+                to.display()
+            )
+        })
+    }
+
+    #[instrument(level = "TRACE", skip(self), ret, err)]
+    fn metadata(&mut self, path: &Path) -> anyhow::Result<FileMetadata> {
+        let content =
+            fs::read(path).with_context(|| format!("failed to read file: '{}'", path.display()))?;
+
+        let FileStat { modified, .. } = self.stat(path)?;
+
+        Ok(FileMetadata::new(&content, modified))
+    }
+
+    #[instrument(level = "TRACE", skip(self), ret, err)]
+    fn stat(&mut self, path: &Path) -> anyhow::Result<FileStat> {
+        let metadata = fs::metadata(path)
+            .with_context(|| format!("failed to read file: '{}'", path.display()))?;
+
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_millis() as u64);
+
+        Ok(FileStat {
+            size: metadata.len(),
+            modified,
+        })
+    }
+
+    #[instrument(level = "TRACE", skip(self), ret, err)]
+    fn remove_file(&mut self, path: &Path) -> anyhow::Result<()> {
+        fs::remove_file(path)
+            .with_context(|| format!("failed to remove file: '{}'", path.display()))
+    }
 }
 
 fn entry_to_path_if_file(
@@ -108,6 +175,17 @@ mod tests {
             "test content"
         );
 
+        let moved_path = tmpdir.path().join("folder2").join("file3");
+        local_file_system.move_file(&file3_path, &moved_path)?;
+        assert!(
+            !local_file_system.file_exists(&file3_path)?,
+            "file should have moved"
+        );
+        assert_eq!(
+            local_file_system.read_file_to_string(&moved_path)?,
+            "test content"
+        );
+
         Ok(())
     }
 }
@@ -7,7 +7,7 @@ use std::{
 
 use crate::error::testing_error::MockError;
 
-use super::FileSystem;
+use super::{FileMetadata, FileStat, FileSystem};
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum Status {
@@ -67,6 +67,21 @@ pub enum Event {
     /// contains: path of the file, its status
     ReadFileToString(PathBuf, Status),
 
+    /// contains: path of the file, its status, how many bytes were written
+    WriteFile(PathBuf, Status, usize),
+
+    /// contains: path of the file, where it was moved to, its status
+    MoveFile(PathBuf, PathBuf, Status),
+
+    /// contains: path of the file, its status
+    Metadata(PathBuf, Status),
+
+    /// contains: path of the file, its status
+    Stat(PathBuf, Status),
+
+    /// contains: path of the file, its status
+    RemoveFile(PathBuf, Status),
+
     /// contains: path of the relevant thing, a message
     TestFailure(PathBuf, String),
 }
@@ -390,6 +405,128 @@ impl FileSystem for MockFileSystem {
             ),
         }
     }
+
+    fn write_file(&mut self, content: &[u8], path: &Path) -> anyhow::Result<()> {
+        match self.objects.get(path) {
+            Some(Object::File(f)) => {
+                let status = f.status;
+                println!("{}", Event::WriteFile(path.into(), status, content.len()));
+                match status {
+                    Status::Exists => Ok(()),
+                    status @ Status::Missing | status @ Status::Error => {
+                        Err(MockError::new(status))?
+                    }
+                }
+            }
+            _ => panic!(
+                "'{}' should be a file in mock filesystem: {self:?}",
+                path.display()
+            ),
+        }
+    }
+
+    fn move_file(&mut self, from: &Path, to: &Path) -> anyhow::Result<()> {
+        let file = match self.objects.get(from) {
+            Some(Object::File(f)) => f.clone(),
+            _ => panic!(
+                "'{}' should be a file in mock filesystem: {self:?}",
+                from.display()
+            ),
+        };
+
+        println!("{}", Event::MoveFile(from.into(), to.into(), file.status));
+
+        match file.status {
+            Status::Exists => {
+                self.objects.remove(from);
+                for object in self.objects.values_mut() {
+                    if let Object::Folder(folder) = object {
+                        folder.files.retain(|f| f.path != from);
+                    }
+                }
+
+                let moved = File::new(to.to_path_buf(), Status::Exists, file.content);
+                if let Some(Object::Folder(folder)) =
+                    to.parent().and_then(|parent| self.objects.get_mut(parent))
+                {
+                    folder.files.push(moved.clone());
+                }
+                self.objects.insert(to.to_path_buf(), Object::File(moved));
+
+                Ok(())
+            }
+            status @ Status::Missing | status @ Status::Error => Err(MockError::new(status))?,
+        }
+    }
+
+    fn metadata(&mut self, path: &Path) -> anyhow::Result<FileMetadata> {
+        match self.objects.get(path) {
+            Some(Object::File(f)) => {
+                let status = f.status;
+                println!("{}", Event::Metadata(path.into(), status));
+                match status {
+                    Status::Exists => Ok(FileMetadata::new(
+                        f.content.as_deref().unwrap_or_default().as_bytes(),
+                        None,
+                    )),
+                    status @ Status::Missing | status @ Status::Error => {
+                        Err(MockError::new(status))?
+                    }
+                }
+            }
+            _ => panic!(
+                "'{}' should be a file in mock filesystem: {self:?}",
+                path.display()
+            ),
+        }
+    }
+
+    fn stat(&mut self, path: &Path) -> anyhow::Result<FileStat> {
+        match self.objects.get(path) {
+            Some(Object::File(f)) => {
+                let status = f.status;
+                println!("{}", Event::Stat(path.into(), status));
+                match status {
+                    Status::Exists => Ok(FileStat {
+                        size: f.content.as_deref().unwrap_or_default().len() as u64,
+                        modified: None,
+                    }),
+                    status @ Status::Missing | status @ Status::Error => {
+                        Err(MockError::new(status))?
+                    }
+                }
+            }
+            _ => panic!(
+                "'{}' should be a file in mock filesystem: {self:?}",
+                path.display()
+            ),
+        }
+    }
+
+    fn remove_file(&mut self, path: &Path) -> anyhow::Result<()> {
+        let status = match self.objects.get(path) {
+            Some(Object::File(f)) => f.status,
+            _ => panic!(
+                "'{}' should be a file in mock filesystem: {self:?}",
+                path.display()
+            ),
+        };
+
+        println!("{}", Event::RemoveFile(path.into(), status));
+
+        match status {
+            Status::Exists => {
+                self.objects.remove(path);
+                for object in self.objects.values_mut() {
+                    if let Object::Folder(folder) = object {
+                        folder.files.retain(|f| f.path != path);
+                    }
+                }
+                Ok(())
+            }
+            status @ Status::Missing | status @ Status::Error => Err(MockError::new(status))?,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -476,6 +613,31 @@ mod tests {
         );
     }
 
+    #[test]
+    fn move_file_between_folders() -> anyhow::Result<()> {
+        let string = r"
+/from/
+    file_a
+/to/
+    file_b
+";
+
+        let mut mock_file_system: MockFileSystem = string.parse().unwrap();
+
+        mock_file_system.move_file(Path::new("/from/file_a"), Path::new("/to/file_a"))?;
+
+        assert!(mock_file_system
+            .files_in_folder(Path::new("/from/"))?
+            .is_empty());
+        assert_eq!(
+            mock_file_system.files_in_folder(Path::new("/to/"))?,
+            vec![PathBuf::from("/to/file_b"), PathBuf::from("/to/file_a")]
+        );
+        assert!(mock_file_system.file_exists(Path::new("/to/file_a"))?);
+
+        Ok(())
+    }
+
     #[test]
     fn parse_mock_file_system_statuses() {
         let string = r"
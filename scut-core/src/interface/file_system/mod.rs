@@ -4,6 +4,8 @@ use std::{
 };
 
 use dyn_clone::DynClone;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 pub mod local_file_system;
 
@@ -18,6 +20,69 @@ pub trait FileSystem: DynClone + Send + Sync {
     fn write_string_to_file(&mut self, content: &str, path: &Path) -> anyhow::Result<()>;
 
     fn read_file_to_string(&mut self, path: &Path) -> anyhow::Result<String>;
+
+    /// Write bytes to a file, creating the parent folder if it doesn't exist, and flush it to disk before returning
+    fn write_file(&mut self, content: &[u8], path: &Path) -> anyhow::Result<()>;
+
+    /// Move a file, creating the destination folder if it doesn't exist
+    fn move_file(&mut self, from: &Path, to: &Path) -> anyhow::Result<()>;
+
+    /// Read the size, modification time and content digest of a file
+    fn metadata(&mut self, path: &Path) -> anyhow::Result<FileMetadata>;
+
+    /// Read the size and modification time of a file, without reading its content
+    fn stat(&mut self, path: &Path) -> anyhow::Result<FileStat>;
+
+    /// Delete a file
+    fn remove_file(&mut self, path: &Path) -> anyhow::Result<()>;
+}
+
+/// What is known about a file's content, used to tell whether two copies of a file are the same or which was written last
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileMetadata {
+    /// The size of the file in bytes
+    pub size: u64,
+
+    /// When the file was last modified, in milliseconds since the unix epoch, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified: Option<u64>,
+
+    /// A SHA-256 digest of the file's content in hex, see [`content_digest`]
+    #[serde(default)]
+    pub digest: String,
+}
+
+/// The size and modification time of a file, which are cheap to read, unlike its [`FileMetadata`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileStat {
+    /// The size of the file in bytes
+    pub size: u64,
+
+    /// When the file was last modified, in milliseconds since the unix epoch, if known
+    pub modified: Option<u64>,
+}
+
+impl FileMetadata {
+    /// Describe a file with the given content
+    pub fn new(content: &[u8], modified: Option<u64>) -> Self {
+        FileMetadata {
+            size: content.len() as u64,
+            modified,
+            digest: content_digest(content),
+        }
+    }
+
+    /// Returns true if both files have the same content, regardless of when they were modified
+    pub fn same_content(&self, other: &FileMetadata) -> bool {
+        self.size == other.size && self.digest == other.digest
+    }
+}
+
+/// A SHA-256 digest of `content`, in hex.
+///
+/// This is used both to notice when a file has changed, and as what signatures are made over, see [`signature`](crate::signature).
+pub fn content_digest(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
 }
 
 impl Clone for Box<dyn FileSystem> {
@@ -33,3 +98,16 @@ pub fn is_not_found_err(error: &anyhow::Error) -> bool {
         None => false,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_digest_is_sha256() {
+        assert_eq!(
+            content_digest(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+}
@@ -18,6 +18,11 @@ impl MockIndex {
         let saves = saves.into_iter().cloned().collect();
         MockIndex { saves }
     }
+
+    /// Remove a save from the index
+    pub fn remove(&mut self, save: &Save) {
+        self.saves.retain(|s| s != save);
+    }
 }
 
 impl<'a> IterIndex<'a> for MockIndex {
@@ -1,6 +1,6 @@
 use compose::prelude::*;
 
-use crate::{Save, Side};
+use crate::{Aliases, Save, Side};
 
 use self::turn_number::TurnNumberRange;
 
@@ -28,6 +28,8 @@ pub struct Query<'a> {
     pub player: Option<QueryParam<Option<&'a str>>>,
     /// match the part - use `Some(None)` to match saves with no part
     pub part: Option<QueryParam<Option<&'a str>>>,
+    /// treat players' aliases as the same player when matching the player
+    pub aliases: Option<&'a Aliases>,
 }
 
 impl<'a> Query<'a> {
@@ -58,7 +60,13 @@ impl<'a> Matches for Query<'a> {
         let player_matches = self
             .player
             .as_ref()
-            .map(|param| param.matches(&save.player.as_deref()))
+            .map(|param| match self.aliases {
+                Some(aliases) => param.matches_by(&save.player.as_deref(), |a, b| match (a, b) {
+                    (Some(a), Some(b)) => aliases.same_player(a, b),
+                    (a, b) => a == b,
+                }),
+                None => param.matches(&save.player.as_deref()),
+            })
             .unwrap_or(true);
 
         let part_matches = self
@@ -137,15 +145,27 @@ impl<T> QueryParam<T> {
     }
 }
 
+impl<T> QueryParam<T> {
+    /// Like [`matches`](Matches::matches), but using `eq` to decide whether values are equal
+    fn matches_by(&self, value: &T, eq: impl Fn(&T, &T) -> bool) -> bool {
+        let matches = |x: &Bool<T>| match x {
+            Bool::Is(x) => eq(x, value),
+            Bool::IsNot(x) => !eq(x, value),
+        };
+
+        match self {
+            QueryParam::Single(x) => matches(x),
+            QueryParam::Multi(xs) => xs.iter().any(matches),
+        }
+    }
+}
+
 impl<T> Matches<T> for QueryParam<T>
 where
     T: PartialEq,
 {
     fn matches(&self, value: &T) -> bool {
-        match self {
-            QueryParam::Single(x) => x.matches(value),
-            QueryParam::Multi(xs) => xs.iter().any(|x| x.matches(value)),
-        }
+        self.matches_by(value, T::eq)
     }
 }
 
@@ -414,4 +434,34 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn player_queries_match_aliases() {
+        let saves = &[
+            Save::from_parts(Side::Allies, 1).player("GM"),
+            Save::from_parts(Side::Allies, 2).player("GMa"),
+            Save::from_parts(Side::Allies, 2).player("TG"),
+            Save::from_parts(Side::Allies, 3),
+        ];
+
+        let mock_index = MockIndex::new(saves);
+
+        let mut aliases = Aliases::default();
+        aliases.insert("GMa", "GM");
+
+        for (query, expected_count) in &[
+            (Query::new().player(Some("GMa")), 1),
+            (Query::new().aliases(&aliases).player(Some("GMa")), 2),
+            (Query::new().aliases(&aliases).player(Some("GM")), 2),
+            (Query::new().aliases(&aliases).not_player(Some("GMa")), 2),
+            (Query::new().aliases(&aliases).player(None), 1),
+            (Query::new().aliases(&aliases).not_player(None), 3),
+        ] {
+            assert_eq!(
+                mock_index.search(query).unwrap().len(),
+                *expected_count,
+                "query {query:?} had wrong count {expected_count}"
+            );
+        }
+    }
 }
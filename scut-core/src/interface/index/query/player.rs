@@ -1,3 +1,5 @@
+use crate::Aliases;
+
 use super::{Query, QueryParam};
 
 impl<'a> Query<'a> {
@@ -26,4 +28,10 @@ impl<'a> Query<'a> {
         self.player = self.player.map(|p| p.or_not(player));
         self
     }
+
+    /// Treat every alias of a player as that same player when searching for players
+    pub fn aliases(mut self, aliases: &'a Aliases) -> Self {
+        self.aliases = Some(aliases);
+        self
+    }
 }
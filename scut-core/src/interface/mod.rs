@@ -3,6 +3,7 @@ pub mod config;
 pub mod file_system;
 pub mod index;
 pub mod predict;
+pub(crate) mod process;
 pub mod storage;
 pub mod user_interaction;
 
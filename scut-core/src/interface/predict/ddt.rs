@@ -0,0 +1,205 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    interface::{
+        file_system::local_file_system::LocalFileSystem,
+        storage::mock_index_storage::MockIndexStorage,
+    },
+    snapshot::{parse_snapshots, Snapshot},
+};
+
+use super::{
+    handoff::Handoff, observer_predict::ObserverPredict, simple_predict::SimplePredict,
+    AutosavePrediction, Predict,
+};
+use crate::{HandoffOrder, Save, Side};
+use pretty_assertions::assert_eq;
+
+#[derive(Debug)]
+pub struct TestCase {
+    local: MockIndexStorage,
+    remote: MockIndexStorage,
+    side: Side,
+    player: String,
+    playing_solo: Option<bool>,
+    expected_autosave_prediction: AutosavePrediction,
+    downloads_expected: Vec<Save>,
+    uploads_expected: Vec<Save>,
+    handoff_expected: Option<Handoff>,
+}
+
+impl TestCase {
+    pub fn run(&mut self, idx: usize, pred: &dyn Predict) -> anyhow::Result<()> {
+        let turn = pred.predict_turn(
+            self.side,
+            self.player.as_str(),
+            self.playing_solo.unwrap_or_default(),
+            &mut self.local,
+            &mut self.remote,
+        )?;
+
+        let actual_downloads = pred.predict_downloads(
+            turn,
+            self.side,
+            self.player.as_str(),
+            self.playing_solo.unwrap_or_default(),
+            &mut self.local,
+            &mut self.remote,
+        )?;
+
+        let actual_uploads = pred.predict_uploads(
+            turn,
+            self.side,
+            self.player.as_str(),
+            self.playing_solo.unwrap_or_default(),
+            &mut self.local,
+            &mut self.remote,
+        )?;
+
+        let actual_handoff = pred.predict_handoff(
+            turn,
+            self.side,
+            self.player.as_str(),
+            &mut self.local,
+            &mut self.remote,
+        )?;
+
+        let actual_autosave_prediction = pred.predict_autosave(
+            turn,
+            &actual_downloads,
+            actual_handoff.as_ref(),
+            self.side,
+            self.player.as_str(),
+            self.playing_solo.unwrap_or_default(),
+            &mut self.local,
+            &mut self.remote,
+        )?;
+
+        assert_eq!(
+            self.expected_autosave_prediction, actual_autosave_prediction,
+            "Predicted wrong autosave for test_case {idx}"
+        );
+
+        assert_eq!(
+            self.downloads_expected, actual_downloads,
+            "Predicted wrong downloads for test_case {idx}"
+        );
+        assert_eq!(
+            self.uploads_expected, actual_uploads,
+            "Predicted wrong uploads for test_case {idx}"
+        );
+        assert_eq!(
+            self.handoff_expected, actual_handoff,
+            "Predicted wrong handoff for test_case {idx}"
+        );
+
+        Ok(())
+    }
+}
+
+impl From<Snapshot> for TestCase {
+    fn from(snapshot: Snapshot) -> Self {
+        TestCase {
+            local: MockIndexStorage::new(true, snapshot.local),
+            remote: MockIndexStorage::new(true, snapshot.remote),
+            side: snapshot.side,
+            player: snapshot.player,
+            playing_solo: snapshot.playing_solo,
+            expected_autosave_prediction: snapshot.prediction.autosave,
+            downloads_expected: snapshot.prediction.downloads,
+            uploads_expected: snapshot.prediction.uploads,
+            handoff_expected: snapshot.prediction.handoff,
+        }
+    }
+}
+
+pub fn read_test_cases(data_path: &Path) -> anyhow::Result<Vec<TestCase>> {
+    use crate::interface::FileSystem;
+
+    let mut lfs = LocalFileSystem;
+
+    let content = lfs.read_file_to_string(data_path)?;
+
+    Ok(parse_snapshots(content.as_str())?
+        .into_iter()
+        .map(TestCase::from)
+        .collect())
+}
+
+pub fn test_dir(name: &str) -> PathBuf {
+    PathBuf::from("./test_data/").join(name)
+}
+
+/// The handoff order used by the handoff test data: DM then DG, and GM then TG
+fn handoff_predict() -> SimplePredict {
+    SimplePredict::default().handoff(HandoffOrder {
+        axis: vec!["DM".to_string(), "DG".to_string()],
+        allies: vec!["GM".to_string(), "TG".to_string()],
+    })
+}
+
+macro_rules! ddt {
+    ($name:ident, $doc_comment:expr) => {
+        ddt!(
+            $name,
+            $doc_comment,
+            dir = "simple_predict",
+            predictor = SimplePredict::default()
+        );
+    };
+    ($name:ident, $doc_comment:expr, solo=$solo:literal) => {
+        ddt!(
+            $name,
+            $doc_comment,
+            dir = "simple_predict_solo",
+            predictor = SimplePredict::default()
+        );
+    };
+    ($name:ident, $doc_comment:expr, dir=$dir:literal, predictor=$predictor:expr) => {
+        paste::item! {
+            #[doc = $doc_comment]
+            #[test]
+            fn [< $dir _ddt_ $name >]() -> anyhow::Result<()> {
+                let $name = stringify!{ $name };
+                let data_path = test_dir($dir).join(format!("{}.txt", $name));
+
+                let mut test_cases = read_test_cases(data_path.as_path())?;
+
+                for (idx, test_case) in test_cases.iter_mut().enumerate() {
+                    test_case.run(idx + 1, &$predictor)?;
+                }
+
+                Ok(())
+            }
+        }
+    };
+}
+
+ddt!(mixed, "both uploads and downloads (with/without autosave)");
+ddt!(bugs, "Any fixed predict bugs get a test case");
+ddt!(downloads, "predict downloads");
+ddt!(uploads, "predict uploads");
+ddt!(autosave, "predict autosave");
+
+#[rustfmt::skip]
+mod solo {
+    use super::*;
+    ddt!(mixed, "both uploads and downloads (with/without autosave)", solo = true);
+    ddt!(bugs, "Any fixed predict bugs get a test case", solo = true);
+    ddt!(downloads, "predict downloads", solo = true);
+    ddt!(uploads, "predict uploads", solo = true);
+    ddt!(autosave, "predict autosave", solo = true);
+}
+
+#[rustfmt::skip]
+mod handoff {
+    use super::*;
+    ddt!(handoff, "predict the handoff between players playing a turn sequentially", dir = "simple_predict_handoff", predictor = handoff_predict());
+}
+
+#[rustfmt::skip]
+mod observer {
+    use super::*;
+    ddt!(turn_starts, "observers download every turn start save", dir = "observer_predict", predictor = ObserverPredict::new(false));
+    ddt!(player_saves, "observers can download every player save too", dir = "observer_predict", predictor = ObserverPredict::new(true));
+}
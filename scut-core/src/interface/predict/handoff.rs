@@ -0,0 +1,196 @@
+//! Handoffs are for sides whose players play a turn sequentially, e.g. DM plays the navy and saves part A,
+//! then DG loads exactly that save, plays the army and saves part B.
+//!
+//! The order that players hand off to each other is configured per side in the [`HandoffOrder`].
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    interface::{index::Query, LocalStorage, RemoteStorage},
+    part_letter, Aliases, HandoffOrder, Save, Side, Turn,
+};
+
+/// Where a side's turn is up to in its handoff order
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status")]
+pub enum Handoff {
+    /// It's your turn to play your part: load `load` (the turn start or the previous player's part) and save your part as `save_as`
+    YourPart { load: Save, save_as: Save },
+    /// Waiting on a teammate to upload their part
+    WaitingOn { save: Save },
+    /// Every player in the handoff order has played their part
+    Complete,
+}
+
+impl fmt::Display for Handoff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Handoff::YourPart { load, save_as } => {
+                write!(f, "play {load}, save as {save_as}")
+            }
+            Handoff::WaitingOn { save } => write!(f, "waiting for {save}"),
+            Handoff::Complete => write!(f, "complete"),
+        }
+    }
+}
+
+/// Work out where `side`'s turn is up to in the handoff order, from the parts that have been uploaded to remote storage.
+///
+/// Your own part counts as played once it is in local storage, since it will be uploaded.
+///
+/// Returns None if `side` doesn't hand off, or if `player` isn't in the handoff order.
+pub fn predict_handoff(
+    turn: Turn,
+    side: Side,
+    player: &str,
+    order: &HandoffOrder,
+    aliases: &Aliases,
+    local: &dyn LocalStorage,
+    remote: &dyn RemoteStorage,
+) -> anyhow::Result<Option<Handoff>> {
+    let order = order.order(side);
+
+    if turn.side != side || !order.iter().any(|p| aliases.same_player(p, player)) {
+        return Ok(None);
+    }
+
+    let mut load = Save::new(turn);
+
+    for (step, next_player) in order.iter().enumerate() {
+        let part = part_letter(step);
+        let query = Query::new()
+            .aliases(aliases)
+            .turn(turn)
+            .player(Some(next_player.as_str()))
+            .part(Some(part.as_str()));
+
+        let part_save = Save::new(turn).player(next_player).part(part.as_str());
+        let your_part = aliases.same_player(next_player, player);
+
+        let played =
+            remote.index().count(&query)? > 0 || (your_part && local.index().count(&query)? > 0);
+
+        if !played {
+            return Ok(Some(if your_part {
+                Handoff::YourPart {
+                    load,
+                    save_as: part_save,
+                }
+            } else {
+                Handoff::WaitingOn { save: part_save }
+            }));
+        }
+
+        load = part_save;
+    }
+
+    Ok(Some(Handoff::Complete))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interface::storage::mock_index_storage::MockIndexStorage;
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn order() -> HandoffOrder {
+        HandoffOrder {
+            axis: vec!["DM".to_string(), "DG".to_string()],
+            allies: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn handoff_follows_the_order() -> anyhow::Result<()> {
+        let turn = Turn::new(Side::Axis, 6);
+        let aliases = Aliases::default();
+
+        let mut saves = vec![Save::new(turn)];
+        let local = MockIndexStorage::new(false, Vec::new());
+
+        let remote = MockIndexStorage::new(false, saves.clone());
+        assert_eq!(
+            predict_handoff(turn, Side::Axis, "DM", &order(), &aliases, &local, &remote)?,
+            Some(Handoff::YourPart {
+                load: Save::new(turn),
+                save_as: Save::new(turn).player("DM").part("A"),
+            })
+        );
+        assert_eq!(
+            predict_handoff(turn, Side::Axis, "DG", &order(), &aliases, &local, &remote)?,
+            Some(Handoff::WaitingOn {
+                save: Save::new(turn).player("DM").part("A"),
+            })
+        );
+
+        saves.push(Save::new(turn).player("DM").part("A"));
+        let remote = MockIndexStorage::new(false, saves.clone());
+        assert_eq!(
+            predict_handoff(turn, Side::Axis, "DG", &order(), &aliases, &local, &remote)?,
+            Some(Handoff::YourPart {
+                load: Save::new(turn).player("DM").part("A"),
+                save_as: Save::new(turn).player("DG").part("B"),
+            })
+        );
+        assert_eq!(
+            predict_handoff(turn, Side::Axis, "DM", &order(), &aliases, &local, &remote)?,
+            Some(Handoff::WaitingOn {
+                save: Save::new(turn).player("DG").part("B"),
+            })
+        );
+
+        let local = MockIndexStorage::new(false, vec![Save::new(turn).player("DG").part("B")]);
+        assert_eq!(
+            predict_handoff(turn, Side::Axis, "DG", &order(), &aliases, &local, &remote)?,
+            Some(Handoff::Complete)
+        );
+
+        saves.push(Save::new(turn).player("DG").part("B"));
+        let remote = MockIndexStorage::new(false, saves);
+        let local = MockIndexStorage::new(false, Vec::new());
+        assert_eq!(
+            predict_handoff(turn, Side::Axis, "DM", &order(), &aliases, &local, &remote)?,
+            Some(Handoff::Complete)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn no_handoff_outside_the_order() -> anyhow::Result<()> {
+        let turn = Turn::new(Side::Allies, 6);
+        let remote = MockIndexStorage::new(false, vec![Save::new(turn)]);
+        let local = MockIndexStorage::new(false, Vec::new());
+        let aliases = Aliases::default();
+
+        assert_eq!(
+            predict_handoff(
+                turn,
+                Side::Allies,
+                "GM",
+                &order(),
+                &aliases,
+                &local,
+                &remote
+            )?,
+            None
+        );
+        assert_eq!(
+            predict_handoff(
+                Turn::new(Side::Axis, 6),
+                Side::Axis,
+                "XX",
+                &order(),
+                &aliases,
+                &local,
+                &remote
+            )?,
+            None
+        );
+
+        Ok(())
+    }
+}
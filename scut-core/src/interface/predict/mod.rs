@@ -1,10 +1,18 @@
 pub mod classic_predict;
+pub mod handoff;
+pub mod observer_predict;
 pub mod simple_predict;
+pub mod stale_autosave;
+pub mod warnings;
+
+#[cfg(test)]
+mod ddt;
 
 use serde::{Deserialize, Serialize};
 
 use crate::{Save, Side, Turn};
 
+use self::{handoff::Handoff, warnings::PredictionWarning};
 use super::{LocalStorage, RemoteStorage};
 
 /// Scut's prediction of what saves should be uploaded/downloaded
@@ -26,6 +34,14 @@ pub struct Prediction {
 
     /// What saves suggests to download
     pub downloads: Vec<Save>,
+
+    /// Where your side's turn is up to, if your side hands off the turn between players
+    #[serde(default)]
+    pub handoff: Option<Handoff>,
+
+    /// Saves that have changed since they were last synced, which you might want to download or upload again
+    #[serde(default)]
+    pub warnings: Vec<PredictionWarning>,
 }
 
 /// The Save that an autosave would be uploaded as, wrapped in an indication of whether it is ready to upload
@@ -62,6 +78,28 @@ pub enum AutosavePredictionReason {
     TurnNotPlayed(Save),
     /// If the autosave isn't in local storage, you **cannot** upload it
     AutosaveNotAvailable,
+    /// Observers follow the game without playing, so they never upload an autosave
+    Observing,
+    /// When your side hands off the turn between players, the turn isn't finished until every part has been played
+    HandoffNotFinished(Save),
+    /// When the autosave was written before the save you played, or was already uploaded as an earlier turn start, it is probably left over from an earlier turn
+    StaleAutosave(Save),
+}
+
+impl AutosavePredictionReason {
+    /// The name of the reason, as written in snapshots
+    pub fn name(&self) -> &'static str {
+        match self {
+            AutosavePredictionReason::AutosaveAlreadyUploaded => "AutosaveAlreadyUploaded",
+            AutosavePredictionReason::TeammateSaveNotUploaded => "TeammateSaveNotUploaded",
+            AutosavePredictionReason::NewTeammateSaveAvailable(_) => "NewTeammateSaveAvailable",
+            AutosavePredictionReason::TurnNotPlayed(_) => "TurnNotPlayed",
+            AutosavePredictionReason::AutosaveNotAvailable => "AutosaveNotAvailable",
+            AutosavePredictionReason::Observing => "Observing",
+            AutosavePredictionReason::HandoffNotFinished(_) => "HandoffNotFinished",
+            AutosavePredictionReason::StaleAutosave(_) => "StaleAutosave",
+        }
+    }
 }
 
 /// This trait is for the logic behind choosing which saves to download, which saves to upload and what turn the autosave should be uploaded as.
@@ -114,11 +152,41 @@ pub trait Predict {
         remote: &mut dyn RemoteStorage,
     ) -> anyhow::Result<Vec<Save>>;
 
-    /// Predict what to upload the autosave as, if at all
+    /// Predict where your side's turn is up to, if your side hands off the turn between players.
+    ///
+    /// By default, sides don't hand off.
+    fn predict_handoff(
+        &self,
+        _predicted_turn: Turn,
+        _side: Side,
+        _player: &str,
+        _local: &mut dyn LocalStorage,
+        _remote: &mut dyn RemoteStorage,
+    ) -> anyhow::Result<Option<Handoff>> {
+        Ok(None)
+    }
+
+    /// Warn about saves that have changed since they were last uploaded or downloaded.
+    ///
+    /// By default, this compares saves with the [`SyncRecord`](crate::interface::storage::sync_record::SyncRecord) in local storage.
+    fn predict_warnings(
+        &self,
+        predicted_turn: Turn,
+        autosave: &AutosavePrediction,
+        local: &mut dyn LocalStorage,
+        remote: &mut dyn RemoteStorage,
+    ) -> anyhow::Result<Vec<PredictionWarning>> {
+        warnings::predict_warnings(predicted_turn, autosave, local, remote)
+    }
+
+    /// Predict what to upload the autosave as, if at all.
+    ///
+    /// `predicted_handoff` is the result of [`predict_handoff`](Predict::predict_handoff) for the same turn.
     fn predict_autosave(
         &self,
         predicted_turn: Turn,
         predicted_downloads: &[Save],
+        predicted_handoff: Option<&Handoff>,
         side: Side,
         player: &str,
         playing_solo: bool,
@@ -0,0 +1,136 @@
+//! This Predict implementation is for observers, who follow a game without playing a side.
+//!
+//! Observers download every turn start save they don't have yet, so they can load it and watch, and they never upload anything.
+//! Optionally they download every player's saves as well.
+
+use tracing::instrument;
+
+use crate::{
+    interface::{index::Query, LocalStorage, RemoteStorage},
+    Save, Side, Turn,
+};
+
+use super::{handoff::Handoff, AutosavePrediction, AutosavePredictionReason, Predict, Prediction};
+
+#[derive(Debug, Default)]
+pub struct ObserverPredict {
+    player_saves: bool,
+}
+
+impl ObserverPredict {
+    /// Create an ObserverPredict, that also downloads every player's saves if `player_saves` is true
+    pub fn new(player_saves: bool) -> Self {
+        ObserverPredict { player_saves }
+    }
+}
+
+impl Predict for ObserverPredict {
+    fn predict(
+        &self,
+        side: Side,
+        player: &str,
+        _turn_override: Option<u32>,
+        playing_solo: bool,
+        local: &mut dyn LocalStorage,
+        remote: &mut dyn RemoteStorage,
+    ) -> anyhow::Result<Prediction> {
+        let turn = self.predict_turn(side, player, playing_solo, local, remote)?;
+
+        let uploads = self.predict_uploads(turn, side, player, playing_solo, local, remote)?;
+        let downloads = self.predict_downloads(turn, side, player, playing_solo, local, remote)?;
+
+        let autosave = self.predict_autosave(
+            turn,
+            &downloads,
+            None,
+            side,
+            player,
+            playing_solo,
+            local,
+            remote,
+        )?;
+
+        let warnings = self.predict_warnings(turn, &autosave, local, remote)?;
+
+        Ok(Prediction {
+            autosave,
+            uploads,
+            downloads,
+            handoff: None,
+            warnings,
+        })
+    }
+
+    /// The current turn is the latest turn with a turn start save
+    #[instrument(skip(self, _local, remote), ret, err)]
+    fn predict_turn(
+        &self,
+        _side: Side,
+        _player: &str,
+        _playing_solo: bool,
+        _local: &mut dyn LocalStorage,
+        remote: &mut dyn RemoteStorage,
+    ) -> anyhow::Result<Turn> {
+        Ok(remote
+            .index()
+            .latest(&Query::new().player(None))?
+            .map(|save| save.turn)
+            .unwrap_or(Turn::new(Side::first(), 1)))
+    }
+
+    #[instrument(skip(self, local, remote), ret, err)]
+    fn predict_downloads(
+        &self,
+        _predicted_turn: Turn,
+        _side: Side,
+        _player: &str,
+        _playing_solo: bool,
+        local: &mut dyn LocalStorage,
+        remote: &mut dyn RemoteStorage,
+    ) -> anyhow::Result<Vec<Save>> {
+        let query = if self.player_saves {
+            Query::new()
+        } else {
+            Query::new().player(None)
+        };
+
+        let local_saves = local.index().search(&query)?;
+        let remote_saves = remote.index().search(&query)?;
+
+        Ok(remote_saves
+            .into_iter()
+            .filter(|s| !local_saves.contains(s))
+            .collect())
+    }
+
+    /// Observers never upload
+    fn predict_uploads(
+        &self,
+        _predicted_turn: Turn,
+        _side: Side,
+        _player: &str,
+        _playing_solo: bool,
+        _local: &mut dyn LocalStorage,
+        _remote: &mut dyn RemoteStorage,
+    ) -> anyhow::Result<Vec<Save>> {
+        Ok(Vec::new())
+    }
+
+    /// Observers never upload, including the autosave
+    fn predict_autosave(
+        &self,
+        predicted_turn: Turn,
+        _predicted_downloads: &[Save],
+        _predicted_handoff: Option<&Handoff>,
+        _side: Side,
+        _player: &str,
+        _playing_solo: bool,
+        _local: &mut dyn LocalStorage,
+        _remote: &mut dyn RemoteStorage,
+    ) -> anyhow::Result<AutosavePrediction> {
+        Ok(AutosavePrediction::NotReady(
+            Save::new(predicted_turn.next()),
+            AutosavePredictionReason::Observing,
+        ))
+    }
+}
@@ -7,16 +7,41 @@ use tracing::instrument;
 
 use crate::{
     interface::{index::Query, LocalStorage, RemoteStorage},
-    Save, Side, Turn,
+    Aliases, HandoffOrder, Save, Side, Turn,
 };
 
-#[cfg(test)]
-mod ddt;
-
-use super::{AutosavePrediction, AutosavePredictionReason, Predict, Prediction};
+use super::{
+    handoff::{predict_handoff, Handoff},
+    stale_autosave::predict_stale_autosave,
+    AutosavePrediction, AutosavePredictionReason, Predict, Prediction,
+};
 
 #[derive(Debug, Default)]
-pub struct SimplePredict;
+pub struct SimplePredict {
+    aliases: Aliases,
+    handoff: HandoffOrder,
+}
+
+impl SimplePredict {
+    /// Create a SimplePredict that treats every alias of a player as that same player
+    pub fn new(aliases: Aliases) -> Self {
+        SimplePredict {
+            aliases,
+            handoff: HandoffOrder::default(),
+        }
+    }
+
+    /// Builder method to set the order that players on each side hand off the turn to each other
+    pub fn handoff(mut self, handoff: HandoffOrder) -> Self {
+        self.handoff = handoff;
+        self
+    }
+
+    /// Start a new [`Query`] that matches players by any of their aliases
+    fn query(&self) -> Query<'_> {
+        Query::new().aliases(&self.aliases)
+    }
+}
 
 impl Predict for SimplePredict {
     fn predict(
@@ -35,16 +60,49 @@ impl Predict for SimplePredict {
         let uploads = self.predict_uploads(turn, side, player, playing_solo, local, remote)?;
         let downloads = self.predict_downloads(turn, side, player, playing_solo, local, remote)?;
 
-        let autosave =
-            self.predict_autosave(turn, &downloads, side, player, playing_solo, local, remote)?;
+        let handoff = self.predict_handoff(turn, side, player, local, remote)?;
+
+        let autosave = self.predict_autosave(
+            turn,
+            &downloads,
+            handoff.as_ref(),
+            side,
+            player,
+            playing_solo,
+            local,
+            remote,
+        )?;
+
+        let warnings = self.predict_warnings(turn, &autosave, local, remote)?;
 
         Ok(Prediction {
             autosave,
             uploads,
             downloads,
+            handoff,
+            warnings,
         })
     }
 
+    fn predict_handoff(
+        &self,
+        predicted_turn: Turn,
+        side: Side,
+        player: &str,
+        local: &mut dyn LocalStorage,
+        remote: &mut dyn RemoteStorage,
+    ) -> anyhow::Result<Option<Handoff>> {
+        predict_handoff(
+            predicted_turn,
+            side,
+            player,
+            &self.handoff,
+            &self.aliases,
+            local,
+            remote,
+        )
+    }
+
     #[instrument(skip(self, local, remote), ret, err)]
     fn predict_turn(
         &self,
@@ -60,15 +118,15 @@ impl Predict for SimplePredict {
         let enemy_side = side.other_side();
 
         let your_last_uploaded_turn = remote_index
-            .latest(&Query::new().side(side).player(Some(player)))?
+            .latest(&self.query().side(side).player(Some(player)))?
             .map(|s| s.turn);
 
         let last_friendly_start_turn = remote_index
-            .latest(&Query::new().side(side).player(None))?
+            .latest(&self.query().side(side).player(None))?
             .map(|s| s.turn);
 
         let last_enemy_start_turn = remote_index
-            .latest(&Query::new().side(enemy_side).player(None))?
+            .latest(&self.query().side(enemy_side).player(None))?
             .map(|s| s.turn);
 
         match (
@@ -121,6 +179,7 @@ impl Predict for SimplePredict {
         &self,
         predicted_turn: Turn,
         predicted_downloads: &[Save],
+        predicted_handoff: Option<&Handoff>,
         side: Side,
         player: &str,
         playing_solo: bool,
@@ -137,23 +196,44 @@ impl Predict for SimplePredict {
             ));
         }
 
-        let query_played_save = Query::new()
+        let query_played_save = self
+            .query()
             .side(side)
             .turn_number(predicted_turn.number)
             .player(Some(player));
 
-        let have_played_your_turn = local.index().count(&query_played_save)? >= 1;
-        if !have_played_your_turn {
+        let played_saves = local.index().search(&query_played_save)?;
+        if played_saves.is_empty() {
             return Ok(AutosavePrediction::NotReady(
                 autosave,
                 AutosavePredictionReason::TurnNotPlayed(Save::new(predicted_turn).player(player)),
             ));
         }
 
+        if let Some(save) = predict_stale_autosave(&autosave, &played_saves, local)? {
+            return Ok(AutosavePrediction::NotReady(
+                autosave,
+                AutosavePredictionReason::StaleAutosave(save),
+            ));
+        }
+
+        match predicted_handoff {
+            Some(Handoff::YourPart { save_as: save, .. }) | Some(Handoff::WaitingOn { save }) => {
+                return Ok(AutosavePrediction::NotReady(
+                    autosave,
+                    AutosavePredictionReason::HandoffNotFinished(save.clone()),
+                ));
+            }
+            Some(Handoff::Complete) | None => {}
+        }
+
         let new_teammate_save_available = predicted_downloads
             .iter()
             .filter(|save| {
-                save.player.as_ref().is_some_and(|p| p != player) && save.turn.side == side
+                save.player
+                    .as_ref()
+                    .is_some_and(|p| !self.aliases.same_player(p, player))
+                    && save.turn.side == side
             })
             .next();
 
@@ -164,7 +244,8 @@ impl Predict for SimplePredict {
             ));
         }
 
-        let query_autosave = Query::new()
+        let query_autosave = self
+            .query()
             .side(side.other_side())
             .turn_number(predicted_turn.next().number)
             .player(None)
@@ -182,12 +263,13 @@ impl Predict for SimplePredict {
             return Ok(AutosavePrediction::Ready(autosave));
         }
 
-        let query = Query::new()
+        let query = self
+            .query()
             .not_player(Some(player))
             .side(predicted_turn.side)
             .turn_number(predicted_turn.number)
             .and(
-                Query::new()
+                self.query()
                     .not_player(None)
                     .side(predicted_turn.side)
                     .turn_number(predicted_turn.number),
@@ -195,7 +277,7 @@ impl Predict for SimplePredict {
 
         let friendly_turn = remote.index().search(&query)?;
 
-        remote.index().search(&Query::new())?;
+        remote.index().search(&self.query())?;
 
         if friendly_turn.is_empty() {
             Ok(AutosavePrediction::NotReady(
@@ -217,12 +299,13 @@ impl Predict for SimplePredict {
         local: &mut dyn LocalStorage,
         remote: &mut dyn RemoteStorage,
     ) -> anyhow::Result<Vec<crate::Save>> {
-        let query = Query::new()
+        let query = self
+            .query()
             .side(side)
             .turn_number_in_range(Some(predicted_turn.number - 1), Some(predicted_turn.number));
 
-        let our_save = Query::new().player(Some(player));
-        let turn_start_save = Query::new().player(None).turn(predicted_turn);
+        let our_save = self.query().player(Some(player));
+        let turn_start_save = self.query().player(None).turn(predicted_turn);
         let teammate_save = query
             .clone()
             .not_player(Some(player))
@@ -265,7 +348,7 @@ impl Predict for SimplePredict {
         local: &mut dyn LocalStorage,
         remote: &mut dyn RemoteStorage,
     ) -> anyhow::Result<Vec<crate::Save>> {
-        let query = Query::new().min_turn(predicted_turn);
+        let query = self.query().min_turn(predicted_turn);
 
         let local_saves = local.index().search(&query)?;
         let remote_saves = remote.index().search(&query)?;
@@ -287,7 +370,7 @@ mod tests {
 
     #[test]
     fn simple_predict_works() -> anyhow::Result<()> {
-        let predict = SimplePredict;
+        let predict = SimplePredict::default();
 
         let mut remote_storage = MockIndexStorage::new(
             true,
@@ -332,4 +415,56 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn aliases_are_not_teammates() -> anyhow::Result<()> {
+        let remote_saves = vec![
+            Save::from_parts(Side::Axis, 1),
+            Save::from_parts(Side::Allies, 1),
+            Save::from_parts(Side::Allies, 1).player("GM"),
+            Save::from_parts(Side::Allies, 1).player("TG"),
+            Save::from_parts(Side::Axis, 2),
+            Save::from_parts(Side::Allies, 2),
+        ];
+        let local_saves = vec![
+            Save::from_parts(Side::Allies, 1).player("TG"),
+            Save::from_parts(Side::Allies, 2),
+        ];
+
+        let mut aliases = Aliases::default();
+        aliases.insert("GMa", "GM");
+
+        for (predict, expected) in [
+            (
+                SimplePredict::default(),
+                vec![Save::from_parts(Side::Allies, 1).player("GM")],
+            ),
+            (SimplePredict::new(aliases), vec![]),
+        ] {
+            let mut remote_storage = MockIndexStorage::new(true, remote_saves.clone());
+            let mut local_storage = MockIndexStorage::new(true, local_saves.clone());
+
+            let turn = predict.predict_turn(
+                Side::Allies,
+                "GMa",
+                false,
+                &mut local_storage,
+                &mut remote_storage,
+            )?;
+
+            assert_eq!(
+                predict.predict_downloads(
+                    turn,
+                    Side::Allies,
+                    "GMa",
+                    false,
+                    &mut local_storage,
+                    &mut remote_storage
+                )?,
+                expected
+            );
+        }
+
+        Ok(())
+    }
 }
@@ -0,0 +1,116 @@
+//! Strategic Command only writes the autosave when a turn is ended, so the autosave in your game saves folder
+//! might be left over from an earlier turn, e.g. if you saved your turn but haven't ended it yet.
+//!
+//! Uploading that autosave would hand the enemy the start of the wrong turn, so the autosave is compared with
+//! the save you played, and with the autosaves you uploaded before, see [`SyncRecord`](crate::interface::storage::sync_record::SyncRecord).
+
+use crate::{
+    interface::{storage::sync_record::Direction, LocalStorage},
+    Save,
+};
+
+/// Check whether the autosave is stale or a duplicate.
+///
+/// Returns the save that shows the autosave is stale:
+/// * one of `played`, if the autosave was modified before it
+/// * a turn start other than `upload_as` that was previously uploaded from an autosave with the same size and content
+///
+/// An autosave that was already uploaded as `upload_as` isn't stale, that is [`AutosaveAlreadyUploaded`](super::AutosavePredictionReason::AutosaveAlreadyUploaded).
+///
+/// Returns None if the autosave looks fresh, or if there isn't enough metadata to tell.
+pub fn predict_stale_autosave(
+    upload_as: &Save,
+    played: &[Save],
+    local: &mut dyn LocalStorage,
+) -> anyhow::Result<Option<Save>> {
+    let Some(autosave) = local.autosave_metadata()? else {
+        return Ok(None);
+    };
+
+    if let Some(modified) = autosave.modified {
+        for save in played {
+            let played_later = local
+                .metadata(save)?
+                .and_then(|metadata| metadata.modified)
+                .is_some_and(|played_modified| played_modified > modified);
+
+            if played_later {
+                return Ok(Some(save.clone()));
+            }
+        }
+    }
+
+    let duplicate = local
+        .sync_record()?
+        .iter()
+        .filter(|(save, synced)| {
+            synced.direction == Direction::UploadedAutosave && save != upload_as
+        })
+        .find(|(_, synced)| synced.local.same_content(&autosave))
+        .map(|(save, _)| save);
+
+    Ok(duplicate)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{
+        interface::{
+            storage::memory_storage::{MemoryFiles, MemoryLocalStorage, MemoryRemoteStorage},
+            RemoteStorage,
+        },
+        record_synced_saves, Side,
+    };
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn stale_and_duplicate_autosaves() -> anyhow::Result<()> {
+        let files = MemoryFiles::new();
+        let mut local = MemoryLocalStorage::new(PathBuf::from("/local"), files.clone());
+        let mut remote = MemoryRemoteStorage::new(PathBuf::from("/remote"), files.clone());
+
+        let turn_12 = Save::from_parts(Side::Axis, 12).player("DM");
+        let allies_12 = Save::from_parts(Side::Allies, 12);
+        let turn_13 = Save::from_parts(Side::Axis, 13).player("DM");
+        let allies_13 = Save::from_parts(Side::Allies, 13);
+
+        // we play turn 12 and end it
+        local.write_save(&turn_12, "DM's turn 12");
+        local.write_autosave("end of Axis 12");
+        assert_eq!(
+            predict_stale_autosave(&allies_12, &[turn_12], &mut local)?,
+            None
+        );
+
+        let path = local.locate_autosave()?.expect("autosave was written");
+        remote.upload(&allies_12, &path)?;
+        record_synced_saves(&mut local, &mut remote, &[], &[], Some(&allies_12))?;
+
+        // we save turn 13 without ending it, so the autosave is still from turn 12
+        local.write_save(&turn_13, "DM's turn 13");
+        assert_eq!(
+            predict_stale_autosave(&allies_13, &[turn_13.clone()], &mut local)?,
+            Some(turn_13.clone())
+        );
+
+        // the autosave is rewritten with the same content it had when it was uploaded as Allies 12
+        local.write_autosave("end of Axis 12");
+        assert_eq!(
+            predict_stale_autosave(&allies_13, &[turn_13.clone()], &mut local)?,
+            Some(allies_12)
+        );
+
+        // ending turn 13 writes a fresh autosave
+        local.write_autosave("end of Axis 13");
+        assert_eq!(
+            predict_stale_autosave(&allies_13, &[turn_13], &mut local)?,
+            None
+        );
+
+        Ok(())
+    }
+}
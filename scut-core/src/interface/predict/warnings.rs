@@ -0,0 +1,147 @@
+//! Warnings about saves that have changed since scut last uploaded or downloaded them.
+//!
+//! Predictions compare saves by name only, so a save that is re-uploaded with the same name, or an autosave left over
+//! from an earlier turn, looks the same as the real thing. The [`SyncRecord`](crate::interface::storage::sync_record::SyncRecord)
+//! remembers what each save looked like when it was synced, so that these can be flagged separately.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    interface::{storage::sync_record::Direction, LocalStorage, RemoteStorage},
+    Save, Turn,
+};
+
+use super::AutosavePrediction;
+
+/// Something that changed since saves were last synced, that you might want to act on
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "save")]
+pub enum PredictionWarning {
+    /// The remote copy of a save has changed since it was last synced, e.g. a teammate re-uploaded a corrected save
+    RemoteDiffers(Save),
+    /// Your local copy of a save has changed since it was last synced, e.g. you saved over it after uploading it
+    LocalDiffers(Save),
+    /// Your autosave hasn't been written since your last upload, so it is probably left over from an earlier turn
+    AutosaveOlderThanLastUpload(Save),
+}
+
+/// Compare saves from the predicted turn and the turn before with how they looked when they were last synced.
+///
+/// The autosave is only checked if it is predicted to be ready to upload.
+pub fn predict_warnings(
+    predicted_turn: Turn,
+    autosave: &AutosavePrediction,
+    local: &mut dyn LocalStorage,
+    remote: &mut dyn RemoteStorage,
+) -> anyhow::Result<Vec<PredictionWarning>> {
+    let record = local.sync_record()?;
+    let mut warnings = Vec::new();
+
+    let recent = record
+        .iter()
+        .filter(|(save, _)| save.turn.number + 1 >= predicted_turn.number);
+
+    for (save, synced) in recent {
+        if let Some(metadata) = remote.metadata(&save)? {
+            if !metadata.same_content(&synced.remote) {
+                warnings.push(PredictionWarning::RemoteDiffers(save.clone()));
+            }
+        }
+
+        // the local copy of an uploaded autosave is whatever autosave was written since
+        if synced.direction == Direction::UploadedAutosave {
+            continue;
+        }
+
+        if let Some(metadata) = local.metadata(&save)? {
+            if !metadata.same_content(&synced.local) {
+                warnings.push(PredictionWarning::LocalDiffers(save));
+            }
+        }
+    }
+
+    if let AutosavePrediction::Ready(_) = autosave {
+        if let (Some(autosave), Some((last_upload, synced))) =
+            (local.autosave_metadata()?, record.last_upload())
+        {
+            if autosave.modified.is_some() && autosave.modified <= synced.local.modified {
+                warnings.push(PredictionWarning::AutosaveOlderThanLastUpload(last_upload));
+            }
+        }
+    }
+
+    Ok(warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{
+        interface::storage::memory_storage::{
+            MemoryFiles, MemoryLocalStorage, MemoryRemoteStorage,
+        },
+        record_synced_saves, Side,
+    };
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn warn_about_saves_changed_since_last_sync() -> anyhow::Result<()> {
+        let files = MemoryFiles::new();
+        let mut local = MemoryLocalStorage::new(PathBuf::from("/local"), files.clone());
+        let mut remote = MemoryRemoteStorage::new(PathBuf::from("/remote"), files.clone());
+
+        let turn = Turn::new(Side::Axis, 12);
+        let ours = Save::new(turn).player("DM");
+        let theirs = Save::new(turn).player("DG");
+        let autosave = Save::new(turn.next());
+
+        // DG uploaded their turn, and we downloaded it
+        files.write(&PathBuf::from("/remote/Axis DG 12.sav"), "DG's turn");
+        remote.download(&theirs, local.location())?;
+
+        // we played our turn and uploaded it with the autosave
+        local.write_save(&ours, "DM's turn");
+        local.write_autosave("end of Axis 12");
+        let path = local.locate_save(&ours)?.expect("save was written");
+        remote.upload(&ours, &path)?;
+        let path = local.locate_autosave()?.expect("autosave was written");
+        remote.upload(&autosave, &path)?;
+
+        record_synced_saves(
+            &mut local,
+            &mut remote,
+            std::slice::from_ref(&ours),
+            std::slice::from_ref(&theirs),
+            Some(&autosave),
+        )?;
+
+        let ready = AutosavePrediction::Ready(autosave.clone());
+        assert_eq!(
+            predict_warnings(turn, &ready, &mut local, &mut remote)?,
+            vec![PredictionWarning::AutosaveOlderThanLastUpload(
+                autosave.clone()
+            )]
+        );
+
+        // DG re-uploads a corrected turn, and we save over ours
+        files.write(
+            &PathBuf::from("/remote/Axis DG 12.sav"),
+            "DG's corrected turn",
+        );
+        local.write_save(&ours, "DM's replayed turn");
+        local.write_autosave("end of Axis 12, again");
+
+        assert_eq!(
+            predict_warnings(turn, &ready, &mut local, &mut remote)?,
+            vec![
+                PredictionWarning::RemoteDiffers(theirs),
+                PredictionWarning::LocalDiffers(ours),
+            ]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,22 @@
+//! Running other programs, e.g. 7-Zip and git, in the background.
+
+use std::ffi::OsStr;
+use std::process::Command;
+
+/// Stops a console window from flashing up while the program runs
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+/// A command that runs `program` without a console window on Windows
+pub(crate) fn background<S: AsRef<OsStr>>(program: S) -> Command {
+    #[allow(unused_mut)]
+    let mut command = Command::new(program);
+
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+
+    command
+}
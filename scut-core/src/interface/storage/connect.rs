@@ -0,0 +1,223 @@
+//! Connects to the remote storage that the config describes: the `[remote]` with its `folder`,
+//! any `[[mirrors]]`, and the `shared_folder` that turn starts are exchanged in.
+//!
+//! Each remote that is reached over the network is behind a cargo feature named like its provider, e.g. `webdav`,
+//! which are all on by default.
+
+use std::path::Path;
+
+use anyhow::Context;
+use tracing::warn;
+
+#[cfg(feature = "email")]
+use crate::interface::storage::email::PlayByEmail;
+#[cfg(feature = "server")]
+use crate::interface::storage::http_remote::HttpRemote;
+#[cfg(feature = "s3")]
+use crate::interface::storage::s3::S3Bucket;
+#[cfg(feature = "sftp")]
+use crate::interface::storage::sftp::{self, SftpFolder};
+#[cfg(feature = "webdav")]
+use crate::interface::storage::webdav::WebDav;
+use crate::{
+    error::ErrorSuggestions,
+    interface::{
+        compression::SevenZipCompression,
+        file_system::local_file_system::LocalFileSystem,
+        storage::{
+            git::GitRepository, mirrored_remote::MirroredRemote, split_remote::SplitRemote,
+            synced_folder::SyncedFolder,
+        },
+        RemoteStorage,
+    },
+    Config, Key, Remote, RemoteKind,
+};
+
+/// Connect to remote storage: the `folder`, or every mirror of it, and the `shared_folder` if there is one
+pub fn remote_storage(config: &Config) -> anyhow::Result<Box<dyn RemoteStorage>> {
+    let compression = compression(config);
+
+    let private_storage = mirrored_folder(config, compression.clone())?;
+
+    match &config.shared_folder {
+        Some(shared_folder) => {
+            let shared_storage = remote_folder(config, &config.remote, shared_folder, compression)?;
+            Ok(Box::new(SplitRemote::new(private_storage, shared_storage)))
+        }
+        None => Ok(private_storage),
+    }
+}
+
+/// Connect to `remote` and each of the `mirrors`, skipping those that can't be reached as long as one can
+///
+/// Returns the names of the mirrors that were skipped.
+pub fn mirrors(config: &Config) -> anyhow::Result<(MirroredRemote, Vec<String>)> {
+    let compression = compression(config);
+
+    let primary = (
+        config.remote.describe(&config.folder),
+        &config.remote,
+        Ok(config.folder.as_path()),
+    );
+    let mirrors = config
+        .mirrors
+        .iter()
+        .map(|mirror| (mirror.describe(), &mirror.remote, mirror.folder()));
+
+    let mut mirrored: Option<MirroredRemote> = None;
+    let mut skipped = Vec::new();
+    let mut first_error = None;
+    for (name, remote, location) in std::iter::once(primary).chain(mirrors) {
+        match location
+            .and_then(|location| remote_folder(config, remote, location, compression.clone()))
+        {
+            Ok(storage) => {
+                mirrored = Some(match mirrored {
+                    Some(mirrored) => mirrored.mirror(name, storage),
+                    None => MirroredRemote::new(name, storage),
+                });
+            }
+            Err(error) => {
+                warn!("failed to connect to {name}: {error:#}");
+                skipped.push(name);
+                first_error.get_or_insert(error);
+            }
+        }
+    }
+
+    match mirrored {
+        Some(mirrored) => Ok((mirrored, skipped)),
+        None => Err(first_error.unwrap_or_else(|| anyhow::anyhow!("there are no remotes")))
+            .context("failed to connect to any of your mirrors"),
+    }
+}
+
+/// The compression for saves that only your team should see, encrypting turn starts, which are shared with the enemy team,
+/// with a passphrase of their own
+fn compression(config: &Config) -> SevenZipCompression {
+    SevenZipCompression::new(&config.seven_zip_path)
+        .passphrase(config.team_passphrase.clone(), Key::TeamPassphrase)
+        .turn_start_passphrase(config.game_passphrase.clone(), Key::GamePassphrase)
+}
+
+/// The remote storage in `folder`, mirrored to each of the `mirrors` if there are any
+fn mirrored_folder(
+    config: &Config,
+    compression: SevenZipCompression,
+) -> anyhow::Result<Box<dyn RemoteStorage>> {
+    if config.mirrors.is_empty() {
+        remote_folder(config, &config.remote, &config.folder, compression)
+    } else {
+        Ok(Box::new(mirrors(config)?.0))
+    }
+}
+
+/// The remote folder at `location`, or at `remote.url` for remotes that are reached over the network
+fn remote_folder(
+    config: &Config,
+    remote: &Remote,
+    location: &Path,
+    compression: SevenZipCompression,
+) -> anyhow::Result<Box<dyn RemoteStorage>> {
+    let compression = Box::new(compression);
+
+    match remote.provider {
+        RemoteKind::Synced(provider) => Ok(Box::new(
+            SyncedFolder::new(
+                location.to_path_buf(),
+                provider,
+                Box::new(LocalFileSystem::new()),
+                compression,
+            )
+            .with_context(|| {
+                format!(
+                    "failed to load {} with path '{}'",
+                    provider.folder_name(),
+                    location.display()
+                )
+            })?,
+        )),
+        #[cfg(feature = "webdav")]
+        RemoteKind::WebDav => {
+            let url = remote.url()?;
+            Ok(Box::new(
+                WebDav::new(url, remote.credentials(), compression)
+                    .with_context(|| format!("failed to load WebDAV folder with url '{url}'"))?,
+            ))
+        }
+        #[cfg(feature = "s3")]
+        RemoteKind::S3 => {
+            let url = remote.url()?;
+            Ok(Box::new(
+                S3Bucket::new(url, remote.credentials(), compression)
+                    .with_context(|| format!("failed to load S3 bucket with url '{url}'"))?,
+            ))
+        }
+        #[cfg(feature = "sftp")]
+        RemoteKind::Sftp => {
+            let url = remote.url()?;
+            Ok(Box::new(
+                SftpFolder::new(
+                    url,
+                    remote.credentials(),
+                    remote.key()?,
+                    &sftp::known_hosts()?,
+                    compression,
+                )
+                .with_context(|| format!("failed to load SFTP folder with url '{url}'"))?,
+            ))
+        }
+        #[cfg(feature = "server")]
+        RemoteKind::Server => {
+            let url = remote.url()?;
+            Ok(Box::new(
+                HttpRemote::new(url, remote.password.as_deref(), compression).with_context(
+                    || format!("failed to load game on the scut server with url '{url}'"),
+                )?,
+            ))
+        }
+        RemoteKind::Git => {
+            let url = remote.url()?;
+            Ok(Box::new(
+                GitRepository::new(
+                    url,
+                    location.to_path_buf(),
+                    &config.player,
+                    Box::new(LocalFileSystem::new()),
+                    compression,
+                )
+                .with_context(|| {
+                    format!(
+                        "failed to load git clone of '{url}' with path '{}'",
+                        location.display()
+                    )
+                })?,
+            ))
+        }
+        #[cfg(feature = "email")]
+        RemoteKind::Email => {
+            let url = remote.url()?;
+            Ok(Box::new(
+                PlayByEmail::new(
+                    url,
+                    remote.smtp()?,
+                    remote.mailing_list()?,
+                    remote.credentials(),
+                    compression,
+                )
+                .with_context(|| {
+                    format!("failed to load emails in the IMAP folder with url '{url}'")
+                })?,
+            ))
+        }
+        // the remotes whose cargo feature was left out of this build
+        #[allow(unreachable_patterns)]
+        kind => {
+            let feature = String::from(kind);
+            Ok(
+                Err(anyhow::anyhow!("this build of scut can't connect to {kind} remotes"))
+                    .suggest(format!("Install a build of scut with the `{feature}` cargo feature, which is on by default"))?,
+            )
+        }
+    }
+}
@@ -0,0 +1,786 @@
+//! Plays by email, the way Strategic Command's own PBEM games are played: each upload is emailed to the game's mailing list
+//! with the save attached, and saves are downloaded from the emails that the list delivers to an IMAP folder.
+//!
+//! Emails can't be changed once they are sent, so everything is recorded by sending another email, named by its subject:
+//!
+//! ```text
+//! [scut] Axis DM 12                   an upload, with the archive attached
+//! [scut] supersede Axis DM 12         the save was superseded, e.g. by a rollback
+//! [scut] record ledger                a record, e.g. a ledger entry, in the body
+//! ```
+//!
+//! The latest upload of each save is the current copy, and the uploads before it are its versions.
+//! Mailing lists often tag subjects, e.g. `[ww1] [scut] Axis DM 12`, so anything before `[scut]` is ignored.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use anyhow::Context;
+use imap::Session;
+use lettre::message::{header::ContentType, Attachment, Mailbox, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use mail_parser::{MessageParser, MimeHeaders};
+use rustls::{pki_types::ServerName, ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+use url::Url;
+
+use crate::interface::file_system::FileMetadata;
+use crate::interface::index::IterIndex;
+use crate::interface::storage::staging;
+use crate::interface::{Compression, RemoteStorage};
+use crate::ledger::LedgerEntry;
+use crate::signature::Signature;
+use crate::substitute::Substitution;
+use crate::versions::Version;
+use crate::{error::ErrorSuggestions, RemoteKind, Save};
+
+/// What the subject of every email that scut sends starts with
+const SUBJECT_TAG: &str = "[scut] ";
+
+/// The records that are emailed, by their name in the subject
+const SUBSTITUTIONS_RECORD: &str = "substitutions";
+const SIGNATURES_RECORD: &str = "signatures";
+const LEDGER_RECORD: &str = "ledger";
+
+/// A connection to a mail server, with or without TLS
+trait Stream: Read + Write + Send {}
+impl<T: Read + Write + Send> Stream for T {}
+
+/// This implementation is used to share saves by emailing them to the game's mailing list.
+#[derive(Clone)]
+pub struct PlayByEmail {
+    /// The IMAP folder that the mailing list delivers to
+    imap: Url,
+    /// The SMTP server that emails are sent through
+    smtp: Url,
+    mailing_list: Mailbox,
+    from: Mailbox,
+    username: String,
+    password: String,
+    compression: Box<dyn Compression>,
+    /// Every email from scut in the folder, oldest first
+    emails: Vec<Email>,
+    /// Where the current copy of each save is
+    saves: BTreeMap<Save, Latest>,
+    /// The saves uploaded since the folder was read, which the mailing list may not have delivered yet
+    ///
+    /// Shared between clones, so that an upload through one clone can be recorded through another.
+    sent: Arc<Mutex<BTreeMap<Save, (String, Vec<u8>)>>>,
+}
+
+/// An email from scut in the IMAP folder
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Email {
+    uid: u32,
+    subject: Subject,
+    /// When the email was sent, in seconds since the unix epoch
+    timestamp: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Subject {
+    Upload(Save),
+    Supersede(Save),
+    Record(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Latest {
+    /// Attached to the email with this uid
+    Email(u32),
+    /// Sent by this computer, named and with the content of the archive
+    Sent(String, Vec<u8>),
+}
+
+impl PlayByEmail {
+    /// Log in to the IMAP folder at `imap` and read which saves have been emailed to it.
+    ///
+    /// `imaps://` and `smtps://` urls connect with TLS, and `smtp://` urls upgrade to TLS with STARTTLS unless they are on this computer.
+    /// `imap://` urls are only accepted for servers on this computer, since they would log in without TLS.
+    pub fn new(
+        imap: &str,
+        smtp: &str,
+        mailing_list: &str,
+        credentials: Option<(&str, Option<&str>)>,
+        compression: Box<dyn Compression>,
+    ) -> anyhow::Result<Self> {
+        let parse = |url: &str, key: &str| {
+            Url::parse(url)
+                .with_context(|| format!("`{url}` is not a valid url"))
+                .suggest(format!("Use `scut config set {key} <URL>` to set the url, e.g. `imaps://imap.example.com/scut-ww1`"))
+        };
+        let imap = parse(imap, "remote.url")?;
+        let smtp = parse(smtp, "remote.smtp")?;
+
+        if imap.scheme() != "imaps" && !imap.host_str().is_some_and(is_local) {
+            return Ok(Err(anyhow::anyhow!(
+                "`{imap}` would send your password to the IMAP server without TLS"
+            ))
+            .suggest("Use `scut config set remote.url <URL>` to set an `imaps://` url, e.g. `imaps://imap.example.com/scut-ww1`")?);
+        }
+
+        let Some((username, Some(password))) = credentials else {
+            return Ok(Err(anyhow::anyhow!(
+                "remote.username and remote.password must be set to play by email"
+            ))
+            .suggest(
+                "Set them to the email address and password you log in to your email with",
+            )?);
+        };
+
+        let mailing_list = mailing_list
+            .parse()
+            .with_context(|| format!("`{mailing_list}` is not a valid email address"))
+            .suggest("Use `scut config set remote.mailing_list <ADDRESS>` to set the address of your game's mailing list")?;
+        let from = username
+            .parse()
+            .with_context(|| format!("`{username}` is not a valid email address"))
+            .suggest("Set remote.username to the email address you log in to your email with")?;
+
+        let mut email = PlayByEmail {
+            imap,
+            smtp,
+            mailing_list,
+            from,
+            username: username.to_string(),
+            password: password.to_string(),
+            compression,
+            emails: Vec::new(),
+            saves: BTreeMap::new(),
+            sent: Arc::new(Mutex::new(BTreeMap::new())),
+        };
+
+        email.refresh_saves()?;
+
+        Ok(email)
+    }
+
+    /// Reads again which emails from scut are in the IMAP folder
+    pub fn refresh_saves(&mut self) -> anyhow::Result<()> {
+        let headers = self
+            .session(|session, exists| {
+                // fetching from an empty folder is an error
+                if exists == 0 {
+                    return Ok(Vec::new());
+                }
+                let fetches = session.uid_fetch("1:*", "RFC822.HEADER")?;
+                Ok(fetches
+                    .iter()
+                    .filter_map(|fetch| Some((fetch.uid?, fetch.header()?.to_vec())))
+                    .collect())
+            })
+            .context("failed to read the emails in your IMAP folder")?;
+
+        self.emails = headers
+            .into_iter()
+            .filter_map(|(uid, header)| {
+                let header = MessageParser::default().parse(&header)?;
+                Some(Email {
+                    uid,
+                    subject: header.subject()?.parse().ok()?,
+                    timestamp: header
+                        .date()
+                        .map(|date| date.to_timestamp().max(0) as u64)
+                        .unwrap_or_default(),
+                })
+            })
+            .collect();
+        self.emails.sort_by_key(|email| email.uid);
+
+        self.saves.clear();
+        for email in &self.emails {
+            match &email.subject {
+                Subject::Upload(save) => {
+                    self.saves.insert(save.clone(), Latest::Email(email.uid));
+                }
+                Subject::Supersede(save) => {
+                    self.saves.remove(save);
+                }
+                Subject::Record(_) => {}
+            }
+        }
+        let sent = self.sent().clone();
+        for (save, (name, content)) in sent {
+            self.saves.insert(save, Latest::Sent(name, content));
+        }
+
+        Ok(())
+    }
+
+    /// Log in to the IMAP folder and run `f` with the number of emails in it
+    fn session<T, F>(&self, f: F) -> anyhow::Result<T>
+    where
+        F: FnOnce(&mut Session<Box<dyn Stream>>, u32) -> imap::error::Result<T>,
+    {
+        let host = self
+            .imap
+            .host_str()
+            .ok_or_else(|| anyhow::anyhow!("`{}` doesn't name a server", self.imap))?;
+        // `new` only accepts `imap://` urls for servers on this computer
+        let tls = self.imap.scheme() == "imaps";
+        let port = match self.imap.port() {
+            Some(port) => port,
+            None if tls => 993,
+            None => 143,
+        };
+
+        let tcp = TcpStream::connect((host, port))
+            .with_context(|| format!("failed to connect to {host}:{port}"))
+            .suggest(RemoteKind::Email.sync_suggestion())?;
+        let stream: Box<dyn Stream> = if tls {
+            Box::new(
+                tls_stream(host, tcp)
+                    .with_context(|| format!("failed to connect securely to {host}:{port}"))?,
+            )
+        } else {
+            Box::new(tcp)
+        };
+
+        let mut client = imap::Client::new(stream);
+        client
+            .read_greeting()
+            .with_context(|| format!("{host} isn't an IMAP server"))?;
+        let mut session = client
+            .login(&self.username, &self.password)
+            .map_err(|(error, _)| error)
+            .with_context(|| format!("{host} didn't accept {} logging in", self.username))
+            .suggest("Check remote.username and remote.password in your config")?;
+
+        let folder = folder_name(&self.imap);
+        let mailbox = session
+            .select(&folder)
+            .with_context(|| format!("failed to open the IMAP folder {folder}"))
+            .suggest(RemoteKind::Email.sync_suggestion())?;
+
+        let result = f(&mut session, mailbox.exists)?;
+        let _ = session.logout();
+        Ok(result)
+    }
+
+    /// Fetch whole emails by uid, oldest first
+    fn fetch(&self, uids: &[u32]) -> anyhow::Result<Vec<Vec<u8>>> {
+        if uids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let set = uids
+            .iter()
+            .map(u32::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+        let mut fetched = self
+            .session(|session, _| {
+                Ok(session
+                    .uid_fetch(&set, "RFC822")?
+                    .iter()
+                    .filter_map(|fetch| Some((fetch.uid?, fetch.body()?.to_vec())))
+                    .collect::<Vec<_>>())
+            })
+            .context("failed to download emails from your IMAP folder")?;
+        fetched.sort_by_key(|(uid, _)| *uid);
+
+        Ok(fetched.into_iter().map(|(_, email)| email).collect())
+    }
+
+    /// The name and content of the archive attached to the email with `uid`
+    fn attachment(&self, uid: u32) -> anyhow::Result<Option<(String, Vec<u8>)>> {
+        let Some(email) = self.fetch(&[uid])?.pop() else {
+            return Ok(None);
+        };
+        let email = MessageParser::default()
+            .parse(&email)
+            .ok_or_else(|| anyhow::anyhow!("failed to read an email in your IMAP folder"))?;
+
+        Ok(email.attachments().find_map(|attachment| {
+            Some((
+                attachment.attachment_name()?.to_string(),
+                attachment.contents().to_vec(),
+            ))
+        }))
+    }
+
+    /// The saves uploaded since the folder was read, by this or any clone of it
+    fn sent(&self) -> MutexGuard<'_, BTreeMap<Save, (String, Vec<u8>)>> {
+        self.sent
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// The name and content of the current copy of `save`
+    fn archive(&mut self, save: &Save) -> anyhow::Result<Option<(String, Vec<u8>)>> {
+        if let Some(sent) = self.sent().get(save) {
+            return Ok(Some(sent.clone()));
+        }
+        if !self.saves.contains_key(save) {
+            self.refresh_saves()?;
+        }
+
+        match self.saves.get(save).cloned() {
+            Some(Latest::Email(uid)) => self.attachment(uid),
+            Some(Latest::Sent(name, content)) => Ok(Some((name, content))),
+            None => Ok(None),
+        }
+    }
+
+    /// Email the mailing list, attaching the archive if there is one
+    fn send(
+        &self,
+        subject: &Subject,
+        body: String,
+        archive: Option<(&str, Vec<u8>)>,
+    ) -> anyhow::Result<()> {
+        let builder = Message::builder()
+            .from(self.from.clone())
+            .to(self.mailing_list.clone())
+            .subject(subject.to_string())
+            .date_now();
+
+        let email = match archive {
+            Some((name, content)) => builder.multipart(
+                MultiPart::mixed()
+                    .singlepart(SinglePart::plain(body))
+                    .singlepart(
+                        Attachment::new(name.to_string())
+                            .body(content, ContentType::parse("application/octet-stream")?),
+                    ),
+            ),
+            None => builder.singlepart(SinglePart::plain(body)),
+        }
+        .with_context(|| format!("failed to write the email `{subject}`"))?;
+
+        let host = self
+            .smtp
+            .host_str()
+            .ok_or_else(|| anyhow::anyhow!("`{}` doesn't name a server", self.smtp))?;
+        let transport = if self.smtp.scheme() == "smtps" {
+            SmtpTransport::relay(host)?
+        } else if is_local(host) {
+            SmtpTransport::builder_dangerous(host)
+        } else {
+            SmtpTransport::starttls_relay(host)?
+        };
+        let transport = match self.smtp.port() {
+            Some(port) => transport.port(port),
+            None => transport,
+        };
+
+        transport
+            .credentials(Credentials::new(
+                self.username.clone(),
+                self.password.clone(),
+            ))
+            .build()
+            .send(&email)
+            .with_context(|| format!("failed to send the email `{subject}`"))
+            .suggest("Is your email provider online, and are remote.smtp, remote.username and remote.password correct?")?;
+        Ok(())
+    }
+
+    fn send_record(&self, record: &str, line: &dyn fmt::Display) -> anyhow::Result<()> {
+        self.send(&Subject::Record(record.to_string()), line.to_string(), None)
+    }
+
+    /// Read every record of one kind, oldest first
+    fn read_records<T>(&self, record: &str) -> anyhow::Result<Vec<T>>
+    where
+        T: FromStr<Err = anyhow::Error>,
+    {
+        let uids: Vec<u32> = self
+            .emails
+            .iter()
+            .filter(|email| email.subject == Subject::Record(record.to_string()))
+            .map(|email| email.uid)
+            .collect();
+
+        let mut records = Vec::new();
+        for email in self.fetch(&uids)? {
+            let Some(email) = MessageParser::default().parse(&email) else {
+                continue;
+            };
+            for line in email.body_text(0).unwrap_or_default().lines() {
+                if !line.trim().is_empty() {
+                    records.push(line.trim().parse()?);
+                }
+            }
+        }
+        Ok(records)
+    }
+
+    /// The uid and timestamp of each upload of `save` before the current copy, oldest first
+    fn version_emails(&self, save: &Save) -> Vec<(u32, u64)> {
+        let mut uploads: Vec<&Email> = self
+            .emails
+            .iter()
+            .filter(|email| email.subject == Subject::Upload(save.clone()))
+            .collect();
+
+        // the latest upload is the current copy, unless this computer has uploaded since
+        if matches!(self.saves.get(save), Some(Latest::Email(_))) {
+            uploads.pop();
+        }
+
+        let mut versions = Vec::new();
+        let mut previous = None;
+        for upload in uploads {
+            // versions are identified by timestamp, so two sent within a second are told apart
+            let timestamp = match previous {
+                Some(previous) if upload.timestamp <= previous => previous + 1,
+                _ => upload.timestamp,
+            };
+            versions.push((upload.uid, timestamp));
+            previous = Some(timestamp);
+        }
+        versions
+    }
+}
+
+impl fmt::Display for Subject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Subject::Upload(save) => write!(f, "{SUBJECT_TAG}{save}"),
+            Subject::Supersede(save) => write!(f, "{SUBJECT_TAG}supersede {save}"),
+            Subject::Record(record) => write!(f, "{SUBJECT_TAG}record {record}"),
+        }
+    }
+}
+
+impl FromStr for Subject {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (_, subject) = s
+            .split_once(SUBJECT_TAG)
+            .ok_or_else(|| anyhow::anyhow!("`{s}` isn't an email from scut"))?;
+        let subject = subject.trim();
+
+        if let Some(save) = subject.strip_prefix("supersede ") {
+            Ok(Subject::Supersede(save.parse()?))
+        } else if let Some(record) = subject.strip_prefix("record ") {
+            Ok(Subject::Record(record.to_string()))
+        } else {
+            Ok(Subject::Upload(subject.parse()?))
+        }
+    }
+}
+
+/// The IMAP folder named by the path of `url`, the inbox if it has none
+fn folder_name(url: &Url) -> String {
+    let folder = percent_encoding::percent_decode_str(url.path().trim_matches('/'))
+        .decode_utf8_lossy()
+        .into_owned();
+    if folder.is_empty() {
+        String::from("INBOX")
+    } else {
+        folder
+    }
+}
+
+/// Returns true if `host` is this computer, which is reached without TLS unless the url asks for it
+fn is_local(host: &str) -> bool {
+    matches!(host, "localhost" | "127.0.0.1" | "[::1]")
+}
+
+/// Connect with TLS over `tcp`, trusting the same root certificates as web browsers
+fn tls_stream(
+    host: &str,
+    tcp: TcpStream,
+) -> anyhow::Result<StreamOwned<ClientConnection, TcpStream>> {
+    let mut roots = RootCertStore::empty();
+    roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    let config = ClientConfig::builder()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+
+    let name = ServerName::try_from(host.to_string())
+        .with_context(|| format!("`{host}` is not a valid server name"))?;
+    let connection = ClientConnection::new(Arc::new(config), name)?;
+    Ok(StreamOwned::new(connection, tcp))
+}
+
+impl RemoteStorage for PlayByEmail {
+    fn download(&mut self, save: &Save, local_path: &Path) -> anyhow::Result<()> {
+        let Some((name, content)) = self.archive(save)? else {
+            return Ok(Err(anyhow::anyhow!("{save} not found in your IMAP folder"))
+                .suggest(RemoteKind::Email.sync_suggestion())
+                .suggest("Have your friends emailed their turn?")?);
+        };
+
+        staging::decompress(self.compression.as_ref(), &name, &content, local_path)
+            .with_context(|| format!("failed to download {save}"))
+    }
+
+    fn upload(&mut self, save: &Save, local_path: &Path) -> anyhow::Result<()> {
+        let (name, content) = staging::compress(self.compression.as_ref(), save, local_path)
+            .with_context(|| format!("failed to upload {save}"))?;
+
+        self.send(
+            &Subject::Upload(save.clone()),
+            format!("{save} is attached, download it with scut."),
+            Some((&name, content.clone())),
+        )
+        .with_context(|| format!("failed to upload {save}"))?;
+
+        self.saves
+            .insert(save.clone(), Latest::Sent(name.clone(), content.clone()));
+        self.sent().insert(save.clone(), (name, content));
+        Ok(())
+    }
+
+    fn supersede(&mut self, save: &Save) -> anyhow::Result<()> {
+        self.send(
+            &Subject::Supersede(save.clone()),
+            format!("{save} has been superseded."),
+            None,
+        )
+        .with_context(|| format!("failed to supersede {save}"))?;
+
+        self.saves.remove(save);
+        self.sent().remove(save);
+        Ok(())
+    }
+
+    fn record_substitution(&mut self, substitution: &Substitution) -> anyhow::Result<()> {
+        self.send_record(SUBSTITUTIONS_RECORD, substitution)
+            .with_context(|| format!("failed to record {substitution}"))
+    }
+
+    fn substitutions(&mut self) -> anyhow::Result<Vec<Substitution>> {
+        self.read_records(SUBSTITUTIONS_RECORD)
+            .context("failed to read substitutions")
+    }
+
+    fn record_signature(&mut self, signature: &Signature) -> anyhow::Result<()> {
+        self.send_record(SIGNATURES_RECORD, signature)
+            .with_context(|| format!("failed to record the signature of {}", signature.save))
+    }
+
+    fn signatures(&mut self) -> anyhow::Result<Vec<Signature>> {
+        self.read_records(SIGNATURES_RECORD)
+            .context("failed to read signatures")
+    }
+
+    fn record_upload(&mut self, entry: &LedgerEntry) -> anyhow::Result<()> {
+        self.send_record(LEDGER_RECORD, entry)
+            .with_context(|| format!("failed to record the upload of {}", entry.save))
+    }
+
+    fn ledger(&mut self) -> anyhow::Result<Vec<LedgerEntry>> {
+        let mut ledger: Vec<LedgerEntry> = self
+            .read_records(LEDGER_RECORD)
+            .context("failed to read the ledger")?;
+        ledger.sort_by_key(|entry| entry.timestamp);
+        Ok(ledger)
+    }
+
+    fn versions(&mut self, save: &Save) -> anyhow::Result<Vec<Version>> {
+        let mut versions = Vec::new();
+        for (uid, timestamp) in self.version_emails(save) {
+            let size = self
+                .attachment(uid)?
+                .map(|(_, content)| content.len() as u64)
+                .unwrap_or_default();
+            versions.push(Version {
+                save: save.clone(),
+                timestamp,
+                size,
+            });
+        }
+        Ok(versions)
+    }
+
+    fn restore(&mut self, save: &Save, version: u64) -> anyhow::Result<()> {
+        let uid = self
+            .version_emails(save)
+            .into_iter()
+            .find(|(_, timestamp)| *timestamp == version)
+            .map(|(uid, _)| uid);
+        let Some((name, content)) = uid.map(|uid| self.attachment(uid)).transpose()?.flatten()
+        else {
+            return Ok(Err(anyhow::anyhow!(
+                "{save} version {version} not found in your IMAP folder"
+            ))
+            .suggest(RemoteKind::Email.sync_suggestion())?);
+        };
+
+        // the restored copy is emailed again, so that it is the latest upload
+        self.send(
+            &Subject::Upload(save.clone()),
+            format!("{save} version {version} has been restored, download it with scut."),
+            Some((&name, content.clone())),
+        )
+        .with_context(|| format!("failed to restore {save} version {version}"))?;
+
+        self.saves
+            .insert(save.clone(), Latest::Sent(name.clone(), content.clone()));
+        self.sent().insert(save.clone(), (name, content));
+        Ok(())
+    }
+
+    fn metadata(&mut self, save: &Save) -> anyhow::Result<Option<FileMetadata>> {
+        Ok(self
+            .archive(save)
+            .with_context(|| format!("failed to read {save} in your IMAP folder"))?
+            .map(|(_, content)| FileMetadata::new(&content, None)))
+    }
+
+    fn index(&self) -> &dyn crate::interface::Index {
+        self
+    }
+}
+
+impl<'a> IterIndex<'a> for PlayByEmail {
+    type Iter = std::collections::btree_map::Keys<'a, Save, Latest>;
+
+    fn iter(&'a self) -> Self::Iter {
+        self.saves.keys()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{
+        interface::{
+            compression::copy_compression::CopyCompression, index::Query,
+            storage::mock_mail_server::MockMailServer,
+        },
+        ledger::{find_uploads, record_uploads},
+        Side,
+    };
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn connect(server: &MockMailServer) -> anyhow::Result<PlayByEmail> {
+        PlayByEmail::new(
+            &server.imap,
+            &server.smtp,
+            "ww1@groups.example.com",
+            Some(("dm@example.com", Some("secret"))),
+            Box::new(CopyCompression),
+        )
+    }
+
+    #[test]
+    fn subjects_round_trip() -> anyhow::Result<()> {
+        let save = Save::from_parts(Side::Axis, 12).player("DM");
+        for subject in [
+            Subject::Upload(save.clone()),
+            Subject::Supersede(save.clone()),
+            Subject::Record(LEDGER_RECORD.to_string()),
+        ] {
+            assert_eq!(subject.to_string().parse::<Subject>()?, subject);
+        }
+
+        // tagged by the mailing list
+        assert_eq!(
+            "[ww1] [scut] Axis DM 12".parse::<Subject>()?,
+            Subject::Upload(save)
+        );
+        assert!("Re: dinner on Friday?".parse::<Subject>().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn email_upload_and_download() -> anyhow::Result<()> {
+        let server = MockMailServer::start("dm@example.com", "secret");
+        let local = tempfile::tempdir()?;
+        let teammate = tempfile::tempdir()?;
+
+        let save = Save::from_parts(Side::Axis, 12).player("DM");
+        let path = local.path().join("Axis DM 12.sav");
+        fs::write(&path, "DM's turn")?;
+
+        let mut remote = connect(&server)?;
+        remote.upload(&save, &path)?;
+        let uploaded = find_uploads(&mut remote, &[save.clone()])?;
+        record_uploads(&mut remote, &uploaded, "DM")?;
+
+        let emails = server.emails();
+        assert_eq!(emails.len(), 2);
+        assert!(emails[0].contains("Subject: [scut] Axis DM 12"));
+        assert!(emails[0].contains("To: ww1@groups.example.com"));
+
+        // re-uploading keeps the previous email as a version
+        fs::write(&path, "DM's turn, again")?;
+        remote.upload(&save, &path)?;
+
+        let mut other = connect(&server)?;
+        assert_eq!(other.index().search(&Query::new())?, vec![save.clone()]);
+        other.download(&save, teammate.path())?;
+        assert_eq!(
+            fs::read_to_string(teammate.path().join("Axis DM 12.sav"))?,
+            "DM's turn, again"
+        );
+        assert_eq!(other.versions(&save)?.len(), 1);
+        assert_eq!(other.ledger()?.len(), 1);
+
+        other.supersede(&save)?;
+        let other = connect(&server)?;
+        assert_eq!(other.index().search(&Query::new())?, Vec::new());
+
+        Ok(())
+    }
+
+    #[test]
+    fn email_uploads_are_recorded_through_another_clone() -> anyhow::Result<()> {
+        let server = MockMailServer::start("dm@example.com", "secret");
+        let local = tempfile::tempdir()?;
+
+        let save = Save::from_parts(Side::Axis, 12).player("DM");
+        let path = local.path().join("Axis DM 12.sav");
+        fs::write(&path, "DM's turn")?;
+
+        // the mailing list hasn't delivered the upload by the time it is recorded
+        server.hold();
+        let mut remote = connect(&server)?;
+        let mut uploader = remote.clone();
+        uploader.upload(&save, &path)?;
+        let uploaded = find_uploads(&mut remote, &[save.clone()])?;
+        record_uploads(&mut remote, &uploaded, "DM")?;
+        server.deliver();
+
+        let mut other = connect(&server)?;
+        let ledger = other.ledger()?;
+        assert_eq!(ledger.len(), 1);
+        assert_eq!(ledger[0].save, save);
+        assert_eq!(ledger[0].digest, uploaded[0].metadata.digest);
+        assert_eq!(other.metadata(&save)?, Some(uploaded[0].metadata.clone()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn email_refuses_to_log_in_without_tls() {
+        let error = PlayByEmail::new(
+            "imap://imap.example.com/scut-ww1",
+            "smtp://smtp.example.com",
+            "ww1@groups.example.com",
+            Some(("dm@example.com", Some("secret"))),
+            Box::new(CopyCompression),
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("without TLS"));
+    }
+
+    #[test]
+    fn email_wrong_password() {
+        let server = MockMailServer::start("dm@example.com", "secret");
+
+        assert!(PlayByEmail::new(
+            &server.imap,
+            &server.smtp,
+            "ww1@groups.example.com",
+            Some(("dm@example.com", Some("wrong"))),
+            Box::new(CopyCompression),
+        )
+        .is_err());
+    }
+}
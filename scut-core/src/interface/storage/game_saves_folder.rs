@@ -3,7 +3,10 @@ use std::path::{Path, PathBuf};
 
 use anyhow::Context;
 
+use crate::interface::file_system::FileMetadata;
 use crate::interface::index::IterIndex;
+use crate::interface::storage::sync_record::SyncRecord;
+use crate::interface::storage::{SUPERSEDED_FOLDER, SYNC_RECORD_FILE};
 use crate::interface::{FileSystem, LocalStorage};
 use crate::save::{path_to_save, SaveOrAutosave};
 use crate::Save;
@@ -121,6 +124,63 @@ impl LocalStorage for GameSavesFolder {
         self.location.as_path()
     }
 
+    fn supersede(&mut self, save: &Save) -> anyhow::Result<()> {
+        let path = self.saves.remove(save).ok_or_else(|| {
+            anyhow::anyhow!(
+                "{save} not found in your game saves folder {}",
+                self.location.display()
+            )
+        })?;
+
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| anyhow::anyhow!("{} is not a file", path.display()))?;
+        let superseded = self.location.join(SUPERSEDED_FOLDER).join(file_name);
+
+        self.file_system
+            .move_file(&path, &superseded)
+            .with_context(|| format!("failed to supersede {save}"))
+    }
+
+    fn metadata(&mut self, save: &Save) -> anyhow::Result<Option<FileMetadata>> {
+        match self.locate_save(save)? {
+            Some(path) => Ok(Some(self.file_system.metadata(&path).with_context(
+                || format!("failed to read {save} in your game saves folder"),
+            )?)),
+            None => Ok(None),
+        }
+    }
+
+    fn autosave_metadata(&mut self) -> anyhow::Result<Option<FileMetadata>> {
+        match self.locate_autosave()? {
+            Some(path) => {
+                Ok(Some(self.file_system.metadata(&path).context(
+                    "failed to read the autosave in your game saves folder",
+                )?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn sync_record(&mut self) -> anyhow::Result<SyncRecord> {
+        let path = self.location.join(SYNC_RECORD_FILE);
+
+        if !self.file_system.file_exists(&path)? {
+            return Ok(SyncRecord::default());
+        }
+
+        SyncRecord::from_toml(&self.file_system.read_file_to_string(&path)?)
+            .with_context(|| format!("failed to read '{}'", path.display()))
+    }
+
+    fn write_sync_record(&mut self, record: &SyncRecord) -> anyhow::Result<()> {
+        let path = self.location.join(SYNC_RECORD_FILE);
+
+        self.file_system
+            .write_string_to_file(&record.to_toml()?, &path)
+            .with_context(|| format!("failed to write '{}'", path.display()))
+    }
+
     fn index(&self) -> &dyn crate::interface::Index {
         self
     }
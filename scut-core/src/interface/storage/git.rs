@@ -0,0 +1,474 @@
+//! Keeps saves in a local clone of a git repository, so that every upload is a commit, with history and attribution for free.
+//!
+//! The clone is an ordinary folder of saves, see [`SyncedFolder`], that scut keeps in sync with the repository by running `git`:
+//!
+//! * the latest commits are pulled when the storage is loaded, before anything is predicted
+//! * uploads, and the records of them in the ledger and signatures, are committed and pushed together when they are
+//!   [published](RemoteStorage::publish), e.g. `Axis DM 12 uploaded by DM`
+//! * superseding and archiving saves is committed and pushed straight away
+//!
+//! Clones of the storage share the working tree, e.g. to upload player saves in another thread,
+//! so only one of them changes it or runs git at a time.
+//!
+//! If someone else pushed first, the push is rejected. The clone is then reset to match the repository and the upload has to be made again,
+//! rather than scut trying to merge saves.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::Context;
+use tracing::{debug, instrument};
+
+use crate::interface::file_system::FileMetadata;
+use crate::interface::process::background;
+use crate::interface::storage::{synced_folder::SyncedFolder, Archive};
+use crate::interface::{Compression, FileSystem, Index, RemoteStorage};
+use crate::ledger::LedgerEntry;
+use crate::signature::Signature;
+use crate::substitute::Substitution;
+use crate::versions::Version;
+use crate::{error::ErrorSuggestions, Provider, RemoteKind, Save};
+
+/// This implementation is used to store the saves in a git repository, which is shared with other players by pushing and pulling.
+#[derive(Clone)]
+pub struct GitRepository {
+    /// The working tree of the clone
+    folder: SyncedFolder,
+    location: PathBuf,
+    /// The player that commits are made by
+    player: String,
+    /// What has changed in the working tree since the last commit, e.g. `Axis DM 12 uploaded`, shared with every clone
+    unpublished: Arc<Mutex<Vec<String>>>,
+}
+
+/// A commit in the repository's history
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Commit {
+    pub id: String,
+    pub author: String,
+    /// When the commit was made, in seconds since the unix epoch
+    pub timestamp: u64,
+    /// The first line of the commit message, e.g. `Axis DM 12 uploaded by DM`
+    pub message: String,
+}
+
+impl GitRepository {
+    /// Clone the repository at `url` into `location`, unless it has been cloned already, then pull the latest commits
+    pub fn new(
+        url: &str,
+        location: PathBuf,
+        player: &str,
+        file_system: Box<dyn FileSystem>,
+        compression: Box<dyn Compression>,
+    ) -> anyhow::Result<Self> {
+        if !location.join(".git").exists() {
+            let parent = location.parent().unwrap_or(Path::new("."));
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("failed to create '{}'", parent.display()))?;
+
+            run(parent, &["clone", url, &location.to_string_lossy()])
+                .with_context(|| format!("failed to clone {url}"))
+                .suggest(RemoteKind::Git.sync_suggestion())?;
+        }
+
+        // nothing else syncs the clone, so git never leaves conflicted copies in it
+        let mut repository = GitRepository {
+            folder: SyncedFolder::new(
+                location.clone(),
+                Provider::Folder,
+                file_system,
+                compression,
+            )?,
+            location,
+            player: player.to_string(),
+            unpublished: Arc::new(Mutex::new(Vec::new())),
+        };
+
+        repository.pull()?;
+
+        Ok(repository)
+    }
+
+    /// Fetch the latest commits and fast-forward the clone to them
+    #[instrument(skip(self), err)]
+    pub fn pull(&mut self) -> anyhow::Result<()> {
+        self.git(&["fetch", "--quiet"])
+            .context("failed to pull the latest saves")
+            .suggest(RemoteKind::Git.sync_suggestion())?;
+
+        // nobody has pushed anything yet
+        if !self.has_upstream() {
+            return Ok(());
+        }
+
+        self.git(&["merge", "--ff-only", "--quiet", "@{u}"])
+            .context("failed to pull the latest saves, your clone has commits that aren't in the repository")
+            .suggest(format!(
+                "Check `git status` in '{}', or delete it to clone the repository again",
+                self.location.display()
+            ))?;
+
+        self.folder.refresh_saves()
+    }
+
+    /// Every commit in the repository, newest first
+    pub fn history(&self) -> anyhow::Result<Vec<Commit>> {
+        if self
+            .git(&["rev-parse", "--verify", "--quiet", "HEAD"])
+            .is_err()
+        {
+            return Ok(Vec::new());
+        }
+
+        self.git(&["log", "--format=%H%x09%an%x09%at%x09%s"])?
+            .lines()
+            .map(|line| {
+                let mut fields = line.splitn(4, '\t');
+                let mut field = || {
+                    fields
+                        .next()
+                        .ok_or_else(|| anyhow::anyhow!("`{line}` is not a valid commit"))
+                };
+                Ok(Commit {
+                    id: field()?.to_string(),
+                    author: field()?.to_string(),
+                    timestamp: field()?
+                        .parse()
+                        .with_context(|| format!("`{line}` is not a valid commit"))?,
+                    message: field()?.to_string(),
+                })
+            })
+            .collect()
+    }
+
+    /// Make a change to the working tree, to be committed when it is published as `description`, if it has one.
+    fn stage<F>(&mut self, description: Option<String>, change: F) -> anyhow::Result<()>
+    where
+        F: FnOnce(&mut SyncedFolder) -> anyhow::Result<()>,
+    {
+        let unpublished = Arc::clone(&self.unpublished);
+        let mut unpublished = unpublished
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        change(&mut self.folder)?;
+        unpublished.extend(description);
+        Ok(())
+    }
+
+    fn git(&self, args: &[&str]) -> anyhow::Result<String> {
+        run(&self.location, args)
+    }
+
+    fn has_upstream(&self) -> bool {
+        self.git(&["rev-parse", "--verify", "--quiet", "@{u}"])
+            .is_ok()
+    }
+
+    /// Commit every change in the clone with `message` and push it, if anything changed
+    #[instrument(skip(self), err)]
+    fn commit_and_push(&mut self, message: &str) -> anyhow::Result<()> {
+        self.git(&["add", "--all"])?;
+
+        // nothing to commit
+        if self.git(&["diff", "--cached", "--quiet"]).is_ok() {
+            return Ok(());
+        }
+
+        // commits are attributed to the player, unless git already knows who they are
+        let name = format!("user.name={}", self.player);
+        let email = format!("user.email={}@scut.invalid", self.player);
+        let mut commit = Vec::new();
+        if self.git(&["config", "user.email"]).is_err() {
+            commit.extend(["-c", name.as_str(), "-c", email.as_str()]);
+        }
+        commit.extend(["commit", "--quiet", "-m", message]);
+        self.git(&commit)
+            .with_context(|| format!("failed to commit `{message}`"))?;
+
+        let Err(error) = self.git(&["push", "--quiet", "--set-upstream", "origin", "HEAD"]) else {
+            return Ok(());
+        };
+
+        if !is_rejected(&error) {
+            return Err(error)
+                .with_context(|| format!("failed to push `{message}`"))
+                .suggest(RemoteKind::Git.sync_suggestion())?;
+        }
+
+        // put the clone back how the repository has it, so that the next pull fast-forwards
+        self.git(&["fetch", "--quiet"])?;
+        self.git(&["reset", "--hard", "--quiet", "@{u}"])?;
+        self.folder.refresh_saves()?;
+
+        Err(anyhow::anyhow!(
+            "failed to push `{message}`, someone else pushed to the repository since you last pulled"
+        ))
+        .suggest("Run scut again to pull their changes, then try again")?
+    }
+}
+
+/// Run git in `folder`, returning what it printed
+fn run(folder: &Path, args: &[&str]) -> anyhow::Result<String> {
+    debug!(?folder, ?args, "running git");
+
+    let output = background("git")
+        .current_dir(folder)
+        .args(args)
+        .output()
+        .context("failed to run git")
+        .suggest("Install git from https://git-scm.com and make sure it is on your PATH")?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "`git {}` failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Returns true if a push failed because the repository has commits that the clone doesn't
+fn is_rejected(error: &anyhow::Error) -> bool {
+    let message = error.to_string();
+    ["[rejected]", "non-fast-forward", "fetch first"]
+        .iter()
+        .any(|reason| message.contains(reason))
+}
+
+impl RemoteStorage for GitRepository {
+    fn download(&mut self, save: &Save, local_path: &Path) -> anyhow::Result<()> {
+        self.folder.download(save, local_path)
+    }
+
+    fn upload(&mut self, save: &Save, local_path: &Path) -> anyhow::Result<()> {
+        self.stage(Some(format!("{save} uploaded")), |folder| {
+            folder.upload(save, local_path)
+        })
+    }
+
+    fn supersede(&mut self, save: &Save) -> anyhow::Result<()> {
+        self.stage(Some(format!("{save} superseded")), |folder| {
+            folder.supersede(save)
+        })?;
+        self.publish()
+    }
+
+    fn record_substitution(&mut self, substitution: &Substitution) -> anyhow::Result<()> {
+        self.stage(None, |folder| folder.record_substitution(substitution))
+    }
+
+    fn substitutions(&mut self) -> anyhow::Result<Vec<Substitution>> {
+        self.folder.substitutions()
+    }
+
+    fn record_signature(&mut self, signature: &Signature) -> anyhow::Result<()> {
+        self.stage(None, |folder| folder.record_signature(signature))
+    }
+
+    fn signatures(&mut self) -> anyhow::Result<Vec<Signature>> {
+        self.folder.signatures()
+    }
+
+    fn record_upload(&mut self, entry: &LedgerEntry) -> anyhow::Result<()> {
+        self.stage(None, |folder| folder.record_upload(entry))
+    }
+
+    fn ledger(&mut self) -> anyhow::Result<Vec<LedgerEntry>> {
+        self.folder.ledger()
+    }
+
+    fn versions(&mut self, save: &Save) -> anyhow::Result<Vec<Version>> {
+        self.folder.versions(save)
+    }
+
+    fn restore(&mut self, save: &Save, version: u64) -> anyhow::Result<()> {
+        self.stage(
+            Some(format!("{save} version {version} restored")),
+            |folder| folder.restore(save, version),
+        )
+    }
+
+    fn metadata(&mut self, save: &Save) -> anyhow::Result<Option<FileMetadata>> {
+        self.folder.metadata(save)
+    }
+
+    fn archive(&mut self, name: &str, saves: &[Save]) -> anyhow::Result<()> {
+        self.stage(Some(format!("Archive {name} pruned")), |folder| {
+            folder.archive(name, saves)
+        })?;
+        self.publish()
+    }
+
+    fn archives(&mut self) -> anyhow::Result<Vec<Archive>> {
+        self.folder.archives()
+    }
+
+    /// Commit everything that changed since the last commit, by this clone or any other, and push it
+    fn publish(&mut self) -> anyhow::Result<()> {
+        let unpublished = Arc::clone(&self.unpublished);
+        let mut unpublished = unpublished
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        let message = match unpublished.as_slice() {
+            // only records changed, e.g. a substitution
+            [] => format!("Records updated by {}", self.player),
+            changes => format!("{} by {}", changes.join(", "), self.player),
+        };
+
+        // the changes are committed now, or thrown away if the push was rejected, so they aren't described again
+        let pushed = self.commit_and_push(&message);
+        unpublished.clear();
+        pushed
+    }
+
+    fn index(&self) -> &dyn Index {
+        self.folder.index()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{
+        interface::{
+            compression::copy_compression::CopyCompression,
+            file_system::local_file_system::LocalFileSystem, index::Query,
+        },
+        ledger::{find_uploads, record_uploads},
+        signature::{sign_uploads, SigningKey},
+        Side,
+    };
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn clone(remote: &Path, location: PathBuf, player: &str) -> anyhow::Result<GitRepository> {
+        GitRepository::new(
+            &remote.to_string_lossy(),
+            location,
+            player,
+            Box::new(LocalFileSystem::new()),
+            Box::new(CopyCompression),
+        )
+    }
+
+    fn bare_repository(dir: &Path) -> anyhow::Result<PathBuf> {
+        let remote = dir.join("campaign.git");
+        run(
+            dir,
+            &["init", "--bare", "--quiet", &remote.to_string_lossy()],
+        )?;
+        Ok(remote)
+    }
+
+    #[test]
+    fn git_upload_and_download() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let remote = bare_repository(dir.path())?;
+        let local = tempfile::tempdir()?;
+        let teammate = tempfile::tempdir()?;
+
+        let save = Save::from_parts(Side::Axis, 12).player("DM");
+        let path = local.path().join("Axis DM 12.sav");
+        fs::write(&path, "DM's turn")?;
+
+        let mut dm = clone(&remote, dir.path().join("dm"), "DM")?;
+        dm.upload(&save, &path)?;
+        dm.publish()?;
+
+        // a teammate clones the repository and downloads the save
+        let mut dg = clone(&remote, dir.path().join("dg"), "DG")?;
+        assert_eq!(dg.index().search(&Query::new())?, vec![save.clone()]);
+        dg.download(&save, teammate.path())?;
+        assert_eq!(
+            fs::read_to_string(teammate.path().join("Axis DM 12.sav"))?,
+            "DM's turn"
+        );
+
+        let history = dg.history()?;
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].message, "Axis DM 12 uploaded by DM");
+
+        Ok(())
+    }
+
+    #[test]
+    fn git_rejects_uploads_behind_the_repository() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let remote = bare_repository(dir.path())?;
+        let local = tempfile::tempdir()?;
+
+        let dm_save = Save::from_parts(Side::Axis, 12).player("DM");
+        let dg_save = Save::from_parts(Side::Axis, 12).player("DG");
+        fs::write(local.path().join("Axis DM 12.sav"), "DM's turn")?;
+        fs::write(local.path().join("Axis DG 12.sav"), "DG's turn")?;
+
+        // both players pull before either uploads
+        let mut dm = clone(&remote, dir.path().join("dm"), "DM")?;
+        let mut dg = clone(&remote, dir.path().join("dg"), "DG")?;
+
+        dg.upload(&dg_save, &local.path().join("Axis DG 12.sav"))?;
+        dg.publish()?;
+        dm.upload(&dm_save, &local.path().join("Axis DM 12.sav"))?;
+        assert!(dm.publish().is_err());
+
+        // DM's clone now matches the repository, so pulling and uploading again works
+        let mut dm = clone(&remote, dir.path().join("dm"), "DM")?;
+        assert_eq!(dm.index().search(&Query::new())?, vec![dg_save.clone()]);
+        dm.upload(&dm_save, &local.path().join("Axis DM 12.sav"))?;
+        dm.publish()?;
+        assert_eq!(dm.history()?.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn git_commits_uploads_from_every_clone_with_their_records() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let remote = bare_repository(dir.path())?;
+        let local = tempfile::tempdir()?;
+        let key = SigningKey::from_bytes(&[7; 32]);
+
+        let player_save = Save::from_parts(Side::Axis, 12).player("DM");
+        let autosave = Save::from_parts(Side::Allies, 12);
+        fs::write(local.path().join("Axis DM 12.sav"), "DM's turn")?;
+        fs::write(local.path().join("Allies 12.sav"), "end of Axis 12")?;
+
+        // the player save is uploaded by a clone in another thread while the autosave is uploaded
+        let mut dm = clone(&remote, dir.path().join("dm"), "DM")?;
+        let uploading = {
+            let mut dm = dm.clone();
+            let (save, path) = (player_save.clone(), local.path().join("Axis DM 12.sav"));
+            std::thread::spawn(move || dm.upload(&save, &path))
+        };
+        dm.upload(&autosave, &local.path().join("Allies 12.sav"))?;
+        uploading.join().expect("the upload thread panicked")?;
+
+        let uploads = find_uploads(&mut dm, &[player_save, autosave])?;
+        record_uploads(&mut dm, &uploads, "DM")?;
+        sign_uploads(&mut dm, &uploads, "DM", &key)?;
+        dm.publish()?;
+
+        // one commit with both saves, and the records of them
+        assert_eq!(dm.history()?.len(), 1);
+        let committed = dm.git(&["show", "--name-only", "--format=", "HEAD"])?;
+        let mut files = committed.lines().collect::<Vec<_>>();
+        files.sort();
+        assert_eq!(
+            files,
+            vec![
+                "Allies 12.copy",
+                "Axis DM 12.copy",
+                "ledger/DM.txt",
+                "signatures/DM.txt"
+            ]
+        );
+
+        Ok(())
+    }
+}
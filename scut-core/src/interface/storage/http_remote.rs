@@ -0,0 +1,357 @@
+//! Uploads saves to a game on a scut server, see `scut-server`, so that no sync client is needed.
+//!
+//! The url is the game on the server, e.g. `https://scut.example.com/games/ww1/`, and each request carries the player's token.
+//! The server checks the names of uploaded saves against the game's roster, and keeps the superseded saves, versions and records.
+//! It records each upload in the ledger itself, by the player whose token uploaded it, so that nobody can make up an upload.
+//!
+//! The API, relative to the game's url:
+//!
+//! ```text
+//! GET  saves                                    one "<size> <name>" line per save
+//! GET  saves/<name>                             the archive
+//! PUT  saves/<name>                             upload the archive, keeping the previous copy as a version and recording it in the ledger
+//! POST saves/<name>/supersede
+//! GET  saves/<save>/versions                    one "<timestamp> <size>" line per version
+//! POST saves/<save>/versions/<timestamp>/restore restore the version, recording it in the ledger
+//! GET  records/<substitutions|signatures|ledger> one record per line, oldest first
+//! POST records/<substitutions|signatures>        append the record in the body
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::Context;
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use reqwest::{Method, StatusCode};
+use url::Url;
+
+use crate::interface::file_system::FileMetadata;
+use crate::interface::index::IterIndex;
+use crate::interface::storage::staging;
+use crate::interface::{Compression, RemoteStorage};
+use crate::ledger::LedgerEntry;
+use crate::save::path_to_save;
+use crate::signature::Signature;
+use crate::substitute::Substitution;
+use crate::versions::Version;
+use crate::{error::ErrorSuggestions, RemoteKind, Save};
+
+/// The records that the server keeps, by their name in the API
+pub const SUBSTITUTIONS_RECORD: &str = "substitutions";
+pub const SIGNATURES_RECORD: &str = "signatures";
+pub const LEDGER_RECORD: &str = "ledger";
+
+/// This implementation is used to store the saves in a game on a scut server, which shares them with the other players.
+#[derive(Clone)]
+pub struct HttpRemote {
+    /// The url of the game, ending in a slash
+    url: Url,
+    token: Option<String>,
+    client: Client,
+    compression: Box<dyn Compression>,
+    /// The name of each save's archive on the server
+    saves: BTreeMap<Save, String>,
+}
+
+impl HttpRemote {
+    pub fn new(
+        url: &str,
+        token: Option<&str>,
+        compression: Box<dyn Compression>,
+    ) -> anyhow::Result<Self> {
+        let mut url = Url::parse(url)
+            .with_context(|| format!("`{url}` is not a valid url"))
+            .suggest("Use `scut config set remote.url <URL>` to set the url of your game on the scut server")?;
+
+        // paths are joined onto the game's url, which only works if it ends in a slash
+        if !url.path().ends_with('/') {
+            let path = format!("{}/", url.path());
+            url.set_path(&path);
+        }
+
+        let mut remote = HttpRemote {
+            url,
+            token: token.map(String::from),
+            client: Client::new(),
+            compression,
+            saves: BTreeMap::new(),
+        };
+
+        remote.refresh_saves()?;
+
+        Ok(remote)
+    }
+
+    /// Reloads from the server what saves are in this game
+    pub fn refresh_saves(&mut self) -> anyhow::Result<()> {
+        let listing = self
+            .get(&["saves"])?
+            .ok_or_else(|| anyhow::anyhow!("{} not found on the scut server", self.url))
+            .suggest(RemoteKind::Server.sync_suggestion())?;
+
+        self.saves = parse_listing(&listing)?
+            .into_iter()
+            .filter_map(|(_, name)| path_to_save(Path::new(&name)).map(|save| (save, name)))
+            .collect();
+
+        Ok(())
+    }
+
+    /// The url of `path` inside this game
+    fn url_for(&self, path: &[&str]) -> anyhow::Result<Url> {
+        let mut url = self.url.clone();
+        url.path_segments_mut()
+            .map_err(|_| anyhow::anyhow!("{} is not a valid game url", self.url))?
+            .pop_if_empty()
+            .extend(path);
+        Ok(url)
+    }
+
+    fn request(&self, method: Method, path: &[&str]) -> anyhow::Result<RequestBuilder> {
+        let request = self.client.request(method, self.url_for(path)?);
+        Ok(match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        })
+    }
+
+    /// Send a request, returning None if what it asks for doesn't exist
+    fn send(&self, request: RequestBuilder, action: &str) -> anyhow::Result<Option<Response>> {
+        let response = request
+            .send()
+            .with_context(|| format!("failed to {action}"))
+            .suggest(RemoteKind::Server.sync_suggestion())?;
+
+        check(response, action)
+    }
+
+    /// Fetch `path`, returning None if it doesn't exist
+    fn get(&self, path: &[&str]) -> anyhow::Result<Option<Vec<u8>>> {
+        let action = format!("download {}", path.join("/"));
+
+        let Some(response) = self.send(self.request(Method::GET, path)?, &action)? else {
+            return Ok(None);
+        };
+        Ok(Some(
+            response
+                .bytes()
+                .with_context(|| format!("failed to {action}"))?
+                .to_vec(),
+        ))
+    }
+
+    /// Send `content` to `path`, which must exist unless it is being created
+    fn send_to(&self, method: Method, path: &[&str], content: Vec<u8>) -> anyhow::Result<()> {
+        let action = format!("{} {}", method.as_str().to_lowercase(), path.join("/"));
+
+        self.send(self.request(method, path)?.body(content), &action)?
+            .ok_or_else(|| anyhow::anyhow!("failed to {action}: it doesn't exist"))
+            .suggest(RemoteKind::Server.sync_suggestion())?;
+        Ok(())
+    }
+
+    /// The name of a save's archive on the server, if it exists
+    fn locate_save(&mut self, save: &Save) -> anyhow::Result<Option<String>> {
+        if !self.saves.contains_key(save) {
+            self.refresh_saves()?;
+        }
+        Ok(self.saves.get(save).cloned())
+    }
+
+    fn append_record(&self, record: &str, line: &dyn fmt::Display) -> anyhow::Result<()> {
+        self.send_to(
+            Method::POST,
+            &["records", record],
+            line.to_string().into_bytes(),
+        )
+    }
+
+    /// Read every record of one kind, oldest first
+    fn read_records<T>(&self, record: &str) -> anyhow::Result<Vec<T>>
+    where
+        T: FromStr<Err = anyhow::Error>,
+    {
+        let Some(content) = self.get(&["records", record])? else {
+            return Ok(Vec::new());
+        };
+
+        String::from_utf8(content)?
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.parse())
+            .collect()
+    }
+}
+
+/// Turn error statuses into errors, returning None if what was asked for doesn't exist
+///
+/// The server explains why it refused a request in the body of the response.
+fn check(response: Response, action: &str) -> anyhow::Result<Option<Response>> {
+    match response.status() {
+        StatusCode::NOT_FOUND => Ok(None),
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(anyhow::anyhow!(
+            "failed to {action}: the scut server didn't accept your token"
+        ))
+        .suggest("Check remote.password in your config is the token the server's admin gave you")
+        .map_err(anyhow::Error::from),
+        status if status.is_success() => Ok(Some(response)),
+        status if status.is_client_error() => {
+            let reason = response.text().unwrap_or_default();
+            Err(anyhow::anyhow!("failed to {action}: {}", reason.trim()))
+                .suggest("Check the save's name and the roster with the server's admin")
+                .map_err(anyhow::Error::from)
+        }
+        status => Err(anyhow::anyhow!(
+            "failed to {action}: the scut server responded {status}"
+        )),
+    }
+}
+
+/// Read the lines of a listing, each a number followed by a name
+fn parse_listing(listing: &[u8]) -> anyhow::Result<Vec<(u64, String)>> {
+    std::str::from_utf8(listing)
+        .context("the listing isn't valid UTF-8")?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let (number, name) = line
+                .split_once(' ')
+                .ok_or_else(|| anyhow::anyhow!("`{line}` is not a valid listing"))?;
+            Ok((
+                number
+                    .parse()
+                    .with_context(|| format!("`{line}` is not a valid listing"))?,
+                name.to_string(),
+            ))
+        })
+        .collect()
+}
+
+impl RemoteStorage for HttpRemote {
+    fn download(&mut self, save: &Save, local_path: &Path) -> anyhow::Result<()> {
+        let not_found = || {
+            Err(anyhow::anyhow!("{save} not found on the scut server"))
+                .suggest("Have your friends uploaded their turn?")
+        };
+
+        let Some(name) = self.locate_save(save)? else {
+            return Ok(not_found()?);
+        };
+        let Some(content) = self.get(&["saves", &name])? else {
+            return Ok(not_found()?);
+        };
+
+        staging::decompress(self.compression.as_ref(), &name, &content, local_path)
+            .with_context(|| format!("failed to download {save}"))
+    }
+
+    fn upload(&mut self, save: &Save, local_path: &Path) -> anyhow::Result<()> {
+        let (name, content) = staging::compress(self.compression.as_ref(), save, local_path)
+            .with_context(|| format!("failed to upload {save}"))?;
+
+        self.send_to(Method::PUT, &["saves", &name], content)
+            .with_context(|| format!("failed to upload {save}"))?;
+
+        // the server kept the previous copy as a version, which may have had another extension
+        self.saves.insert(save.clone(), name);
+        Ok(())
+    }
+
+    fn supersede(&mut self, save: &Save) -> anyhow::Result<()> {
+        let name = self
+            .locate_save(save)?
+            .ok_or_else(|| anyhow::anyhow!("{save} not found on the scut server"))?;
+
+        self.send_to(Method::POST, &["saves", &name, "supersede"], Vec::new())
+            .with_context(|| format!("failed to supersede {save}"))?;
+        self.saves.remove(save);
+        Ok(())
+    }
+
+    fn record_substitution(&mut self, substitution: &Substitution) -> anyhow::Result<()> {
+        self.append_record(SUBSTITUTIONS_RECORD, substitution)
+            .with_context(|| format!("failed to record {substitution}"))
+    }
+
+    fn substitutions(&mut self) -> anyhow::Result<Vec<Substitution>> {
+        self.read_records(SUBSTITUTIONS_RECORD)
+            .context("failed to read substitutions")
+    }
+
+    fn record_signature(&mut self, signature: &Signature) -> anyhow::Result<()> {
+        self.append_record(SIGNATURES_RECORD, signature)
+            .with_context(|| format!("failed to record the signature of {}", signature.save))
+    }
+
+    fn signatures(&mut self) -> anyhow::Result<Vec<Signature>> {
+        self.read_records(SIGNATURES_RECORD)
+            .context("failed to read signatures")
+    }
+
+    /// The server records each upload in the ledger itself, when it receives the save
+    fn record_upload(&mut self, _entry: &LedgerEntry) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn ledger(&mut self) -> anyhow::Result<Vec<LedgerEntry>> {
+        self.read_records(LEDGER_RECORD)
+            .context("failed to read the ledger")
+    }
+
+    fn versions(&mut self, save: &Save) -> anyhow::Result<Vec<Version>> {
+        let Some(listing) = self.get(&["saves", &save.to_string(), "versions"])? else {
+            return Ok(Vec::new());
+        };
+
+        parse_listing(&listing)?
+            .into_iter()
+            .map(|(timestamp, size)| {
+                Ok(Version {
+                    save: save.clone(),
+                    timestamp,
+                    size: size
+                        .parse()
+                        .with_context(|| format!("`{size}` is not a valid size"))?,
+                })
+            })
+            .collect()
+    }
+
+    fn restore(&mut self, save: &Save, version: u64) -> anyhow::Result<()> {
+        let save_name = save.to_string();
+        let version_name = version.to_string();
+        let path = ["saves", &save_name, "versions", &version_name, "restore"];
+        let action = format!("restore {save} version {version}");
+
+        self.send(self.request(Method::POST, &path)?, &action)?
+            .ok_or_else(|| anyhow::anyhow!("{save} version {version} not found on the scut server"))
+            .suggest("Use `scut versions` to list the versions that can be restored")?;
+
+        self.refresh_saves()
+    }
+
+    fn metadata(&mut self, save: &Save) -> anyhow::Result<Option<FileMetadata>> {
+        let Some(name) = self.locate_save(save)? else {
+            return Ok(None);
+        };
+
+        Ok(self
+            .get(&["saves", &name])
+            .with_context(|| format!("failed to read {save} on the scut server"))?
+            .map(|content| FileMetadata::new(&content, None)))
+    }
+
+    fn index(&self) -> &dyn crate::interface::Index {
+        self
+    }
+}
+
+impl<'a> IterIndex<'a> for HttpRemote {
+    type Iter = std::collections::btree_map::Keys<'a, Save, String>;
+
+    fn iter(&'a self) -> Self::Iter {
+        self.saves.keys()
+    }
+}
@@ -0,0 +1,480 @@
+//! In-memory implementations of [`LocalStorage`] and [`RemoteStorage`], used to simulate many players sharing saves without touching disk.
+//!
+//! Both storages keep their files in [`MemoryFiles`], which is shared between clones the same way a disk is shared between processes.
+//! Like their on-disk counterparts, each storage lists its saves when it is created, so create a new one to see changes made by other players.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use anyhow::Context;
+
+use crate::interface::file_system::FileMetadata;
+use crate::interface::index::IterIndex;
+use crate::interface::storage::object_store::{self, Object, ObjectStore};
+use crate::interface::storage::sync_record::SyncRecord;
+use crate::interface::storage::{SUPERSEDED_FOLDER, SYNC_RECORD_FILE};
+use crate::interface::{Index, LocalStorage, RemoteStorage};
+use crate::ledger::LedgerEntry;
+use crate::save::path_to_save;
+use crate::signature::Signature;
+use crate::substitute::Substitution;
+use crate::versions::Version;
+use crate::Save;
+
+/// The file name of the autosave written by Strategic Command when ending the turn
+pub const AUTOSAVE_FILE: &str = "autosave.sav";
+
+/// The contents of files held in memory, keyed by their path.
+///
+/// Clones share the same files.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryFiles(Arc<Mutex<MemoryDisk>>);
+
+#[derive(Debug, Default)]
+struct MemoryDisk {
+    files: BTreeMap<PathBuf, MemoryFile>,
+    /// Counts every write, standing in for the time that files are modified
+    clock: u64,
+}
+
+/// A file held in memory, with the save its name parses as, so that listing saves doesn't parse every file name again
+#[derive(Debug, Clone)]
+struct MemoryFile {
+    save: Option<Save>,
+    contents: String,
+    modified: u64,
+}
+
+impl MemoryFiles {
+    pub fn new() -> Self {
+        MemoryFiles::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MemoryDisk> {
+        // a panic while holding the lock can't leave the map half-written, so it's safe to carry on
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Returns the contents of the file at `path`, if it exists
+    pub fn read(&self, path: &Path) -> Option<String> {
+        self.lock()
+            .files
+            .get(path)
+            .map(|file| file.contents.clone())
+    }
+
+    /// Returns the metadata of the file at `path`, if it exists.
+    ///
+    /// Files are modified in the order they were written.
+    pub fn metadata(&self, path: &Path) -> Option<FileMetadata> {
+        self.lock()
+            .files
+            .get(path)
+            .map(|file| FileMetadata::new(file.contents.as_bytes(), Some(file.modified)))
+    }
+
+    /// Create or overwrite the file at `path`
+    pub fn write<S>(&self, path: &Path, contents: S)
+    where
+        S: Into<String>,
+    {
+        let mut disk = self.lock();
+        disk.clock += 1;
+        let file = MemoryFile {
+            save: path_to_save(path),
+            contents: contents.into(),
+            modified: disk.clock,
+        };
+        disk.files.insert(path.to_path_buf(), file);
+    }
+
+    /// Move the file at `from` to `to`, overwriting any file already at `to`
+    pub fn move_file(&self, from: &Path, to: &Path) -> anyhow::Result<()> {
+        let files = &mut self.lock().files;
+        let mut file = files
+            .remove(from)
+            .ok_or_else(|| anyhow::anyhow!("{} does not exist", from.display()))?;
+        file.save = path_to_save(to);
+        files.insert(to.to_path_buf(), file);
+        Ok(())
+    }
+
+    /// The paths of every file directly inside `folder`
+    pub fn files_in_folder(&self, folder: &Path) -> Vec<PathBuf> {
+        self.lock()
+            .files
+            .keys()
+            .filter(|path| path.parent() == Some(folder))
+            .cloned()
+            .collect()
+    }
+
+    /// The saves in `folder`, with their paths
+    fn saves_in_folder(&self, folder: &Path) -> BTreeMap<Save, PathBuf> {
+        self.lock()
+            .files
+            .iter()
+            .filter(|(path, _)| path.parent() == Some(folder))
+            .filter_map(|(path, file)| file.save.clone().map(|save| (save, path.clone())))
+            .collect()
+    }
+
+    /// Move a file into the `superseded` subfolder of `folder`
+    fn supersede(&self, folder: &Path, path: &Path) -> anyhow::Result<()> {
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| anyhow::anyhow!("{} is not a file", path.display()))?;
+        self.move_file(path, &folder.join(SUPERSEDED_FOLDER).join(file_name))
+    }
+}
+
+/// The file name that a save is stored as in memory
+fn file_name(save: &Save) -> String {
+    format!("{save}.sav")
+}
+
+/// A player's saved games folder, held in memory
+#[derive(Debug, Clone)]
+pub struct MemoryLocalStorage {
+    location: PathBuf,
+    saves: BTreeMap<Save, PathBuf>,
+    files: MemoryFiles,
+}
+
+impl MemoryLocalStorage {
+    pub fn new(location: PathBuf, files: MemoryFiles) -> Self {
+        let mut storage = MemoryLocalStorage {
+            location,
+            saves: BTreeMap::new(),
+            files,
+        };
+        storage.refresh_saves();
+        storage
+    }
+
+    /// Reloads from memory what saves are in this storage
+    pub fn refresh_saves(&mut self) {
+        self.saves = self.files.saves_in_folder(&self.location);
+    }
+
+    /// Save the game as `save`, the way a player does after playing their part of a turn
+    pub fn write_save<S>(&mut self, save: &Save, contents: S)
+    where
+        S: Into<String>,
+    {
+        let path = self.location.join(file_name(save));
+        self.files.write(&path, contents);
+        self.saves.insert(save.clone(), path);
+    }
+
+    /// Write the autosave, the way Strategic Command does when a player ends the turn
+    pub fn write_autosave<S>(&mut self, contents: S)
+    where
+        S: Into<String>,
+    {
+        self.files
+            .write(&self.location.join(AUTOSAVE_FILE), contents);
+    }
+}
+
+impl LocalStorage for MemoryLocalStorage {
+    fn locate_save(&mut self, save: &Save) -> anyhow::Result<Option<PathBuf>> {
+        if !self.saves.contains_key(save) {
+            self.refresh_saves();
+        }
+        Ok(self.saves.get(save).cloned())
+    }
+
+    fn locate_autosave(&mut self) -> anyhow::Result<Option<PathBuf>> {
+        let path = self.location.join(AUTOSAVE_FILE);
+        Ok(self.files.read(&path).map(|_| path))
+    }
+
+    fn location(&self) -> &Path {
+        self.location.as_path()
+    }
+
+    fn supersede(&mut self, save: &Save) -> anyhow::Result<()> {
+        let path = self
+            .saves
+            .remove(save)
+            .ok_or_else(|| anyhow::anyhow!("{save} not found in {}", self.location.display()))?;
+
+        self.files
+            .supersede(&self.location, &path)
+            .with_context(|| format!("failed to supersede {save}"))
+    }
+
+    fn metadata(&mut self, save: &Save) -> anyhow::Result<Option<FileMetadata>> {
+        Ok(self
+            .locate_save(save)?
+            .and_then(|path| self.files.metadata(&path)))
+    }
+
+    fn autosave_metadata(&mut self) -> anyhow::Result<Option<FileMetadata>> {
+        Ok(self.files.metadata(&self.location.join(AUTOSAVE_FILE)))
+    }
+
+    fn sync_record(&mut self) -> anyhow::Result<SyncRecord> {
+        match self.files.read(&self.location.join(SYNC_RECORD_FILE)) {
+            Some(toml) => SyncRecord::from_toml(&toml),
+            None => Ok(SyncRecord::default()),
+        }
+    }
+
+    fn write_sync_record(&mut self, record: &SyncRecord) -> anyhow::Result<()> {
+        self.files
+            .write(&self.location.join(SYNC_RECORD_FILE), record.to_toml()?);
+        Ok(())
+    }
+
+    fn index(&self) -> &dyn Index {
+        self
+    }
+}
+
+impl<'a> IterIndex<'a> for MemoryLocalStorage {
+    type Iter = std::collections::btree_map::Keys<'a, Save, PathBuf>;
+
+    fn iter(&'a self) -> Self::Iter {
+        self.saves.keys()
+    }
+}
+
+/// Shared remote storage, held in memory.
+///
+/// Every upload is logged, so that a simulation can check nothing is uploaded twice.
+#[derive(Debug, Clone)]
+pub struct MemoryRemoteStorage {
+    location: PathBuf,
+    saves: BTreeMap<Save, PathBuf>,
+    files: MemoryFiles,
+    uploads: Arc<Mutex<Vec<Save>>>,
+}
+
+impl MemoryRemoteStorage {
+    pub fn new(location: PathBuf, files: MemoryFiles) -> Self {
+        let mut storage = MemoryRemoteStorage {
+            location,
+            saves: BTreeMap::new(),
+            files,
+            uploads: Arc::default(),
+        };
+        storage.refresh_saves();
+        storage
+    }
+
+    /// Connect to the same remote storage again, seeing any changes made since this storage was created.
+    ///
+    /// The upload log is shared with this storage.
+    pub fn reconnect(&self) -> Self {
+        let mut storage = self.clone();
+        storage.refresh_saves();
+        storage
+    }
+
+    /// Reloads from memory what saves are in this storage
+    pub fn refresh_saves(&mut self) {
+        self.saves = self.files.saves_in_folder(&self.location);
+    }
+
+    /// Every save uploaded to this storage or any storage it was reconnected from, in the order they were uploaded
+    pub fn uploads(&self) -> Vec<Save> {
+        self.uploads
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// The contents of `save` in this storage, if it exists
+    pub fn read(&self, save: &Save) -> Option<String> {
+        self.files.read(&self.location.join(file_name(save)))
+    }
+}
+
+impl ObjectStore for MemoryRemoteStorage {
+    fn list(&mut self, folder: &str) -> anyhow::Result<Option<Vec<Object>>> {
+        let mut objects = Vec::new();
+        for path in self.files.files_in_folder(&self.location.join(folder)) {
+            let (Some(name), Some(metadata)) = (
+                path.file_name().and_then(|name| name.to_str()),
+                self.files.metadata(&path),
+            ) else {
+                continue;
+            };
+            objects.push(Object {
+                name: name.to_string(),
+                size: metadata.size,
+            });
+        }
+        Ok(Some(objects))
+    }
+
+    fn get(&mut self, name: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        Ok(self
+            .files
+            .read(&self.location.join(name))
+            .map(String::into_bytes))
+    }
+
+    fn put(&mut self, name: &str, content: Vec<u8>) -> anyhow::Result<()> {
+        let content = String::from_utf8(content).context("only text is held in memory")?;
+        self.files.write(&self.location.join(name), content);
+        Ok(())
+    }
+
+    fn rename(&mut self, from: &str, to: &str) -> anyhow::Result<()> {
+        self.files
+            .move_file(&self.location.join(from), &self.location.join(to))
+    }
+}
+
+impl RemoteStorage for MemoryRemoteStorage {
+    fn download(&mut self, save: &Save, local_path: &Path) -> anyhow::Result<()> {
+        let contents = self
+            .read(save)
+            .ok_or_else(|| anyhow::anyhow!("{save} not found in {}", self.location.display()))?;
+
+        self.files
+            .write(&local_path.join(file_name(save)), contents);
+        Ok(())
+    }
+
+    fn upload(&mut self, save: &Save, local_path: &Path) -> anyhow::Result<()> {
+        let contents = self.files.read(local_path).ok_or_else(|| {
+            anyhow::anyhow!(
+                "failed to upload {save}: {} does not exist",
+                local_path.display()
+            )
+        })?;
+
+        let name = file_name(save);
+        if self.files.read(&self.location.join(&name)).is_some() {
+            object_store::keep_version(self, save, &name)?;
+            self.saves.remove(save);
+        }
+
+        self.files.write(&self.location.join(name), contents);
+        self.uploads
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .push(save.clone());
+        Ok(())
+    }
+
+    fn supersede(&mut self, save: &Save) -> anyhow::Result<()> {
+        let path = self
+            .saves
+            .remove(save)
+            .ok_or_else(|| anyhow::anyhow!("{save} not found in {}", self.location.display()))?;
+
+        self.files
+            .supersede(&self.location, &path)
+            .with_context(|| format!("failed to supersede {save}"))
+    }
+
+    fn record_substitution(&mut self, substitution: &Substitution) -> anyhow::Result<()> {
+        object_store::record_substitution(self, substitution)
+    }
+
+    fn substitutions(&mut self) -> anyhow::Result<Vec<Substitution>> {
+        object_store::substitutions(self)
+    }
+
+    fn record_signature(&mut self, signature: &Signature) -> anyhow::Result<()> {
+        object_store::record_signature(self, signature)
+    }
+
+    fn signatures(&mut self) -> anyhow::Result<Vec<Signature>> {
+        object_store::signatures(self)
+    }
+
+    fn record_upload(&mut self, entry: &LedgerEntry) -> anyhow::Result<()> {
+        object_store::record_upload(self, entry)
+    }
+
+    fn ledger(&mut self) -> anyhow::Result<Vec<LedgerEntry>> {
+        object_store::ledger(self)
+    }
+
+    fn versions(&mut self, save: &Save) -> anyhow::Result<Vec<Version>> {
+        object_store::versions(self, save)
+    }
+
+    fn restore(&mut self, save: &Save, version: u64) -> anyhow::Result<()> {
+        let name = file_name(save);
+        let current = self
+            .files
+            .read(&self.location.join(&name))
+            .map(|_| name.as_str());
+
+        if !object_store::restore(self, save, version, current)? {
+            anyhow::bail!("{save} version {version} not found");
+        }
+        self.refresh_saves();
+        Ok(())
+    }
+
+    fn metadata(&mut self, save: &Save) -> anyhow::Result<Option<FileMetadata>> {
+        Ok(self.files.metadata(&self.location.join(file_name(save))))
+    }
+
+    fn index(&self) -> &dyn Index {
+        self
+    }
+}
+
+impl<'a> IterIndex<'a> for MemoryRemoteStorage {
+    type Iter = std::collections::btree_map::Keys<'a, Save, PathBuf>;
+
+    fn iter(&'a self) -> Self::Iter {
+        self.saves.keys()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{interface::index::Query, Side};
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn memory_storage_round_trip() -> anyhow::Result<()> {
+        let files = MemoryFiles::new();
+        let mut remote = MemoryRemoteStorage::new(PathBuf::from("/remote"), files.clone());
+        let mut dm = MemoryLocalStorage::new(PathBuf::from("/DM"), files.clone());
+        let dg = MemoryLocalStorage::new(PathBuf::from("/DG"), files.clone());
+
+        let save = Save::from_parts(Side::Axis, 1).player("DM");
+        dm.write_save(&save, "DM's turn");
+        dm.write_autosave("Allies 1");
+
+        let path = dm.locate_save(&save)?.expect("save was written");
+        remote.upload(&save, &path)?;
+
+        let autosave = dm.locate_autosave()?.expect("autosave was written");
+        remote.upload(&Save::from_parts(Side::Allies, 1), &autosave)?;
+
+        // the remote only lists saves that existed when it was created
+        assert_eq!(remote.index().count(&Query::new())?, 0);
+
+        let mut remote = remote.reconnect();
+        assert_eq!(remote.index().count(&Query::new())?, 2);
+
+        remote.download(&save, dg.location())?;
+        let dg = MemoryLocalStorage::new(PathBuf::from("/DG"), files);
+        assert_eq!(dg.index().search(&Query::new())?, vec![save.clone()]);
+
+        assert_eq!(remote.read(&save), Some("DM's turn".to_string()));
+        assert_eq!(
+            remote.uploads(),
+            vec![save, Save::from_parts(Side::Allies, 1)]
+        );
+
+        Ok(())
+    }
+}
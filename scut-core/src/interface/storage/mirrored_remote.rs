@@ -0,0 +1,531 @@
+//! Mirrors remote storage, e.g. a Dropbox folder and a folder on a NAS, so that the game carries on while one of them is down.
+//!
+//! * uploads, supersedes and records go to every mirror, and only fail if every mirror fails
+//! * saves are downloaded from the first mirror, in the order they are configured, that has them
+//! * indexes and records are merged, so a save or record that only reached some mirrors is still found
+//!
+//! A mirror that was down misses the uploads made meanwhile, which [`MirroredRemote::divergence`] finds and [`MirroredRemote::resync`] copies to it.
+//! The mirrors that missed an upload are listed by [`lagging_mirrors`](RemoteStorage::lagging_mirrors), so the user can be told to resync them.
+
+use std::fmt;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Context;
+use tracing::warn;
+
+use crate::{
+    interface::{
+        file_system::FileMetadata,
+        index::{query::Matches, Query},
+        storage::{staging::Staging, Archive},
+        Index, RemoteStorage,
+    },
+    ledger::LedgerEntry,
+    save::path_to_save,
+    signature::Signature,
+    substitute::Substitution,
+    versions::Version,
+    Save,
+};
+
+/// Sends everything to each of its mirrors, and reads from the first mirror that answers
+#[derive(Clone)]
+pub struct MirroredRemote {
+    /// The primary first, then the other mirrors in the order they were added
+    mirrors: Vec<Mirror>,
+    /// The names of the mirrors that missed an upload, shared between clones since uploads run in parallel
+    lagging: Arc<Mutex<Vec<String>>>,
+}
+
+#[derive(Clone)]
+struct Mirror {
+    name: String,
+    remote: Box<dyn RemoteStorage>,
+}
+
+/// The saves that one mirror is missing, which the other mirrors have
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    pub mirror: String,
+    pub missing: Vec<Save>,
+}
+
+impl MirroredRemote {
+    /// Mirror `primary`, which is read from first, named e.g. after its folder so that divergence can be reported
+    pub fn new(name: impl Into<String>, primary: Box<dyn RemoteStorage>) -> Self {
+        MirroredRemote {
+            mirrors: vec![Mirror {
+                name: name.into(),
+                remote: primary,
+            }],
+            lagging: Arc::default(),
+        }
+    }
+
+    /// Also mirror to `remote`, which is read from if the mirrors before it can't be
+    pub fn mirror(mut self, name: impl Into<String>, remote: Box<dyn RemoteStorage>) -> Self {
+        self.mirrors.push(Mirror {
+            name: name.into(),
+            remote,
+        });
+        self
+    }
+
+    /// The saves that each mirror is missing, for the mirrors that are missing any
+    pub fn divergence(&self) -> anyhow::Result<Vec<Divergence>> {
+        let saves = self.search(&Query::new())?;
+
+        let mut divergence = Vec::new();
+        for mirror in &self.mirrors {
+            let has = mirror.remote.index().search(&Query::new())?;
+            let missing: Vec<Save> = saves
+                .iter()
+                .filter(|save| !has.contains(save))
+                .cloned()
+                .collect();
+
+            if !missing.is_empty() {
+                divergence.push(Divergence {
+                    mirror: mirror.name.clone(),
+                    missing,
+                });
+            }
+        }
+        Ok(divergence)
+    }
+
+    /// Copy the saves that each mirror is missing, as found by [`divergence`](Self::divergence), from a mirror that has them
+    ///
+    /// Records aren't copied, they are merged from every mirror whenever they are read.
+    pub fn resync(&mut self, divergence: &[Divergence]) -> anyhow::Result<()> {
+        for lagging in divergence {
+            let to = self.position(&lagging.mirror)?;
+            for save in &lagging.missing {
+                let from = self
+                    .mirrors
+                    .iter()
+                    .position(|mirror| has(&*mirror.remote, save).unwrap_or_default())
+                    .ok_or_else(|| anyhow::anyhow!("{save} not found in any mirror"))?;
+
+                self.copy(save, from, to)
+                    .with_context(|| format!("failed to resync {}", lagging.mirror))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn position(&self, name: &str) -> anyhow::Result<usize> {
+        self.mirrors
+            .iter()
+            .position(|mirror| mirror.name == name)
+            .ok_or_else(|| anyhow::anyhow!("there is no mirror called {name}"))
+    }
+
+    /// Copy `save` from one mirror to another, by way of a staging folder on this computer
+    fn copy(&mut self, save: &Save, from: usize, to: usize) -> anyhow::Result<()> {
+        let staging = Staging::new()?;
+        self.mirrors[from]
+            .remote
+            .download(save, staging.path())
+            .with_context(|| {
+                format!("failed to download {save} from {}", self.mirrors[from].name)
+            })?;
+
+        // the compression chooses what the extracted save is called
+        let extracted = staging
+            .paths()?
+            .into_iter()
+            .find(|path| path_to_save(path).as_ref() == Some(save))
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "{save} was downloaded from {}, but no save was extracted",
+                    self.mirrors[from].name
+                )
+            })?;
+        self.mirrors[to]
+            .remote
+            .upload(save, &extracted)
+            .and_then(|_| self.mirrors[to].remote.publish())
+            .with_context(|| format!("failed to upload {save} to {}", self.mirrors[to].name))
+    }
+
+    /// Remember that the mirror called `name` missed an upload
+    fn lag(&self, name: &str) {
+        let mut lagging = self
+            .lagging
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if !lagging.iter().any(|lagging| lagging == name) {
+            lagging.push(name.to_string());
+        }
+    }
+
+    /// Do the same thing to every mirror, which only fails if it fails for every mirror
+    ///
+    /// Returns the names of the mirrors that it failed for.
+    fn each<F>(&mut self, action: &str, mut f: F) -> anyhow::Result<Vec<String>>
+    where
+        F: FnMut(&mut dyn RemoteStorage) -> anyhow::Result<()>,
+    {
+        let mut first_error = None;
+        let mut failed = Vec::new();
+
+        for mirror in self.mirrors.iter_mut() {
+            if let Err(error) = f(&mut *mirror.remote) {
+                warn!(mirror = %mirror.name, "failed to {action}: {error:#}");
+                failed.push(mirror.name.clone());
+                first_error.get_or_insert(error);
+            }
+        }
+
+        match first_error {
+            Some(error) if failed.len() == self.mirrors.len() => {
+                Err(error).with_context(|| format!("failed to {action} in every mirror"))
+            }
+            _ => Ok(failed),
+        }
+    }
+
+    /// Read from the first mirror that answers, falling back to the next while they fail
+    fn first<T, F>(&mut self, action: &str, mut f: F) -> anyhow::Result<T>
+    where
+        F: FnMut(&mut dyn RemoteStorage) -> anyhow::Result<T>,
+    {
+        let mut first_error = None;
+
+        for mirror in self.mirrors.iter_mut() {
+            match f(&mut *mirror.remote) {
+                Ok(result) => return Ok(result),
+                Err(error) => {
+                    warn!(mirror = %mirror.name, "failed to {action}: {error:#}");
+                    first_error.get_or_insert(error);
+                }
+            }
+        }
+
+        Err(first_error.unwrap_or_else(|| anyhow::anyhow!("there are no mirrors")))
+            .with_context(|| format!("failed to {action} from any mirror"))
+    }
+
+    /// Read records from every mirror that answers, keeping one of each
+    fn merged<T, F>(&mut self, action: &str, mut f: F) -> anyhow::Result<Vec<T>>
+    where
+        T: PartialEq,
+        F: FnMut(&mut dyn RemoteStorage) -> anyhow::Result<Vec<T>>,
+    {
+        let mut merged = Vec::new();
+        self.each(action, |remote| {
+            for record in f(remote)? {
+                if !merged.contains(&record) {
+                    merged.push(record);
+                }
+            }
+            Ok(())
+        })?;
+        Ok(merged)
+    }
+}
+
+/// Returns true if `remote` has `save`, a mirror that missed its upload doesn't
+fn has(remote: &dyn RemoteStorage, save: &Save) -> anyhow::Result<bool> {
+    Ok(remote.index().search(&Query::new())?.contains(save))
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let missing: Vec<String> = self.missing.iter().map(Save::to_string).collect();
+        write!(f, "{} is missing {}", self.mirror, missing.join(", "))
+    }
+}
+
+impl RemoteStorage for MirroredRemote {
+    fn download(&mut self, save: &Save, local_path: &Path) -> anyhow::Result<()> {
+        self.first(&format!("download {save}"), |remote| {
+            anyhow::ensure!(has(remote, save)?, "{save} not found");
+            remote.download(save, local_path)
+        })
+    }
+
+    fn upload(&mut self, save: &Save, local_path: &Path) -> anyhow::Result<()> {
+        let failed = self.each(&format!("upload {save}"), |remote| {
+            remote.upload(save, local_path)
+        })?;
+        for name in failed {
+            self.lag(&name);
+        }
+        Ok(())
+    }
+
+    fn supersede(&mut self, save: &Save) -> anyhow::Result<()> {
+        self.each(&format!("supersede {save}"), |remote| {
+            if !has(remote, save)? {
+                return Ok(());
+            }
+            remote.supersede(save)
+        })?;
+        Ok(())
+    }
+
+    fn record_substitution(&mut self, substitution: &Substitution) -> anyhow::Result<()> {
+        self.each(&format!("record {substitution}"), |remote| {
+            remote.record_substitution(substitution)
+        })?;
+        Ok(())
+    }
+
+    fn substitutions(&mut self) -> anyhow::Result<Vec<Substitution>> {
+        self.merged("read substitutions", |remote| remote.substitutions())
+    }
+
+    fn record_signature(&mut self, signature: &Signature) -> anyhow::Result<()> {
+        self.each(
+            &format!("record the signature of {}", signature.save),
+            |remote| remote.record_signature(signature),
+        )?;
+        Ok(())
+    }
+
+    fn signatures(&mut self) -> anyhow::Result<Vec<Signature>> {
+        self.merged("read signatures", |remote| remote.signatures())
+    }
+
+    fn record_upload(&mut self, entry: &LedgerEntry) -> anyhow::Result<()> {
+        self.each(&format!("record the upload of {}", entry.save), |remote| {
+            remote.record_upload(entry)
+        })?;
+        Ok(())
+    }
+
+    fn ledger(&mut self) -> anyhow::Result<Vec<LedgerEntry>> {
+        let mut ledger = self.merged("read the ledger", |remote| remote.ledger())?;
+        ledger.sort_by_key(|entry| entry.timestamp);
+        Ok(ledger)
+    }
+
+    fn versions(&mut self, save: &Save) -> anyhow::Result<Vec<Version>> {
+        self.first(&format!("list the versions of {save}"), |remote| {
+            remote.versions(save)
+        })
+    }
+
+    fn restore(&mut self, save: &Save, version: u64) -> anyhow::Result<()> {
+        // versions are listed from the first mirror that answers, so that's the one that has them
+        let from = self
+            .mirrors
+            .iter_mut()
+            .position(|mirror| mirror.remote.versions(save).is_ok())
+            .ok_or_else(|| {
+                anyhow::anyhow!("failed to list the versions of {save} from any mirror")
+            })?;
+
+        self.mirrors[from]
+            .remote
+            .restore(save, version)
+            .with_context(|| format!("failed to restore {save} version {version}"))?;
+
+        // the other mirrors keep their own versions, so they are given a copy of the restored save
+        for to in 0..self.mirrors.len() {
+            if to != from {
+                if let Err(error) = self.copy(save, from, to) {
+                    warn!(
+                        mirror = %self.mirrors[to].name,
+                        "failed to mirror the restored {save}: {error:#}"
+                    );
+                    self.lag(&self.mirrors[to].name);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn publish(&mut self) -> anyhow::Result<()> {
+        let failed = self.each("publish", |remote| remote.publish())?;
+        for name in failed {
+            self.lag(&name);
+        }
+        Ok(())
+    }
+
+    fn metadata(&mut self, save: &Save) -> anyhow::Result<Option<FileMetadata>> {
+        if !self.search(&Query::new())?.contains(save) {
+            return Ok(None);
+        }
+
+        self.first(&format!("read {save}"), |remote| {
+            anyhow::ensure!(has(remote, save)?, "{save} not found");
+            remote.metadata(save)
+        })
+    }
+
+    fn archive(&mut self, name: &str, saves: &[Save]) -> anyhow::Result<()> {
+        self.each(&format!("archive {name}"), |remote| {
+            // a mirror that missed an upload archives the saves it has
+            let mut has_saves = Vec::new();
+            for save in saves {
+                if has(remote, save)? {
+                    has_saves.push(save.clone());
+                }
+            }
+            remote.archive(name, &has_saves)
+        })?;
+        Ok(())
+    }
+
+    fn archives(&mut self) -> anyhow::Result<Vec<Archive>> {
+        self.merged("read archives", |remote| remote.archives())
+    }
+
+    fn lagging_mirrors(&self) -> Vec<String> {
+        self.lagging
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    fn index(&self) -> &dyn Index {
+        self
+    }
+}
+
+/// Searches every mirror, so a save is found even if only some mirrors have it
+impl<'a> Index<'a> for MirroredRemote {
+    fn search(&'a self, query: &dyn Matches<Save>) -> anyhow::Result<Vec<Save>> {
+        let mut saves = Vec::new();
+        for mirror in &self.mirrors {
+            for save in mirror.remote.index().search(query)? {
+                if !saves.contains(&save) {
+                    saves.push(save);
+                }
+            }
+        }
+        Ok(saves)
+    }
+
+    fn count(&'a self, query: &dyn Matches<Save>) -> anyhow::Result<usize> {
+        Ok(self.search(query)?.len())
+    }
+
+    fn latest(&'a self, query: &dyn Matches<Save>) -> anyhow::Result<Option<Save>> {
+        let mut latest = Vec::new();
+        for mirror in &self.mirrors {
+            latest.extend(mirror.remote.index().latest(query)?);
+        }
+        Ok(latest.into_iter().max_by_key(|save| save.turn))
+    }
+
+    fn earliest(&'a self, query: &dyn Matches<Save>) -> anyhow::Result<Option<Save>> {
+        let mut earliest = Vec::new();
+        for mirror in &self.mirrors {
+            earliest.extend(mirror.remote.index().earliest(query)?);
+        }
+        Ok(earliest.into_iter().min_by_key(|save| save.turn))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{
+        interface::{
+            compression::copy_compression::CopyCompression,
+            file_system::local_file_system::LocalFileSystem,
+            storage::{
+                memory_storage::{MemoryFiles, MemoryLocalStorage, MemoryRemoteStorage},
+                synced_folder::SyncedFolder,
+            },
+            LocalStorage,
+        },
+        Provider, Side,
+    };
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn mirrored_remote_falls_back_and_resyncs() -> anyhow::Result<()> {
+        let files = MemoryFiles::new();
+        let mut local = MemoryLocalStorage::new(PathBuf::from("/local"), files.clone());
+        let dropbox = MemoryRemoteStorage::new(PathBuf::from("/dropbox"), files.clone());
+        let nas = MemoryRemoteStorage::new(PathBuf::from("/nas"), files.clone());
+
+        let first = Save::from_parts(Side::Axis, 3).player("DM");
+        let second = Save::from_parts(Side::Axis, 4).player("DM");
+        local.write_save(&first, "DM's turn 3");
+        local.write_save(&second, "DM's turn 4");
+
+        let mut remote = MirroredRemote::new("dropbox", Box::new(dropbox.clone()))
+            .mirror("nas", Box::new(nas.clone()));
+        let path = local.locate_save(&first)?.expect("save was written");
+        remote.upload(&first, &path)?;
+        assert_eq!(dropbox.uploads(), vec![first.clone()]);
+        assert_eq!(nas.uploads(), vec![first.clone()]);
+        assert_eq!(remote.divergence()?, Vec::new());
+
+        // the NAS was down for the next upload
+        let mut dropbox = dropbox.reconnect();
+        let path = local.locate_save(&second)?.expect("save was written");
+        dropbox.upload(&second, &path)?;
+
+        let mut remote = MirroredRemote::new("nas", Box::new(nas.reconnect()))
+            .mirror("dropbox", Box::new(dropbox.reconnect()));
+        assert_eq!(remote.index().count(&Query::new())?, 2);
+        assert_eq!(
+            remote.divergence()?,
+            vec![Divergence {
+                mirror: "nas".to_string(),
+                missing: vec![second.clone()],
+            }]
+        );
+
+        // downloads fall back to the mirror that has the save
+        remote.download(&second, &PathBuf::from("/teammate"))?;
+        assert_eq!(
+            files.read(&PathBuf::from("/teammate/Axis DM 4.sav")),
+            Some("DM's turn 4".to_string())
+        );
+
+        let divergence = remote.divergence()?;
+        remote.resync(&divergence)?;
+        assert_eq!(nas.read(&second), Some("DM's turn 4".to_string()));
+
+        let remote = MirroredRemote::new("nas", Box::new(nas.reconnect()))
+            .mirror("dropbox", Box::new(dropbox.reconnect()));
+        assert_eq!(remote.divergence()?, Vec::new());
+
+        Ok(())
+    }
+
+    #[test]
+    fn mirrored_remote_reports_lagging_mirrors() -> anyhow::Result<()> {
+        let files = MemoryFiles::new();
+        let dropbox = MemoryRemoteStorage::new(PathBuf::from("/dropbox"), files.clone());
+        let nas = tempfile::tempdir()?;
+        let local = tempfile::tempdir()?;
+        let save = Save::from_parts(Side::Axis, 3).player("DM");
+        std::fs::write(local.path().join("Axis DM 3.sav"), "DM's turn 3")?;
+
+        let folder = SyncedFolder::new(
+            nas.path().to_path_buf(),
+            Provider::Folder,
+            Box::new(LocalFileSystem::new()),
+            Box::new(CopyCompression),
+        )?;
+        let remote =
+            MirroredRemote::new("dropbox", Box::new(dropbox)).mirror("nas", Box::new(folder));
+
+        // the NAS goes away before the upload
+        std::fs::remove_dir(nas.path())?;
+        std::fs::write(nas.path(), "not a folder")?;
+
+        // uploads run on a clone, the user is told about lagging mirrors from the original
+        let mut uploader = dyn_clone::clone_box(&remote);
+        uploader.upload(&save, &local.path().join("Axis DM 3.sav"))?;
+
+        assert_eq!(remote.lagging_mirrors(), vec!["nas".to_string()]);
+
+        std::fs::remove_file(nas.path())?;
+        Ok(())
+    }
+}
@@ -0,0 +1,129 @@
+//! A tiny HTTP/1.1 server that runs inside the tests, for standing in for the servers that remote storage talks to.
+//!
+//! See [`MockWebDavServer`](super::mock_webdav_server::MockWebDavServer) and [`MockS3Server`](super::mock_s3_server::MockS3Server).
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread;
+
+use percent_encoding::percent_decode_str;
+
+pub struct Request {
+    pub method: String,
+    /// The decoded path, without a trailing slash
+    pub path: String,
+    /// The decoded query parameters
+    pub query: HashMap<String, String>,
+    /// Header names are lowercase
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(String::as_str)
+    }
+
+    pub fn query(&self, name: &str) -> Option<&str> {
+        self.query.get(name).map(String::as_str)
+    }
+}
+
+/// The status line, e.g. `200 OK`, and body of a response
+pub type Response = (&'static str, Vec<u8>);
+
+/// Start serving on a random local port, answering each request with `handler`
+pub fn start<H>(handler: H) -> SocketAddr
+where
+    H: Fn(&Request) -> Response + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind mock HTTP server");
+    let address = listener.local_addr().expect("mock HTTP server address");
+
+    let handler = Arc::new(handler);
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let handler = handler.clone();
+            thread::spawn(move || serve(stream, handler.as_ref()));
+        }
+    });
+
+    address
+}
+
+fn serve(stream: TcpStream, handler: &dyn Fn(&Request) -> Response) {
+    let mut reader = BufReader::new(stream.try_clone().expect("clone stream"));
+    let mut writer = stream;
+
+    while let Some(request) = read_request(&mut reader) {
+        let (status, body) = handler(&request);
+
+        let head = format!(
+            "HTTP/1.1 {status}\r\nContent-Length: {}\r\nContent-Type: application/xml; charset=utf-8\r\nWWW-Authenticate: Basic realm=\"scut\"\r\n\r\n",
+            body.len()
+        );
+        if writer.write_all(head.as_bytes()).is_err() || writer.write_all(&body).is_err() {
+            return;
+        }
+    }
+}
+
+fn read_request(reader: &mut impl BufRead) -> Option<Request> {
+    let mut line = String::new();
+    if reader.read_line(&mut line).ok()? == 0 {
+        return None;
+    }
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let target = parts.next()?;
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+    let mut headers = HashMap::new();
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).ok()?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            headers.insert(name.trim().to_lowercase(), value.trim().to_string());
+        }
+    }
+
+    let length = headers
+        .get("content-length")
+        .and_then(|length| length.parse().ok())
+        .unwrap_or(0);
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).ok()?;
+
+    Some(Request {
+        method,
+        path: decode_path(path),
+        query: query
+            .split('&')
+            .filter(|parameter| !parameter.is_empty())
+            .map(|parameter| {
+                let (name, value) = parameter.split_once('=').unwrap_or((parameter, ""));
+                (decode(name), decode(value))
+            })
+            .collect(),
+        headers,
+        body,
+    })
+}
+
+fn decode(text: &str) -> String {
+    percent_decode_str(text).decode_utf8_lossy().into_owned()
+}
+
+/// Decode a path, without a trailing slash
+pub fn decode_path(path: &str) -> String {
+    match decode(path).trim_end_matches('/') {
+        "" => String::from("/"),
+        path => path.to_string(),
+    }
+}
@@ -2,19 +2,25 @@ use std::path::{Path, PathBuf};
 
 use crate::{
     interface::{
+        file_system::FileMetadata,
         index::{mock_index::MockIndex, IterIndex},
         Index,
     },
+    ledger::LedgerEntry,
+    signature::Signature,
+    substitute::Substitution,
+    versions::Version,
     Save,
 };
 
-use super::{LocalStorage, RemoteStorage};
+use super::{sync_record::SyncRecord, LocalStorage, RemoteStorage};
 
 #[derive(Debug, Clone)]
 pub struct MockIndexStorage {
     index: MockIndex,
     path: PathBuf,
     autosave: bool,
+    substitutions: Vec<Substitution>,
 }
 
 impl MockIndexStorage {
@@ -23,6 +29,7 @@ impl MockIndexStorage {
             autosave,
             index: MockIndex::new(saves.iter()),
             path: PathBuf::from("wherever"),
+            substitutions: Vec::new(),
         }
     }
 }
@@ -52,6 +59,27 @@ impl LocalStorage for MockIndexStorage {
         self.path.as_path()
     }
 
+    fn supersede(&mut self, save: &Save) -> anyhow::Result<()> {
+        self.index.remove(save);
+        Ok(())
+    }
+
+    fn metadata(&mut self, _save: &Save) -> anyhow::Result<Option<FileMetadata>> {
+        Ok(None)
+    }
+
+    fn autosave_metadata(&mut self) -> anyhow::Result<Option<FileMetadata>> {
+        Ok(None)
+    }
+
+    fn sync_record(&mut self) -> anyhow::Result<SyncRecord> {
+        Ok(SyncRecord::default())
+    }
+
+    fn write_sync_record(&mut self, _record: &SyncRecord) -> anyhow::Result<()> {
+        Ok(())
+    }
+
     fn index(&self) -> &dyn Index {
         self
     }
@@ -66,6 +94,48 @@ impl RemoteStorage for MockIndexStorage {
         Ok(())
     }
 
+    fn supersede(&mut self, save: &Save) -> anyhow::Result<()> {
+        self.index.remove(save);
+        Ok(())
+    }
+
+    fn record_substitution(&mut self, substitution: &Substitution) -> anyhow::Result<()> {
+        self.substitutions.push(substitution.clone());
+        Ok(())
+    }
+
+    fn substitutions(&mut self) -> anyhow::Result<Vec<Substitution>> {
+        Ok(self.substitutions.clone())
+    }
+
+    fn record_signature(&mut self, _signature: &Signature) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn signatures(&mut self) -> anyhow::Result<Vec<Signature>> {
+        Ok(Vec::new())
+    }
+
+    fn record_upload(&mut self, _entry: &LedgerEntry) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn ledger(&mut self) -> anyhow::Result<Vec<LedgerEntry>> {
+        Ok(Vec::new())
+    }
+
+    fn versions(&mut self, _save: &Save) -> anyhow::Result<Vec<Version>> {
+        Ok(Vec::new())
+    }
+
+    fn restore(&mut self, save: &Save, version: u64) -> anyhow::Result<()> {
+        anyhow::bail!("{save} version {version} not found")
+    }
+
+    fn metadata(&mut self, _save: &Save) -> anyhow::Result<Option<FileMetadata>> {
+        Ok(None)
+    }
+
     fn index(&self) -> &dyn Index {
         self
     }
@@ -0,0 +1,242 @@
+//! A mail server that runs inside the tests, standing in for a mailing list.
+//!
+//! It accepts email over SMTP and delivers every email it is sent to a single IMAP folder, without TLS,
+//! and only understands the commands that [`PlayByEmail`](super::email::PlayByEmail) sends.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+pub struct MockMailServer {
+    /// The url of the IMAP folder, e.g. `imap://127.0.0.1:1234/INBOX`
+    pub imap: String,
+    /// The url of the SMTP server, e.g. `smtp://127.0.0.1:1235`
+    pub smtp: String,
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Default)]
+struct State {
+    username: String,
+    password: String,
+    /// Every email delivered, oldest first, so the uid of each is its index plus one
+    emails: Vec<String>,
+    /// Whether emails are held back, as a slow mailing list would
+    holding: bool,
+    /// The emails sent while holding, delivered once the mailing list catches up
+    held: Vec<String>,
+}
+
+impl MockMailServer {
+    /// Start accepting email from `username`, who logs in with `password`
+    pub fn start(username: &str, password: &str) -> Self {
+        let state = Arc::new(Mutex::new(State {
+            username: username.to_string(),
+            password: password.to_string(),
+            ..State::default()
+        }));
+
+        let imap = listen(state.clone(), serve_imap);
+        let smtp = listen(state.clone(), serve_smtp);
+
+        MockMailServer {
+            imap: format!("imap://{imap}/INBOX"),
+            smtp: format!("smtp://{smtp}"),
+            state,
+        }
+    }
+
+    /// Hold back the emails sent from now on, until they are [delivered](Self::deliver)
+    pub fn hold(&self) {
+        self.state.lock().expect("mock mail server state").holding = true;
+    }
+
+    /// Deliver the emails that were held back, and every email sent from now on
+    pub fn deliver(&self) {
+        let mut state = self.state.lock().expect("mock mail server state");
+        state.holding = false;
+        let held = std::mem::take(&mut state.held);
+        state.emails.extend(held);
+    }
+
+    /// Every email delivered, oldest first
+    pub fn emails(&self) -> Vec<String> {
+        self.state
+            .lock()
+            .expect("mock mail server state")
+            .emails
+            .clone()
+    }
+}
+
+/// Start serving on a random local port, answering each connection with `serve`
+fn listen(
+    state: Arc<Mutex<State>>,
+    serve: fn(TcpStream, &Mutex<State>) -> Option<()>,
+) -> std::net::SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind mock mail server");
+    let address = listener.local_addr().expect("mock mail server address");
+
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let state = state.clone();
+            thread::spawn(move || serve(stream, &state));
+        }
+    });
+
+    address
+}
+
+fn serve_smtp(stream: TcpStream, state: &Mutex<State>) -> Option<()> {
+    let mut reader = BufReader::new(stream.try_clone().ok()?);
+    let mut writer = stream;
+
+    write!(writer, "220 localhost ESMTP mock\r\n").ok()?;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).ok()? == 0 {
+            return Some(());
+        }
+        let command = line.trim_end().to_ascii_uppercase();
+
+        if command.starts_with("EHLO") || command.starts_with("HELO") {
+            write!(writer, "250-localhost\r\n250 AUTH PLAIN LOGIN\r\n").ok()?;
+        } else if command.starts_with("AUTH") {
+            write!(writer, "235 2.7.0 Authentication successful\r\n").ok()?;
+        } else if command.starts_with("MAIL") || command.starts_with("RCPT") {
+            write!(writer, "250 OK\r\n").ok()?;
+        } else if command.starts_with("DATA") {
+            write!(writer, "354 End data with <CR><LF>.<CR><LF>\r\n").ok()?;
+
+            let mut email = String::new();
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).ok()? == 0 {
+                    return Some(());
+                }
+                if line == ".\r\n" {
+                    break;
+                }
+                // undo dot stuffing
+                email.push_str(line.strip_prefix('.').unwrap_or(&line));
+            }
+
+            let mut state = state.lock().ok()?;
+            if state.holding {
+                state.held.push(email);
+            } else {
+                state.emails.push(email);
+            }
+            drop(state);
+            write!(writer, "250 OK queued\r\n").ok()?;
+        } else if command.starts_with("QUIT") {
+            write!(writer, "221 Bye\r\n").ok()?;
+            return Some(());
+        } else {
+            write!(writer, "250 OK\r\n").ok()?;
+        }
+    }
+}
+
+fn serve_imap(stream: TcpStream, state: &Mutex<State>) -> Option<()> {
+    let mut reader = BufReader::new(stream.try_clone().ok()?);
+    let mut writer = stream;
+
+    write!(writer, "* OK mock IMAP server ready\r\n").ok()?;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).ok()? == 0 {
+            return Some(());
+        }
+        let mut parts = line.trim_end().splitn(3, ' ');
+        let tag = parts.next()?;
+        let command = parts.next()?.to_ascii_uppercase();
+        let arguments = parts.next().unwrap_or_default();
+
+        match command.as_str() {
+            "CAPABILITY" => {
+                write!(
+                    writer,
+                    "* CAPABILITY IMAP4rev1\r\n{tag} OK CAPABILITY completed\r\n"
+                )
+                .ok()?;
+            }
+            "LOGIN" => {
+                let state = state.lock().ok()?;
+                let expected = format!("{} {}", quote(&state.username), quote(&state.password));
+                if arguments == expected {
+                    write!(writer, "{tag} OK LOGIN completed\r\n").ok()?;
+                } else {
+                    write!(
+                        writer,
+                        "{tag} NO [AUTHENTICATIONFAILED] Invalid credentials\r\n"
+                    )
+                    .ok()?;
+                }
+            }
+            "SELECT" | "EXAMINE" => {
+                let exists = state.lock().ok()?.emails.len();
+                write!(
+                    writer,
+                    "* {exists} EXISTS\r\n* 0 RECENT\r\n* OK [UIDVALIDITY 1] UIDs valid\r\n* FLAGS (\\Seen)\r\n{tag} OK [READ-WRITE] SELECT completed\r\n"
+                )
+                .ok()?;
+            }
+            "UID" => {
+                let mut arguments = arguments.splitn(3, ' ');
+                let (Some(_fetch), Some(set), Some(item)) =
+                    (arguments.next(), arguments.next(), arguments.next())
+                else {
+                    write!(writer, "{tag} BAD unknown command\r\n").ok()?;
+                    continue;
+                };
+
+                let emails = state.lock().ok()?.emails.clone();
+                for uid in uids(set, emails.len()) {
+                    let Some(email) = emails.get(uid as usize - 1) else {
+                        continue;
+                    };
+                    let (item, content) = if item.contains("HEADER") {
+                        let header = email.split("\r\n\r\n").next().unwrap_or_default();
+                        ("RFC822.HEADER", format!("{header}\r\n\r\n"))
+                    } else {
+                        ("RFC822", email.clone())
+                    };
+                    write!(
+                        writer,
+                        "* {uid} FETCH (UID {uid} {item} {{{}}}\r\n{content})\r\n",
+                        content.len()
+                    )
+                    .ok()?;
+                }
+                write!(writer, "{tag} OK UID FETCH completed\r\n").ok()?;
+            }
+            "LOGOUT" => {
+                write!(writer, "* BYE logging out\r\n{tag} OK LOGOUT completed\r\n").ok()?;
+                return Some(());
+            }
+            _ => {
+                write!(writer, "{tag} OK\r\n").ok()?;
+            }
+        }
+    }
+}
+
+/// The uids in a set such as `1:*` or `2,5`
+fn uids(set: &str, count: usize) -> Vec<u32> {
+    set.split(',')
+        .flat_map(|range| match range.split_once(':') {
+            Some((from, _)) => (from.parse().unwrap_or(1)..=count as u32).collect::<Vec<_>>(),
+            None => range.parse().into_iter().collect(),
+        })
+        .collect()
+}
+
+/// How the imap crate quotes login arguments
+fn quote(argument: &str) -> String {
+    format!(
+        "\"{}\"",
+        argument.replace('\\', "\\\\").replace('"', "\\\"")
+    )
+}
@@ -0,0 +1,200 @@
+//! An S3-compatible server that runs inside the tests, keeping one bucket's objects in memory.
+//!
+//! It understands just enough of the S3 API for [`S3Bucket`](super::s3::S3Bucket): ListObjectsV2, GetObject, PutObject,
+//! CopyObject and DeleteObject. Requests must be signed with the right access key, but the signature itself isn't checked.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use super::mock_http_server::{self, decode_path, Request, Response};
+
+/// How many objects are listed per page, kept small so that tests page through listings
+const PAGE_SIZE: usize = 2;
+
+pub struct MockS3Server {
+    /// The url of the endpoint, ending in a slash
+    pub url: String,
+    state: Arc<Mutex<State>>,
+}
+
+struct State {
+    bucket: String,
+    access_key: String,
+    objects: BTreeMap<String, Vec<u8>>,
+    /// Whether `If-None-Match` is supported, as it isn't by every S3-compatible server
+    conditional_writes: bool,
+    /// Whether PutObject fails, as if the server were out of space
+    refusing_uploads: bool,
+}
+
+impl MockS3Server {
+    /// Start serving an empty `bucket` to requests signed with `access_key`
+    pub fn start(bucket: &str, access_key: &str) -> Self {
+        let state = Arc::new(Mutex::new(State {
+            bucket: bucket.to_string(),
+            access_key: access_key.to_string(),
+            objects: BTreeMap::new(),
+            conditional_writes: true,
+            refusing_uploads: false,
+        }));
+
+        let shared = state.clone();
+        let address = mock_http_server::start(move |request| {
+            respond(request, &mut shared.lock().expect("mock S3 server state"))
+        });
+
+        MockS3Server {
+            url: format!("http://{address}/"),
+            state,
+        }
+    }
+
+    pub fn support_conditional_writes(&self, supported: bool) {
+        self.state
+            .lock()
+            .expect("mock S3 server state")
+            .conditional_writes = supported;
+    }
+
+    /// Make every PutObject fail, as if the server were out of space, until told otherwise
+    pub fn refuse_uploads(&self, refusing: bool) {
+        self.state
+            .lock()
+            .expect("mock S3 server state")
+            .refusing_uploads = refusing;
+    }
+
+    /// The key of every object in the bucket
+    pub fn objects(&self) -> Vec<String> {
+        self.state
+            .lock()
+            .expect("mock S3 server state")
+            .objects
+            .keys()
+            .cloned()
+            .collect()
+    }
+
+    /// The content of an object in the bucket, if it exists
+    pub fn object(&self, key: &str) -> Option<String> {
+        self.state
+            .lock()
+            .expect("mock S3 server state")
+            .objects
+            .get(key)
+            .map(|content| String::from_utf8_lossy(content).into_owned())
+    }
+}
+
+fn respond(request: &Request, state: &mut State) -> Response {
+    let credential = format!("AWS4-HMAC-SHA256 Credential={}/", state.access_key);
+    if !request
+        .header("authorization")
+        .is_some_and(|authorization| authorization.starts_with(&credential))
+    {
+        return ("403 Forbidden", error("InvalidAccessKeyId"));
+    }
+
+    let (bucket, key) = request
+        .path
+        .trim_start_matches('/')
+        .split_once('/')
+        .unwrap_or((request.path.trim_start_matches('/'), ""));
+    if bucket != state.bucket {
+        return ("404 Not Found", error("NoSuchBucket"));
+    }
+
+    match (request.method.as_str(), key) {
+        ("GET", "") => list(request, state),
+        ("GET", key) => match state.objects.get(key) {
+            Some(content) => ("200 OK", content.clone()),
+            None => ("404 Not Found", error("NoSuchKey")),
+        },
+        ("PUT", key) => {
+            if let Some(source) = request.header("x-amz-copy-source") {
+                let source = decode_path(source);
+                let source = source
+                    .trim_start_matches('/')
+                    .strip_prefix(&format!("{}/", state.bucket))
+                    .unwrap_or_default();
+                return match state.objects.get(source).cloned() {
+                    Some(content) => {
+                        state.objects.insert(key.to_string(), content);
+                        ("200 OK", b"<CopyObjectResult></CopyObjectResult>".to_vec())
+                    }
+                    None => ("404 Not Found", error("NoSuchKey")),
+                };
+            }
+
+            if state.refusing_uploads {
+                return ("507 Insufficient Storage", error("InsufficientStorage"));
+            }
+            if request.header("if-none-match") == Some("*") {
+                if !state.conditional_writes {
+                    return ("501 Not Implemented", error("NotImplemented"));
+                }
+                if state.objects.contains_key(key) {
+                    return ("412 Precondition Failed", error("PreconditionFailed"));
+                }
+            }
+
+            state.objects.insert(key.to_string(), request.body.clone());
+            ("200 OK", Vec::new())
+        }
+        ("DELETE", key) => {
+            state.objects.remove(key);
+            ("204 No Content", Vec::new())
+        }
+        _ => ("405 Method Not Allowed", error("MethodNotAllowed")),
+    }
+}
+
+/// ListObjectsV2, a page at a time
+fn list(request: &Request, state: &State) -> Response {
+    let prefix = request.query("prefix").unwrap_or_default();
+    let delimiter = request.query("delimiter");
+    let after = request.query("continuation-token").unwrap_or_default();
+
+    let mut keys = state
+        .objects
+        .iter()
+        .filter(|(key, _)| key.starts_with(prefix) && key.as_str() > after)
+        // objects in subfolders are rolled up into common prefixes, which scut doesn't use
+        .filter(|(key, _)| {
+            delimiter.is_none_or(|delimiter| !key[prefix.len()..].contains(delimiter))
+        })
+        .peekable();
+
+    let mut xml = String::from(
+        r#"<?xml version="1.0" encoding="UTF-8"?><ListBucketResult xmlns="http://s3.amazonaws.com/doc/2006-03-01/">"#,
+    );
+    let mut last = None;
+    for (key, content) in keys.by_ref().take(PAGE_SIZE) {
+        xml.push_str(&format!(
+            "<Contents><Key>{}</Key><Size>{}</Size></Contents>",
+            escape(key),
+            content.len()
+        ));
+        last = Some(key);
+    }
+    match (keys.peek(), last) {
+        (Some(_), Some(last)) => xml.push_str(&format!(
+            "<IsTruncated>true</IsTruncated><NextContinuationToken>{}</NextContinuationToken>",
+            escape(last)
+        )),
+        _ => xml.push_str("<IsTruncated>false</IsTruncated>"),
+    }
+    xml.push_str("</ListBucketResult>");
+
+    ("200 OK", xml.into_bytes())
+}
+
+fn error(code: &str) -> Vec<u8> {
+    format!("<Error><Code>{code}</Code></Error>").into_bytes()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
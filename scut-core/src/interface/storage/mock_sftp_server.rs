@@ -0,0 +1,144 @@
+//! An SSH server that runs inside the tests, for [`SftpFolder`](super::sftp::SftpFolder) to upload to.
+//!
+//! Writing an SSH server is out of the question, so this runs OpenSSH's `sshd` as the current user on a random port,
+//! with freshly generated host and user keys, serving a temporary folder. Tests that need it are skipped where `sshd` isn't installed.
+
+use std::fs;
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use tempfile::TempDir;
+
+/// Where `sshd` is usually installed
+const SSHD: &[&str] = &["/usr/sbin/sshd", "/usr/bin/sshd", "/usr/local/sbin/sshd"];
+
+pub struct MockSftpServer {
+    /// The url of the served folder
+    pub url: String,
+    pub username: String,
+    /// The private key that logs in
+    pub key: PathBuf,
+    /// A known hosts file with the server's host key in it
+    pub known_hosts: PathBuf,
+    /// The served folder, on this computer
+    pub folder: PathBuf,
+    sshd: Child,
+    _dir: TempDir,
+}
+
+impl MockSftpServer {
+    /// Start `sshd`, returning None if it isn't installed
+    pub fn start() -> anyhow::Result<Option<Self>> {
+        let Some(sshd) = SSHD.iter().map(Path::new).find(|sshd| sshd.exists()) else {
+            return Ok(None);
+        };
+
+        let dir = tempfile::tempdir()?;
+        let host_key = dir.path().join("host_key");
+        let key = dir.path().join("user_key");
+        generate_key(&host_key)?;
+        generate_key(&key)?;
+        fs::copy(
+            key.with_extension("pub"),
+            dir.path().join("authorized_keys"),
+        )?;
+
+        let folder = dir.path().join("scut");
+        fs::create_dir(&folder)?;
+
+        // a free port, released for sshd to listen on
+        let port = TcpListener::bind("127.0.0.1:0")?.local_addr()?.port();
+
+        let config = dir.path().join("sshd_config");
+        fs::write(
+            &config,
+            format!(
+                "Port {port}\n\
+                 ListenAddress 127.0.0.1\n\
+                 HostKey {}\n\
+                 PidFile {}\n\
+                 AuthorizedKeysFile {}\n\
+                 PubkeyAuthentication yes\n\
+                 PasswordAuthentication no\n\
+                 KbdInteractiveAuthentication no\n\
+                 UsePAM no\n\
+                 StrictModes no\n\
+                 Subsystem sftp internal-sftp\n",
+                host_key.display(),
+                dir.path().join("sshd.pid").display(),
+                dir.path().join("authorized_keys").display(),
+            ),
+        )?;
+
+        let host_key_line = fs::read_to_string(host_key.with_extension("pub"))?;
+        let mut host_key_parts = host_key_line.split_whitespace();
+        let known_hosts = dir.path().join("known_hosts");
+        fs::write(
+            &known_hosts,
+            format!(
+                "[127.0.0.1]:{port} {} {}\n",
+                host_key_parts.next().unwrap_or_default(),
+                host_key_parts.next().unwrap_or_default()
+            ),
+        )?;
+
+        let sshd = Command::new(sshd)
+            .arg("-D")
+            .arg("-f")
+            .arg(&config)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .context("failed to start sshd")?;
+
+        let username = whoami()?;
+        let server = MockSftpServer {
+            url: format!("sftp://127.0.0.1:{port}{}", folder.display()),
+            username,
+            key,
+            known_hosts,
+            folder,
+            sshd,
+            _dir: dir,
+        };
+
+        let started = Instant::now();
+        while TcpStream::connect(("127.0.0.1", port)).is_err() {
+            if started.elapsed() > Duration::from_secs(10) {
+                anyhow::bail!("sshd didn't start listening on port {port}");
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+
+        Ok(Some(server))
+    }
+}
+
+impl Drop for MockSftpServer {
+    fn drop(&mut self) {
+        let _ = self.sshd.kill();
+        let _ = self.sshd.wait();
+    }
+}
+
+fn generate_key(path: &Path) -> anyhow::Result<()> {
+    let status = Command::new("ssh-keygen")
+        .args(["-q", "-t", "ed25519", "-N", "", "-f"])
+        .arg(path)
+        .status()
+        .context("failed to run ssh-keygen")?;
+    anyhow::ensure!(status.success(), "ssh-keygen failed to generate a key");
+    Ok(())
+}
+
+/// The user that sshd runs as, which is the only user it can log in
+fn whoami() -> anyhow::Result<String> {
+    let output = Command::new("whoami")
+        .output()
+        .context("failed to run whoami")?;
+    Ok(String::from_utf8(output.stdout)?.trim().to_string())
+}
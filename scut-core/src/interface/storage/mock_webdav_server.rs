@@ -0,0 +1,169 @@
+//! A WebDAV server that runs inside the tests, keeping its files in memory.
+//!
+//! It understands just enough WebDAV for [`WebDav`](super::webdav::WebDav): PROPFIND, GET, PUT, MKCOL and MOVE behind basic auth.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex};
+
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use url::Url;
+
+use super::mock_http_server::{self, decode_path, Request, Response};
+
+pub struct MockWebDavServer {
+    /// The url of the served folder, ending in a slash
+    pub url: String,
+    state: Arc<Mutex<State>>,
+}
+
+struct State {
+    folders: BTreeSet<String>,
+    files: BTreeMap<String, Vec<u8>>,
+    /// The value of the `Authorization` header that logs in
+    authorization: String,
+    /// Whether PUT fails, as if the server were out of space
+    refusing_uploads: bool,
+}
+
+impl MockWebDavServer {
+    /// Start serving an empty `folder`, e.g. `/dav/scut`, to a user who logs in with `authorization`, e.g. `Basic RE06c2VjcmV0`
+    pub fn start(folder: &str, authorization: &str) -> Self {
+        let state = Arc::new(Mutex::new(State {
+            folders: BTreeSet::from([folder.trim_end_matches('/').to_string()]),
+            files: BTreeMap::new(),
+            authorization: authorization.to_string(),
+            refusing_uploads: false,
+        }));
+
+        let shared = state.clone();
+        let address = mock_http_server::start(move |request| {
+            respond(
+                request,
+                &mut shared.lock().expect("mock WebDAV server state"),
+            )
+        });
+
+        MockWebDavServer {
+            url: format!("http://{address}{}/", folder.trim_end_matches('/')),
+            state,
+        }
+    }
+
+    /// Make every PUT fail, as if the server were out of space, until told otherwise
+    pub fn refuse_uploads(&self, refusing: bool) {
+        self.state
+            .lock()
+            .expect("mock WebDAV server state")
+            .refusing_uploads = refusing;
+    }
+
+    /// The path of every file on the server
+    pub fn files(&self) -> Vec<String> {
+        self.state
+            .lock()
+            .expect("mock WebDAV server state")
+            .files
+            .keys()
+            .cloned()
+            .collect()
+    }
+}
+
+fn respond(request: &Request, state: &mut State) -> Response {
+    if request.header("authorization") != Some(state.authorization.as_str()) {
+        return ("401 Unauthorized", Vec::new());
+    }
+
+    let path = &request.path;
+    match request.method.as_str() {
+        "PROPFIND" => propfind(path, state),
+        "GET" => match state.files.get(path) {
+            Some(content) => ("200 OK", content.clone()),
+            None => ("404 Not Found", Vec::new()),
+        },
+        "PUT" => {
+            if state.refusing_uploads {
+                return ("507 Insufficient Storage", Vec::new());
+            }
+            if !state.folders.contains(parent(path)) {
+                return ("409 Conflict", Vec::new());
+            }
+            state.files.insert(path.clone(), request.body.clone());
+            ("201 Created", Vec::new())
+        }
+        "MKCOL" => {
+            if state.folders.contains(path) || state.files.contains_key(path) {
+                ("405 Method Not Allowed", Vec::new())
+            } else if !state.folders.contains(parent(path)) {
+                ("409 Conflict", Vec::new())
+            } else {
+                state.folders.insert(path.clone());
+                ("201 Created", Vec::new())
+            }
+        }
+        "MOVE" => {
+            let Some(destination) = request
+                .header("destination")
+                .and_then(|destination| Url::parse(destination).ok())
+                .map(|destination| decode_path(destination.path()))
+            else {
+                return ("400 Bad Request", Vec::new());
+            };
+            if !state.folders.contains(parent(&destination)) {
+                return ("409 Conflict", Vec::new());
+            }
+            match state.files.remove(path) {
+                Some(content) => {
+                    state.files.insert(destination, content);
+                    ("201 Created", Vec::new())
+                }
+                None => ("404 Not Found", Vec::new()),
+            }
+        }
+        _ => ("405 Method Not Allowed", Vec::new()),
+    }
+}
+
+/// List the folder and its children, like a real server does for `Depth: 1`
+fn propfind(path: &str, state: &State) -> Response {
+    if !state.folders.contains(path) {
+        return ("404 Not Found", Vec::new());
+    }
+
+    let mut xml =
+        String::from(r#"<?xml version="1.0" encoding="utf-8"?><d:multistatus xmlns:d="DAV:">"#);
+    let folders = state
+        .folders
+        .iter()
+        .filter(|folder| folder.as_str() == path || parent(folder) == path);
+    for folder in folders {
+        xml.push_str(&format!(
+            "<d:response><d:href>{}/</d:href><d:propstat><d:prop><d:resourcetype><d:collection/></d:resourcetype></d:prop><d:status>HTTP/1.1 200 OK</d:status></d:propstat></d:response>",
+            encode(folder)
+        ));
+    }
+    for (file, content) in state.files.iter().filter(|(file, _)| parent(file) == path) {
+        xml.push_str(&format!(
+            "<d:response><d:href>{}</d:href><d:propstat><d:prop><d:resourcetype/><d:getcontentlength>{}</d:getcontentlength></d:prop><d:status>HTTP/1.1 200 OK</d:status></d:propstat></d:response>",
+            encode(file),
+            content.len()
+        ));
+    }
+    xml.push_str("</d:multistatus>");
+
+    ("207 Multi-Status", xml.into_bytes())
+}
+
+fn parent(path: &str) -> &str {
+    match path.rsplit_once('/') {
+        Some(("", _)) | None => "/",
+        Some((parent, _)) => parent,
+    }
+}
+
+fn encode(path: &str) -> String {
+    path.split('/')
+        .map(|segment| utf8_percent_encode(segment, NON_ALPHANUMERIC).to_string())
+        .collect::<Vec<_>>()
+        .join("/")
+}
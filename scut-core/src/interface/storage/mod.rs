@@ -4,15 +4,79 @@
 
 use dyn_clone::DynClone;
 
-pub mod dropbox_folder;
+pub mod connect;
+#[cfg(feature = "email")]
+pub mod email;
 pub mod game_saves_folder;
+pub mod git;
+#[cfg(feature = "server")]
+pub mod http_remote;
+pub mod memory_storage;
+pub mod mirrored_remote;
+pub mod object_store;
+#[cfg(feature = "s3")]
+pub mod s3;
+#[cfg(feature = "sftp")]
+pub mod sftp;
+pub mod split_remote;
+pub(crate) mod staging;
+pub mod sync_record;
+pub mod synced_folder;
+#[cfg(feature = "webdav")]
+pub mod webdav;
 
 #[cfg(test)]
 pub mod mock_index_storage;
 
+#[cfg(all(test, feature = "email"))]
+pub mod mock_mail_server;
+
+#[cfg(all(test, any(feature = "webdav", feature = "s3")))]
+pub mod mock_http_server;
+
+#[cfg(all(test, feature = "s3"))]
+pub mod mock_s3_server;
+
+#[cfg(all(test, feature = "sftp"))]
+pub mod mock_sftp_server;
+
+#[cfg(all(test, feature = "webdav"))]
+pub mod mock_webdav_server;
+
 use std::path::{Path, PathBuf};
 
-use crate::{interface::Index, Save};
+use crate::{
+    error::ErrorSuggestions,
+    interface::{file_system::FileMetadata, Index},
+    ledger::LedgerEntry,
+    signature::Signature,
+    substitute::Substitution,
+    versions::Version,
+    Save,
+};
+
+use self::sync_record::SyncRecord;
+
+/// The name of the subfolder that superseded saves are moved into
+pub const SUPERSEDED_FOLDER: &str = "superseded";
+
+/// The name of the subfolder that records saves uploaded by substitutes, one segment file per substitute
+pub const SUBSTITUTIONS_FOLDER: &str = "substitutions";
+
+/// The name of the subfolder that records the signature of each upload, one segment file per signer
+pub const SIGNATURES_FOLDER: &str = "signatures";
+
+/// The name of the subfolder that holds the ledger of uploads, one segment file per uploader
+pub const LEDGER_FOLDER: &str = "ledger";
+
+/// The name of the subfolder that overwritten saves are kept in, see [`versions`](crate::versions)
+pub const VERSIONS_FOLDER: &str = "versions";
+
+/// The name of the subfolder that old saves are bundled into by [`prune`](crate::prune)
+pub const ARCHIVE_FOLDER: &str = "archive";
+
+/// The name of the file in local storage that records what saves looked like when they were last synced
+pub const SYNC_RECORD_FILE: &str = "scut-sync.toml";
 
 /// Local storage is where the saved Games are ready to be loaded by Strategic Command and played.
 ///
@@ -32,6 +96,23 @@ pub trait LocalStorage: DynClone + Send + Sync {
     /// The location of this storage, i.e. the folder where saves should be extracted to
     fn location(&self) -> &Path;
 
+    /// Move a save out of the way, into a `superseded` subfolder, so that it is no longer part of this storage.
+    ///
+    /// This is used when rolling back to replay an earlier turn.
+    fn supersede(&mut self, save: &Save) -> anyhow::Result<()>;
+
+    /// Returns the size, modification time and content digest of a save, if it exists.
+    fn metadata(&mut self, save: &Save) -> anyhow::Result<Option<FileMetadata>>;
+
+    /// Returns the size, modification time and content digest of the autosave, if it exists.
+    fn autosave_metadata(&mut self) -> anyhow::Result<Option<FileMetadata>>;
+
+    /// Returns the record of what saves looked like when they were last uploaded or downloaded, see [`SyncRecord`].
+    fn sync_record(&mut self) -> anyhow::Result<SyncRecord>;
+
+    /// Replace the record of what saves looked like when they were last uploaded or downloaded.
+    fn write_sync_record(&mut self, record: &SyncRecord) -> anyhow::Result<()>;
+
     /// Return a reference to an implementation of Index that provides the [`search`] method used to find certain saves within this storage.
     ///
     /// Note that the result of a [`search`] only contains the saves that matched, and not their path within local storage.
@@ -60,8 +141,71 @@ pub trait RemoteStorage: DynClone + Send + Sync {
     /// Move a game save file from local storage to remote storage.
     ///
     /// The game save file could be compressed when moved to remote storage.
+    ///
+    /// If the save is already in remote storage, the previous copy is kept as a [`Version`].
     fn upload(&mut self, save: &Save, local_path: &Path) -> anyhow::Result<()>;
 
+    /// Move a save out of the way, into a `superseded` subfolder, so that it is no longer part of this storage.
+    ///
+    /// This is used when rolling back to replay an earlier turn.
+    fn supersede(&mut self, save: &Save) -> anyhow::Result<()>;
+
+    /// Keep a record that a save was uploaded by a substitute, on behalf of the player who signed it.
+    fn record_substitution(&mut self, substitution: &Substitution) -> anyhow::Result<()>;
+
+    /// Every save recorded as uploaded by a substitute, each substitute's oldest first.
+    fn substitutions(&mut self) -> anyhow::Result<Vec<Substitution>>;
+
+    /// Append a signature to the signer's segment of the signatures, see [`signature`](crate::signature).
+    fn record_signature(&mut self, signature: &Signature) -> anyhow::Result<()>;
+
+    /// Every signature recorded for an upload, from every segment, oldest first.
+    fn signatures(&mut self) -> anyhow::Result<Vec<Signature>>;
+
+    /// Append an upload to the uploader's segment of the ledger, see [`ledger`](crate::ledger).
+    fn record_upload(&mut self, entry: &LedgerEntry) -> anyhow::Result<()>;
+
+    /// Every upload recorded in the ledger, from every segment, oldest first.
+    fn ledger(&mut self) -> anyhow::Result<Vec<LedgerEntry>>;
+
+    /// Every previous copy of a save that was kept when it was overwritten, oldest first.
+    fn versions(&mut self, save: &Save) -> anyhow::Result<Vec<Version>>;
+
+    /// Put a previous copy of a save back in place, identified by its [`timestamp`](Version::timestamp).
+    ///
+    /// The current copy of the save is kept as a version in its place.
+    fn restore(&mut self, save: &Save, version: u64) -> anyhow::Result<()>;
+
+    /// Returns the size, modification time and content digest of a save as it is stored remotely, e.g. compressed, if it exists.
+    fn metadata(&mut self, save: &Save) -> anyhow::Result<Option<FileMetadata>>;
+
+    /// Bundle `saves` into a single archive called `name`, e.g. `Turns 1-10`, removing them from this storage, see [`prune`](crate::prune).
+    ///
+    /// Archived saves are still in the [`index`](RemoteStorage::index), and are extracted from their archive when downloaded.
+    fn archive(&mut self, _name: &str, _saves: &[Save]) -> anyhow::Result<()> {
+        Err(anyhow::anyhow!("this remote storage can't archive saves"))
+            .suggest("Saves can only be pruned from a synced folder or a git repository")?
+    }
+
+    /// Every archive that saves have been bundled into, oldest first.
+    fn archives(&mut self) -> anyhow::Result<Vec<Archive>> {
+        Ok(Vec::new())
+    }
+
+    /// Share the uploads, restores and records made since the last publish with the other players, through this storage or any of its clones.
+    ///
+    /// Storage that shares each of them as soon as it is made has nothing left to publish.
+    fn publish(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// The names of the mirrors that missed an upload made through this storage or any of its clones, see [`mirrored_remote`].
+    ///
+    /// Storage that isn't mirrored has no mirrors to miss anything.
+    fn lagging_mirrors(&self) -> Vec<String> {
+        Vec::new()
+    }
+
     /// Return a reference to an implementation of [`Index`] that provides the [`search`] method used to find certain saves within this storage.
     ///
     /// Note that the result of a [`search`] only contains the saves that matched, and not their location within remote storage.
@@ -70,6 +214,15 @@ pub trait RemoteStorage: DynClone + Send + Sync {
     fn index(&self) -> &dyn Index;
 }
 
+/// An archive in remote storage that old saves were bundled into by [`prune`](crate::prune)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Archive {
+    /// The name of the archive, e.g. `Turns 1-10`
+    pub name: String,
+    /// The saves in the archive, in turn order
+    pub saves: Vec<Save>,
+}
+
 impl Clone for Box<dyn RemoteStorage> {
     fn clone(&self) -> Self {
         dyn_clone::clone_box(&**self)
@@ -0,0 +1,495 @@
+//! Remote storage that keeps each save as a file lays out everything else the same way, whether it is a
+//! [`SyncedFolder`](super::synced_folder::SyncedFolder), a WebDAV folder, an S3 bucket, an SFTP folder or a scut server,
+//! so that a game can be moved from one to another by copying its files:
+//!
+//! ```text
+//! Axis DM 12.7z                        the current copy of a save
+//! superseded/Axis DM 11/1700000000.7z  a save that was superseded, e.g. by a rollback, named by when it was
+//! versions/Axis DM 12/1700000000.7z    a previous copy of a save, named by when it was overwritten
+//! ledger/DM.txt                        one uploader's segment of the ledger
+//! substitutions/DM.txt                 the saves DM uploaded as a substitute
+//! signatures/DM.txt                    the signatures DM made
+//! ```
+//!
+//! Each storage only has to move files around, by implementing [`ObjectStore`], and the layout is built on top of that here.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::Context;
+
+use crate::interface::storage::{
+    LEDGER_FOLDER, SIGNATURES_FOLDER, SUBSTITUTIONS_FOLDER, SUPERSEDED_FOLDER, VERSIONS_FOLDER,
+};
+use crate::ledger::{self, LedgerEntry};
+use crate::save::path_to_save;
+use crate::signature::Signature;
+use crate::substitute::Substitution;
+use crate::versions::{version_timestamp, Version};
+use crate::Save;
+
+/// A place that files are kept in, each named by its path from the root with a `/` between folders,
+/// e.g. `versions/Axis DM 12/1700000000.7z`
+pub trait ObjectStore {
+    /// The files directly in `folder`, or in the root if `folder` is empty. Returns None if the folder doesn't exist
+    fn list(&mut self, folder: &str) -> anyhow::Result<Option<Vec<Object>>>;
+
+    /// The content of a file, or None if it doesn't exist
+    fn get(&mut self, name: &str) -> anyhow::Result<Option<Vec<u8>>>;
+
+    /// Create or overwrite a file, creating the folders it is in
+    fn put(&mut self, name: &str, content: Vec<u8>) -> anyhow::Result<()>;
+
+    /// Move a file, replacing any file already at `to` and creating the folders it is in
+    fn rename(&mut self, from: &str, to: &str) -> anyhow::Result<()>;
+
+    /// Add a line to the end of a file, creating it if it doesn't exist.
+    ///
+    /// The file is read and written back whole, so two players appending at once can lose a line.
+    /// That's why every record is split into a segment per player, who is the only one that appends to it.
+    fn append(&mut self, name: &str, line: &str) -> anyhow::Result<()> {
+        let mut content = self.get(name)?.unwrap_or_default();
+        content.extend_from_slice(line.as_bytes());
+        content.push(b'\n');
+        self.put(name, content)
+    }
+}
+
+/// A file listed in an [`ObjectStore`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Object {
+    /// The name of the file, without the folder it is in
+    pub name: String,
+
+    /// The size of the file in bytes
+    pub size: u64,
+}
+
+/// The file each save is kept in, by save. Returns None if the root of the store doesn't exist
+pub fn saves(store: &mut dyn ObjectStore) -> anyhow::Result<Option<BTreeMap<Save, Object>>> {
+    Ok(store.list("")?.map(|objects| {
+        objects
+            .into_iter()
+            // uploads that are still being written
+            .filter(|object| !object.name.starts_with('.'))
+            .filter_map(|object| path_to_save(Path::new(&object.name)).map(|save| (save, object)))
+            .collect()
+    }))
+}
+
+/// The temporary file next to `name` that an upload is written to before it is renamed into place.
+///
+/// It starts with a `.`, so that it isn't mistaken for a save while it is being written.
+pub fn temporary(name: &str) -> String {
+    let (folder, file_name) = match name.rsplit_once('/') {
+        Some((folder, file_name)) => (format!("{folder}/"), file_name),
+        None => (String::new(), name),
+    };
+    format!("{folder}.{file_name}.{}.uploading", std::process::id())
+}
+
+/// Upload `content` as the file `name`, the new copy of `save`, keeping `current`, the file of the save's previous copy if it has one, as a version.
+///
+/// The new copy is put under a [temporary] name first, so that the previous copy stays in place if the upload fails.
+pub fn replace(
+    store: &mut dyn ObjectStore,
+    save: &Save,
+    name: &str,
+    content: Vec<u8>,
+    current: Option<&str>,
+) -> anyhow::Result<()> {
+    let temporary = temporary(name);
+    store.put(&temporary, content)?;
+
+    if let Some(current) = current {
+        keep_version(store, save, current)?;
+    }
+    store.rename(&temporary, name)
+}
+
+/// Move the file `name`, the current copy of `save`, into the save's versions folder so that it isn't lost when the save is overwritten
+pub fn keep_version(store: &mut dyn ObjectStore, save: &Save, name: &str) -> anyhow::Result<()> {
+    let version = timestamped_name(store, &format!("{VERSIONS_FOLDER}/{save}"), name)?;
+    store
+        .rename(name, &version)
+        .with_context(|| format!("failed to keep the previous version of {save}"))
+}
+
+/// Each version of `save`, oldest first
+pub fn versions(store: &mut dyn ObjectStore, save: &Save) -> anyhow::Result<Vec<Version>> {
+    Ok(version_objects(store, save)?
+        .into_iter()
+        .map(|(timestamp, object)| Version {
+            save: save.clone(),
+            timestamp,
+            size: object.size,
+        })
+        .collect())
+}
+
+/// Put the version of `save` kept at `timestamp` back in place, keeping `current`, the file of the save's current copy if it has one, as a version.
+///
+/// Returns false if there is no such version.
+pub fn restore(
+    store: &mut dyn ObjectStore,
+    save: &Save,
+    timestamp: u64,
+    current: Option<&str>,
+) -> anyhow::Result<bool> {
+    let Some(version) = version_objects(store, save)?.remove(&timestamp) else {
+        return Ok(false);
+    };
+
+    if let Some(current) = current {
+        keep_version(store, save, current)?;
+    }
+
+    store
+        .rename(
+            &format!("{VERSIONS_FOLDER}/{save}/{}", version.name),
+            &with_extension(&save.to_string(), &version.name),
+        )
+        .with_context(|| format!("failed to restore {save} version {timestamp}"))?;
+    Ok(true)
+}
+
+/// Move the file `name`, the current copy of `save`, into the superseded folder, beside any copies of the save superseded before
+pub fn supersede(store: &mut dyn ObjectStore, save: &Save, name: &str) -> anyhow::Result<()> {
+    let superseded = timestamped_name(store, &format!("{SUPERSEDED_FOLDER}/{save}"), name)?;
+    store
+        .rename(name, &superseded)
+        .with_context(|| format!("failed to supersede {save}"))
+}
+
+/// Append `substitution` to the substitute's segment of the substitutions
+pub fn record_substitution(
+    store: &mut dyn ObjectStore,
+    substitution: &Substitution,
+) -> anyhow::Result<()> {
+    let segment = segment(SUBSTITUTIONS_FOLDER, &substitution.uploaded_by);
+    append_record(store, &segment, substitution)
+        .with_context(|| format!("failed to record {substitution}"))
+}
+
+/// Every substitution, each substitute's oldest first
+pub fn substitutions(store: &mut dyn ObjectStore) -> anyhow::Result<Vec<Substitution>> {
+    read_segments(store, SUBSTITUTIONS_FOLDER).context("failed to read substitutions")
+}
+
+/// Append `signature` to the signer's segment of the signatures
+pub fn record_signature(store: &mut dyn ObjectStore, signature: &Signature) -> anyhow::Result<()> {
+    let segment = segment(SIGNATURES_FOLDER, &signature.signer);
+    append_record(store, &segment, signature)
+        .with_context(|| format!("failed to record the signature of {}", signature.save))
+}
+
+/// Every segment of the signatures merged together, oldest first
+pub fn signatures(store: &mut dyn ObjectStore) -> anyhow::Result<Vec<Signature>> {
+    let mut signatures: Vec<Signature> =
+        read_segments(store, SIGNATURES_FOLDER).context("failed to read signatures")?;
+    signatures.sort_by_key(|signature| signature.timestamp);
+    Ok(signatures)
+}
+
+/// Append `entry` to its uploader's segment of the ledger
+pub fn record_upload(store: &mut dyn ObjectStore, entry: &LedgerEntry) -> anyhow::Result<()> {
+    let segment = segment(LEDGER_FOLDER, &entry.uploader);
+    append_record(store, &segment, entry)
+        .with_context(|| format!("failed to record the upload of {}", entry.save))
+}
+
+/// Every segment of the ledger merged together, oldest upload first
+pub fn ledger(store: &mut dyn ObjectStore) -> anyhow::Result<Vec<LedgerEntry>> {
+    let mut ledger: Vec<LedgerEntry> =
+        read_segments(store, LEDGER_FOLDER).context("failed to read the ledger")?;
+    ledger.sort_by_key(|entry| entry.timestamp);
+    Ok(ledger)
+}
+
+/// Each version of `save` in the store, by timestamp
+fn version_objects(
+    store: &mut dyn ObjectStore,
+    save: &Save,
+) -> anyhow::Result<BTreeMap<u64, Object>> {
+    Ok(store
+        .list(&format!("{VERSIONS_FOLDER}/{save}"))?
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|object| {
+            version_timestamp(Path::new(&object.name)).map(|timestamp| (timestamp, object))
+        })
+        .collect())
+}
+
+/// A free name in `folder` to keep the file `name` under, named by the second it is kept in.
+///
+/// A later file kept in the same second takes the next free name, so that nothing already kept is replaced.
+fn timestamped_name(
+    store: &mut dyn ObjectStore,
+    folder: &str,
+    name: &str,
+) -> anyhow::Result<String> {
+    let taken: BTreeSet<u64> = store
+        .list(folder)?
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|object| version_timestamp(Path::new(&object.name)))
+        .collect();
+
+    let mut timestamp = ledger::now();
+    while taken.contains(&timestamp) {
+        timestamp += 1;
+    }
+    Ok(format!(
+        "{folder}/{}",
+        with_extension(&timestamp.to_string(), name)
+    ))
+}
+
+/// `stem` with the extension of the file `name`, if it has one
+fn with_extension(stem: &str, name: &str) -> String {
+    match Path::new(name).extension().and_then(|e| e.to_str()) {
+        Some(extension) => format!("{stem}.{extension}"),
+        None => stem.to_string(),
+    }
+}
+
+/// The segment of the records in `folder` that only `player` appends to
+fn segment(folder: &str, player: &str) -> String {
+    format!("{folder}/{player}.txt")
+}
+
+/// Read every record from every segment in `folder`, each segment's oldest first
+fn read_segments<T>(store: &mut dyn ObjectStore, folder: &str) -> anyhow::Result<Vec<T>>
+where
+    T: FromStr<Err = anyhow::Error>,
+{
+    let mut records = Vec::new();
+    for segment in store.list(folder)?.unwrap_or_default() {
+        // the segments are named after their player, anything else was put there by hand
+        if segment.name.ends_with(".txt") {
+            records.extend(read_records(store, &format!("{folder}/{}", segment.name))?);
+        }
+    }
+    Ok(records)
+}
+
+/// Append a record to a file in the store, one record per line
+fn append_record(
+    store: &mut dyn ObjectStore,
+    file: &str,
+    record: &dyn fmt::Display,
+) -> anyhow::Result<()> {
+    store.append(file, &record.to_string())
+}
+
+/// Read every record from a file in the store, oldest first
+fn read_records<T>(store: &mut dyn ObjectStore, file: &str) -> anyhow::Result<Vec<T>>
+where
+    T: FromStr<Err = anyhow::Error>,
+{
+    let Some(content) = store.get(file)? else {
+        return Ok(Vec::new());
+    };
+
+    String::from_utf8(content)
+        .with_context(|| format!("{file} isn't text"))?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.parse())
+        .collect::<anyhow::Result<_>>()
+        .with_context(|| format!("failed to read {file}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Side;
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    /// Files by their name, with folders that exist only while they have files in them
+    impl ObjectStore for BTreeMap<String, Vec<u8>> {
+        fn list(&mut self, folder: &str) -> anyhow::Result<Option<Vec<Object>>> {
+            let prefix = match folder {
+                "" => String::new(),
+                folder => format!("{folder}/"),
+            };
+
+            let objects: Vec<Object> = self
+                .iter()
+                .filter_map(|(name, content)| {
+                    let name = name.strip_prefix(&prefix)?;
+                    (!name.contains('/')).then(|| Object {
+                        name: name.to_string(),
+                        size: content.len() as u64,
+                    })
+                })
+                .collect();
+            Ok((folder.is_empty() || !objects.is_empty()).then_some(objects))
+        }
+
+        fn get(&mut self, name: &str) -> anyhow::Result<Option<Vec<u8>>> {
+            Ok(BTreeMap::get(self, name).cloned())
+        }
+
+        fn put(&mut self, name: &str, content: Vec<u8>) -> anyhow::Result<()> {
+            self.insert(name.to_string(), content);
+            Ok(())
+        }
+
+        fn rename(&mut self, from: &str, to: &str) -> anyhow::Result<()> {
+            let content = self
+                .remove(from)
+                .ok_or_else(|| anyhow::anyhow!("{from} doesn't exist"))?;
+            self.insert(to.to_string(), content);
+            Ok(())
+        }
+    }
+
+    fn names(store: &BTreeMap<String, Vec<u8>>) -> Vec<&str> {
+        store.keys().map(String::as_str).collect()
+    }
+
+    #[test]
+    fn saves_skip_uploads_in_progress_and_subfolders() -> anyhow::Result<()> {
+        let mut store = BTreeMap::new();
+        for name in [
+            "Axis DM 5.7z",
+            ".Axis DM 6.7z.partial",
+            "superseded/Axis DM 4.7z",
+            "notes.txt",
+        ] {
+            store.put(name, b"content".to_vec())?;
+        }
+
+        let saves = saves(&mut store)?.unwrap_or_default();
+        assert_eq!(
+            saves.into_iter().collect::<Vec<_>>(),
+            vec![(
+                Save::from_parts(Side::Axis, 5).player("DM"),
+                Object {
+                    name: "Axis DM 5.7z".to_string(),
+                    size: 7,
+                }
+            )]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn versions_are_kept_and_restored() -> anyhow::Result<()> {
+        let mut store = BTreeMap::new();
+        let save = Save::from_parts(Side::Allies, 5);
+
+        // overwritten twice in quick succession
+        store.put("Allies 5.7z", b"first".to_vec())?;
+        keep_version(&mut store, &save, "Allies 5.7z")?;
+        store.put("Allies 5.7z", b"second".to_vec())?;
+        keep_version(&mut store, &save, "Allies 5.7z")?;
+        store.put("Allies 5.7z", b"third".to_vec())?;
+
+        let kept = versions(&mut store, &save)?;
+        assert_eq!(
+            kept.iter().map(|version| version.size).collect::<Vec<_>>(),
+            vec![5, 6]
+        );
+        assert!(kept[0].timestamp < kept[1].timestamp);
+
+        // the copy that is replaced is kept too
+        assert!(restore(
+            &mut store,
+            &save,
+            kept[0].timestamp,
+            Some("Allies 5.7z")
+        )?);
+        assert_eq!(
+            BTreeMap::get(&store, "Allies 5.7z"),
+            Some(&b"first".to_vec())
+        );
+        assert_eq!(versions(&mut store, &save)?.len(), 2);
+        assert!(!restore(&mut store, &save, 0, Some("Allies 5.7z"))?);
+
+        supersede(&mut store, &save, "Allies 5.7z")?;
+        assert_eq!(saves(&mut store)?, Some(BTreeMap::new()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn superseding_a_save_again_keeps_both_copies() -> anyhow::Result<()> {
+        let mut store = BTreeMap::new();
+        let save = Save::from_parts(Side::Allies, 5);
+
+        // the same turn rolled back twice
+        store.put("Allies 5.7z", b"first".to_vec())?;
+        supersede(&mut store, &save, "Allies 5.7z")?;
+        store.put("Allies 5.7z", b"second".to_vec())?;
+        supersede(&mut store, &save, "Allies 5.7z")?;
+
+        let superseded: Vec<&Vec<u8>> = store
+            .iter()
+            .filter(|(name, _)| name.starts_with("superseded/Allies 5/"))
+            .map(|(_, content)| content)
+            .collect();
+        assert_eq!(superseded, vec![&b"first".to_vec(), &b"second".to_vec()]);
+        assert_eq!(saves(&mut store)?, Some(BTreeMap::new()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn ledger_merges_every_segment() -> anyhow::Result<()> {
+        let mut store = BTreeMap::new();
+        let entry = |save: Save, uploader: &str, timestamp| LedgerEntry {
+            save,
+            uploader: uploader.to_string(),
+            timestamp,
+            size: 2048,
+            digest: "ca97".to_string(),
+            version: "0.5.0".to_string(),
+        };
+
+        let uploads = vec![
+            entry(Save::from_parts(Side::Axis, 5).player("DM"), "DM", 100),
+            entry(Save::from_parts(Side::Allies, 5).player("GM"), "GM", 200),
+            entry(Save::from_parts(Side::Axis, 6).player("DG"), "DM", 300),
+        ];
+        for upload in &uploads {
+            record_upload(&mut store, upload)?;
+        }
+        store.put("ledger/README", b"written by hand".to_vec())?;
+
+        assert_eq!(
+            names(&store),
+            vec!["ledger/DM.txt", "ledger/GM.txt", "ledger/README"]
+        );
+        assert_eq!(ledger(&mut store)?, uploads);
+
+        Ok(())
+    }
+
+    #[test]
+    fn records_round_trip() -> anyhow::Result<()> {
+        let mut store = BTreeMap::new();
+        let substitution = Substitution::new(Save::from_parts(Side::Axis, 5).player("DG"), "DM");
+
+        assert_eq!(substitutions(&mut store)?, Vec::new());
+        record_substitution(&mut store, &substitution)?;
+        record_substitution(&mut store, &substitution)?;
+        assert_eq!(
+            substitutions(&mut store)?,
+            vec![substitution.clone(), substitution]
+        );
+
+        assert_eq!(names(&store), vec!["substitutions/DM.txt"]);
+
+        store.put("signatures/DM.txt", b"not a signature\n".to_vec())?;
+        assert!(signatures(&mut store).is_err());
+
+        Ok(())
+    }
+}
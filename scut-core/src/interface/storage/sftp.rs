@@ -0,0 +1,556 @@
+//! Uploads saves to a folder on a server reachable over SSH, e.g. a VPS, using SFTP so that no sync client is needed.
+//!
+//! The url names the server and the folder, e.g. `sftp://vps.example.com/srv/scut/ww1`, and you log in with an SSH key.
+//! The server must already be in your `known_hosts`, e.g. from connecting to it once with `ssh`,
+//! so that scut can tell that it is talking to the right server before sending it anything.
+//!
+//! Uploads are written to a temporary file next to the save and then renamed into place, so nobody ever downloads half an upload.
+
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use ssh2::{
+    CheckResult, ErrorCode, KnownHostFileKind, OpenFlags, OpenType, RenameFlags, Session, Sftp,
+};
+use url::Url;
+
+use crate::interface::file_system::FileMetadata;
+use crate::interface::index::IterIndex;
+use crate::interface::storage::object_store::{self, Object, ObjectStore};
+use crate::interface::storage::staging;
+use crate::interface::{Compression, RemoteStorage};
+use crate::ledger::LedgerEntry;
+use crate::signature::Signature;
+use crate::substitute::Substitution;
+use crate::versions::Version;
+use crate::{error::ErrorSuggestions, RemoteKind, Save};
+
+/// The status code SFTP servers respond with when a file or folder doesn't exist
+const NO_SUCH_FILE: i32 = 2;
+
+/// This implementation is used to store the saves in a folder on an SSH server, where they can be shared with other players.
+#[derive(Clone)]
+pub struct SftpFolder {
+    session: Session,
+    /// The folder on the server, without a trailing slash
+    location: String,
+    compression: Box<dyn Compression>,
+    /// The name of each save's file in the folder
+    saves: BTreeMap<Save, String>,
+}
+
+impl SftpFolder {
+    /// Connect to the server at `url` and log in with the private `key`, after checking the server's key is in `known_hosts`.
+    ///
+    /// The username comes from `credentials`, or else from the url, and the password in `credentials` is the key's passphrase.
+    pub fn new(
+        url: &str,
+        credentials: Option<(&str, Option<&str>)>,
+        key: &Path,
+        known_hosts: &Path,
+        compression: Box<dyn Compression>,
+    ) -> anyhow::Result<Self> {
+        let url = Url::parse(url)
+            .with_context(|| format!("`{url}` is not a valid url"))
+            .suggest("Set remote.url to the server followed by the folder, e.g. `sftp://vps.example.com/srv/scut/ww1`")?;
+        let host = url
+            .host_str()
+            .ok_or_else(|| anyhow::anyhow!("`{url}` doesn't name a server"))?
+            .to_string();
+        let port = url.port().unwrap_or(22);
+        let username = match credentials {
+            Some((username, _)) => username.to_string(),
+            None if !url.username().is_empty() => url.username().to_string(),
+            None => return Err(anyhow::anyhow!("remote.username must be set to use SFTP"))
+                .suggest(
+                "Use `scut config set remote.username <USERNAME>` to set the user you log in as",
+            )?,
+        };
+        let passphrase = credentials.and_then(|(_, password)| password);
+        let location = percent_encoding::percent_decode_str(url.path())
+            .decode_utf8_lossy()
+            .trim_end_matches('/')
+            .to_string();
+
+        let stream = TcpStream::connect((host.as_str(), port))
+            .with_context(|| format!("failed to connect to {host}:{port}"))
+            .suggest(RemoteKind::Sftp.sync_suggestion())?;
+        let mut session = Session::new().context("failed to start an SSH session")?;
+        session.set_tcp_stream(stream);
+        session
+            .handshake()
+            .with_context(|| format!("failed to start an SSH session with {host}"))?;
+
+        check_host_key(&session, &host, port, known_hosts)?;
+
+        session
+            .userauth_pubkey_file(&username, None, key, passphrase)
+            .with_context(|| {
+                format!(
+                    "{host} didn't accept {username} logging in with the key '{}'",
+                    key.display()
+                )
+            })
+            .suggest("Check remote.username, remote.key and, if your key has a passphrase, remote.password in your config")?;
+
+        let mut sftp = SftpFolder {
+            session,
+            location,
+            compression,
+            saves: BTreeMap::new(),
+        };
+
+        sftp.refresh_saves()?;
+
+        Ok(sftp)
+    }
+
+    /// Reloads from the server what saves are in this folder
+    pub fn refresh_saves(&mut self) -> anyhow::Result<()> {
+        let saves = object_store::saves(self)?
+            .ok_or_else(|| anyhow::anyhow!("{} not found on the SFTP server", self.location))
+            .suggest(RemoteKind::Sftp.sync_suggestion())?;
+
+        self.saves = saves
+            .into_iter()
+            .map(|(save, object)| (save, object.name))
+            .collect();
+
+        Ok(())
+    }
+
+    fn sftp(&self) -> anyhow::Result<Sftp> {
+        self.session
+            .sftp()
+            .context("failed to start SFTP on the server")
+    }
+
+    /// The path of a file or folder inside this folder, named the way an [`ObjectStore`] names it.
+    /// Paths on the server always use forward slashes, whatever this computer uses.
+    fn path(&self, name: &str) -> PathBuf {
+        match name {
+            "" => PathBuf::from(&self.location),
+            name => PathBuf::from(format!("{}/{name}", self.location)),
+        }
+    }
+
+    /// Write `content` to the file `name`
+    fn write(&self, name: &str, content: &[u8]) -> anyhow::Result<()> {
+        let path = self.path(name);
+
+        let mut file = self
+            .sftp()?
+            .create(&path)
+            .with_context(|| format!("failed to create {}", path.display()))?;
+        file.write_all(content)
+            .with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    /// Create the folders that the file `name` is in, if they don't exist already
+    fn make_folders(&self, name: &str) -> anyhow::Result<()> {
+        let sftp = self.sftp()?;
+
+        let mut folder = String::new();
+        let segments: Vec<&str> = name.split('/').collect();
+        for segment in &segments[..segments.len() - 1] {
+            if !folder.is_empty() {
+                folder.push('/');
+            }
+            folder.push_str(segment);
+
+            let path = self.path(&folder);
+            if sftp.stat(&path).is_ok() {
+                continue;
+            }
+            sftp.mkdir(&path, 0o755)
+                .with_context(|| format!("failed to create the folder {}", path.display()))?;
+        }
+        Ok(())
+    }
+
+    /// The name of a save's file in this folder, if it exists
+    fn locate_save(&mut self, save: &Save) -> anyhow::Result<Option<String>> {
+        if !self.saves.contains_key(save) {
+            self.refresh_saves()?;
+        }
+        Ok(self.saves.get(save).cloned())
+    }
+}
+
+impl ObjectStore for SftpFolder {
+    fn list(&mut self, folder: &str) -> anyhow::Result<Option<Vec<Object>>> {
+        let folder = self.path(folder);
+
+        let listing = match self.sftp()?.readdir(&folder) {
+            Ok(listing) => listing,
+            Err(error) if is_not_found(&error) => return Ok(None),
+            Err(error) => {
+                return Err(error).with_context(|| format!("failed to list {}", folder.display()))
+            }
+        };
+
+        Ok(Some(
+            listing
+                .into_iter()
+                .filter(|(_, stat)| !stat.is_dir())
+                .filter_map(|(path, stat)| {
+                    Some(Object {
+                        name: path.file_name()?.to_str()?.to_string(),
+                        size: stat.size.unwrap_or_default(),
+                    })
+                })
+                .collect(),
+        ))
+    }
+
+    fn get(&mut self, name: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        let path = self.path(name);
+
+        let mut file = match self.sftp()?.open(&path) {
+            Ok(file) => file,
+            Err(error) if is_not_found(&error) => return Ok(None),
+            Err(error) => {
+                return Err(error).with_context(|| format!("failed to open {}", path.display()))
+            }
+        };
+
+        let mut content = Vec::new();
+        file.read_to_end(&mut content)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        Ok(Some(content))
+    }
+
+    fn put(&mut self, name: &str, content: Vec<u8>) -> anyhow::Result<()> {
+        self.make_folders(name)?;
+        let temporary = object_store::temporary(name);
+
+        let written = self
+            .write(&temporary, &content)
+            .and_then(|_| self.rename(&temporary, name));
+        if written.is_err() {
+            // don't leave half an upload behind, there's nothing more to do if it can't be removed either
+            let _ = self.sftp().map(|sftp| sftp.unlink(&self.path(&temporary)));
+        }
+        written
+    }
+
+    fn rename(&mut self, from: &str, to: &str) -> anyhow::Result<()> {
+        self.make_folders(to)?;
+        let (from, to) = (self.path(from), self.path(to));
+
+        self.sftp()?
+            .rename(
+                &from,
+                &to,
+                Some(RenameFlags::OVERWRITE | RenameFlags::ATOMIC | RenameFlags::NATIVE),
+            )
+            .with_context(|| format!("failed to move {} to {}", from.display(), to.display()))
+    }
+
+    /// SFTP can open a file to append to, so the line is added on the server instead of the whole file being read and written back.
+    /// Nothing syncs the folder behind scut's back, so there are no conflicted copies to avoid,
+    /// and each record is still split into a segment per player so that two players never append to the same file.
+    fn append(&mut self, name: &str, line: &str) -> anyhow::Result<()> {
+        self.make_folders(name)?;
+        let path = self.path(name);
+
+        let mut file = self
+            .sftp()?
+            .open_mode(
+                &path,
+                OpenFlags::WRITE | OpenFlags::APPEND | OpenFlags::CREATE,
+                0o644,
+                OpenType::File,
+            )
+            .with_context(|| format!("failed to open {}", path.display()))?;
+        writeln!(file, "{line}").with_context(|| format!("failed to write {}", path.display()))
+    }
+}
+
+/// Where OpenSSH keeps the host keys of the servers you have connected to, `~/.ssh/known_hosts`
+pub fn known_hosts() -> anyhow::Result<PathBuf> {
+    Ok(dirs::home_dir()
+        .context("unable to find your home folder")?
+        .join(".ssh")
+        .join("known_hosts"))
+}
+
+/// Make sure the server is the one in `known_hosts`, so that saves and logins aren't sent to an impostor
+fn check_host_key(
+    session: &Session,
+    host: &str,
+    port: u16,
+    known_hosts: &Path,
+) -> anyhow::Result<()> {
+    let suggestion = format!("Connect to {host} once with `ssh` to add it to your known hosts");
+
+    let mut known = session.known_hosts()?;
+    if known_hosts.exists() {
+        known
+            .read_file(known_hosts, KnownHostFileKind::OpenSSH)
+            .with_context(|| format!("failed to read '{}'", known_hosts.display()))?;
+    }
+    let (key, _) = session
+        .host_key()
+        .ok_or_else(|| anyhow::anyhow!("{host} didn't send its host key"))?;
+
+    match known.check_port(host, port, key) {
+        CheckResult::Match => Ok(()),
+        CheckResult::NotFound => Err(anyhow::anyhow!(
+            "{host} is not in your known hosts '{}'",
+            known_hosts.display()
+        ))
+        .suggest(suggestion)
+        .map_err(anyhow::Error::from),
+        CheckResult::Mismatch => Err(anyhow::anyhow!(
+            "the host key of {host} doesn't match the one in your known hosts '{}'",
+            known_hosts.display()
+        ))
+        .suggest("Someone may be pretending to be the server, check with whoever runs it before connecting again")
+        .map_err(anyhow::Error::from),
+        CheckResult::Failure => Err(anyhow::anyhow!("failed to check the host key of {host}")),
+    }
+}
+
+fn is_not_found(error: &ssh2::Error) -> bool {
+    matches!(error.code(), ErrorCode::SFTP(NO_SUCH_FILE))
+}
+
+impl RemoteStorage for SftpFolder {
+    fn download(&mut self, save: &Save, local_path: &Path) -> anyhow::Result<()> {
+        let not_found = || {
+            Err(anyhow::anyhow!("{save} not found in your SFTP folder"))
+                .suggest(RemoteKind::Sftp.sync_suggestion())
+                .suggest("Have your friends uploaded their turn?")
+        };
+
+        let Some(name) = self.locate_save(save)? else {
+            return Ok(not_found()?);
+        };
+        let Some(content) = self.get(&name)? else {
+            return Ok(not_found()?);
+        };
+
+        staging::decompress(self.compression.as_ref(), &name, &content, local_path)
+            .with_context(|| format!("failed to download {save}"))
+    }
+
+    fn upload(&mut self, save: &Save, local_path: &Path) -> anyhow::Result<()> {
+        let (name, content) = staging::compress(self.compression.as_ref(), save, local_path)
+            .with_context(|| format!("failed to upload {save}"))?;
+
+        let current = self.locate_save(save)?;
+        object_store::replace(self, save, &name, content, current.as_deref())
+            .with_context(|| format!("failed to upload {save}"))?;
+
+        self.saves.insert(save.clone(), name);
+        Ok(())
+    }
+
+    fn supersede(&mut self, save: &Save) -> anyhow::Result<()> {
+        let name = self
+            .locate_save(save)?
+            .ok_or_else(|| anyhow::anyhow!("{save} not found in your SFTP folder"))?;
+
+        object_store::supersede(self, save, &name)?;
+        self.saves.remove(save);
+        Ok(())
+    }
+
+    fn record_substitution(&mut self, substitution: &Substitution) -> anyhow::Result<()> {
+        object_store::record_substitution(self, substitution)
+    }
+
+    fn substitutions(&mut self) -> anyhow::Result<Vec<Substitution>> {
+        object_store::substitutions(self)
+    }
+
+    fn record_signature(&mut self, signature: &Signature) -> anyhow::Result<()> {
+        object_store::record_signature(self, signature)
+    }
+
+    fn signatures(&mut self) -> anyhow::Result<Vec<Signature>> {
+        object_store::signatures(self)
+    }
+
+    fn record_upload(&mut self, entry: &LedgerEntry) -> anyhow::Result<()> {
+        object_store::record_upload(self, entry)
+    }
+
+    fn ledger(&mut self) -> anyhow::Result<Vec<LedgerEntry>> {
+        object_store::ledger(self)
+    }
+
+    fn versions(&mut self, save: &Save) -> anyhow::Result<Vec<Version>> {
+        object_store::versions(self, save)
+    }
+
+    fn restore(&mut self, save: &Save, version: u64) -> anyhow::Result<()> {
+        let current = self.locate_save(save)?;
+        if !object_store::restore(self, save, version, current.as_deref())? {
+            return Err(anyhow::anyhow!(
+                "{save} version {version} not found in your SFTP folder"
+            ))
+            .suggest(RemoteKind::Sftp.sync_suggestion())?;
+        }
+
+        self.refresh_saves()
+    }
+
+    fn metadata(&mut self, save: &Save) -> anyhow::Result<Option<FileMetadata>> {
+        let Some(name) = self.locate_save(save)? else {
+            return Ok(None);
+        };
+
+        let modified = self
+            .sftp()?
+            .stat(&self.path(&name))
+            .ok()
+            .and_then(|stat| stat.mtime)
+            .map(|seconds| seconds * 1000);
+
+        Ok(self
+            .get(&name)
+            .with_context(|| format!("failed to read {save} in your SFTP folder"))?
+            .map(|content| FileMetadata::new(&content, modified)))
+    }
+
+    fn index(&self) -> &dyn crate::interface::Index {
+        self
+    }
+}
+
+impl<'a> IterIndex<'a> for SftpFolder {
+    type Iter = std::collections::btree_map::Keys<'a, Save, String>;
+
+    fn iter(&'a self) -> Self::Iter {
+        self.saves.keys()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{
+        interface::{
+            compression::copy_compression::CopyCompression, index::Query,
+            storage::mock_sftp_server::MockSftpServer,
+        },
+        ledger::{find_uploads, record_uploads},
+        Side,
+    };
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn connect(server: &MockSftpServer) -> anyhow::Result<SftpFolder> {
+        SftpFolder::new(
+            &server.url,
+            Some((&server.username, None)),
+            &server.key,
+            &server.known_hosts,
+            Box::new(CopyCompression),
+        )
+    }
+
+    #[test]
+    fn sftp_upload_and_download() -> anyhow::Result<()> {
+        let Some(server) = MockSftpServer::start()? else {
+            eprintln!("skipping, sshd is not installed");
+            return Ok(());
+        };
+        let local = tempfile::tempdir()?;
+        let teammate = tempfile::tempdir()?;
+
+        let save = Save::from_parts(Side::Axis, 5).player("DM");
+        let path = local.path().join("Axis DM 5.sav");
+        fs::write(&path, "DM's turn")?;
+
+        let mut sftp = connect(&server)?;
+        sftp.upload(&save, &path)?;
+        let uploaded = find_uploads(&mut sftp, &[save.clone()])?;
+        record_uploads(&mut sftp, &uploaded, "DM")?;
+
+        // a teammate connects and downloads the save
+        let mut sftp = connect(&server)?;
+        assert_eq!(sftp.index().search(&Query::new())?, vec![save.clone()]);
+        sftp.download(&save, teammate.path())?;
+        assert_eq!(
+            fs::read_to_string(teammate.path().join("Axis DM 5.sav"))?,
+            "DM's turn"
+        );
+        assert_eq!(sftp.ledger()?.len(), 1);
+
+        // overwriting the save keeps the previous version
+        fs::write(&path, "DM's turn, again")?;
+        sftp.upload(&save, &path)?;
+        let versions = sftp.versions(&save)?;
+        assert_eq!(versions.len(), 1);
+        assert_eq!(versions[0].size, "DM's turn".len() as u64);
+        assert_eq!(
+            fs::read_to_string(server.folder.join("Axis DM 5.copy"))?,
+            "DM's turn, again"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn sftp_renames_files_into_place() -> anyhow::Result<()> {
+        let Some(server) = MockSftpServer::start()? else {
+            eprintln!("skipping, sshd is not installed");
+            return Ok(());
+        };
+        let mut sftp = connect(&server)?;
+
+        sftp.put("versions/Axis DM 5/1700000000.copy", b"DM's turn".to_vec())?;
+        sftp.append("ledger/DM.txt", "first")?;
+        sftp.append("ledger/DM.txt", "second")?;
+
+        // nothing is left behind but the files themselves, and folders aren't listed
+        let mut files = fs::read_dir(&server.folder)?
+            .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        files.sort();
+        assert_eq!(files, vec!["ledger", "versions"]);
+        assert_eq!(sftp.list("")?, Some(Vec::new()));
+
+        assert_eq!(
+            sftp.list("versions/Axis DM 5")?,
+            Some(vec![Object {
+                name: "1700000000.copy".to_string(),
+                size: 9,
+            }])
+        );
+        assert_eq!(
+            sftp.get("ledger/DM.txt")?,
+            Some(b"first\nsecond\n".to_vec())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn sftp_unknown_host() -> anyhow::Result<()> {
+        let Some(server) = MockSftpServer::start()? else {
+            eprintln!("skipping, sshd is not installed");
+            return Ok(());
+        };
+        let empty = tempfile::NamedTempFile::new()?;
+
+        let sftp = SftpFolder::new(
+            &server.url,
+            Some((&server.username, None)),
+            &server.key,
+            empty.path(),
+            Box::new(CopyCompression),
+        );
+        assert!(sftp.is_err());
+
+        Ok(())
+    }
+}
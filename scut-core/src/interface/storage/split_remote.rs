@@ -0,0 +1,244 @@
+//! Splits remote storage in two, so that the enemy team can't download your player saves and see your moves:
+//!
+//! * a private folder, only shared with your teammates, for player and part saves
+//! * a shared folder, shared with everyone, where turn start saves (uploaded autosaves) are exchanged
+//!
+//! Substitutions are recorded in the private folder, signatures and uploads in the ledger are recorded in the same folder as their save.
+//! Pruned saves are archived in the same folder as their save too, turn starts in an archive of their own.
+
+use std::path::Path;
+
+use crate::{
+    interface::{
+        file_system::FileMetadata, index::query::Matches, storage::Archive, Index, RemoteStorage,
+    },
+    ledger::LedgerEntry,
+    signature::Signature,
+    substitute::Substitution,
+    versions::Version,
+    Save,
+};
+
+/// Routes each save to the private or shared remote storage depending on whether it is a turn start
+#[derive(Clone)]
+pub struct SplitRemote {
+    private: Box<dyn RemoteStorage>,
+    shared: Box<dyn RemoteStorage>,
+}
+
+impl SplitRemote {
+    pub fn new(private: Box<dyn RemoteStorage>, shared: Box<dyn RemoteStorage>) -> Self {
+        SplitRemote { private, shared }
+    }
+
+    /// Turn start saves are exchanged with the enemy team, every other save stays private
+    fn route(&mut self, save: &Save) -> &mut dyn RemoteStorage {
+        if is_turn_start(save) {
+            &mut *self.shared
+        } else {
+            &mut *self.private
+        }
+    }
+}
+
+/// Turn starts are the autosaves uploaded at the end of a turn, they aren't signed by a player
+pub fn is_turn_start(save: &Save) -> bool {
+    save.player.is_none() && save.part.is_none()
+}
+
+impl RemoteStorage for SplitRemote {
+    fn download(&mut self, save: &Save, local_path: &Path) -> anyhow::Result<()> {
+        self.route(save).download(save, local_path)
+    }
+
+    fn upload(&mut self, save: &Save, local_path: &Path) -> anyhow::Result<()> {
+        self.route(save).upload(save, local_path)
+    }
+
+    fn supersede(&mut self, save: &Save) -> anyhow::Result<()> {
+        self.route(save).supersede(save)
+    }
+
+    fn record_substitution(&mut self, substitution: &Substitution) -> anyhow::Result<()> {
+        self.private.record_substitution(substitution)
+    }
+
+    fn substitutions(&mut self) -> anyhow::Result<Vec<Substitution>> {
+        self.private.substitutions()
+    }
+
+    fn record_signature(&mut self, signature: &Signature) -> anyhow::Result<()> {
+        self.route(&signature.save).record_signature(signature)
+    }
+
+    fn signatures(&mut self) -> anyhow::Result<Vec<Signature>> {
+        let mut signatures = self.private.signatures()?;
+        for signature in self.shared.signatures()? {
+            // both folders might be the same folder
+            if !signatures.contains(&signature) {
+                signatures.push(signature);
+            }
+        }
+        Ok(signatures)
+    }
+
+    fn record_upload(&mut self, entry: &LedgerEntry) -> anyhow::Result<()> {
+        self.route(&entry.save).record_upload(entry)
+    }
+
+    fn ledger(&mut self) -> anyhow::Result<Vec<LedgerEntry>> {
+        let mut ledger = self.private.ledger()?;
+        for entry in self.shared.ledger()? {
+            if !ledger.contains(&entry) {
+                ledger.push(entry);
+            }
+        }
+        ledger.sort_by_key(|entry| entry.timestamp);
+        Ok(ledger)
+    }
+
+    fn versions(&mut self, save: &Save) -> anyhow::Result<Vec<Version>> {
+        self.route(save).versions(save)
+    }
+
+    fn restore(&mut self, save: &Save, version: u64) -> anyhow::Result<()> {
+        self.route(save).restore(save, version)
+    }
+
+    fn metadata(&mut self, save: &Save) -> anyhow::Result<Option<FileMetadata>> {
+        self.route(save).metadata(save)
+    }
+
+    fn publish(&mut self) -> anyhow::Result<()> {
+        self.private.publish()?;
+        self.shared.publish()
+    }
+
+    fn archive(&mut self, name: &str, saves: &[Save]) -> anyhow::Result<()> {
+        let (turn_starts, others): (Vec<Save>, Vec<Save>) =
+            saves.iter().cloned().partition(is_turn_start);
+
+        if !others.is_empty() {
+            self.private.archive(name, &others)?;
+        }
+        if !turn_starts.is_empty() {
+            self.shared
+                .archive(&format!("{name} turn starts"), &turn_starts)?;
+        }
+        Ok(())
+    }
+
+    fn archives(&mut self) -> anyhow::Result<Vec<Archive>> {
+        let mut archives = self.private.archives()?;
+        for archive in self.shared.archives()? {
+            if !archives.contains(&archive) {
+                archives.push(archive);
+            }
+        }
+        Ok(archives)
+    }
+
+    fn lagging_mirrors(&self) -> Vec<String> {
+        let mut lagging = self.private.lagging_mirrors();
+        for name in self.shared.lagging_mirrors() {
+            if !lagging.contains(&name) {
+                lagging.push(name);
+            }
+        }
+        lagging
+    }
+
+    fn index(&self) -> &dyn Index {
+        self
+    }
+}
+
+/// Searches both folders, so predictions see every save whichever folder it is in
+impl<'a> Index<'a> for SplitRemote {
+    fn search(&'a self, query: &dyn Matches<Save>) -> anyhow::Result<Vec<Save>> {
+        let mut saves = self.private.index().search(query)?;
+        for save in self.shared.index().search(query)? {
+            if !saves.contains(&save) {
+                saves.push(save);
+            }
+        }
+        Ok(saves)
+    }
+
+    fn count(&'a self, query: &dyn Matches<Save>) -> anyhow::Result<usize> {
+        Ok(self.search(query)?.len())
+    }
+
+    fn latest(&'a self, query: &dyn Matches<Save>) -> anyhow::Result<Option<Save>> {
+        Ok(self
+            .private
+            .index()
+            .latest(query)?
+            .into_iter()
+            .chain(self.shared.index().latest(query)?)
+            .max_by_key(|save| save.turn))
+    }
+
+    fn earliest(&'a self, query: &dyn Matches<Save>) -> anyhow::Result<Option<Save>> {
+        Ok(self
+            .private
+            .index()
+            .earliest(query)?
+            .into_iter()
+            .chain(self.shared.index().earliest(query)?)
+            .min_by_key(|save| save.turn))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{
+        interface::{
+            index::Query,
+            storage::memory_storage::{MemoryFiles, MemoryLocalStorage, MemoryRemoteStorage},
+            LocalStorage,
+        },
+        Side,
+    };
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn split_remote_routes_by_save_kind() -> anyhow::Result<()> {
+        let files = MemoryFiles::new();
+        let mut local = MemoryLocalStorage::new(PathBuf::from("/local"), files.clone());
+        let private = MemoryRemoteStorage::new(PathBuf::from("/axis"), files.clone());
+        let shared = MemoryRemoteStorage::new(PathBuf::from("/shared"), files.clone());
+        let mut remote = SplitRemote::new(Box::new(private.clone()), Box::new(shared.clone()));
+
+        let ours = Save::from_parts(Side::Axis, 3).player("DM");
+        let part = Save::from_parts(Side::Axis, 3).player("DM").part("A");
+        let turn_start = Save::from_parts(Side::Allies, 3);
+
+        local.write_save(&ours, "DM's turn");
+        local.write_save(&part, "DM's navy");
+        local.write_autosave("end of Axis 3");
+
+        for save in [&ours, &part] {
+            let path = local.locate_save(save)?.expect("save was written");
+            remote.upload(save, &path)?;
+        }
+        let path = local.locate_autosave()?.expect("autosave was written");
+        remote.upload(&turn_start, &path)?;
+
+        assert_eq!(private.uploads(), vec![ours.clone(), part.clone()]);
+        assert_eq!(shared.uploads(), vec![turn_start.clone()]);
+
+        let remote = SplitRemote::new(Box::new(private.reconnect()), Box::new(shared.reconnect()));
+        assert_eq!(remote.index().count(&Query::new())?, 3);
+        assert_eq!(
+            remote.index().latest(&Query::new().player(None))?,
+            Some(turn_start)
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,134 @@
+//! Remote storage that is reached over the network, rather than a synced folder, can't compress saves straight into place.
+//!
+//! Instead archives are compressed into, and downloaded to, a temporary staging folder on this computer on their way.
+//!
+//! Synced folders compress saves in staging too, then [`place`] the archive in the folder all at once,
+//! so that the provider never syncs an archive that is only partly written.
+//!
+//! Staging is always on this computer's disk, where the compression can reach it, so it is read and written with [`std::fs`].
+//! Anything put into a synced folder goes through its [`FileSystem`] instead.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::Context;
+
+use crate::{
+    interface::{Compression, FileSystem},
+    Save,
+};
+
+/// A temporary folder for archives on their way to or from remote storage, removed when dropped
+pub(crate) struct Staging(PathBuf);
+
+impl Staging {
+    pub(crate) fn new() -> anyhow::Result<Self> {
+        // uploads and downloads run in parallel
+        static STAGED: AtomicUsize = AtomicUsize::new(0);
+
+        let path = std::env::temp_dir().join(format!(
+            "scut-staging-{}-{}",
+            std::process::id(),
+            STAGED.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&path)
+            .with_context(|| format!("failed to create a temporary folder '{}'", path.display()))?;
+
+        Ok(Staging(path))
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// The path of every file in this staging folder
+    pub(crate) fn paths(&self) -> anyhow::Result<Vec<PathBuf>> {
+        fs::read_dir(&self.0)?
+            .map(|entry| Ok(entry?.path()))
+            .collect::<anyhow::Result<_>>()
+            .with_context(|| format!("failed to list files in '{}'", self.0.display()))
+    }
+
+    /// The name and content of every file in this staging folder
+    pub(crate) fn files(&self) -> anyhow::Result<Vec<(String, Vec<u8>)>> {
+        let mut files = Vec::new();
+        for path in self.paths()? {
+            let name = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .ok_or_else(|| anyhow::anyhow!("'{}' is not a valid file name", path.display()))?
+                .to_string();
+            let content =
+                fs::read(&path).with_context(|| format!("failed to read '{}'", path.display()))?;
+            files.push((name, content));
+        }
+        Ok(files)
+    }
+}
+
+impl Drop for Staging {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Compress the save at `local_path`, returning the file name the compression chose for the archive and its content
+pub(crate) fn compress(
+    compression: &dyn Compression,
+    save: &Save,
+    local_path: &Path,
+) -> anyhow::Result<(String, Vec<u8>)> {
+    let staging = Staging::new()?;
+    compression
+        .compress(local_path, &staging.path().join(save.to_string()))
+        .with_context(|| format!("failed to compress {save}"))?;
+
+    // the compression chooses the extension of the archive
+    staging
+        .files()?
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("failed to compress {save}: no archive was made"))
+}
+
+/// Decompress the archive `name` with `content` into the folder `local_path`
+pub(crate) fn decompress(
+    compression: &dyn Compression,
+    name: &str,
+    content: &[u8],
+    local_path: &Path,
+) -> anyhow::Result<()> {
+    let staging = Staging::new()?;
+    let archive = staging.path().join(name);
+    fs::write(&archive, content)
+        .with_context(|| format!("failed to save '{}'", archive.display()))?;
+
+    compression.check(&archive)?;
+    compression.decompress(&archive, local_path)
+}
+
+/// Write an archive with `content` to `path` so that it appears there complete, or not at all.
+///
+/// The archive is written under a temporary name starting with a dot, which isn't a valid save, e.g. `.Axis DM 12.7z.partial`,
+/// then flushed to disk and renamed into place.
+pub(crate) fn place(
+    file_system: &mut dyn FileSystem,
+    content: &[u8],
+    path: &Path,
+) -> anyhow::Result<()> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("'{}' is not a valid file name", path.display()))?;
+    let partial = path.with_file_name(format!(".{}.partial", name.to_string_lossy()));
+
+    let placed = file_system
+        .write_file(content, &partial)
+        .and_then(|()| file_system.move_file(&partial, path));
+    if let Err(error) = placed {
+        let _ = file_system.remove_file(&partial);
+        return Err(error).with_context(|| format!("failed to write '{}'", path.display()));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,144 @@
+//! The sync record remembers what each save looked like, in local and remote storage, the last time scut uploaded or downloaded it.
+//!
+//! Saves are compressed in remote storage, so the local and remote copies of a save can't be compared directly.
+//! Instead each copy is compared with how it looked when it was synced, to notice when a teammate re-uploads a save
+//! or when a local save is overwritten after it was uploaded.
+//!
+//! The record is kept in local storage as TOML, keyed by save:
+//!
+//! ```toml
+//! ["Axis DM 5"]
+//! direction = "uploaded"
+//!
+//! ["Axis DM 5".local]
+//! size = 1024
+//! modified = 1700000000000
+//! digest = "ca978112ca1bbdcafac231b39a23dc4da786eff8147c4e72b9807785afee48bb"
+//!
+//! ["Axis DM 5".remote]
+//! size = 512
+//! digest = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+//! ```
+
+use std::collections::BTreeMap;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::{interface::file_system::FileMetadata, Save};
+
+/// How a save was synced
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Uploaded,
+    Downloaded,
+    /// The autosave was uploaded as this save, so the local copy is the autosave
+    UploadedAutosave,
+}
+
+/// What both copies of a save looked like when it was synced
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncedSave {
+    pub direction: Direction,
+    pub local: FileMetadata,
+    pub remote: FileMetadata,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SyncRecord(BTreeMap<String, SyncedSave>);
+
+impl SyncRecord {
+    /// Read a sync record from TOML
+    pub fn from_toml(toml: &str) -> anyhow::Result<Self> {
+        toml::from_str(toml).context("failed to read the sync record")
+    }
+
+    /// Write this sync record as TOML
+    pub fn to_toml(&self) -> anyhow::Result<String> {
+        toml::to_string_pretty(self).context("failed to write the sync record")
+    }
+
+    /// Remember what `save` looked like when it was synced, replacing anything remembered before
+    pub fn insert(&mut self, save: &Save, synced: SyncedSave) {
+        self.0.insert(save.to_string(), synced);
+    }
+
+    /// What `save` looked like when it was last synced, if it has been
+    pub fn get(&self, save: &Save) -> Option<&SyncedSave> {
+        self.0.get(&save.to_string())
+    }
+
+    /// Every save in the record, ignoring any entries that aren't valid saves
+    pub fn iter(&self) -> impl Iterator<Item = (Save, &SyncedSave)> {
+        self.0
+            .iter()
+            .filter_map(|(save, synced)| save.parse().ok().map(|save| (save, synced)))
+    }
+
+    /// The save that was uploaded most recently, going by when its local copy was modified
+    pub fn last_upload(&self) -> Option<(Save, &SyncedSave)> {
+        self.iter()
+            .filter(|(_, synced)| synced.direction != Direction::Downloaded)
+            .filter(|(_, synced)| synced.local.modified.is_some())
+            .max_by_key(|(_, synced)| synced.local.modified)
+    }
+
+    /// Forget every save from before turn number `number`, along with any entries that aren't valid saves.
+    ///
+    /// Predictions only compare recent saves, so this keeps the record from growing for the whole game.
+    pub fn forget_before(&mut self, number: u32) {
+        self.0.retain(|save, _| {
+            save.parse::<Save>()
+                .is_ok_and(|save| save.turn.number >= number)
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Side;
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn synced(direction: Direction, modified: u64) -> SyncedSave {
+        SyncedSave {
+            direction,
+            local: FileMetadata::new(b"local", Some(modified)),
+            remote: FileMetadata::new(b"remote", None),
+        }
+    }
+
+    #[test]
+    fn sync_record_round_trip() -> anyhow::Result<()> {
+        let mut record = SyncRecord::default();
+        record.insert(
+            &Save::from_parts(Side::Axis, 5).player("DM"),
+            synced(Direction::Uploaded, 10),
+        );
+        record.insert(
+            &Save::from_parts(Side::Allies, 5),
+            synced(Direction::UploadedAutosave, 20),
+        );
+        record.insert(
+            &Save::from_parts(Side::Axis, 5).player("DG"),
+            synced(Direction::Downloaded, 30),
+        );
+
+        assert_eq!(SyncRecord::from_toml(&record.to_toml()?)?, record);
+
+        let (last, _) = record.last_upload().expect("saves were uploaded");
+        assert_eq!(last, Save::from_parts(Side::Allies, 5));
+
+        record.forget_before(6);
+        assert!(record.is_empty());
+
+        Ok(())
+    }
+}
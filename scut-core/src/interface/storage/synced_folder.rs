@@ -0,0 +1,791 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+
+use crate::interface::file_system::FileMetadata;
+use crate::interface::index::IterIndex;
+use crate::interface::storage::object_store::{self, Object, ObjectStore};
+use crate::interface::storage::staging::{self, Staging};
+use crate::interface::storage::{Archive, ARCHIVE_FOLDER};
+use crate::interface::{Compression, FileSystem, RemoteStorage};
+use crate::ledger::LedgerEntry;
+use crate::save::{parse_save, path_to_save};
+use crate::signature::Signature;
+use crate::substitute::Substitution;
+use crate::versions::Version;
+use crate::{error::ErrorSuggestions, Provider, Save};
+
+/// How long an archive must go unchanged before it is downloaded, in milliseconds, in case the provider is still syncing it
+const SETTLE_TIME: u64 = 1000;
+
+/// How many times to wait for an archive that keeps changing before giving up
+const SETTLE_ATTEMPTS: usize = 5;
+
+/// This implementation is used to store the saves in a synced folder where they can be shared with other players,
+/// e.g. your dropbox folder. The [`Provider`], e.g. Dropbox, handles the syncing between your local filesytem and their servers.
+#[derive(Clone)]
+pub struct SyncedFolder {
+    pub location: PathBuf,
+    provider: Provider,
+    saves: BTreeMap<Save, PathBuf>,
+    /// Saves that were bundled into an archive by [`prune`](crate::prune), by the name of the archive
+    archived: BTreeMap<Save, String>,
+    /// Copies of saves that the provider made when two computers changed the same save at once
+    conflicted_copies: Vec<PathBuf>,
+    file_system: Box<dyn FileSystem>,
+    compression: Box<dyn Compression>,
+}
+
+impl SyncedFolder {
+    pub fn new(
+        location: PathBuf,
+        provider: Provider,
+        file_system: Box<dyn FileSystem>,
+        compression: Box<dyn Compression>,
+    ) -> anyhow::Result<Self> {
+        let mut folder = SyncedFolder {
+            location,
+            provider,
+            saves: BTreeMap::new(),
+            archived: BTreeMap::new(),
+            conflicted_copies: Vec::new(),
+            compression,
+            file_system,
+        };
+
+        folder.refresh_saves()?;
+
+        Ok(folder)
+    }
+}
+
+impl SyncedFolder {
+    fn attempt_download(
+        &mut self,
+        attempt: usize,
+        save: &Save,
+        local_path: &Path,
+    ) -> anyhow::Result<()> {
+        if attempt >= 1 {
+            return Err(anyhow::anyhow!(
+                "{save} not found in your {} '{}'",
+                self.provider.folder_name(),
+                self.location.display()
+            ))
+            .suggest(self.provider.sync_suggestion())
+            .suggest("Have your friends uploaded their turn?")?;
+        }
+
+        if let Some(src) = self.locate_save(save)?.map(Path::to_path_buf) {
+            self.wait_until_settled(save, &src)
+                .and_then(|()| self.compression.check(&src))
+                .and_then(|()| self.compression.decompress(&src, local_path))
+                .with_context(|| format!("failed to download {save}"))
+        } else if let Some(archive) = self.archived.get(save).cloned() {
+            self.extract(save, &archive, local_path)
+                .with_context(|| format!("failed to download {save} from the archive {archive}"))
+        } else {
+            // Retry after loading contents from disk again
+            self.refresh_saves()
+                .with_context(|| format!("failed to download {save}"))?;
+            self.attempt_download(attempt + 1, save, local_path)
+        }
+    }
+
+    /// Wait until the archive at `path` has stopped changing, so that an archive the provider is still syncing isn't downloaded.
+    ///
+    /// Only the size and modification time are compared, so the archive isn't read while waiting.
+    fn wait_until_settled(&mut self, save: &Save, path: &Path) -> anyhow::Result<()> {
+        let mut before = self.file_system.stat(path)?;
+
+        for _ in 0..SETTLE_ATTEMPTS {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_millis() as u64)
+                .unwrap_or_default();
+            // usually the archive arrived long ago, otherwise it is watched for a moment
+            let settled = match before.modified {
+                Some(modified) => modified + SETTLE_TIME < now,
+                None => true,
+            };
+            if settled {
+                return Ok(());
+            }
+
+            thread::sleep(Duration::from_millis(SETTLE_TIME));
+            let after = self.file_system.stat(path)?;
+            if after == before {
+                return Ok(());
+            }
+            before = after;
+        }
+
+        Err(anyhow::anyhow!(
+            "{save} is still changing in your {}",
+            self.provider.folder_name()
+        ))
+        .suggest(self.provider.sync_suggestion())?
+    }
+
+    /// Look for a save in this Folder and return its path if it exists
+    pub fn locate_save(&self, save: &Save) -> anyhow::Result<Option<&Path>> {
+        Ok(self.saves.get(save).map(|p| p.as_path()))
+    }
+
+    /// Construct the path describing where a save should be stored in this Folder
+    pub fn choose_location_for_save(&self, save: &Save) -> PathBuf {
+        self.location.join(save.to_string())
+    }
+
+    /// Move the current copy of a save, if there is one, into its versions folder so that it isn't lost when the save is overwritten
+    fn keep_version(&mut self, save: &Save) -> anyhow::Result<()> {
+        if !self.saves.contains_key(save) {
+            self.refresh_saves()?;
+        }
+        let Some(path) = self.saves.remove(save) else {
+            return Ok(());
+        };
+
+        object_store::keep_version(self, save, &file_name(&path)?)
+    }
+
+    /// Every archive in this Folder, read from the list of saves that is kept next to each one
+    fn read_archives(&mut self) -> anyhow::Result<Vec<Archive>> {
+        let folder = self.location.join(ARCHIVE_FOLDER);
+        if !self.file_system.file_exists(&folder)? {
+            return Ok(Vec::new());
+        }
+
+        let mut archives = Vec::new();
+        for path in self.file_system.files_in_folder(&folder)? {
+            if !path.extension().is_some_and(|extension| extension == "txt") {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|name| name.to_str()) else {
+                continue;
+            };
+
+            let saves = self
+                .file_system
+                .read_file_to_string(&path)?
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(|line| line.parse::<Save>())
+                .collect::<Result<_, _>>()
+                .with_context(|| format!("failed to read '{}'", path.display()))?;
+
+            archives.push(Archive {
+                name: name.to_string(),
+                saves,
+            });
+        }
+        archives.sort_by(|a, b| a.saves.first().cmp(&b.saves.first()));
+        Ok(archives)
+    }
+
+    /// The path of the archive called `name`, whichever extension the compression gave it
+    fn archive_path(&mut self, name: &str) -> anyhow::Result<PathBuf> {
+        let folder = self.location.join(ARCHIVE_FOLDER);
+        self.file_system
+            .files_in_folder(&folder)?
+            .into_iter()
+            .find(|path| {
+                path.file_stem().is_some_and(|stem| stem == name)
+                    && !path.extension().is_some_and(|extension| extension == "txt")
+            })
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "the archive {name} is missing from your {} '{}'",
+                    self.provider.folder_name(),
+                    folder.display()
+                )
+            })
+    }
+
+    /// Extract an archived save from the archive it was bundled into, into the folder `local_path`
+    fn extract(&mut self, save: &Save, archive: &str, local_path: &Path) -> anyhow::Result<()> {
+        let path = self.archive_path(archive)?;
+
+        self.wait_until_settled(save, &path)?;
+        self.compression.check(&path)?;
+
+        let staging = Staging::new()?;
+        self.compression.decompress(&path, staging.path())?;
+
+        // every save in the archive is extracted, only the one that was asked for is kept
+        let (file_name, content) = staging
+            .files()?
+            .into_iter()
+            .find(|(file_name, _)| path_to_save(Path::new(file_name)).as_ref() == Some(save))
+            .ok_or_else(|| anyhow::anyhow!("{save} is missing from '{}'", path.display()))?;
+
+        self.file_system
+            .write_file(&content, &local_path.join(file_name))
+            .with_context(|| format!("failed to save {save} in '{}'", local_path.display()))
+    }
+
+    /// Copies of saves in this Folder that the provider made when two computers changed the same save at once.
+    ///
+    /// They aren't treated as saves, the original is used instead.
+    pub fn conflicted_copies(&self) -> &[PathBuf] {
+        &self.conflicted_copies
+    }
+
+    /// Reloads from disk what saves are in this Folder
+    ///
+    /// TODO: fix for potential to have multiple files that parse to the same Save
+    /// currently, one of the paths will "win" but I don't think it's deterministic which one will win,
+    /// so it seems preferable to detect such duplicates and error early
+    pub fn refresh_saves(&mut self) -> anyhow::Result<()> {
+        let all_files = self.file_system.files_in_folder(&self.location)?;
+
+        let (conflicted_copies, files): (Vec<_>, Vec<_>) = all_files
+            .into_iter()
+            .partition(|path| is_conflicted_copy(self.provider, path));
+
+        let saves = files
+            .into_iter()
+            .filter_map(|path| path_to_save(&path).map(|save| (save, path)));
+
+        self.saves = saves.collect();
+        self.conflicted_copies = conflicted_copies;
+
+        // a save that was uploaded again after it was archived is downloaded from the folder
+        let mut archived = BTreeMap::new();
+        for archive in self.read_archives()? {
+            for save in archive.saves {
+                if !self.saves.contains_key(&save) {
+                    archived.insert(save, archive.name.clone());
+                }
+            }
+        }
+        self.archived = archived;
+
+        Ok(())
+    }
+}
+
+/// Returns true if `path` is a copy that `provider` made when two computers changed the same file at once.
+///
+/// The copies are named after the original, so they would otherwise be mistaken for the same save.
+pub fn is_conflicted_copy(provider: Provider, path: &Path) -> bool {
+    let Some(name) = path.file_stem().and_then(|name| name.to_str()) else {
+        return false;
+    };
+
+    match provider {
+        // Allies 5 (DM's conflicted copy 2023-10-01).7z
+        Provider::Dropbox => name.contains("conflicted copy"),
+        // Allies 5-DESKTOP-1A2B3C.7z, named after the computer that made the copy
+        // only what follows the save name is the suffix, so players with hyphenated names are still saves
+        Provider::OneDrive => parse_save(name).is_ok_and(|(_, suffix)| {
+            suffix.strip_prefix('-').is_some_and(|machine| {
+                !machine.is_empty()
+                    && machine
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-')
+            })
+        }),
+        // Allies 5 (1).7z
+        Provider::GoogleDrive => name
+            .strip_suffix(')')
+            .and_then(|name| name.rsplit_once(" ("))
+            .is_some_and(|(_, copy)| copy.parse::<u32>().is_ok()),
+        // Allies 5.sync-conflict-20231001-120000-ABCDEFG.7z
+        Provider::Syncthing => name.contains(".sync-conflict-"),
+        Provider::Folder => false,
+    }
+}
+
+/// Files in the folder are named relative to its location
+impl ObjectStore for SyncedFolder {
+    fn list(&mut self, folder: &str) -> anyhow::Result<Option<Vec<Object>>> {
+        let folder = self.location.join(folder);
+        if !self.file_system.file_exists(&folder)? {
+            return Ok(None);
+        }
+
+        let mut objects = Vec::new();
+        for path in self.file_system.files_in_folder(&folder)? {
+            let size = self.file_system.stat(&path)?.size;
+            objects.push(Object {
+                name: file_name(&path)?,
+                size,
+            });
+        }
+        Ok(Some(objects))
+    }
+
+    /// Only records are read this way, which are text
+    fn get(&mut self, name: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        let path = self.location.join(name);
+        if !self.file_system.file_exists(&path)? {
+            return Ok(None);
+        }
+
+        Ok(Some(
+            self.file_system.read_file_to_string(&path)?.into_bytes(),
+        ))
+    }
+
+    fn put(&mut self, name: &str, content: Vec<u8>) -> anyhow::Result<()> {
+        self.file_system
+            .write_file(&content, &self.location.join(name))
+    }
+
+    fn rename(&mut self, from: &str, to: &str) -> anyhow::Result<()> {
+        self.file_system
+            .move_file(&self.location.join(from), &self.location.join(to))
+    }
+}
+
+/// The name of the file at `path`, without the folder it is in
+fn file_name(path: &Path) -> anyhow::Result<String> {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .map(String::from)
+        .ok_or_else(|| anyhow::anyhow!("{} is not a file", path.display()))
+}
+
+impl RemoteStorage for SyncedFolder {
+    fn download(&mut self, save: &Save, local_path: &Path) -> anyhow::Result<()> {
+        self.attempt_download(0, save, local_path)
+    }
+
+    fn upload(&mut self, save: &Save, local_path: &Path) -> anyhow::Result<()> {
+        // compressed outside the folder, then placed in it whole, so the provider never syncs a partly written archive
+        let (name, content) = staging::compress(&*self.compression, save, local_path)
+            .with_context(|| format!("failed to upload {save}"))?;
+
+        // only once the new archive is ready, so a failed upload leaves the current copy where it was
+        self.keep_version(save)?;
+        staging::place(&mut *self.file_system, &content, &self.location.join(name))
+            .with_context(|| format!("failed to upload {save}"))
+    }
+
+    fn supersede(&mut self, save: &Save) -> anyhow::Result<()> {
+        let path = self.saves.remove(save).ok_or_else(|| {
+            anyhow::anyhow!("{save} not found in your {}", self.provider.folder_name())
+        })?;
+
+        object_store::supersede(self, save, &file_name(&path)?)
+    }
+
+    fn record_substitution(&mut self, substitution: &Substitution) -> anyhow::Result<()> {
+        object_store::record_substitution(self, substitution)
+    }
+
+    fn substitutions(&mut self) -> anyhow::Result<Vec<Substitution>> {
+        object_store::substitutions(self)
+    }
+
+    fn record_signature(&mut self, signature: &Signature) -> anyhow::Result<()> {
+        object_store::record_signature(self, signature)
+    }
+
+    fn signatures(&mut self) -> anyhow::Result<Vec<Signature>> {
+        object_store::signatures(self)
+    }
+
+    fn record_upload(&mut self, entry: &LedgerEntry) -> anyhow::Result<()> {
+        object_store::record_upload(self, entry)
+    }
+
+    fn ledger(&mut self) -> anyhow::Result<Vec<LedgerEntry>> {
+        object_store::ledger(self)
+    }
+
+    fn versions(&mut self, save: &Save) -> anyhow::Result<Vec<Version>> {
+        object_store::versions(self, save)
+    }
+
+    fn restore(&mut self, save: &Save, version: u64) -> anyhow::Result<()> {
+        if !self.saves.contains_key(save) {
+            self.refresh_saves()?;
+        }
+        let current = self
+            .saves
+            .get(save)
+            .map(|path| file_name(path))
+            .transpose()?;
+
+        if !object_store::restore(self, save, version, current.as_deref())? {
+            return Err(anyhow::anyhow!(
+                "{save} version {version} not found in your {}",
+                self.provider.folder_name()
+            ))
+            .suggest(self.provider.sync_suggestion())?;
+        }
+
+        self.refresh_saves()
+    }
+
+    fn metadata(&mut self, save: &Save) -> anyhow::Result<Option<FileMetadata>> {
+        if !self.saves.contains_key(save) {
+            self.refresh_saves()?;
+        }
+
+        match self.saves.get(save) {
+            Some(path) => Ok(Some(self.file_system.metadata(path).with_context(
+                || {
+                    format!(
+                        "failed to read {save} in your {}",
+                        self.provider.folder_name()
+                    )
+                },
+            )?)),
+            None => Ok(None),
+        }
+    }
+
+    fn archive(&mut self, name: &str, saves: &[Save]) -> anyhow::Result<()> {
+        let folder = self.location.join(ARCHIVE_FOLDER);
+        let list = folder.join(format!("{name}.txt"));
+        if self.file_system.file_exists(&list)? {
+            anyhow::bail!(
+                "an archive called {name} already exists in your {}",
+                self.provider.folder_name()
+            );
+        }
+
+        let staging = Staging::new()?;
+        let mut paths = Vec::new();
+        for save in saves {
+            let path = self.saves.get(save).cloned().ok_or_else(|| {
+                anyhow::anyhow!("{save} not found in your {}", self.provider.folder_name())
+            })?;
+            self.compression
+                .decompress(&path, staging.path())
+                .with_context(|| format!("failed to archive {save}"))?;
+            paths.push(path);
+        }
+
+        // bundled outside the folder too, then placed in it whole
+        let bundled = Staging::new()?;
+        self.compression
+            .compress(staging.path(), &bundled.path().join(name))
+            .with_context(|| format!("failed to archive {name}"))?;
+
+        for (file_name, content) in bundled.files()? {
+            staging::place(&mut *self.file_system, &content, &folder.join(file_name))
+                .with_context(|| format!("failed to archive {name}"))?;
+        }
+
+        // the list is placed once the archive is complete, so an interrupted prune leaves every save where it was
+        // and the provider never syncs half a list
+        let content: String = saves.iter().map(|save| format!("{save}\n")).collect();
+        staging::place(&mut *self.file_system, content.as_bytes(), &list)
+            .with_context(|| format!("failed to archive {name}"))?;
+
+        for (save, path) in saves.iter().zip(paths) {
+            self.file_system
+                .remove_file(&path)
+                .with_context(|| format!("failed to remove {save} once it was archived"))?;
+            self.saves.remove(save);
+            self.archived.insert(save.clone(), name.to_string());
+        }
+        Ok(())
+    }
+
+    fn archives(&mut self) -> anyhow::Result<Vec<Archive>> {
+        self.read_archives().context("failed to read archives")
+    }
+
+    fn index(&self) -> &dyn crate::interface::Index {
+        self
+    }
+}
+
+/// Folders are able to return an iterator of saves, so they fulfil the blanket implementation of [`Index`](crate::interface::Index) for iterators of saves...
+/// and get a free implementation of Index - hooray!
+///
+/// Archived saves are included, they are extracted from their archive when downloaded.
+impl<'a> IterIndex<'a> for SyncedFolder {
+    type Iter = std::iter::Chain<
+        std::collections::btree_map::Keys<'a, Save, PathBuf>,
+        std::collections::btree_map::Keys<'a, Save, String>,
+    >;
+
+    fn iter(&'a self) -> Self::Iter {
+        self.saves.keys().chain(self.archived.keys())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf, str::FromStr};
+
+    use indoc::indoc;
+
+    use crate::{
+        interface::{
+            compression::{copy_compression::CopyCompression, mock_compression::MockCompression},
+            file_system::{local_file_system::LocalFileSystem, mock_file_system::MockFileSystem},
+            index::Query,
+            RemoteStorage,
+        },
+        Side,
+    };
+
+    use super::*;
+
+    #[test]
+    fn synced_folder_works() -> Result<(), Box<dyn std::error::Error>> {
+        let mock_file_system = MockFileSystem::from_str(indoc! {r"
+            /local/
+                ?autosave.sav
+
+            /remote/
+                Axis DM 1.sav
+                Axis DG 1.sav
+                Allies 1.sav
+                bystander.txt
+                nonsense.sav
+        "})?;
+
+        let mut dropbox = SyncedFolder::new(
+            PathBuf::from("/remote"),
+            Provider::Dropbox,
+            Box::new(mock_file_system),
+            Box::new(MockCompression::new()),
+        )?;
+
+        dropbox.download(
+            &Save::from_parts(Side::Allies, 1),
+            PathBuf::from("/local/").as_path(),
+        )?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn synced_folder_supersede() -> Result<(), Box<dyn std::error::Error>> {
+        let mock_file_system = MockFileSystem::from_str(indoc! {r"
+            /remote/
+                Axis DM 1.7z
+                Allies 1.7z
+        "})?;
+
+        let mut dropbox = SyncedFolder::new(
+            PathBuf::from("/remote"),
+            Provider::Dropbox,
+            Box::new(mock_file_system),
+            Box::new(MockCompression::new()),
+        )?;
+
+        dropbox.supersede(&Save::from_parts(Side::Axis, 1).player("DM"))?;
+        assert_eq!(
+            dropbox.locate_save(&Save::from_parts(Side::Axis, 1).player("DM"))?,
+            None
+        );
+
+        dropbox.refresh_saves()?;
+        assert_eq!(
+            dropbox.iter().collect::<Vec<_>>(),
+            vec![&Save::from_parts(Side::Allies, 1)]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn synced_folder_archives_saves() -> anyhow::Result<()> {
+        let dropbox = tempfile::tempdir()?;
+        let local = tempfile::tempdir()?;
+        fs::write(dropbox.path().join("Axis DM 1.copy"), "DM's turn")?;
+        fs::write(dropbox.path().join("Allies 1.copy"), "Allies' turn")?;
+        fs::write(dropbox.path().join("Axis 2.copy"), "Axis' turn")?;
+
+        let open = || {
+            SyncedFolder::new(
+                dropbox.path().to_path_buf(),
+                Provider::Folder,
+                Box::new(LocalFileSystem::new()),
+                Box::new(CopyCompression),
+            )
+        };
+
+        let archived = vec![
+            Save::from_parts(Side::Axis, 1).player("DM"),
+            Save::from_parts(Side::Allies, 1),
+        ];
+        open()?.archive("Turns 1-10", &archived)?;
+        assert!(!dropbox.path().join("Axis DM 1.copy").exists());
+        assert!(dropbox.path().join("Axis 2.copy").exists());
+        assert!(!dropbox
+            .path()
+            .join("archive/.Turns 1-10.txt.partial")
+            .exists());
+
+        // archived saves are still listed, and are extracted when downloaded
+        let mut folder = open()?;
+        assert_eq!(folder.index().count(&Query::new())?, 3);
+        assert_eq!(
+            folder.archives()?,
+            vec![Archive {
+                name: "Turns 1-10".to_string(),
+                saves: archived.clone(),
+            }]
+        );
+
+        folder.download(&archived[0], local.path())?;
+        assert_eq!(
+            fs::read_to_string(local.path().join("Axis DM 1.sav"))?,
+            "DM's turn"
+        );
+        assert!(!local.path().join("Allies 1.sav").exists());
+
+        assert!(folder.archive("Turns 1-10", &[]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn synced_folder_places_whole_archives() -> anyhow::Result<()> {
+        let dropbox = tempfile::tempdir()?;
+        let local = tempfile::tempdir()?;
+        fs::write(local.path().join("Axis DM 1.sav"), "DM's turn")?;
+        // left behind by an upload that was interrupted
+        fs::write(dropbox.path().join(".Axis DM 2.copy.partial"), "DM's")?;
+
+        let mut folder = SyncedFolder::new(
+            dropbox.path().to_path_buf(),
+            Provider::Dropbox,
+            Box::new(LocalFileSystem::new()),
+            Box::new(CopyCompression),
+        )?;
+        assert_eq!(folder.index().count(&Query::new())?, 0);
+
+        let save = Save::from_parts(Side::Axis, 1).player("DM");
+        folder.upload(&save, &local.path().join("Axis DM 1.sav"))?;
+        assert_eq!(
+            fs::read_to_string(dropbox.path().join("Axis DM 1.copy"))?,
+            "DM's turn"
+        );
+        assert!(!dropbox.path().join(".Axis DM 1.copy.partial").exists());
+
+        folder.refresh_saves()?;
+        assert_eq!(folder.index().search(&Query::new())?, vec![save]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn synced_folder_keeps_the_save_when_compression_fails() -> anyhow::Result<()> {
+        let dropbox = tempfile::tempdir()?;
+        let local = tempfile::tempdir()?;
+        fs::write(dropbox.path().join("Axis DM 1.copy"), "DM's turn")?;
+
+        let mut folder = SyncedFolder::new(
+            dropbox.path().to_path_buf(),
+            Provider::Dropbox,
+            Box::new(LocalFileSystem::new()),
+            Box::new(CopyCompression),
+        )?;
+
+        // there is nothing to compress
+        let save = Save::from_parts(Side::Axis, 1).player("DM");
+        assert!(folder
+            .upload(&save, &local.path().join("Axis DM 1.sav"))
+            .is_err());
+
+        assert_eq!(
+            fs::read_to_string(dropbox.path().join("Axis DM 1.copy"))?,
+            "DM's turn"
+        );
+        assert!(folder.versions(&save)?.is_empty());
+
+        folder.refresh_saves()?;
+        assert_eq!(folder.index().search(&Query::new())?, vec![save]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn synced_folder_ignores_conflicted_copies() -> Result<(), Box<dyn std::error::Error>> {
+        let mock_file_system = MockFileSystem::from_str(indoc! {r"
+            /remote/
+                Allies 5.7z
+                Allies 5.sync-conflict-20231001-120000-ABCDEFG.7z
+        "})?;
+
+        let folder = SyncedFolder::new(
+            PathBuf::from("/remote"),
+            Provider::Syncthing,
+            Box::new(mock_file_system),
+            Box::new(MockCompression::new()),
+        )?;
+
+        assert_eq!(
+            folder.locate_save(&Save::from_parts(Side::Allies, 5))?,
+            Some(PathBuf::from("/remote/Allies 5.7z").as_path())
+        );
+        assert_eq!(
+            folder.conflicted_copies(),
+            &[PathBuf::from(
+                "/remote/Allies 5.sync-conflict-20231001-120000-ABCDEFG.7z"
+            )]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn synced_folder_keeps_hyphenated_players_on_onedrive() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let mock_file_system = MockFileSystem::from_str(indoc! {r"
+            /remote/
+                Axis Jean-Luc 5.7z
+                Axis Jean-Luc 5-DESKTOP-1A2B3C.7z
+        "})?;
+
+        let folder = SyncedFolder::new(
+            PathBuf::from("/remote"),
+            Provider::OneDrive,
+            Box::new(mock_file_system),
+            Box::new(MockCompression::new()),
+        )?;
+
+        assert_eq!(
+            folder.locate_save(&Save::from_parts(Side::Axis, 5).player("Jean-Luc"))?,
+            Some(PathBuf::from("/remote/Axis Jean-Luc 5.7z").as_path())
+        );
+        assert_eq!(
+            folder.conflicted_copies(),
+            &[PathBuf::from("/remote/Axis Jean-Luc 5-DESKTOP-1A2B3C.7z")]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn conflicted_copy_naming() {
+        let cases = [
+            (
+                Provider::Dropbox,
+                "Allies 5 (DM's conflicted copy 2023-10-01).7z",
+            ),
+            (Provider::OneDrive, "Allies 5-DESKTOP-1A2B3C.7z"),
+            (Provider::OneDrive, "Axis Jean-Luc 5 A-LAPTOP.7z"),
+            (Provider::GoogleDrive, "Allies 5 (1).7z"),
+            (
+                Provider::Syncthing,
+                "Allies 5.sync-conflict-20231001-120000-ABCDEFG.7z",
+            ),
+        ];
+
+        for (provider, copy) in cases {
+            assert!(is_conflicted_copy(provider, Path::new(copy)), "{copy}");
+            assert!(!is_conflicted_copy(provider, Path::new("Allies 5.7z")));
+            assert!(!is_conflicted_copy(provider, Path::new("Axis DM 5 A.7z")));
+            assert!(!is_conflicted_copy(
+                provider,
+                Path::new("Axis Jean-Luc 5.7z")
+            ));
+            assert!(!is_conflicted_copy(Provider::Folder, Path::new(copy)));
+        }
+    }
+}
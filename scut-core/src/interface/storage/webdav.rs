@@ -0,0 +1,590 @@
+//! Uploads saves straight to a folder on a WebDAV server, e.g. Nextcloud, so that no sync client is needed.
+//!
+//! The folder is listed with PROPFIND to find the saves, and archives are sent with PUT and fetched with GET,
+//! passing through a temporary folder on this computer to be compressed and decompressed.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::Context;
+use percent_encoding::percent_decode_str;
+use quick_xml::{events::Event, Reader};
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use reqwest::{Method, StatusCode};
+use url::Url;
+
+use crate::interface::file_system::FileMetadata;
+use crate::interface::index::IterIndex;
+use crate::interface::storage::object_store::{self, Object, ObjectStore};
+use crate::interface::storage::staging;
+use crate::interface::{Compression, RemoteStorage};
+use crate::ledger::LedgerEntry;
+use crate::signature::Signature;
+use crate::substitute::Substitution;
+use crate::versions::Version;
+use crate::{error::ErrorSuggestions, RemoteKind, Save};
+
+/// Asks for the properties of each file that scut needs
+const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:"><d:prop><d:resourcetype/><d:getcontentlength/></d:prop></d:propfind>"#;
+
+/// This implementation is used to store the saves in a folder on a WebDAV server, where they can be shared with other players.
+#[derive(Clone)]
+pub struct WebDav {
+    /// The url of the folder, ending in a slash
+    url: Url,
+    credentials: Option<(String, Option<String>)>,
+    client: Client,
+    compression: Box<dyn Compression>,
+    /// The name of each save's file in the folder
+    saves: BTreeMap<Save, String>,
+}
+
+impl WebDav {
+    pub fn new(
+        url: &str,
+        credentials: Option<(&str, Option<&str>)>,
+        compression: Box<dyn Compression>,
+    ) -> anyhow::Result<Self> {
+        let mut url = Url::parse(url)
+            .with_context(|| format!("`{url}` is not a valid url"))
+            .suggest(
+                "Use `scut config set remote.url <URL>` to set the url of your WebDAV folder",
+            )?;
+
+        // file names are joined onto the folder's url, which only works if it ends in a slash
+        if !url.path().ends_with('/') {
+            let path = format!("{}/", url.path());
+            url.set_path(&path);
+        }
+
+        let mut webdav = WebDav {
+            url,
+            credentials: credentials
+                .map(|(username, password)| (username.to_string(), password.map(String::from))),
+            client: Client::new(),
+            compression,
+            saves: BTreeMap::new(),
+        };
+
+        webdav.refresh_saves()?;
+
+        Ok(webdav)
+    }
+
+    /// Reloads from the server what saves are in this folder
+    pub fn refresh_saves(&mut self) -> anyhow::Result<()> {
+        let saves = object_store::saves(self)?
+            .ok_or_else(|| anyhow::anyhow!("{} not found on the WebDAV server", self.url))
+            .suggest(RemoteKind::WebDav.sync_suggestion())?;
+
+        self.saves = saves
+            .into_iter()
+            .map(|(save, object)| (save, object.name))
+            .collect();
+
+        Ok(())
+    }
+
+    /// The url of a file or folder inside this folder, named the way an [`ObjectStore`] names it. Folders end in a slash
+    fn url_for(&self, name: &str, folder: bool) -> anyhow::Result<Url> {
+        let mut url = self.url.clone();
+        {
+            let mut segments = url
+                .path_segments_mut()
+                .map_err(|_| anyhow::anyhow!("{} is not a folder", self.url))?;
+            segments
+                .pop_if_empty()
+                .extend(name.split('/').filter(|segment| !segment.is_empty()));
+            if folder {
+                segments.push("");
+            }
+        }
+        Ok(url)
+    }
+
+    fn request(&self, method: Method, url: Url) -> RequestBuilder {
+        let request = self.client.request(method, url);
+        match &self.credentials {
+            Some((username, password)) => request.basic_auth(username, password.as_ref()),
+            None => request,
+        }
+    }
+
+    /// Send a request, returning None if the file or folder doesn't exist
+    fn send(&self, request: RequestBuilder, action: &str) -> anyhow::Result<Option<Response>> {
+        let response = request
+            .send()
+            .with_context(|| format!("failed to {action}"))
+            .suggest(RemoteKind::WebDav.sync_suggestion())?;
+
+        check(response, action)
+    }
+
+    /// Create the folders that the file `name` is in, if they don't exist already. WebDAV only creates one folder at a time
+    fn make_folders(&self, name: &str) -> anyhow::Result<()> {
+        let segments: Vec<&str> = name.split('/').collect();
+        let mut folder = String::new();
+        for segment in &segments[..segments.len() - 1] {
+            folder.push_str(segment);
+            folder.push('/');
+
+            let url = self.url_for(&folder, true)?;
+            let action = format!("create the folder {url}");
+
+            let response = self
+                .request(Method::from_bytes(b"MKCOL")?, url)
+                .send()
+                .with_context(|| format!("failed to {action}"))
+                .suggest(RemoteKind::WebDav.sync_suggestion())?;
+
+            // the folder already exists
+            if response.status() == StatusCode::METHOD_NOT_ALLOWED {
+                continue;
+            }
+
+            check(response, &action)?.ok_or_else(|| {
+                anyhow::anyhow!("failed to {action}: its parent folder doesn't exist")
+            })?;
+        }
+        Ok(())
+    }
+
+    /// The name of a save's file in this folder, if it exists
+    fn locate_save(&mut self, save: &Save) -> anyhow::Result<Option<String>> {
+        if !self.saves.contains_key(save) {
+            self.refresh_saves()?;
+        }
+        Ok(self.saves.get(save).cloned())
+    }
+}
+
+impl ObjectStore for WebDav {
+    fn list(&mut self, folder: &str) -> anyhow::Result<Option<Vec<Object>>> {
+        let url = self.url_for(folder, true)?;
+        let request = self
+            .request(Method::from_bytes(b"PROPFIND")?, url.clone())
+            .header("Depth", "1")
+            .header("Content-Type", "application/xml")
+            .body(PROPFIND_BODY);
+
+        let Some(response) = self.send(request, &format!("list {url}"))? else {
+            return Ok(None);
+        };
+        let xml = response
+            .text()
+            .with_context(|| format!("failed to list {url}"))?;
+
+        let mut objects = Vec::new();
+        for (href, size, is_folder) in parse_multistatus(&xml)
+            .with_context(|| format!("failed to read the listing of {url}"))?
+        {
+            // the folder is listed along with its contents, and only files are wanted
+            if is_folder {
+                continue;
+            }
+
+            // hrefs can be absolute urls or just the path
+            let path = match Url::parse(&href) {
+                Ok(href) => decode(href.path())?,
+                Err(_) => decode(&href)?,
+            };
+
+            if let Some(name) = path.rsplit('/').next() {
+                objects.push(Object {
+                    name: name.to_string(),
+                    size,
+                });
+            }
+        }
+
+        Ok(Some(objects))
+    }
+
+    fn get(&mut self, name: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        let url = self.url_for(name, false)?;
+        let action = format!("download {url}");
+
+        let Some(response) = self.send(self.request(Method::GET, url), &action)? else {
+            return Ok(None);
+        };
+        Ok(Some(
+            response
+                .bytes()
+                .with_context(|| format!("failed to {action}"))?
+                .to_vec(),
+        ))
+    }
+
+    fn put(&mut self, name: &str, content: Vec<u8>) -> anyhow::Result<()> {
+        self.make_folders(name)?;
+
+        let url = self.url_for(name, false)?;
+        let action = format!("upload {url}");
+
+        self.send(self.request(Method::PUT, url).body(content), &action)?
+            .ok_or_else(|| anyhow::anyhow!("failed to {action}: its folder doesn't exist"))?;
+        Ok(())
+    }
+
+    fn rename(&mut self, from: &str, to: &str) -> anyhow::Result<()> {
+        self.make_folders(to)?;
+
+        let from = self.url_for(from, false)?;
+        let to = self.url_for(to, false)?;
+        let action = format!("move {from} to {to}");
+
+        let request = self
+            .request(Method::from_bytes(b"MOVE")?, from)
+            .header("Destination", to.as_str())
+            .header("Overwrite", "T");
+
+        self.send(request, &action)?
+            .ok_or_else(|| anyhow::anyhow!("failed to {action}: it doesn't exist"))?;
+        Ok(())
+    }
+}
+
+/// Turn error statuses into errors, returning None if the file or folder doesn't exist
+fn check(response: Response, action: &str) -> anyhow::Result<Option<Response>> {
+    match response.status() {
+        StatusCode::NOT_FOUND => Ok(None),
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(anyhow::anyhow!(
+            "failed to {action}: the WebDAV server didn't accept your login"
+        ))
+        .suggest("Check remote.username and remote.password in your config")
+        .map_err(anyhow::Error::from),
+        status if status.is_success() => Ok(Some(response)),
+        status => Err(anyhow::anyhow!(
+            "failed to {action}: the WebDAV server responded {status}"
+        )),
+    }
+}
+
+fn decode(path: &str) -> anyhow::Result<String> {
+    Ok(percent_decode_str(path)
+        .decode_utf8()
+        .with_context(|| format!("`{path}` is not a valid path"))?
+        .into_owned())
+}
+
+/// Read the href, size and whether it is a folder of each response in a PROPFIND multistatus
+fn parse_multistatus(xml: &str) -> anyhow::Result<Vec<(String, u64, bool)>> {
+    let mut reader = Reader::from_str(xml);
+    reader.trim_text(true);
+
+    let mut responses = Vec::new();
+    let (mut href, mut size, mut is_folder) = (String::new(), 0, false);
+    // the element whose text is being read
+    let mut element = Vec::new();
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(start) => {
+                let name = start.local_name().as_ref().to_vec();
+                match name.as_slice() {
+                    b"response" => (href, size, is_folder) = (String::new(), 0, false),
+                    b"collection" => is_folder = true,
+                    _ => {}
+                }
+                element = name;
+            }
+            Event::Empty(empty) => {
+                if empty.local_name().as_ref() == b"collection" {
+                    is_folder = true;
+                }
+            }
+            Event::Text(text) => match element.as_slice() {
+                b"href" => href = text.unescape()?.into_owned(),
+                b"getcontentlength" => size = text.unescape()?.trim().parse().unwrap_or_default(),
+                _ => {}
+            },
+            Event::End(end) => {
+                if end.local_name().as_ref() == b"response" {
+                    responses.push((std::mem::take(&mut href), size, is_folder));
+                }
+                element.clear();
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(responses)
+}
+
+impl RemoteStorage for WebDav {
+    fn download(&mut self, save: &Save, local_path: &Path) -> anyhow::Result<()> {
+        let not_found = || {
+            Err(anyhow::anyhow!("{save} not found in your WebDAV folder"))
+                .suggest(RemoteKind::WebDav.sync_suggestion())
+                .suggest("Have your friends uploaded their turn?")
+        };
+
+        let Some(name) = self.locate_save(save)? else {
+            return Ok(not_found()?);
+        };
+        let Some(content) = self.get(&name)? else {
+            return Ok(not_found()?);
+        };
+
+        staging::decompress(self.compression.as_ref(), &name, &content, local_path)
+            .with_context(|| format!("failed to download {save}"))
+    }
+
+    fn upload(&mut self, save: &Save, local_path: &Path) -> anyhow::Result<()> {
+        let (name, content) = staging::compress(self.compression.as_ref(), save, local_path)
+            .with_context(|| format!("failed to upload {save}"))?;
+
+        let current = self.locate_save(save)?;
+        object_store::replace(self, save, &name, content, current.as_deref())
+            .with_context(|| format!("failed to upload {save}"))?;
+        self.saves.insert(save.clone(), name);
+        Ok(())
+    }
+
+    fn supersede(&mut self, save: &Save) -> anyhow::Result<()> {
+        let name = self
+            .locate_save(save)?
+            .ok_or_else(|| anyhow::anyhow!("{save} not found in your WebDAV folder"))?;
+
+        object_store::supersede(self, save, &name)?;
+        self.saves.remove(save);
+        Ok(())
+    }
+
+    fn record_substitution(&mut self, substitution: &Substitution) -> anyhow::Result<()> {
+        object_store::record_substitution(self, substitution)
+    }
+
+    fn substitutions(&mut self) -> anyhow::Result<Vec<Substitution>> {
+        object_store::substitutions(self)
+    }
+
+    fn record_signature(&mut self, signature: &Signature) -> anyhow::Result<()> {
+        object_store::record_signature(self, signature)
+    }
+
+    fn signatures(&mut self) -> anyhow::Result<Vec<Signature>> {
+        object_store::signatures(self)
+    }
+
+    fn record_upload(&mut self, entry: &LedgerEntry) -> anyhow::Result<()> {
+        object_store::record_upload(self, entry)
+    }
+
+    fn ledger(&mut self) -> anyhow::Result<Vec<LedgerEntry>> {
+        object_store::ledger(self)
+    }
+
+    fn versions(&mut self, save: &Save) -> anyhow::Result<Vec<Version>> {
+        object_store::versions(self, save)
+    }
+
+    fn restore(&mut self, save: &Save, version: u64) -> anyhow::Result<()> {
+        let current = self.locate_save(save)?;
+        if !object_store::restore(self, save, version, current.as_deref())? {
+            return Err(anyhow::anyhow!(
+                "{save} version {version} not found in your WebDAV folder"
+            ))
+            .suggest(RemoteKind::WebDav.sync_suggestion())?;
+        }
+
+        self.refresh_saves()
+    }
+
+    fn metadata(&mut self, save: &Save) -> anyhow::Result<Option<FileMetadata>> {
+        let Some(name) = self.locate_save(save)? else {
+            return Ok(None);
+        };
+
+        Ok(self
+            .get(&name)
+            .with_context(|| format!("failed to read {save} in your WebDAV folder"))?
+            .map(|content| FileMetadata::new(&content, None)))
+    }
+
+    fn index(&self) -> &dyn crate::interface::Index {
+        self
+    }
+}
+
+impl<'a> IterIndex<'a> for WebDav {
+    type Iter = std::collections::btree_map::Keys<'a, Save, String>;
+
+    fn iter(&'a self) -> Self::Iter {
+        self.saves.keys()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{
+        interface::{
+            compression::copy_compression::CopyCompression, index::Query,
+            storage::mock_webdav_server::MockWebDavServer,
+        },
+        ledger::{find_uploads, record_uploads},
+        Side,
+    };
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    // "DM:secret" in base64
+    const AUTHORIZATION: &str = "Basic RE06c2VjcmV0";
+
+    fn connect(server: &MockWebDavServer) -> anyhow::Result<WebDav> {
+        WebDav::new(
+            &server.url,
+            Some(("DM", Some("secret"))),
+            Box::new(CopyCompression),
+        )
+    }
+
+    #[test]
+    fn webdav_creates_folders_one_at_a_time() -> anyhow::Result<()> {
+        let server = MockWebDavServer::start("/dav/scut", AUTHORIZATION);
+        let mut webdav = connect(&server)?;
+
+        webdav.put("Axis DM 5.copy", b"DM's turn".to_vec())?;
+        webdav.rename("Axis DM 5.copy", "versions/Axis DM 5/1700000000.copy")?;
+        assert_eq!(
+            server.files(),
+            vec!["/dav/scut/versions/Axis DM 5/1700000000.copy".to_string()]
+        );
+
+        // the folder isn't listed among its own contents
+        assert_eq!(webdav.list("")?, Some(Vec::new()));
+        assert_eq!(
+            webdav.list("versions/Axis DM 5")?,
+            Some(vec![Object {
+                name: "1700000000.copy".to_string(),
+                size: 9,
+            }])
+        );
+        assert_eq!(webdav.list("superseded")?, None);
+        assert_eq!(webdav.get("Axis DM 5.copy")?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn webdav_upload_and_download() -> anyhow::Result<()> {
+        let server = MockWebDavServer::start("/dav/scut", AUTHORIZATION);
+        let local = tempfile::tempdir()?;
+        let teammate = tempfile::tempdir()?;
+
+        let save = Save::from_parts(Side::Axis, 5).player("DM");
+        let path = local.path().join("Axis DM 5.sav");
+        fs::write(&path, "DM's turn")?;
+
+        let mut webdav = connect(&server)?;
+        webdav.upload(&save, &path)?;
+        let uploaded = find_uploads(&mut webdav, &[save.clone()])?;
+        record_uploads(&mut webdav, &uploaded, "DM")?;
+        assert_eq!(
+            server.files(),
+            vec![
+                "/dav/scut/Axis DM 5.copy".to_string(),
+                "/dav/scut/ledger/DM.txt".to_string(),
+            ]
+        );
+
+        // a teammate connects and downloads the save
+        let mut webdav = connect(&server)?;
+        assert_eq!(webdav.index().search(&Query::new())?, vec![save.clone()]);
+        webdav.download(&save, teammate.path())?;
+        assert_eq!(
+            fs::read_to_string(teammate.path().join("Axis DM 5.sav"))?,
+            "DM's turn"
+        );
+        assert_eq!(webdav.ledger()?.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn webdav_upload_over_an_existing_save() -> anyhow::Result<()> {
+        let server = MockWebDavServer::start("/dav/scut", AUTHORIZATION);
+        let local = tempfile::tempdir()?;
+        let teammate = tempfile::tempdir()?;
+
+        let save = Save::from_parts(Side::Axis, 5).player("DM");
+        let path = local.path().join("Axis DM 5.sav");
+        fs::write(&path, "DM's turn")?;
+        let mut webdav = connect(&server)?;
+        webdav.upload(&save, &path)?;
+
+        // an upload that fails leaves the previous copy in place
+        fs::write(&path, "DM's turn, again")?;
+        server.refuse_uploads(true);
+        assert!(webdav.upload(&save, &path).is_err());
+        server.refuse_uploads(false);
+        assert_eq!(server.files(), vec!["/dav/scut/Axis DM 5.copy".to_string()]);
+
+        // overwriting the save keeps the previous version
+        webdav.upload(&save, &path)?;
+        let versions = webdav.versions(&save)?;
+        assert_eq!(versions.len(), 1);
+        assert_eq!(versions[0].size, "DM's turn".len() as u64);
+
+        let mut webdav = connect(&server)?;
+        webdav.download(&save, teammate.path())?;
+        assert_eq!(
+            fs::read_to_string(teammate.path().join("Axis DM 5.sav"))?,
+            "DM's turn, again"
+        );
+        assert_eq!(
+            server.files(),
+            vec![
+                "/dav/scut/Axis DM 5.copy".to_string(),
+                format!(
+                    "/dav/scut/versions/Axis DM 5/{}.copy",
+                    versions[0].timestamp
+                ),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn webdav_wrong_password() {
+        let server = MockWebDavServer::start("/dav/scut", AUTHORIZATION);
+
+        let webdav = WebDav::new(
+            &server.url,
+            Some(("DM", Some("guess"))),
+            Box::new(CopyCompression),
+        );
+        assert!(webdav.is_err());
+    }
+
+    #[test]
+    fn parse_propfind_listing() -> anyhow::Result<()> {
+        let xml = r#"<?xml version="1.0"?>
+            <d:multistatus xmlns:d="DAV:">
+                <d:response>
+                    <d:href>/dav/scut/</d:href>
+                    <d:propstat><d:prop><d:resourcetype><d:collection/></d:resourcetype></d:prop></d:propstat>
+                </d:response>
+                <d:response>
+                    <d:href>/dav/scut/Allies%205.7z</d:href>
+                    <d:propstat><d:prop><d:resourcetype/><d:getcontentlength>2048</d:getcontentlength></d:prop></d:propstat>
+                </d:response>
+            </d:multistatus>"#;
+
+        assert_eq!(
+            parse_multistatus(xml)?,
+            vec![
+                ("/dav/scut/".to_string(), 0, true),
+                ("/dav/scut/Allies%205.7z".to_string(), 2048, false),
+            ]
+        );
+
+        Ok(())
+    }
+}
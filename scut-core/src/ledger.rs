@@ -0,0 +1,245 @@
+//! The ledger is an append-only record in remote storage of every upload: who uploaded which save, when,
+//! what the archive looked like and which version of scut uploaded it.
+//!
+//! Overwriting a save, e.g. re-uploading an autosave, replaces the archive in remote storage, but the ledger keeps both uploads.
+//!
+//! Dropbox can't merge two computers appending to the same file at once, it keeps one and renames the other as a "conflicted copy".
+//! So each uploader appends to their own segment of the ledger, in a `ledger` subfolder, one upload per line:
+//!
+//! ```text
+//! ledger/DM.txt
+//! Axis DM 5 uploaded by DM at 1700000000 size 2048 sha256 <digest> scut 0.5.0
+//! ```
+//!
+//! Reading the ledger merges every segment, oldest upload first.
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+
+use crate::{
+    interface::{file_system::FileMetadata, RemoteStorage},
+    Save,
+};
+
+/// The version of scut that is recording uploads
+pub const SCUT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// A record that a save was uploaded to remote storage
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LedgerEntry {
+    /// The save that was uploaded
+    pub save: Save,
+
+    /// The player who uploaded the save, whose segment of the ledger it is recorded in
+    pub uploader: String,
+
+    /// When the save was uploaded, in seconds since the unix epoch
+    pub timestamp: u64,
+
+    /// The size of the archive in remote storage, in bytes
+    pub size: u64,
+
+    /// The SHA-256 digest of the archive in remote storage, in hex
+    pub digest: String,
+
+    /// The version of scut that uploaded the save
+    pub version: String,
+}
+
+impl fmt::Display for LedgerEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} uploaded by {} at {} size {} sha256 {} scut {}",
+            self.save, self.uploader, self.timestamp, self.size, self.digest, self.version
+        )
+    }
+}
+
+impl FromStr for LedgerEntry {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || anyhow::anyhow!("`{s}` is not a valid ledger entry");
+
+        let (rest, version) = s.trim().rsplit_once(" scut ").ok_or_else(invalid)?;
+        let (rest, digest) = rest.rsplit_once(" sha256 ").ok_or_else(invalid)?;
+        let (rest, size) = rest.rsplit_once(" size ").ok_or_else(invalid)?;
+        let (rest, timestamp) = rest.rsplit_once(" at ").ok_or_else(invalid)?;
+        let (save, uploader) = rest.rsplit_once(" uploaded by ").ok_or_else(invalid)?;
+
+        Ok(LedgerEntry {
+            save: save
+                .parse()
+                .with_context(|| format!("`{save}` is not a valid save"))?,
+            uploader: uploader.to_string(),
+            timestamp: timestamp
+                .parse()
+                .with_context(|| format!("`{timestamp}` is not a valid timestamp"))?,
+            size: size
+                .parse()
+                .with_context(|| format!("`{size}` is not a valid size"))?,
+            digest: digest.to_string(),
+            version: version.to_string(),
+        })
+    }
+}
+
+/// Seconds since the unix epoch, used to timestamp records in remote storage
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+/// A save that was just uploaded, with the metadata of its archive in remote storage
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Upload {
+    /// The save that was uploaded
+    pub save: Save,
+
+    /// The size and digest of the archive in remote storage
+    pub metadata: FileMetadata,
+}
+
+/// Look up each of `saves` in remote storage after uploading them, to [record](record_uploads) and [sign](crate::signature::sign_uploads).
+///
+/// Getting the digest of an archive can mean downloading it, so this is done once for both.
+pub fn find_uploads(remote: &mut dyn RemoteStorage, saves: &[Save]) -> anyhow::Result<Vec<Upload>> {
+    saves
+        .iter()
+        .map(|save| {
+            let metadata = remote.metadata(save)?.ok_or_else(|| {
+                anyhow::anyhow!("failed to record the upload of {save}: it isn't in remote storage")
+            })?;
+            Ok(Upload {
+                save: save.clone(),
+                metadata,
+            })
+        })
+        .collect()
+}
+
+/// Record in the ledger that each of `uploads` was just uploaded by `uploader`
+pub fn record_uploads(
+    remote: &mut dyn RemoteStorage,
+    uploads: &[Upload],
+    uploader: &str,
+) -> anyhow::Result<()> {
+    let timestamp = now();
+
+    for upload in uploads {
+        let save = &upload.save;
+        let entry = LedgerEntry {
+            save: save.clone(),
+            uploader: uploader.to_string(),
+            timestamp,
+            size: upload.metadata.size,
+            digest: upload.metadata.digest.clone(),
+            version: SCUT_VERSION.to_string(),
+        };
+        remote
+            .record_upload(&entry)
+            .with_context(|| format!("failed to record that {uploader} uploaded {save}"))?;
+    }
+    Ok(())
+}
+
+/// Every save that was uploaded more than once, with each of its uploads, oldest first
+pub fn overwrites(ledger: &[LedgerEntry]) -> BTreeMap<Save, Vec<&LedgerEntry>> {
+    let mut uploads: BTreeMap<Save, Vec<&LedgerEntry>> = BTreeMap::new();
+    for entry in ledger {
+        uploads.entry(entry.save.clone()).or_default().push(entry);
+    }
+
+    uploads.retain(|_, entries| entries.len() > 1);
+    uploads
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{
+        interface::storage::memory_storage::{
+            MemoryFiles, MemoryLocalStorage, MemoryRemoteStorage,
+        },
+        interface::LocalStorage,
+        Side,
+    };
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn ledger_entry_round_trip() -> anyhow::Result<()> {
+        let entry = LedgerEntry {
+            save: Save::from_parts(Side::Axis, 5).player("DM"),
+            uploader: "DM".to_string(),
+            timestamp: 1700000000,
+            size: 2048,
+            digest: "ab12".to_string(),
+            version: "0.5.0".to_string(),
+        };
+
+        let line = "Axis DM 5 uploaded by DM at 1700000000 size 2048 sha256 ab12 scut 0.5.0";
+        assert_eq!(entry.to_string(), line);
+        assert_eq!(line.parse::<LedgerEntry>()?, entry);
+        assert!("Axis DM 5 uploaded by DM".parse::<LedgerEntry>().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn each_uploader_appends_to_their_own_segment() -> anyhow::Result<()> {
+        let files = MemoryFiles::new();
+        let mut local = MemoryLocalStorage::new(PathBuf::from("/local"), files.clone());
+        let mut remote = MemoryRemoteStorage::new(PathBuf::from("/remote"), files.clone());
+
+        let turn_start = Save::from_parts(Side::Allies, 5);
+
+        // DM uploads the autosave, then DG overwrites it
+        local.write_autosave("end of Axis 5");
+        let path = local.locate_autosave()?.expect("autosave was written");
+        remote.upload(&turn_start, &path)?;
+        let uploaded = find_uploads(&mut remote, &[turn_start.clone()])?;
+        record_uploads(&mut remote, &uploaded, "DM")?;
+
+        local.write_autosave("end of Axis 5, again");
+        remote.upload(&turn_start, &path)?;
+        let uploaded = find_uploads(&mut remote, &[turn_start.clone()])?;
+        record_uploads(&mut remote, &uploaded, "DG")?;
+
+        assert!(files
+            .read(&PathBuf::from("/remote/ledger/DM.txt"))
+            .is_some());
+        assert!(files
+            .read(&PathBuf::from("/remote/ledger/DG.txt"))
+            .is_some());
+
+        let ledger = remote.ledger()?;
+        let uploaders = ledger
+            .iter()
+            .map(|entry| entry.uploader.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(uploaders.len(), 2);
+        assert!(uploaders.contains(&"DM") && uploaders.contains(&"DG"));
+
+        let overwritten = overwrites(&ledger);
+        assert_eq!(overwritten.len(), 1);
+        assert_eq!(overwritten[&turn_start].len(), 2);
+        assert_ne!(
+            overwritten[&turn_start][0].digest,
+            overwritten[&turn_start][1].digest
+        );
+
+        Ok(())
+    }
+}
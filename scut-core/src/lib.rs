@@ -6,10 +6,26 @@ mod save;
 use error::ErrorSuggestions;
 pub use save::{Save, SaveOrAutosave, Side, Turn};
 mod config;
-pub use config::{Config, Key, Setting};
+pub use config::{
+    part_letter, Aliases, Config, HandoffOrder, Key, Mirror, Provider, Remote, RemoteKind,
+    Retention, Roster, Setting,
+};
+
+pub mod audit;
+pub mod ledger;
+pub mod prune;
+pub mod rollback;
+pub mod signature;
+pub mod simulation;
+pub mod snapshot;
+pub mod substitute;
+pub mod versions;
 
 pub mod interface;
-use interface::{LocalStorage, RemoteStorage};
+use interface::{
+    storage::sync_record::{Direction, SyncedSave},
+    LocalStorage, RemoteStorage,
+};
 
 /// Uploads a list of saves
 pub fn upload_predicted_saves(
@@ -78,3 +94,56 @@ pub fn upload_predicted_autosave(
     remote.upload(&autosave, local_path.as_path())?;
     Ok(())
 }
+
+/// Record what the saves that were just uploaded or downloaded look like in local and remote storage,
+/// so that later predictions can warn if either copy changes.
+///
+/// See [`SyncRecord`](interface::storage::sync_record::SyncRecord)
+pub fn record_synced_saves(
+    local: &mut dyn LocalStorage,
+    remote: &mut dyn RemoteStorage,
+    uploads: &[Save],
+    downloads: &[Save],
+    autosave: Option<&Save>,
+) -> anyhow::Result<()> {
+    let mut record = local.sync_record()?;
+
+    let synced = uploads
+        .iter()
+        .map(|save| (save, Direction::Uploaded))
+        .chain(downloads.iter().map(|save| (save, Direction::Downloaded)));
+
+    for (save, direction) in synced {
+        if let (Some(local), Some(remote)) = (local.metadata(save)?, remote.metadata(save)?) {
+            record.insert(
+                save,
+                SyncedSave {
+                    direction,
+                    local,
+                    remote,
+                },
+            );
+        }
+    }
+
+    if let Some(save) = autosave {
+        if let (Some(local), Some(remote)) = (local.autosave_metadata()?, remote.metadata(save)?) {
+            record.insert(
+                save,
+                SyncedSave {
+                    direction: Direction::UploadedAutosave,
+                    local,
+                    remote,
+                },
+            );
+        }
+    }
+
+    if let Some(latest) = record.iter().map(|(save, _)| save.turn.number).max() {
+        record.forget_before(latest.saturating_sub(2));
+    }
+
+    local
+        .write_sync_record(&record)
+        .context("failed to record the saves that were synced")
+}
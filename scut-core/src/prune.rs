@@ -0,0 +1,188 @@
+//! Pruning bundles old turns in remote storage into archives, to save space, following the [`Retention`] policy in the config.
+//!
+//! Every save from the latest turns is kept, along with the turn start of every `keep_every`th turn.
+//! The rest are bundled into one archive per range of turns, e.g. `Turns 1-10`, in an `archive` subfolder of the storage.
+//!
+//! Archived saves are still indexed, so they can be listed and downloaded, they are extracted from their archive on demand.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use anyhow::Context;
+
+use crate::{
+    interface::{index::Query, storage::split_remote::is_turn_start, RemoteStorage},
+    Retention, Save,
+};
+
+/// The saves that will be bundled into each archive to prune remote storage
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Prune {
+    /// Saves will be bundled into these archives, oldest first
+    pub bundles: Vec<Bundle>,
+}
+
+/// An archive that will be made by pruning, and the saves that will be bundled into it
+#[derive(Debug, PartialEq, Eq)]
+pub struct Bundle {
+    /// The name of the archive, e.g. `Turns 1-10`
+    pub name: String,
+    /// The saves that will be bundled, in turn order
+    pub saves: Vec<Save>,
+}
+
+impl fmt::Display for Bundle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} saves", self.name, self.saves.len())
+    }
+}
+
+/// Work out which saves in remote storage fall outside the `retention` policy and which archive each will be bundled into, without changing anything.
+///
+/// Saves that have already been archived are left where they are.
+pub fn plan_prune(retention: &Retention, remote: &mut dyn RemoteStorage) -> anyhow::Result<Prune> {
+    let archives = remote.archives().context("failed to read archives")?;
+    let archived: BTreeSet<Save> = archives
+        .iter()
+        .flat_map(|archive| archive.saves.iter().cloned())
+        .collect();
+
+    let mut saves = remote.index().search(&Query::new())?;
+    saves.retain(|save| !archived.contains(save));
+
+    let Some(latest) = saves.iter().map(|save| save.turn.number).max() else {
+        return Ok(Prune::default());
+    };
+
+    let bundle_turns = retention.bundle_turns.max(1);
+    let mut ranges: BTreeMap<u32, Vec<Save>> = BTreeMap::new();
+    for save in saves {
+        if keeps(retention, latest, &save) {
+            continue;
+        }
+        // turns are numbered from 1, so the first range is turns 1-10
+        let range = save.turn.number.saturating_sub(1) / bundle_turns;
+        ranges.entry(range).or_default().push(save);
+    }
+
+    let names: BTreeSet<&str> = archives
+        .iter()
+        .map(|archive| archive.name.as_str())
+        .collect();
+    let bundles = ranges
+        .into_iter()
+        .map(|(range, mut saves)| {
+            let turns = format!(
+                "Turns {}-{}",
+                range * bundle_turns + 1,
+                (range + 1) * bundle_turns
+            );
+            // more saves from a range that was pruned before, e.g. after the policy changed
+            let mut name = turns.clone();
+            let mut copy = 1;
+            while names.contains(name.as_str()) {
+                copy += 1;
+                name = format!("{turns} ({copy})");
+            }
+
+            saves.sort();
+            Bundle { name, saves }
+        })
+        .collect();
+
+    Ok(Prune { bundles })
+}
+
+/// Returns true if `save` should be kept out of the archives when the latest turn is `latest`
+fn keeps(retention: &Retention, latest: u32, save: &Save) -> bool {
+    let number = save.turn.number;
+
+    // the current turn is always kept, even if no turns are
+    let recent = number + retention.keep_turns.max(1) > latest;
+    let milestone = retention
+        .keep_every
+        .is_some_and(|every| every > 0 && number % every == 0 && is_turn_start(save));
+
+    recent || milestone
+}
+
+impl Prune {
+    /// Bundle the planned saves into their archives
+    pub fn execute(self, remote: &mut dyn RemoteStorage) -> anyhow::Result<()> {
+        for bundle in self.bundles.iter() {
+            remote
+                .archive(&bundle.name, &bundle.saves)
+                .with_context(|| format!("failed to archive {}", bundle.name))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{interface::storage::mock_index_storage::MockIndexStorage, Side};
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn plan_keeps_recent_turns_and_milestones() -> anyhow::Result<()> {
+        let mut saves = Vec::new();
+        for number in 1..=25 {
+            saves.push(Save::from_parts(Side::Axis, number));
+            saves.push(Save::from_parts(Side::Axis, number).player("DM"));
+        }
+        let mut remote = MockIndexStorage::new(false, saves);
+
+        let retention = Retention {
+            keep_turns: 3,
+            keep_every: Some(10),
+            bundle_turns: 10,
+        };
+        let prune = plan_prune(&retention, &mut remote)?;
+
+        assert_eq!(
+            prune
+                .bundles
+                .iter()
+                .map(|bundle| bundle.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["Turns 1-10", "Turns 11-20", "Turns 21-30"]
+        );
+
+        // the turn start of turn 10 is kept, DM's save isn't
+        let first = &prune.bundles[0].saves;
+        assert_eq!(first.len(), 19);
+        assert!(!first.contains(&Save::from_parts(Side::Axis, 10)));
+        assert!(first.contains(&Save::from_parts(Side::Axis, 10).player("DM")));
+
+        // turns 23 to 25 are kept
+        let last = &prune.bundles[2].saves;
+        assert_eq!(last.len(), 4);
+        assert!(last.contains(&Save::from_parts(Side::Axis, 22).player("DM")));
+        assert!(!last.contains(&Save::from_parts(Side::Axis, 23)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn plan_nothing_when_every_turn_is_kept() -> anyhow::Result<()> {
+        let mut remote = MockIndexStorage::new(
+            false,
+            vec![
+                Save::from_parts(Side::Axis, 1),
+                Save::from_parts(Side::Allies, 1),
+            ],
+        );
+
+        let retention = Retention {
+            keep_turns: 0,
+            keep_every: None,
+            bundle_turns: 10,
+        };
+
+        assert_eq!(plan_prune(&retention, &mut remote)?, Prune::default());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,221 @@
+//! Rolling back replays an earlier turn, e.g. after a crash or a misclick that everyone agrees to undo.
+//!
+//! Every save after the start of the rolled back turn is superseded: moved into a `superseded` subfolder
+//! of its storage, so that it is kept but no longer indexed. The turn start save is then restored locally.
+//!
+//! Predictions only look at the saves that are indexed, so afterwards they treat the rolled back turn as current.
+
+use anyhow::Context;
+
+use crate::{
+    error::ErrorSuggestions,
+    interface::{index::Query, LocalStorage, RemoteStorage},
+    Save, Turn,
+};
+
+/// The saves that will be superseded to roll back to a turn
+#[derive(Debug, PartialEq, Eq)]
+pub struct Rollback {
+    /// The turn that will be replayed
+    pub turn: Turn,
+
+    /// The turn start save that will be restored to local storage
+    pub turn_start: Save,
+
+    /// Saves in remote storage that will be superseded, in turn order
+    pub remote: Vec<Save>,
+
+    /// Saves in local storage that will be superseded, in turn order
+    pub local: Vec<Save>,
+}
+
+/// Work out which saves need to be superseded to roll back to `turn`, without changing anything.
+///
+/// The turn start save for `turn` must exist in remote storage, it is the save that the turn is replayed from.
+pub fn plan_rollback(
+    turn: Turn,
+    local: &dyn LocalStorage,
+    remote: &dyn RemoteStorage,
+) -> anyhow::Result<Rollback> {
+    let turn_start = Save::new(turn);
+
+    if remote
+        .index()
+        .count(&Query::new().turn(turn).player(None))?
+        == 0
+    {
+        Err(anyhow::anyhow!(
+            "cannot roll back to {turn}: {turn_start} was not found in remote storage"
+        ))
+        .suggest("Use `scut audit` to see which turns are available")?;
+    }
+
+    let remote = superseded_by(turn, remote.index().search(&Query::new())?);
+    let local = superseded_by(turn, local.index().search(&Query::new())?);
+
+    Ok(Rollback {
+        turn,
+        turn_start,
+        remote,
+        local,
+    })
+}
+
+impl Rollback {
+    /// Supersede the planned saves and restore the turn start save to local storage
+    pub fn execute(
+        self,
+        local: &mut dyn LocalStorage,
+        remote: &mut dyn RemoteStorage,
+    ) -> anyhow::Result<()> {
+        for save in self.remote.iter() {
+            remote
+                .supersede(save)
+                .with_context(|| format!("failed to roll back {save} in remote storage"))?;
+        }
+
+        for save in self.local.iter() {
+            local
+                .supersede(save)
+                .with_context(|| format!("failed to roll back {save} in local storage"))?;
+        }
+
+        remote
+            .download(&self.turn_start, local.location())
+            .with_context(|| format!("failed to restore {}", self.turn_start))
+    }
+}
+
+/// The saves made after the start of `turn`
+fn superseded_by(turn: Turn, mut saves: Vec<Save>) -> Vec<Save> {
+    saves.retain(|save| save.turn > turn || (save.turn == turn && save.player.is_some()));
+    saves.sort();
+    saves
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{
+        interface::storage::{
+            memory_storage::{MemoryFiles, MemoryLocalStorage, MemoryRemoteStorage},
+            mock_index_storage::MockIndexStorage,
+            SUPERSEDED_FOLDER,
+        },
+        Side,
+    };
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn storage(saves: &[Save]) -> MockIndexStorage {
+        MockIndexStorage::new(false, saves.to_vec())
+    }
+
+    #[test]
+    fn plan_supersedes_saves_after_turn_start() -> anyhow::Result<()> {
+        let remote = storage(&[
+            Save::from_parts(Side::Axis, 1),
+            Save::from_parts(Side::Axis, 1).player("DM"),
+            Save::from_parts(Side::Allies, 1),
+            Save::from_parts(Side::Allies, 1).player("GM"),
+            Save::from_parts(Side::Axis, 2),
+            Save::from_parts(Side::Axis, 2).player("DM").part("A"),
+        ]);
+        let local = storage(&[
+            Save::from_parts(Side::Axis, 1).player("DM"),
+            Save::from_parts(Side::Axis, 2),
+            Save::from_parts(Side::Axis, 2).player("DM").part("A"),
+        ]);
+
+        let rollback = plan_rollback(Turn::new(Side::Allies, 1), &local, &remote)?;
+
+        assert_eq!(
+            rollback,
+            Rollback {
+                turn: Turn::new(Side::Allies, 1),
+                turn_start: Save::from_parts(Side::Allies, 1),
+                remote: vec![
+                    Save::from_parts(Side::Allies, 1).player("GM"),
+                    Save::from_parts(Side::Axis, 2).player("DM").part("A"),
+                    Save::from_parts(Side::Axis, 2),
+                ],
+                local: vec![
+                    Save::from_parts(Side::Axis, 2).player("DM").part("A"),
+                    Save::from_parts(Side::Axis, 2),
+                ],
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn plan_requires_turn_start_in_remote() {
+        let remote = storage(&[
+            Save::from_parts(Side::Axis, 1),
+            Save::from_parts(Side::Axis, 1).player("DM"),
+        ]);
+        let local = storage(&[]);
+
+        assert!(plan_rollback(Turn::new(Side::Allies, 1), &local, &remote).is_err());
+    }
+
+    #[test]
+    fn rolled_back_turn_is_latest() -> anyhow::Result<()> {
+        let files = MemoryFiles::new();
+        let mut remote = MemoryRemoteStorage::new(PathBuf::from("/remote"), files.clone());
+        let mut dg = MemoryLocalStorage::new(PathBuf::from("/DG"), files.clone());
+
+        for save in [
+            Save::from_parts(Side::Axis, 1),
+            Save::from_parts(Side::Axis, 1).player("DM"),
+            Save::from_parts(Side::Allies, 1),
+            Save::from_parts(Side::Axis, 2),
+        ] {
+            dg.write_save(&save, save.to_string());
+            let path = dg.locate_save(&save)?.expect("save was written");
+            remote.upload(&save, &path)?;
+        }
+        let mut remote = remote.reconnect();
+
+        let mut local = MemoryLocalStorage::new(PathBuf::from("/DM"), files.clone());
+        local.write_save(&Save::from_parts(Side::Axis, 2), "Axis 2");
+
+        let rollback = plan_rollback(Turn::new(Side::Axis, 1), &local, &remote)?;
+        rollback.execute(&mut local, &mut remote)?;
+
+        let remote = remote.reconnect();
+        assert_eq!(
+            RemoteStorage::index(&remote).latest(&Query::new())?,
+            Some(Save::from_parts(Side::Axis, 1))
+        );
+        assert_eq!(
+            RemoteStorage::index(&remote).search(&Query::new())?,
+            vec![Save::from_parts(Side::Axis, 1)]
+        );
+
+        // the rolled back saves are kept, but no longer indexed
+        let mut superseded =
+            files.files_in_folder(&PathBuf::from("/remote").join(SUPERSEDED_FOLDER));
+        superseded.sort();
+        assert_eq!(
+            superseded,
+            vec![
+                PathBuf::from("/remote/superseded/Allies 1.sav"),
+                PathBuf::from("/remote/superseded/Axis 2.sav"),
+                PathBuf::from("/remote/superseded/Axis DM 1.sav"),
+            ]
+        );
+
+        // the turn start is restored locally, replacing the rolled back turn
+        let local = MemoryLocalStorage::new(PathBuf::from("/DM"), files);
+        assert_eq!(
+            LocalStorage::index(&local).search(&Query::new())?,
+            vec![Save::from_parts(Side::Axis, 1)]
+        );
+
+        Ok(())
+    }
+}
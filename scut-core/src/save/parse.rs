@@ -24,7 +24,19 @@ pub fn parse_side(input: &str) -> ParseResult<'_, Side> {
 }
 
 pub fn parse_player(input: &str) -> ParseResult<'_, String> {
-    alpha().many(1..100).map(|s| s.to_string()).parse(input)
+    let word = || alpha().many(1..100).map(|s| s.to_string());
+    let (mut player, mut remaining) = word().parse(input)?;
+
+    // hyphenated names like Jean-Luc, but not a trailing hyphen
+    while let Some((next, after)) = remaining
+        .strip_prefix('-')
+        .and_then(|rest| word().parse(rest).ok())
+    {
+        player = format!("{player}-{next}");
+        remaining = after;
+    }
+
+    Ok((player, remaining))
 }
 
 pub fn parse_turn(input: &str) -> ParseResult<'_, u32> {
@@ -1,4 +1,4 @@
-use std::cmp::Ordering;
+use std::{cmp::Ordering, fmt};
 
 use serde::{Deserialize, Serialize};
 
@@ -30,6 +30,12 @@ impl Turn {
     }
 }
 
+impl fmt::Display for Turn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.side, self.number)
+    }
+}
+
 impl Ord for Turn {
     fn cmp(&self, other: &Self) -> Ordering {
         match self.number.cmp(&other.number) {
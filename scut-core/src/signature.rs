@@ -0,0 +1,482 @@
+//! Signed uploads prove who uploaded each save, e.g. to settle who overwrote an autosave.
+//!
+//! Each player has an ed25519 keypair, kept in a key file beside their config, and lists their public key in the roster.
+//! After uploading a save, scut signs the save, the SHA-256 digest of the archive in remote storage, who uploaded it and when,
+//! and remote storage keeps a record of the signature. Like the [ledger](crate::ledger), each signer appends to their own segment,
+//! in a `signatures` subfolder, one signature per line:
+//!
+//! ```text
+//! signatures/DM.txt
+//! Axis DM 5 signed by DM at 1700000000 sha256 <digest> signature <signature>
+//! ```
+//!
+//! Signatures are checked against the public keys in the roster before saves are downloaded, and when auditing.
+
+use std::{fmt, path::Path, str::FromStr};
+
+use anyhow::Context;
+use ed25519_dalek::{Signer, Verifier, VerifyingKey};
+
+pub use ed25519_dalek::SigningKey;
+
+use crate::{
+    interface::{file_system::is_not_found_err, FileSystem, RemoteStorage},
+    ledger::{self, Upload},
+    substitute::Substitution,
+    Roster, Save,
+};
+
+/// The name of the file beside the config that holds your private signing key
+pub const SIGNING_KEY_FILE: &str = "signing.key";
+
+/// A record that a save was uploaded by `signer`, signed with their private key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    /// The save that was uploaded
+    pub save: Save,
+
+    /// The player who uploaded the save
+    pub signer: String,
+
+    /// When the save was uploaded, in seconds since the unix epoch
+    pub timestamp: u64,
+
+    /// The SHA-256 digest of the archive in remote storage, in hex
+    pub digest: String,
+
+    /// The ed25519 signature of the [message](Signature::message), in hex
+    pub signature: String,
+}
+
+impl Signature {
+    /// Sign an upload of `save` by `signer`
+    pub fn new<S>(save: Save, signer: S, timestamp: u64, digest: String, key: &SigningKey) -> Self
+    where
+        S: Into<String>,
+    {
+        let mut signature = Signature {
+            save,
+            signer: signer.into(),
+            timestamp,
+            digest,
+            signature: String::new(),
+        };
+        signature.signature = to_hex(&key.sign(signature.message().as_bytes()).to_bytes());
+        signature
+    }
+
+    /// What is signed: the save, who uploaded it, when, and the digest of the archive
+    pub fn message(&self) -> String {
+        format!(
+            "{}\n{}\n{}\n{}",
+            self.save, self.signer, self.timestamp, self.digest
+        )
+    }
+
+    /// Returns true if this was signed by the private key belonging to `public_key`, given in hex
+    pub fn is_signed_by(&self, public_key: &str) -> bool {
+        let Some(public_key) = from_hex::<32>(public_key) else {
+            return false;
+        };
+        let Ok(public_key) = VerifyingKey::from_bytes(&public_key) else {
+            return false;
+        };
+        let Some(signature) = from_hex::<64>(&self.signature) else {
+            return false;
+        };
+
+        public_key
+            .verify(
+                self.message().as_bytes(),
+                &ed25519_dalek::Signature::from_bytes(&signature),
+            )
+            .is_ok()
+    }
+}
+
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} signed by {} at {} sha256 {} signature {}",
+            self.save, self.signer, self.timestamp, self.digest, self.signature
+        )
+    }
+}
+
+impl FromStr for Signature {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || anyhow::anyhow!("`{s}` is not a valid signature");
+
+        let (rest, signature) = s.trim().rsplit_once(" signature ").ok_or_else(invalid)?;
+        let (rest, digest) = rest.rsplit_once(" sha256 ").ok_or_else(invalid)?;
+        let (rest, timestamp) = rest.rsplit_once(" at ").ok_or_else(invalid)?;
+        let (save, signer) = rest.rsplit_once(" signed by ").ok_or_else(invalid)?;
+
+        Ok(Signature {
+            save: save
+                .parse()
+                .with_context(|| format!("`{save}` is not a valid save"))?,
+            signer: signer.to_string(),
+            timestamp: timestamp
+                .parse()
+                .with_context(|| format!("`{timestamp}` is not a valid timestamp"))?,
+            digest: digest.to_string(),
+            signature: signature.to_string(),
+        })
+    }
+}
+
+/// Something wrong with the signature of a save
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignatureProblem {
+    /// Nobody signed the save
+    Unsigned(Save),
+    /// The save was signed by a player with no public key in the roster
+    UnknownSigner(Save, String),
+    /// The signature doesn't match the signer's public key, so someone else made it
+    Forged(Save, String),
+    /// The save was signed by a player who may not upload it: neither its player nor their recorded substitute,
+    /// or for a turn start, nobody on the side that ended the turn
+    WrongSigner(Save, String),
+    /// The archive has changed since it was signed, so whoever changed it didn't sign it
+    Changed(Save, String),
+}
+
+impl SignatureProblem {
+    /// The save with the problem
+    pub fn save(&self) -> &Save {
+        match self {
+            SignatureProblem::Unsigned(save)
+            | SignatureProblem::UnknownSigner(save, _)
+            | SignatureProblem::Forged(save, _)
+            | SignatureProblem::WrongSigner(save, _)
+            | SignatureProblem::Changed(save, _) => save,
+        }
+    }
+}
+
+impl fmt::Display for SignatureProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignatureProblem::Unsigned(save) => write!(f, "{save} is not signed"),
+            SignatureProblem::UnknownSigner(save, signer) => write!(
+                f,
+                "{save} was signed by {signer}, who has no public key in the roster"
+            ),
+            SignatureProblem::Forged(save, signer) => write!(
+                f,
+                "{save} claims to be signed by {signer}, but doesn't match their public key"
+            ),
+            SignatureProblem::WrongSigner(save, signer) => match &save.player {
+                Some(player) => write!(
+                    f,
+                    "{save} was signed by {signer}, who isn't {player} or their recorded substitute"
+                ),
+                None => write!(
+                    f,
+                    "{save} was signed by {signer}, who isn't on the {} side that ended the turn",
+                    save.turn.previous().side
+                ),
+            },
+            SignatureProblem::Changed(save, signer) => {
+                write!(f, "{save} has changed since {signer} signed it")
+            }
+        }
+    }
+}
+
+/// Read your signing key from `path`, creating a new one if it doesn't exist yet
+pub fn load_or_create_signing_key(
+    path: &Path,
+    file_system: &mut dyn FileSystem,
+) -> anyhow::Result<SigningKey> {
+    match file_system.read_file_to_string(path) {
+        Ok(hex) => from_hex::<32>(hex.trim())
+            .map(|secret| SigningKey::from_bytes(&secret))
+            .ok_or_else(|| anyhow::anyhow!("'{}' is not a valid signing key", path.display())),
+        Err(e) if is_not_found_err(&e) => {
+            let mut secret = [0; 32];
+            getrandom::getrandom(&mut secret).context("failed to generate a signing key")?;
+
+            file_system
+                .write_string_to_file(&to_hex(&secret), path)
+                .with_context(|| {
+                    format!("failed to save your signing key to '{}'", path.display())
+                })?;
+
+            Ok(SigningKey::from_bytes(&secret))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// The public key for `key`, in hex, as listed in the roster
+pub fn public_key(key: &SigningKey) -> String {
+    to_hex(key.verifying_key().as_bytes())
+}
+
+/// Sign each of `uploads` as uploaded by `signer`, and record the signatures in remote storage
+pub fn sign_uploads(
+    remote: &mut dyn RemoteStorage,
+    uploads: &[Upload],
+    signer: &str,
+    key: &SigningKey,
+) -> anyhow::Result<()> {
+    let timestamp = ledger::now();
+
+    for upload in uploads {
+        let save = &upload.save;
+        let signature = Signature::new(
+            save.clone(),
+            signer,
+            timestamp,
+            upload.metadata.digest.clone(),
+            key,
+        );
+        remote
+            .record_signature(&signature)
+            .with_context(|| format!("failed to record that {signer} uploaded {save}"))?;
+    }
+    Ok(())
+}
+
+/// Check the latest signature of each of `saves` against the public keys in the roster, and against the archive in remote storage.
+///
+/// A save must be signed by its player, or by a substitute recorded as uploading it, and a turn start by a player on the side
+/// that ended the turn, if the roster lists that side. Signatures are only checked if the roster lists any public keys.
+pub fn verify_signatures(
+    remote: &mut dyn RemoteStorage,
+    saves: &[Save],
+    roster: &Roster,
+) -> anyhow::Result<Vec<SignatureProblem>> {
+    if roster.keys.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let signatures = remote.signatures()?;
+    let substitutions = remote.substitutions()?;
+    let mut problems = Vec::new();
+
+    for save in saves {
+        let Some(signature) = signatures.iter().rev().find(|s| &s.save == save) else {
+            problems.push(SignatureProblem::Unsigned(save.clone()));
+            continue;
+        };
+        let signer = signature.signer.clone();
+
+        match roster.public_key(&signer) {
+            None => problems.push(SignatureProblem::UnknownSigner(save.clone(), signer)),
+            Some(public_key) if !signature.is_signed_by(public_key) => {
+                problems.push(SignatureProblem::Forged(save.clone(), signer))
+            }
+            Some(_) if !may_sign(roster, &substitutions, save, &signer) => {
+                problems.push(SignatureProblem::WrongSigner(save.clone(), signer))
+            }
+            Some(_) => {
+                let digest = remote.metadata(save)?.map(|metadata| metadata.digest);
+                if digest.as_ref() != Some(&signature.digest) {
+                    problems.push(SignatureProblem::Changed(save.clone(), signer));
+                }
+            }
+        }
+    }
+
+    Ok(problems)
+}
+
+/// Returns true if `signer` may upload `save`: its player or their recorded substitute, or for a turn start, a player on the side that ended the turn
+fn may_sign(roster: &Roster, substitutions: &[Substitution], save: &Save, signer: &str) -> bool {
+    match &save.player {
+        Some(player) => {
+            roster.aliases.same_player(player, signer)
+                || substitutions.iter().any(|substitution| {
+                    &substitution.save == save
+                        && roster
+                            .aliases
+                            .same_player(&substitution.uploaded_by, signer)
+                })
+        }
+        None => {
+            let players = roster.players(save.turn.previous().side);
+            players.is_empty()
+                || players
+                    .iter()
+                    .any(|player| roster.aliases.same_player(player, signer))
+        }
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn from_hex<const N: usize>(hex: &str) -> Option<[u8; N]> {
+    if hex.len() != N * 2 || !hex.is_ascii() {
+        return None;
+    }
+
+    let mut bytes = [0; N];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{
+        interface::{
+            storage::memory_storage::{MemoryFiles, MemoryLocalStorage, MemoryRemoteStorage},
+            LocalStorage,
+        },
+        Side,
+    };
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn signature_round_trip() -> anyhow::Result<()> {
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let signature = Signature::new(
+            Save::from_parts(Side::Allies, 5),
+            "DM",
+            1_700_000_000,
+            "ab".repeat(32),
+            &key,
+        );
+
+        assert_eq!(signature.to_string().parse::<Signature>()?, signature);
+        assert!(signature.is_signed_by(&public_key(&key)));
+        assert!(!signature.is_signed_by(&public_key(&SigningKey::from_bytes(&[8; 32]))));
+
+        let mut tampered = signature.clone();
+        tampered.signer = "DG".to_string();
+        assert!(!tampered.is_signed_by(&public_key(&key)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn verify_uploaded_signatures() -> anyhow::Result<()> {
+        let files = MemoryFiles::new();
+        let mut local = MemoryLocalStorage::new(PathBuf::from("/local"), files.clone());
+        let mut remote = MemoryRemoteStorage::new(PathBuf::from("/remote"), files.clone());
+
+        let dm = SigningKey::from_bytes(&[1; 32]);
+        let gm = SigningKey::from_bytes(&[2; 32]);
+        let mut roster = Roster::default();
+        roster.keys.insert("DM".to_string(), public_key(&dm));
+
+        let autosave = Save::from_parts(Side::Allies, 5);
+        let ours = Save::from_parts(Side::Axis, 5).player("DM");
+        let unsigned = Save::from_parts(Side::Axis, 5).player("DG");
+
+        local.write_save(&ours, "DM's turn");
+        local.write_save(&unsigned, "DG's turn");
+        local.write_autosave("end of Axis 5");
+        for save in [&ours, &unsigned] {
+            let path = local.locate_save(save)?.expect("save was written");
+            remote.upload(save, &path)?;
+        }
+        let path = local.locate_autosave()?.expect("autosave was written");
+        remote.upload(&autosave, &path)?;
+
+        let uploaded = ledger::find_uploads(&mut remote, &[ours.clone(), autosave.clone()])?;
+        sign_uploads(&mut remote, &uploaded, "DM", &dm)?;
+
+        let saves = [ours.clone(), autosave.clone(), unsigned.clone()];
+        assert_eq!(
+            verify_signatures(&mut remote, &saves, &roster)?,
+            vec![SignatureProblem::Unsigned(unsigned.clone())]
+        );
+
+        // GM overwrites the autosave, and signs it claiming to be DM
+        files.write(&PathBuf::from("/remote/Allies 5.sav"), "GM's autosave");
+        assert_eq!(
+            verify_signatures(&mut remote, &[autosave.clone()], &roster)?,
+            vec![SignatureProblem::Changed(
+                autosave.clone(),
+                "DM".to_string()
+            )]
+        );
+
+        let uploaded = ledger::find_uploads(&mut remote, &[autosave.clone()])?;
+        sign_uploads(&mut remote, &uploaded, "DM", &gm)?;
+        assert_eq!(
+            verify_signatures(&mut remote, &[autosave.clone()], &roster)?,
+            vec![SignatureProblem::Forged(autosave.clone(), "DM".to_string())]
+        );
+
+        let uploaded = ledger::find_uploads(&mut remote, &[autosave.clone()])?;
+        sign_uploads(&mut remote, &uploaded, "GM", &gm)?;
+        assert_eq!(
+            verify_signatures(&mut remote, &[autosave.clone()], &roster)?,
+            vec![SignatureProblem::UnknownSigner(autosave, "GM".to_string())]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn verify_signers_may_upload_the_save() -> anyhow::Result<()> {
+        let files = MemoryFiles::new();
+        let mut local = MemoryLocalStorage::new(PathBuf::from("/local"), files.clone());
+        let mut remote = MemoryRemoteStorage::new(PathBuf::from("/remote"), files.clone());
+
+        let dm = SigningKey::from_bytes(&[1; 32]);
+        let dg = SigningKey::from_bytes(&[2; 32]);
+        let gm = SigningKey::from_bytes(&[3; 32]);
+        let roster = Roster {
+            axis: vec!["DM".to_string(), "DG".to_string()],
+            allies: vec!["GM".to_string()],
+            keys: [("DM", &dm), ("DG", &dg), ("GM", &gm)]
+                .into_iter()
+                .map(|(player, key)| (player.to_string(), public_key(key)))
+                .collect(),
+            ..Roster::default()
+        };
+
+        let ours = Save::from_parts(Side::Axis, 5).player("DM");
+        let autosave = Save::from_parts(Side::Allies, 5);
+        local.write_save(&ours, "DM's turn");
+        local.write_autosave("end of Axis 5");
+        let path = local.locate_save(&ours)?.expect("save was written");
+        remote.upload(&ours, &path)?;
+        let path = local.locate_autosave()?.expect("autosave was written");
+        remote.upload(&autosave, &path)?;
+        let uploaded = ledger::find_uploads(&mut remote, &[ours.clone(), autosave.clone()])?;
+
+        // GM overwrites DM's save and the turn start Axis handed over, signing both with their own valid key
+        sign_uploads(&mut remote, &uploaded, "GM", &gm)?;
+        assert_eq!(
+            verify_signatures(&mut remote, &[ours.clone(), autosave.clone()], &roster)?,
+            vec![
+                SignatureProblem::WrongSigner(ours.clone(), "GM".to_string()),
+                SignatureProblem::WrongSigner(autosave.clone(), "GM".to_string()),
+            ]
+        );
+
+        // DG substituting for DM, and ending the turn for Axis
+        sign_uploads(&mut remote, &uploaded, "DG", &dg)?;
+        assert_eq!(
+            verify_signatures(&mut remote, &[ours.clone()], &roster)?,
+            vec![SignatureProblem::WrongSigner(
+                ours.clone(),
+                "DG".to_string()
+            )]
+        );
+        remote.record_substitution(&Substitution::new(ours.clone(), "DG"))?;
+        assert_eq!(
+            verify_signatures(&mut remote, &[ours, autosave], &roster)?,
+            Vec::new()
+        );
+
+        Ok(())
+    }
+}
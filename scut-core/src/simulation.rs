@@ -0,0 +1,416 @@
+//! The simulation drives several virtual players through many turns of a game, to find bugs that only appear
+//! after a few rounds of players running scut and playing their turns in different orders.
+//!
+//! Each player has their own [`MemoryLocalStorage`], and they all share one [`MemoryRemoteStorage`].
+//! At each step a player is chosen at random to either run scut (predict, download, upload) or play their part of the turn.
+//! The random choices are made from a seed, so a failing simulation can be replayed exactly.
+//!
+//! After every step the simulation checks that:
+//! * no save is lost: every save that reached remote storage is still there, unchanged
+//! * no autosave is uploaded twice
+//! * no player's turn goes backwards
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+use anyhow::Context;
+
+use crate::{
+    download_predicted_saves,
+    interface::{
+        index::Query,
+        predict::{handoff::Handoff, AutosavePrediction, Predict},
+        storage::memory_storage::{MemoryFiles, MemoryLocalStorage, MemoryRemoteStorage},
+        LocalStorage, RemoteStorage,
+    },
+    record_synced_saves, upload_predicted_autosave, upload_predicted_saves, Save, Side, Turn,
+};
+
+/// A small, fast pseudo random number generator (splitmix64), so that simulations are reproducible from their seed
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A number from 0 up to, but not including, `n`
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+/// What a virtual player did in one step of the simulation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Run scut, downloading and uploading whatever it predicts, including the autosave if it is ready
+    Sync,
+    /// Play their part of the turn if they can, saving it and ending the turn
+    Play,
+}
+
+#[derive(Debug)]
+struct VirtualPlayer {
+    side: Side,
+    name: String,
+    location: PathBuf,
+    /// The turn predicted the last time this player ran scut
+    turn: Option<Turn>,
+    /// The handoff predicted the last time this player ran scut
+    handoff: Option<Handoff>,
+}
+
+pub struct Simulation<'a> {
+    predict: &'a dyn Predict,
+    seed: u64,
+    rng: Rng,
+    files: MemoryFiles,
+    remote: MemoryRemoteStorage,
+    players: Vec<VirtualPlayer>,
+    /// The contents of every save that has reached remote storage
+    seen: BTreeMap<Save, String>,
+    steps: usize,
+    log: Vec<String>,
+}
+
+impl<'a> Simulation<'a> {
+    /// Create a simulation with no players, where remote storage starts with the first turn start save
+    pub fn new(predict: &'a dyn Predict, seed: u64) -> Self {
+        let files = MemoryFiles::new();
+        let remote_location = PathBuf::from("/remote");
+
+        let first_turn = Save::new(Turn::new(Side::first(), 1));
+        files.write(
+            &remote_location.join(format!("{first_turn}.sav")),
+            "new game",
+        );
+
+        Simulation {
+            predict,
+            seed,
+            rng: Rng::new(seed),
+            remote: MemoryRemoteStorage::new(remote_location, files.clone()),
+            files,
+            players: Vec::new(),
+            seen: BTreeMap::new(),
+            steps: 0,
+            log: Vec::new(),
+        }
+    }
+
+    /// Builder method to add a player to a side
+    pub fn player<S>(mut self, side: Side, name: S) -> Self
+    where
+        S: Into<String>,
+    {
+        let name = name.into();
+        self.players.push(VirtualPlayer {
+            side,
+            location: PathBuf::from(format!("/players/{name}")),
+            name,
+            turn: None,
+            handoff: None,
+        });
+        self
+    }
+
+    /// Run `steps` randomly chosen steps, checking the invariants after each one
+    pub fn run(&mut self, steps: usize) -> anyhow::Result<()> {
+        for _ in 0..steps {
+            let player = self.rng.below(self.players.len());
+            let action = if self.rng.below(2) == 0 {
+                Action::Sync
+            } else {
+                Action::Play
+            };
+            self.step(player, action)?;
+        }
+        Ok(())
+    }
+
+    /// Have every player run scut twice, in turn, then check that every save a player has played is in remote storage
+    pub fn finish(&mut self) -> anyhow::Result<()> {
+        for _ in 0..2 {
+            for player in 0..self.players.len() {
+                self.step(player, Action::Sync)?;
+            }
+        }
+
+        let remote = self.remote.reconnect();
+        for player in self.players.iter() {
+            let local = MemoryLocalStorage::new(player.location.clone(), self.files.clone());
+            for save in local.index().search(&Query::new().not_player(None))? {
+                if remote.index().count(
+                    &Query::new()
+                        .turn(save.turn)
+                        .player(save.player.as_deref())
+                        .part(save.part.as_deref()),
+                )? == 0
+                {
+                    return Err(self.failure(format!(
+                        "{save} was played by {} but never uploaded",
+                        player.name
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The latest turn start save in remote storage
+    pub fn latest_turn(&self) -> anyhow::Result<Option<Turn>> {
+        Ok(self
+            .remote
+            .reconnect()
+            .index()
+            .latest(&Query::new().player(None))?
+            .map(|save| save.turn))
+    }
+
+    /// A description of every step taken so far
+    pub fn log(&self) -> &[String] {
+        &self.log
+    }
+
+    /// Have one player take an action, then check the invariants
+    pub fn step(&mut self, player: usize, action: Action) -> anyhow::Result<()> {
+        self.steps += 1;
+
+        let result = match action {
+            Action::Sync => self.sync(player),
+            Action::Play => self.play(player),
+        }
+        .and_then(|_| self.check_remote());
+
+        let seed = self.seed;
+        let steps = self.steps;
+        result.with_context(|| format!("simulation with seed {seed} failed at step {steps}"))
+    }
+
+    fn sync(&mut self, idx: usize) -> anyhow::Result<()> {
+        let mut local =
+            MemoryLocalStorage::new(self.players[idx].location.clone(), self.files.clone());
+        let mut remote = self.remote.reconnect();
+        let player = &self.players[idx];
+
+        let turn =
+            self.predict
+                .predict_turn(player.side, &player.name, false, &mut local, &mut remote)?;
+
+        if let Some(previous) = player.turn {
+            if turn < previous {
+                return Err(self.failure(format!(
+                    "{}'s turn went backwards from {previous} to {turn}",
+                    player.name
+                )));
+            }
+        }
+
+        let prediction = self.predict.predict(
+            player.side,
+            &player.name,
+            None,
+            false,
+            &mut local,
+            &mut remote,
+        )?;
+
+        let mut entry = format!("{} syncs at {turn}", player.name);
+        for save in prediction.downloads.iter() {
+            entry.push_str(&format!(", downloads {save}"));
+        }
+        for save in prediction.uploads.iter() {
+            entry.push_str(&format!(", uploads {save}"));
+        }
+
+        download_predicted_saves(&local, &mut remote, prediction.downloads.clone())?;
+        upload_predicted_saves(&mut local, &mut remote, prediction.uploads.clone())?;
+
+        let autosave = match prediction.autosave {
+            AutosavePrediction::Ready(autosave) => {
+                entry.push_str(&format!(", uploads autosave as {autosave}"));
+                upload_predicted_autosave(&mut local, &mut remote, autosave.clone())?;
+                Some(autosave)
+            }
+            AutosavePrediction::NotReady(..) => None,
+        };
+
+        record_synced_saves(
+            &mut local,
+            &mut remote,
+            &prediction.uploads,
+            &prediction.downloads,
+            autosave.as_ref(),
+        )?;
+
+        let player = &mut self.players[idx];
+        player.turn = Some(turn);
+        player.handoff = prediction.handoff;
+        self.log.push(entry);
+
+        Ok(())
+    }
+
+    fn play(&mut self, idx: usize) -> anyhow::Result<()> {
+        let mut local =
+            MemoryLocalStorage::new(self.players[idx].location.clone(), self.files.clone());
+        let player = &self.players[idx];
+
+        let Some(turn) = player.turn.filter(|turn| turn.side == player.side) else {
+            self.log.push(format!("{} waits", player.name));
+            return Ok(());
+        };
+
+        let (load, save_as) = match &player.handoff {
+            Some(Handoff::YourPart { load, save_as }) => (load.clone(), save_as.clone()),
+            Some(Handoff::WaitingOn { .. }) | Some(Handoff::Complete) => {
+                self.log.push(format!("{} waits", player.name));
+                return Ok(());
+            }
+            None => (
+                Save::new(turn),
+                Save::new(turn).player(player.name.as_str()),
+            ),
+        };
+
+        let already_played = local.locate_save(&save_as)?.is_some();
+        let can_load = local.locate_save(&load)?.is_some();
+        if already_played || !can_load {
+            self.log.push(format!("{} waits", player.name));
+            return Ok(());
+        }
+
+        local.write_save(&save_as, format!("{save_as} played from {load}"));
+        local.write_autosave(format!("{} ended by {}", turn.next(), player.name));
+
+        self.log
+            .push(format!("{} loads {load} and plays {save_as}", player.name));
+
+        Ok(())
+    }
+
+    /// Check that no save has been lost from remote storage, and that no autosave has been uploaded twice
+    fn check_remote(&mut self) -> anyhow::Result<()> {
+        let remote = self.remote.reconnect();
+
+        for (save, contents) in self.seen.iter() {
+            match remote.read(save) {
+                None => return Err(self.failure(format!("{save} was lost from remote storage"))),
+                Some(now) if &now != contents => {
+                    return Err(self.failure(format!(
+                        "{save} was overwritten in remote storage: `{contents}` became `{now}`"
+                    )))
+                }
+                Some(_) => {}
+            }
+        }
+
+        let mut autosaves = BTreeSet::new();
+        for save in remote.uploads().into_iter().filter(|s| s.player.is_none()) {
+            if !autosaves.insert(save.clone()) {
+                return Err(self.failure(format!("the autosave was uploaded twice as {save}")));
+            }
+        }
+
+        for save in remote.index().search(&Query::new())? {
+            if let Some(contents) = remote.read(&save) {
+                self.seen.entry(save).or_insert(contents);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// An error describing a broken invariant, with the last few steps to help reproduce it.
+    ///
+    /// [`step`](Simulation::step) adds the seed and step number.
+    fn failure(&self, message: String) -> anyhow::Error {
+        let recent = self
+            .log
+            .iter()
+            .skip(self.log.len().saturating_sub(10))
+            .map(|entry| format!("  {entry}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        anyhow::anyhow!("{message}\nlast steps:\n{recent}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{interface::predict::simple_predict::SimplePredict, HandoffOrder};
+
+    use super::*;
+
+    fn four_players(predict: &dyn Predict, seed: u64) -> Simulation<'_> {
+        Simulation::new(predict, seed)
+            .player(Side::Axis, "DM")
+            .player(Side::Axis, "DG")
+            .player(Side::Allies, "GM")
+            .player(Side::Allies, "TG")
+    }
+
+    #[test]
+    fn rng_is_reproducible() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        for _ in 0..100 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+        assert_ne!(Rng::new(1).next_u64(), Rng::new(2).next_u64());
+    }
+
+    #[test]
+    fn simple_predict_simulation() -> anyhow::Result<()> {
+        let predict = SimplePredict::default();
+
+        for seed in 0..4 {
+            let mut simulation = four_players(&predict, seed);
+            simulation.run(1000)?;
+            simulation.finish()?;
+
+            let latest = simulation.latest_turn()?.expect("the game has started");
+            assert!(
+                latest.number > 5,
+                "seed {seed} only reached {latest}:\n{}",
+                simulation.log().join("\n")
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn handoff_simulation() -> anyhow::Result<()> {
+        let predict = SimplePredict::default().handoff(HandoffOrder {
+            axis: vec!["DM".to_string(), "DG".to_string()],
+            allies: Vec::new(),
+        });
+
+        for seed in 0..4 {
+            let mut simulation = four_players(&predict, seed);
+            simulation.run(1000)?;
+            simulation.finish()?;
+
+            let latest = simulation.latest_turn()?.expect("the game has started");
+            assert!(
+                latest.number > 5,
+                "seed {seed} only reached {latest}:\n{}",
+                simulation.log().join("\n")
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,433 @@
+//! A snapshot captures the saves in local and remote storage, who is predicting, and what scut predicted for them.
+//!
+//! Snapshots are written in the same format as the data driven tests for [`Predict`] implementations,
+//! so a snapshot attached to a bug report can be replayed, and then added to `test_data` once the bug is fixed:
+//!
+//! ```text
+//! <side> <player>[, solo] | <Observer>
+//! Local:
+//! autosave = <save>, Ready | <reason>
+//! <local saves>
+//!
+//! Remote:
+//! <remote saves>
+//!
+//! Downloads:
+//! <downloads>
+//!
+//! Uploads:
+//! <uploads>
+//!
+//! [Handoff: play <save>, save as <save> | waiting for <save> | complete]
+//! ```
+//!
+//! Only the names of saves are captured, never their contents.
+
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use parsely::{any, combinator::pad, result_ext::*, token, ws, Lex, Parse, ParseResult};
+
+use crate::{
+    interface::{
+        index::Query,
+        predict::{handoff::Handoff, AutosavePrediction, AutosavePredictionReason, Predict},
+        storage::memory_storage::{MemoryFiles, MemoryLocalStorage, MemoryRemoteStorage},
+        LocalStorage, Prediction, RemoteStorage,
+    },
+    save::{parse_save, parse_side},
+    Save, Side,
+};
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Snapshot {
+    pub side: Side,
+    pub player: String,
+    pub playing_solo: Option<bool>,
+
+    /// Observers don't play a side, so `side` and `player` are ignored when predicting for them
+    pub observer: bool,
+
+    pub local: Vec<Save>,
+    pub remote: Vec<Save>,
+
+    /// What scut predicted when the snapshot was taken
+    pub prediction: Prediction,
+}
+
+impl Snapshot {
+    /// Capture the saves in local and remote storage, in turn order, along with the prediction made from them
+    pub fn capture(
+        side: Side,
+        player: &str,
+        playing_solo: bool,
+        observer: bool,
+        local: &dyn LocalStorage,
+        remote: &dyn RemoteStorage,
+        prediction: Prediction,
+    ) -> anyhow::Result<Self> {
+        let mut local = local.index().search(&Query::new())?;
+        local.sort();
+        let mut remote = remote.index().search(&Query::new())?;
+        remote.sort();
+
+        Ok(Snapshot {
+            side,
+            player: player.to_string(),
+            playing_solo: playing_solo.then_some(true),
+            observer,
+            local,
+            remote,
+            prediction,
+        })
+    }
+
+    /// Predict again from the captured saves, e.g. to check whether a bug has been fixed.
+    ///
+    /// The autosave is only in local storage if the captured prediction didn't say it was unavailable.
+    /// It is written after the local saves, unless the captured prediction said it was stale.
+    pub fn replay(&self, predict: &dyn Predict) -> anyhow::Result<Prediction> {
+        let files = MemoryFiles::new();
+        let mut local = MemoryLocalStorage::new(PathBuf::from("/local"), files.clone());
+
+        let (autosave_available, autosave_stale) = match &self.prediction.autosave {
+            AutosavePrediction::NotReady(_, AutosavePredictionReason::AutosaveNotAvailable) => {
+                (false, false)
+            }
+            AutosavePrediction::NotReady(_, AutosavePredictionReason::StaleAutosave(_)) => {
+                (true, true)
+            }
+            _ => (true, false),
+        };
+
+        if autosave_stale {
+            local.write_autosave("");
+        }
+        for save in self.local.iter() {
+            local.write_save(save, "");
+        }
+        if autosave_available && !autosave_stale {
+            local.write_autosave("");
+        }
+
+        let remote_location = PathBuf::from("/remote");
+        for save in self.remote.iter() {
+            files.write(&remote_location.join(format!("{save}.sav")), "");
+        }
+        let mut remote = MemoryRemoteStorage::new(remote_location, files);
+
+        predict.predict(
+            self.side,
+            self.player.as_str(),
+            None,
+            self.playing_solo.unwrap_or_default(),
+            &mut local,
+            &mut remote,
+        )
+    }
+}
+
+impl fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.observer {
+            writeln!(f, "<Observer>")?;
+        } else {
+            write!(f, "<{} {}", self.side, self.player)?;
+            if self.playing_solo.unwrap_or_default() {
+                write!(f, ", solo")?;
+            }
+            writeln!(f, ">")?;
+        }
+
+        writeln!(f, "Local:")?;
+        match &self.prediction.autosave {
+            AutosavePrediction::Ready(save) => writeln!(f, "autosave = {save}, Ready")?,
+            AutosavePrediction::NotReady(save, reason) => {
+                write!(f, "autosave = {save}, ")?;
+                match reason {
+                    AutosavePredictionReason::NewTeammateSaveAvailable(save)
+                    | AutosavePredictionReason::TurnNotPlayed(save)
+                    | AutosavePredictionReason::HandoffNotFinished(save)
+                    | AutosavePredictionReason::StaleAutosave(save) => {
+                        writeln!(f, "{}({save})", reason.name())?
+                    }
+                    reason => writeln!(f, "{}", reason.name())?,
+                }
+            }
+        }
+
+        let sections = [
+            (None, &self.local),
+            (Some("Remote:"), &self.remote),
+            (Some("Downloads:"), &self.prediction.downloads),
+            (Some("Uploads:"), &self.prediction.uploads),
+        ];
+        for (heading, saves) in sections {
+            if let Some(heading) = heading {
+                writeln!(f, "\n{heading}")?;
+            }
+            for save in saves {
+                writeln!(f, "{save}")?;
+            }
+        }
+
+        if let Some(handoff) = &self.prediction.handoff {
+            writeln!(f, "\nHandoff: {handoff}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Snapshot {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (snapshot, _) = parse_snapshot.pad().then_end().parse(s).own_err()?;
+
+        Ok(snapshot)
+    }
+}
+
+/// Parse every snapshot in `input`, separated by blank lines
+pub fn parse_snapshots(input: &str) -> anyhow::Result<Vec<Snapshot>> {
+    let (snapshots, _) = parse_snapshot
+        .pad()
+        .many(1..9999)
+        .then_end()
+        .parse(input)
+        .own_err()?;
+
+    Ok(snapshots)
+}
+
+/// Parse a single snapshot, in the format described in the [module documentation](self)
+pub fn parse_snapshot(input: &str) -> ParseResult<Snapshot> {
+    let test_side_player_solo_marker = pad(
+        "<",
+        ">",
+        parse_side
+            .then_skip(ws())
+            .then(
+                any()
+                    .many(1..)
+                    .or_until(token(">").or(token(", ")))
+                    .map(|s| String::from(s)),
+            )
+            .then(", solo".map(|_| true).optional()),
+    )
+    .map(|((side, player), playing_solo)| (side, player, playing_solo, false));
+    // observers don't play a side, so the side and player are ignored when predicting for them
+    let test_observer_marker = token("<Observer>").map(|_| (Side::Axis, String::new(), None, true));
+
+    let ((side, player, playing_solo, observer), remaining) = test_observer_marker
+        .or(test_side_player_solo_marker)
+        .then_skip(ws().optional())
+        .parse(input)?;
+
+    let autosave_prediction_reason = "AutosaveAlreadyUploaded"
+        .map(|_| AutosavePredictionReason::AutosaveAlreadyUploaded)
+        .or("TeammateSaveNotUploaded".map(|_| AutosavePredictionReason::TeammateSaveNotUploaded))
+        .or("NewTeammateSaveAvailable"
+            .skip_then(parse_save.pad_with('(', ')'))
+            .map(AutosavePredictionReason::NewTeammateSaveAvailable))
+        .or("TurnNotPlayed"
+            .skip_then(parse_save.pad_with('(', ')'))
+            .map(AutosavePredictionReason::TurnNotPlayed))
+        .or("AutosaveNotAvailable".map(|_| AutosavePredictionReason::AutosaveNotAvailable))
+        .or("Observing".map(|_| AutosavePredictionReason::Observing))
+        .or("HandoffNotFinished"
+            .skip_then(parse_save.pad_with('(', ')'))
+            .map(AutosavePredictionReason::HandoffNotFinished))
+        .or("StaleAutosave"
+            .skip_then(parse_save.pad_with('(', ')'))
+            .map(AutosavePredictionReason::StaleAutosave));
+
+    let comma = || token(",").then(ws().optional());
+
+    let autosave_prediction = parse_save
+        .then(comma().skip_then(autosave_prediction_reason))
+        .map(|(save, reason)| AutosavePrediction::NotReady(save, reason))
+        .or(parse_save
+            .then_skip(comma().then(token("Ready")))
+            .map(AutosavePrediction::Ready));
+
+    let (autosave, remaining) = token("Local:")
+        .then(ws().many(..5))
+        .then(token("autosave = "))
+        .skip_then(autosave_prediction)
+        .parse(remaining)?;
+
+    let parse_saves = || {
+        ws().optional()
+            .skip_then(parse_save.then_skip(ws()).many(..9999))
+            .then_skip(ws().optional())
+    };
+
+    let (local, rem) = parse_saves().parse(remaining)?;
+    let (remote, rem) = token("Remote:").skip_then(parse_saves()).parse(rem)?;
+    let (downloads, rem) = token("Downloads:").skip_then(parse_saves()).parse(rem)?;
+    let (uploads, rem) = token("Uploads:").skip_then(parse_saves()).parse(rem)?;
+
+    let handoff = "play "
+        .skip_then(parse_save)
+        .then_skip(token(", save as "))
+        .then(parse_save)
+        .map(|(load, save_as)| Handoff::YourPart { load, save_as })
+        .or("waiting for "
+            .skip_then(parse_save)
+            .map(|save| Handoff::WaitingOn { save }))
+        .or("complete".map(|_| Handoff::Complete));
+
+    let (handoff, rem) = token("Handoff:")
+        .then(ws().optional())
+        .skip_then(handoff)
+        .then_skip(ws().optional())
+        .optional()
+        .parse(rem)?;
+
+    Ok((
+        Snapshot {
+            side,
+            player,
+            playing_solo,
+            observer,
+            local,
+            remote,
+            prediction: Prediction {
+                autosave,
+                uploads,
+                downloads,
+                handoff,
+                warnings: Vec::new(),
+            },
+        },
+        rem,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interface::{
+        predict::simple_predict::SimplePredict, storage::mock_index_storage::MockIndexStorage,
+    };
+
+    use super::*;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn snapshot_round_trip() -> anyhow::Result<()> {
+        let local = MockIndexStorage::new(
+            true,
+            vec![
+                Save::from_parts(Side::Axis, 6),
+                Save::from_parts(Side::Axis, 6).player("DM").part("A"),
+            ],
+        );
+        let remote = MockIndexStorage::new(
+            true,
+            vec![
+                Save::from_parts(Side::Axis, 6),
+                Save::from_parts(Side::Axis, 6).player("DG"),
+            ],
+        );
+        let prediction = Prediction {
+            autosave: AutosavePrediction::NotReady(
+                Save::from_parts(Side::Allies, 6),
+                AutosavePredictionReason::NewTeammateSaveAvailable(
+                    Save::from_parts(Side::Axis, 6).player("DG"),
+                ),
+            ),
+            uploads: vec![Save::from_parts(Side::Axis, 6).player("DM").part("A")],
+            downloads: vec![Save::from_parts(Side::Axis, 6).player("DG")],
+            handoff: Some(Handoff::Complete),
+            warnings: Vec::new(),
+        };
+
+        let snapshot =
+            Snapshot::capture(Side::Axis, "DM", true, false, &local, &remote, prediction)?;
+
+        let text = snapshot.to_string();
+        assert_eq!(
+            text,
+            "<Axis DM, solo>\n\
+            Local:\n\
+            autosave = Allies 6, NewTeammateSaveAvailable(Axis DG 6)\n\
+            Axis DM 6A\n\
+            Axis 6\n\
+            \n\
+            Remote:\n\
+            Axis DG 6\n\
+            Axis 6\n\
+            \n\
+            Downloads:\n\
+            Axis DG 6\n\
+            \n\
+            Uploads:\n\
+            Axis DM 6A\n\
+            \n\
+            Handoff: complete\n"
+        );
+        assert_eq!(text.parse::<Snapshot>()?, snapshot);
+
+        Ok(())
+    }
+
+    #[test]
+    fn replay_predicts_again() -> anyhow::Result<()> {
+        let snapshot: Snapshot = indoc! {"
+            <Allies GM>
+            Local:
+            autosave = Axis 2, TurnNotPlayed(Allies GM 1)
+
+            Remote:
+            Axis 1
+            Axis DM 1
+            Axis DG 1
+            Allies 1
+
+            Downloads:
+            Allies 1
+
+            Uploads:
+        "}
+        .parse()?;
+
+        assert_eq!(
+            snapshot.replay(&SimplePredict::default())?,
+            snapshot.prediction
+        );
+
+        Ok(())
+    }
+    #[test]
+    fn replay_stale_autosave() -> anyhow::Result<()> {
+        let snapshot: Snapshot = indoc! {"
+            <Axis DM>
+            Local:
+            autosave = Allies 2, StaleAutosave(Axis DM 2)
+            Axis 2
+            Axis DM 2
+
+            Remote:
+            Axis 2
+            Axis DM 2
+            Axis DG 2
+
+            Downloads:
+            Axis DG 2
+
+            Uploads:
+        "}
+        .parse()?;
+
+        assert_eq!(
+            snapshot.replay(&SimplePredict::default())?,
+            snapshot.prediction
+        );
+
+        Ok(())
+    }
+}
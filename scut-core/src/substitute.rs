@@ -0,0 +1,92 @@
+//! Substitutes play on behalf of a teammate, e.g. while they are on holiday.
+//!
+//! A substitute's saves are signed by the player they are covering for, so that predictions carry on as normal.
+//! Remote storage keeps a record of each save uploaded by a substitute, so it is always known who actually uploaded it.
+
+use std::{fmt, str::FromStr};
+
+use anyhow::Context;
+
+use crate::{interface::RemoteStorage, Save};
+
+/// A record that a save was uploaded by someone other than the player who signed it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Substitution {
+    /// The save that was uploaded
+    pub save: Save,
+
+    /// The player who actually uploaded the save
+    pub uploaded_by: String,
+}
+
+impl Substitution {
+    pub fn new<S>(save: Save, uploaded_by: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Substitution {
+            save,
+            uploaded_by: uploaded_by.into(),
+        }
+    }
+}
+
+impl fmt::Display for Substitution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} uploaded by {}", self.save, self.uploaded_by)
+    }
+}
+
+impl FromStr for Substitution {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (save, uploaded_by) = s
+            .trim()
+            .rsplit_once(" uploaded by ")
+            .ok_or_else(|| anyhow::anyhow!("`{s}` is not a valid substitution"))?;
+
+        let save = save
+            .parse()
+            .with_context(|| format!("`{save}` is not a valid save"))?;
+
+        Ok(Substitution::new(save, uploaded_by))
+    }
+}
+
+/// Record that each of `saves` was uploaded by `uploaded_by`, a substitute
+pub fn record_substitute_uploads(
+    remote: &mut dyn RemoteStorage,
+    saves: &[Save],
+    uploaded_by: &str,
+) -> anyhow::Result<()> {
+    for save in saves {
+        remote
+            .record_substitution(&Substitution::new(save.clone(), uploaded_by))
+            .with_context(|| format!("failed to record that {uploaded_by} uploaded {save}"))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Side;
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn substitution_round_trip() -> anyhow::Result<()> {
+        let substitution =
+            Substitution::new(Save::from_parts(Side::Axis, 6).player("DG").part("A"), "DM");
+
+        assert_eq!(substitution.to_string(), "Axis DG 6A uploaded by DM");
+        assert_eq!(
+            "Axis DG 6A uploaded by DM".parse::<Substitution>()?,
+            substitution
+        );
+        assert!("Axis DG 6A".parse::<Substitution>().is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,117 @@
+//! Overwriting a save in remote storage, e.g. re-uploading an autosave, keeps the previous copy as a version of the save,
+//! so that an upload made by mistake can be undone.
+//!
+//! Versions are kept in a `versions` subfolder, with a folder for each save, named by when they were overwritten:
+//!
+//! ```text
+//! versions/Allies 5/1700000000.7z
+//! ```
+//!
+//! Restoring a version keeps the copy it replaces as a version too, so nothing is lost.
+
+use std::{fmt, path::Path};
+
+use crate::{
+    interface::RemoteStorage,
+    ledger::{find_uploads, record_uploads},
+    signature::{sign_uploads, SigningKey},
+    Save,
+};
+
+/// A previous copy of a save, kept when the save was overwritten
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Version {
+    /// The save that was overwritten
+    pub save: Save,
+
+    /// When the save was overwritten, in seconds since the unix epoch. This identifies the version.
+    pub timestamp: u64,
+
+    /// The size of this copy of the save in remote storage, in bytes
+    pub size: u64,
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} version {} ({} bytes)",
+            self.save, self.timestamp, self.size
+        )
+    }
+}
+
+/// The timestamp of a version, from the name of its file
+pub fn version_timestamp(path: &Path) -> Option<u64> {
+    path.file_stem()?.to_str()?.parse().ok()
+}
+
+/// Put the version of `save` overwritten at `timestamp` back in place of the current copy,
+/// then record the restore in the ledger and sign it as an upload by `restored_by`
+pub fn restore_version(
+    remote: &mut dyn RemoteStorage,
+    save: &Save,
+    timestamp: u64,
+    restored_by: &str,
+    key: &SigningKey,
+) -> anyhow::Result<()> {
+    remote.restore(save, timestamp)?;
+
+    let restored = find_uploads(remote, &[save.clone()])?;
+    record_uploads(remote, &restored, restored_by)?;
+    sign_uploads(remote, &restored, restored_by, key)?;
+
+    remote.publish()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{
+        interface::{
+            storage::memory_storage::{MemoryFiles, MemoryLocalStorage, MemoryRemoteStorage},
+            LocalStorage,
+        },
+        Side,
+    };
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn overwriting_keeps_the_previous_version() -> anyhow::Result<()> {
+        let files = MemoryFiles::new();
+        let mut local = MemoryLocalStorage::new(PathBuf::from("/local"), files.clone());
+        let mut remote = MemoryRemoteStorage::new(PathBuf::from("/remote"), files.clone());
+        let key = SigningKey::from_bytes(&[7; 32]);
+
+        let turn_start = Save::from_parts(Side::Allies, 5);
+
+        local.write_autosave("end of Axis 5");
+        let path = local.locate_autosave()?.expect("autosave was written");
+        remote.upload(&turn_start, &path)?;
+        assert_eq!(remote.versions(&turn_start)?, vec![]);
+
+        // the autosave is overwritten by mistake
+        local.write_autosave("end of Axis 4");
+        remote.upload(&turn_start, &path)?;
+        assert_eq!(remote.read(&turn_start).as_deref(), Some("end of Axis 4"));
+
+        let versions = remote.versions(&turn_start)?;
+        assert_eq!(versions.len(), 1);
+        assert_eq!(versions[0].size, "end of Axis 5".len() as u64);
+
+        restore_version(&mut remote, &turn_start, versions[0].timestamp, "DM", &key)?;
+        assert_eq!(remote.read(&turn_start).as_deref(), Some("end of Axis 5"));
+
+        // the mistaken upload is kept as a version in its place
+        let versions = remote.versions(&turn_start)?;
+        assert_eq!(versions.len(), 1);
+        assert_eq!(versions[0].size, "end of Axis 4".len() as u64);
+
+        assert_eq!(remote.ledger()?.len(), 1);
+
+        Ok(())
+    }
+}
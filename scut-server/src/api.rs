@@ -0,0 +1,462 @@
+//! The REST API that [`HttpRemote`](scut_core::interface::storage::http_remote::HttpRemote) talks to, see there for the routes.
+//!
+//! Requests are handled one at a time, so two players uploading at once can't interleave their changes to a game.
+//!
+//! Uploads are recorded in the ledger by the server when it receives them, rather than by the player, so that nobody can make up an upload.
+
+use std::io::Read;
+use std::path::Path;
+
+use percent_encoding::percent_decode_str;
+use tiny_http::{Header, Response, Server};
+use tracing::{error, info, warn};
+
+use scut_core::{
+    interface::storage::http_remote::{LEDGER_RECORD, SIGNATURES_RECORD, SUBSTITUTIONS_RECORD},
+    signature::Signature,
+    substitute::Substitution,
+    Save,
+};
+
+use crate::config::{Game, ServerConfig};
+use crate::game::{check_record, check_upload, GameFolder};
+
+/// The status and body of a response
+#[derive(Debug, PartialEq, Eq)]
+pub struct Reply {
+    pub status: u16,
+    pub body: Vec<u8>,
+}
+
+impl Reply {
+    fn ok(body: impl Into<Vec<u8>>) -> Self {
+        Reply {
+            status: 200,
+            body: body.into(),
+        }
+    }
+
+    /// A response explaining why the request was refused
+    fn refuse(status: u16, reason: impl ToString) -> Self {
+        Reply {
+            status,
+            body: reason.to_string().into_bytes(),
+        }
+    }
+
+    fn not_found() -> Self {
+        Reply::refuse(404, "not found")
+    }
+}
+
+/// Listen on the address in `config`, serving its games until the process is stopped
+pub fn serve(config: &ServerConfig) -> anyhow::Result<()> {
+    let server = Server::http(&config.address)
+        .map_err(|error| anyhow::anyhow!("failed to listen on {}: {error}", config.address))?;
+    info!(address = %config.address, "serving {} games", config.games.len());
+
+    answer(&server, config);
+    Ok(())
+}
+
+/// Answer the requests that `server` receives, until it is unblocked
+pub fn answer(server: &Server, config: &ServerConfig) {
+    for mut request in server.incoming_requests() {
+        let token = request
+            .headers()
+            .iter()
+            .find(|header| header.field.equiv("Authorization"))
+            .and_then(|header| header.value.as_str().strip_prefix("Bearer "))
+            .map(String::from);
+
+        let mut body = Vec::new();
+        let reply = match request.as_reader().read_to_end(&mut body) {
+            Ok(_) => handle(
+                config,
+                request.method().as_str(),
+                request.url(),
+                token.as_deref(),
+                &body,
+            ),
+            Err(error) => Reply::refuse(400, format!("failed to read the request: {error}")),
+        };
+
+        info!(
+            method = %request.method(),
+            url = request.url(),
+            status = reply.status,
+            "handled request"
+        );
+
+        let response = Response::from_data(reply.body)
+            .with_status_code(reply.status)
+            .with_header(
+                "Content-Type: text/plain; charset=utf-8"
+                    .parse::<Header>()
+                    .expect("valid header"),
+            );
+        if let Err(error) = request.respond(response) {
+            warn!(%error, "failed to respond");
+        }
+    }
+}
+
+/// Answer one request, made with `token` if it has one
+pub fn handle(
+    config: &ServerConfig,
+    method: &str,
+    url: &str,
+    token: Option<&str>,
+    body: &[u8],
+) -> Reply {
+    let path = url.split('?').next().unwrap_or_default();
+    let segments: Vec<String> = path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| percent_decode_str(segment).decode_utf8_lossy().into_owned())
+        .collect();
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+
+    let ["games", name, route @ ..] = segments.as_slice() else {
+        return Reply::not_found();
+    };
+    let Some(game) = config.games.get(*name) else {
+        return Reply::refuse(
+            404,
+            format!("there is no game called {name} on this server"),
+        );
+    };
+    let Some(player) = token.and_then(|token| game.player(token)) else {
+        return Reply::refuse(401, format!("that token isn't for a player in {name}"));
+    };
+
+    let mut folder = match GameFolder::open(&config.data, name) {
+        Ok(folder) => folder,
+        Err(error) => return internal_error(error),
+    };
+
+    match route_request(game, &mut folder, player, method, route, body) {
+        Ok(reply) => reply,
+        Err(error) => internal_error(error),
+    }
+}
+
+fn route_request(
+    game: &Game,
+    folder: &mut GameFolder,
+    player: &str,
+    method: &str,
+    route: &[&str],
+    body: &[u8],
+) -> anyhow::Result<Reply> {
+    Ok(match (method, route) {
+        ("GET", ["saves"]) => Reply::ok(
+            folder
+                .saves()?
+                .into_iter()
+                .map(|(size, name)| format!("{size} {name}\n"))
+                .collect::<String>(),
+        ),
+        ("GET", ["saves", name]) => match file_name(name) {
+            Some(name) => folder
+                .read_save(name)?
+                .map_or_else(Reply::not_found, Reply::ok),
+            None => Reply::not_found(),
+        },
+        ("PUT", ["saves", name]) => {
+            let Some((name, save)) = file_name(name).and_then(|name| {
+                Save::try_from(Path::new(name))
+                    .ok()
+                    .map(|save| (name, save))
+            }) else {
+                return Ok(Reply::refuse(
+                    422,
+                    format!("{name} isn't the name of a save"),
+                ));
+            };
+            if let Err(reason) = check_upload(&game.roster, &save, player) {
+                return Ok(Reply::refuse(422, reason));
+            }
+
+            folder.upload(&save, name, body)?;
+            folder.record_upload(&save, player)?;
+            info!(player, %save, "uploaded");
+            Reply::ok("")
+        }
+        ("POST", ["saves", name, "supersede"]) => {
+            let Some(save) = file_name(name).and_then(|name| Save::try_from(Path::new(name)).ok())
+            else {
+                return Ok(Reply::not_found());
+            };
+            if let Err(reason) = check_upload(&game.roster, &save, player) {
+                return Ok(Reply::refuse(422, reason));
+            }
+
+            if folder.supersede(&save)? {
+                Reply::ok("")
+            } else {
+                Reply::not_found()
+            }
+        }
+        ("GET", ["saves", save, "versions"]) => {
+            let Ok(save) = save.parse::<Save>() else {
+                return Ok(Reply::not_found());
+            };
+            Reply::ok(
+                folder
+                    .versions(&save)?
+                    .into_iter()
+                    .map(|(timestamp, size)| format!("{timestamp} {size}\n"))
+                    .collect::<String>(),
+            )
+        }
+        ("POST", ["saves", save, "versions", timestamp, "restore"]) => {
+            let (Ok(save), Ok(timestamp)) = (save.parse::<Save>(), timestamp.parse()) else {
+                return Ok(Reply::not_found());
+            };
+            if let Err(reason) = check_upload(&game.roster, &save, player) {
+                return Ok(Reply::refuse(422, reason));
+            }
+
+            if folder.restore(&save, timestamp)? {
+                folder.record_upload(&save, player)?;
+                Reply::ok("")
+            } else {
+                Reply::not_found()
+            }
+        }
+        ("GET", ["records", record]) => Reply::ok(match *record {
+            SUBSTITUTIONS_RECORD => lines(folder.substitutions()?),
+            SIGNATURES_RECORD => lines(folder.signatures()?),
+            LEDGER_RECORD => lines(folder.ledger()?),
+            _ => return Ok(Reply::not_found()),
+        }),
+        ("POST", ["records", LEDGER_RECORD]) => Reply::refuse(
+            405,
+            "the server records uploads in the ledger itself, when it receives them",
+        ),
+        ("POST", ["records", record]) => {
+            let line = String::from_utf8_lossy(body);
+            let line = line.trim();
+
+            // each record names who made it, which must be the player sending it
+            let checked = match *record {
+                SUBSTITUTIONS_RECORD => line.parse::<Substitution>().and_then(|substitution| {
+                    check_record(&game.roster, &substitution.uploaded_by, player)
+                }),
+                SIGNATURES_RECORD => line
+                    .parse::<Signature>()
+                    .and_then(|signature| check_record(&game.roster, &signature.signer, player)),
+                _ => return Ok(Reply::not_found()),
+            };
+            if let Err(reason) = checked {
+                return Ok(Reply::refuse(422, reason));
+            }
+
+            match *record {
+                SUBSTITUTIONS_RECORD => folder.record_substitution(&line.parse()?)?,
+                _ => folder.record_signature(&line.parse()?)?,
+            }
+            Reply::ok("")
+        }
+        _ => Reply::not_found(),
+    })
+}
+
+/// Records one per line, the way they are sent
+fn lines<T: std::fmt::Display>(records: Vec<T>) -> String {
+    records.iter().map(|record| format!("{record}\n")).collect()
+}
+
+/// `segment` if it is the name of a file in the game's folder, rather than a path out of it or a hidden file
+fn file_name(segment: &str) -> Option<&str> {
+    let hidden = segment.is_empty() || segment.starts_with('.');
+    let nested = segment.contains(['/', '\\']);
+    (!hidden && !nested).then_some(segment)
+}
+
+fn internal_error(error: anyhow::Error) -> Reply {
+    error!("{error:#}");
+    Reply::refuse(500, "the server failed to handle the request, see its logs")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use scut_core::Roster;
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn config(data: &std::path::Path) -> ServerConfig {
+        let game = Game {
+            roster: Roster {
+                axis: vec!["DM".to_string(), "DG".to_string()],
+                allies: vec!["GM".to_string()],
+                ..Roster::default()
+            },
+            tokens: BTreeMap::from([
+                ("DM".to_string(), "dm-token".to_string()),
+                ("GM".to_string(), "gm-token".to_string()),
+            ]),
+        };
+
+        ServerConfig {
+            address: String::new(),
+            data: data.to_path_buf(),
+            games: BTreeMap::from([("ww1".to_string(), game)]),
+        }
+    }
+
+    #[test]
+    fn uploads_are_checked_against_the_roster() -> anyhow::Result<()> {
+        let data = tempfile::tempdir()?;
+        let config = config(data.path());
+        let put = |url: &str, token: &str| handle(&config, "PUT", url, Some(token), b"archive");
+
+        assert_eq!(
+            put("/games/ww1/saves/Axis%20DM%2012.7z", "dm-token").status,
+            200
+        );
+        assert_eq!(
+            put("/games/ww1/saves/Axis%20XX%2012.7z", "dm-token").status,
+            422
+        );
+        assert_eq!(
+            put("/games/ww1/saves/Axis%20DM%2012.7z", "gm-token").status,
+            422
+        );
+        assert_eq!(
+            put("/games/ww1/saves/Axis%20DM%2012.7z", "nobody").status,
+            401
+        );
+        assert_eq!(
+            put("/games/ww2/saves/Axis%20DM%2012.7z", "dm-token").status,
+            404
+        );
+        assert_eq!(
+            put("/games/ww1/saves/..%2Fescape.7z", "dm-token").status,
+            422
+        );
+
+        let listing = handle(&config, "GET", "/games/ww1/saves", Some("gm-token"), b"");
+        assert_eq!(listing, Reply::ok("7 Axis DM 12.7z\n"));
+
+        // the other side can't supersede it either
+        let supersede = |token: &str| {
+            handle(
+                &config,
+                "POST",
+                "/games/ww1/saves/Axis%20DM%2012.7z/supersede",
+                Some(token),
+                b"",
+            )
+        };
+        assert_eq!(supersede("gm-token").status, 422);
+        assert_eq!(supersede("dm-token").status, 200);
+
+        // Axis ended turn 12, so only they can hand over, supersede or restore the start of Allies 12
+        assert_eq!(
+            put("/games/ww1/saves/Allies%2012.7z", "gm-token").status,
+            422
+        );
+        assert_eq!(
+            put("/games/ww1/saves/Allies%2012.7z", "dm-token").status,
+            200
+        );
+        assert_eq!(
+            put("/games/ww1/saves/Allies%2012.7z", "dm-token").status,
+            200
+        );
+        let turn_start = |method: &str, url: &str, token: &str| {
+            handle(&config, method, url, Some(token), b"").status
+        };
+        let versions = handle(
+            &config,
+            "GET",
+            "/games/ww1/saves/Allies%2012/versions",
+            Some("dm-token"),
+            b"",
+        );
+        let version = String::from_utf8_lossy(&versions.body)
+            .split_whitespace()
+            .next()
+            .expect("the first upload was kept as a version")
+            .to_string();
+        let restore = format!("/games/ww1/saves/Allies%2012/versions/{version}/restore");
+        assert_eq!(turn_start("POST", &restore, "gm-token"), 422);
+        assert_eq!(turn_start("POST", &restore, "dm-token"), 200);
+        let supersede = "/games/ww1/saves/Allies%2012.7z/supersede";
+        assert_eq!(turn_start("POST", supersede, "gm-token"), 422);
+        assert_eq!(turn_start("POST", supersede, "dm-token"), 200);
+
+        Ok(())
+    }
+
+    #[test]
+    fn records_are_made_in_your_own_name() -> anyhow::Result<()> {
+        let data = tempfile::tempdir()?;
+        let config = config(data.path());
+        let post = |record: &str, token: &str| {
+            handle(
+                &config,
+                "POST",
+                "/games/ww1/records/substitutions",
+                Some(token),
+                record.as_bytes(),
+            )
+        };
+
+        assert_eq!(post("Axis DG 12 uploaded by DM", "dm-token").status, 200);
+        assert_eq!(post("Axis DG 12 uploaded by DG", "dm-token").status, 422);
+
+        let records = handle(
+            &config,
+            "GET",
+            "/games/ww1/records/substitutions",
+            Some("gm-token"),
+            b"",
+        );
+        assert_eq!(records, Reply::ok("Axis DG 12 uploaded by DM\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn the_ledger_is_recorded_by_the_server() -> anyhow::Result<()> {
+        let data = tempfile::tempdir()?;
+        let config = config(data.path());
+
+        let made_up = "Axis DM 12 uploaded by DM at 1700000000 size 7 sha256 0 scut 0.4.5";
+        let post = handle(
+            &config,
+            "POST",
+            "/games/ww1/records/ledger",
+            Some("dm-token"),
+            made_up.as_bytes(),
+        );
+        assert_eq!(post.status, 405);
+
+        // DG isn't given a token in this game, so DM uploads for them
+        handle(
+            &config,
+            "PUT",
+            "/games/ww1/saves/Axis%20DG%2012.7z",
+            Some("dm-token"),
+            b"archive",
+        );
+        let ledger = handle(
+            &config,
+            "GET",
+            "/games/ww1/records/ledger",
+            Some("gm-token"),
+            b"",
+        );
+        let ledger = String::from_utf8(ledger.body)?;
+        assert_eq!(ledger.lines().count(), 1);
+        assert!(ledger.starts_with("Axis DG 12 uploaded by DM at "));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,72 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use serde::Deserialize;
+
+use scut_core::Roster;
+
+/// The server's config file, listing each game that it serves and who plays in it.
+///
+/// ```toml
+/// address = "0.0.0.0:8080"
+/// data = "/srv/scut"
+///
+/// [games.ww1.roster]
+/// axis = ["DM", "DG"]
+/// allies = ["GM", "TG"]
+///
+/// [games.ww1.tokens]
+/// DM = "9f86d081884c7d659a2feaa0c55ad015"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct ServerConfig {
+    /// Where to listen for requests
+    #[serde(default = "default_address")]
+    pub address: String,
+
+    /// The folder that each game's saves and records are kept in, one subfolder per game
+    pub data: PathBuf,
+
+    #[serde(default)]
+    pub games: BTreeMap<String, Game>,
+}
+
+/// A game served by the server, at `/games/<name>/`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Game {
+    /// Uploaded saves must be signed by players on the roster, see [`check_upload`](crate::game::check_upload)
+    #[serde(default)]
+    pub roster: Roster,
+
+    /// The token each player logs in with, by player
+    #[serde(default)]
+    pub tokens: BTreeMap<String, String>,
+}
+
+impl ServerConfig {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("failed to read the config at '{}'", path.display()))?;
+
+        toml::from_str(&content)
+            .with_context(|| format!("failed to parse the config at '{}'", path.display()))
+    }
+}
+
+impl Game {
+    /// The player who logs in with `token`
+    pub fn player(&self, token: &str) -> Option<&str> {
+        self.tokens
+            .iter()
+            .find(|(_, player_token)| player_token.as_str() == token)
+            .map(|(player, _)| player.as_str())
+    }
+}
+
+fn default_address() -> String {
+    String::from("127.0.0.1:8080")
+}
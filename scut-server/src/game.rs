@@ -0,0 +1,327 @@
+//! Each game's saves and records are kept in a folder on disk, laid out the way scut lays out any folder of saves,
+//! see [`object_store`](scut_core::interface::storage::object_store),
+//! so that the admin can look through them, or hand them to scut as a `folder` if the server is retired.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+
+use scut_core::{
+    interface::{
+        file_system::FileMetadata,
+        storage::object_store::{self, Object, ObjectStore},
+    },
+    ledger::{self, LedgerEntry, SCUT_VERSION},
+    signature::Signature,
+    substitute::Substitution,
+    Roster, Save, Side,
+};
+
+/// The folder on disk that one game's saves and records are kept in
+pub struct GameFolder {
+    location: PathBuf,
+}
+
+impl GameFolder {
+    /// The folder for the game called `name`, created if it doesn't exist yet
+    pub fn open(data: &Path, name: &str) -> anyhow::Result<Self> {
+        let location = data.join(name);
+        fs::create_dir_all(&location)
+            .with_context(|| format!("failed to create '{}'", location.display()))?;
+        Ok(GameFolder { location })
+    }
+
+    /// The size and name of each save in the game
+    pub fn saves(&mut self) -> anyhow::Result<Vec<(u64, String)>> {
+        Ok(object_store::saves(self)?
+            .unwrap_or_default()
+            .into_values()
+            .map(|object| (object.size, object.name))
+            .collect())
+    }
+
+    /// The archive called `name`, if it exists
+    pub fn read_save(&mut self, name: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        self.get(name)
+    }
+
+    /// Store the archive of `save` as `name`, keeping the copy it replaces as a version
+    ///
+    /// The archive is written beside the save and then renamed into place, so nobody ever downloads half an upload.
+    pub fn upload(&mut self, save: &Save, name: &str, content: &[u8]) -> anyhow::Result<()> {
+        let uploading = format!(".{name}.uploading");
+        self.put(&uploading, content.to_vec())?;
+
+        if let Some(current) = self.current(save)? {
+            object_store::keep_version(self, save, &current)?;
+        }
+
+        self.rename(&uploading, name)
+            .with_context(|| format!("failed to upload {save}"))
+    }
+
+    /// Move the archive of `save` into the superseded folder, returning false if it doesn't exist
+    pub fn supersede(&mut self, save: &Save) -> anyhow::Result<bool> {
+        let Some(current) = self.current(save)? else {
+            return Ok(false);
+        };
+
+        object_store::supersede(self, save, &current)?;
+        Ok(true)
+    }
+
+    /// The timestamp and size of each version of `save`, oldest first
+    pub fn versions(&mut self, save: &Save) -> anyhow::Result<Vec<(u64, u64)>> {
+        Ok(object_store::versions(self, save)?
+            .into_iter()
+            .map(|version| (version.timestamp, version.size))
+            .collect())
+    }
+
+    /// Put the version of `save` kept at `timestamp` back in place, returning false if there is no such version
+    pub fn restore(&mut self, save: &Save, timestamp: u64) -> anyhow::Result<bool> {
+        let current = self.current(save)?;
+        object_store::restore(self, save, timestamp, current.as_deref())
+    }
+
+    /// Every substitution, oldest first
+    pub fn substitutions(&mut self) -> anyhow::Result<Vec<Substitution>> {
+        object_store::substitutions(self)
+    }
+
+    pub fn record_substitution(&mut self, substitution: &Substitution) -> anyhow::Result<()> {
+        object_store::record_substitution(self, substitution)
+    }
+
+    /// Every signature, oldest first
+    pub fn signatures(&mut self) -> anyhow::Result<Vec<Signature>> {
+        object_store::signatures(self)
+    }
+
+    pub fn record_signature(&mut self, signature: &Signature) -> anyhow::Result<()> {
+        object_store::record_signature(self, signature)
+    }
+
+    /// Every segment of the ledger merged together, oldest upload first
+    pub fn ledger(&mut self) -> anyhow::Result<Vec<LedgerEntry>> {
+        object_store::ledger(self)
+    }
+
+    /// Record in `uploader`'s segment of the ledger that they uploaded the current archive of `save`,
+    /// sized and digested as it is stored here, at the time by the server's clock.
+    ///
+    /// The ledger records the server's version of scut, which is what recorded the upload.
+    pub fn record_upload(&mut self, save: &Save, uploader: &str) -> anyhow::Result<()> {
+        let content = match self.current(save)? {
+            Some(name) => self.get(&name)?,
+            None => None,
+        }
+        .ok_or_else(|| anyhow::anyhow!("failed to record the upload of {save}, it isn't here"))?;
+        let metadata = FileMetadata::new(&content, None);
+
+        let entry = LedgerEntry {
+            save: save.clone(),
+            uploader: uploader.to_string(),
+            timestamp: ledger::now(),
+            size: metadata.size,
+            digest: metadata.digest,
+            version: SCUT_VERSION.to_string(),
+        };
+        object_store::record_upload(self, &entry)
+    }
+
+    /// The name of the current archive of `save`, if there is one
+    fn current(&mut self, save: &Save) -> anyhow::Result<Option<String>> {
+        Ok(object_store::saves(self)?
+            .unwrap_or_default()
+            .remove(save)
+            .map(|object| object.name))
+    }
+}
+
+/// Requests are handled one at a time, so records can be read and written back whole
+impl ObjectStore for GameFolder {
+    fn list(&mut self, folder: &str) -> anyhow::Result<Option<Vec<Object>>> {
+        let folder = self.location.join(folder);
+        if !folder.is_dir() {
+            return Ok(None);
+        }
+
+        let mut objects = Vec::new();
+        for entry in fs::read_dir(&folder)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_file() {
+                continue;
+            }
+            objects.push(Object {
+                name: entry.file_name().to_string_lossy().into_owned(),
+                size: entry.metadata()?.len(),
+            });
+        }
+        Ok(Some(objects))
+    }
+
+    fn get(&mut self, name: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        let path = self.location.join(name);
+        match fs::read(&path) {
+            Ok(content) => Ok(Some(content)),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(error) => {
+                Err(error).with_context(|| format!("failed to read '{}'", path.display()))
+            }
+        }
+    }
+
+    fn put(&mut self, name: &str, content: Vec<u8>) -> anyhow::Result<()> {
+        let path = self.location.join(name);
+        create_parent(&path)?;
+        fs::write(&path, content).with_context(|| format!("failed to write '{}'", path.display()))
+    }
+
+    fn rename(&mut self, from: &str, to: &str) -> anyhow::Result<()> {
+        let (from, to) = (self.location.join(from), self.location.join(to));
+        create_parent(&to)?;
+        fs::rename(&from, &to)
+            .with_context(|| format!("failed to move '{}' to '{}'", from.display(), to.display()))
+    }
+}
+
+fn create_parent(path: &Path) -> anyhow::Result<()> {
+    if let Some(folder) = path.parent() {
+        fs::create_dir_all(folder)
+            .with_context(|| format!("failed to create '{}'", folder.display()))?;
+    }
+    Ok(())
+}
+
+/// Check that `player` may upload `save`, explaining why not if they may not
+///
+/// Saves must be signed by a player on the roster for their side, and uploaded by someone on that side,
+/// e.g. a substitute covering for a teammate. A side with nobody listed is treated as unknown, and anyone may upload for it.
+/// Turn starts aren't signed, they must be uploaded by someone on the side that just finished its turn.
+pub fn check_upload(roster: &Roster, save: &Save, player: &str) -> anyhow::Result<()> {
+    let on_side = |side: Side, name: &str| {
+        roster
+            .players(side)
+            .iter()
+            .any(|listed| roster.aliases.same_player(listed, name))
+    };
+
+    let Some(signed) = &save.player else {
+        let ended = save.turn.previous().side;
+        anyhow::ensure!(
+            roster.players(ended).is_empty() || on_side(ended, player),
+            "{player} can't upload {save}, they aren't on the {ended} roster, who ended the turn"
+        );
+        return Ok(());
+    };
+    let side = save.turn.side;
+    if roster.players(side).is_empty() {
+        return Ok(());
+    }
+
+    anyhow::ensure!(
+        on_side(side, signed),
+        "{save} is signed by {signed}, who isn't on the {side} roster"
+    );
+    anyhow::ensure!(
+        on_side(side, player),
+        "{player} can't upload {save}, they aren't on the {side} roster"
+    );
+    Ok(())
+}
+
+/// Check that a record naming `named` as who made it was sent by `player`, so that nobody can make records in someone else's name
+pub fn check_record(roster: &Roster, named: &str, player: &str) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        roster.aliases.same_player(named, player),
+        "{player} can't make a record in {named}'s name"
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use scut_core::Side;
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn roster() -> Roster {
+        Roster {
+            axis: vec!["DM".to_string(), "DG".to_string()],
+            allies: vec!["GM".to_string(), "TG".to_string()],
+            ..Roster::default()
+        }
+    }
+
+    #[test]
+    fn uploads_must_match_the_roster() {
+        let roster = roster();
+        let save = Save::from_parts(Side::Axis, 12).player("DM");
+
+        assert!(check_upload(&roster, &save, "DM").is_ok());
+        // a teammate substituting
+        assert!(check_upload(&roster, &save, "DG").is_ok());
+        // the other side
+        assert!(check_upload(&roster, &save, "GM").is_err());
+        // nobody on the roster
+        let save = Save::from_parts(Side::Axis, 12).player("XX");
+        assert!(check_upload(&roster, &save, "DM").is_err());
+        // turn starts, handed over by the side that ended the turn
+        let save = Save::from_parts(Side::Axis, 13);
+        assert!(check_upload(&roster, &save, "GM").is_ok());
+        assert!(check_upload(&roster, &save, "DM").is_err());
+        let save = Save::from_parts(Side::Allies, 12);
+        assert!(check_upload(&roster, &save, "DG").is_ok());
+        assert!(check_upload(&roster, &save, "TG").is_err());
+        // an unknown side
+        let save = Save::from_parts(Side::Axis, 12).player("XX");
+        assert!(check_upload(&Roster::default(), &save, "XX").is_ok());
+    }
+
+    #[test]
+    fn uploads_are_renamed_into_place() -> anyhow::Result<()> {
+        let data = tempfile::tempdir()?;
+        let mut game = GameFolder::open(data.path(), "ww1")?;
+        let save = Save::from_parts(Side::Allies, 5).player("GM");
+
+        game.upload(&save, "Allies GM 5.7z", b"first")?;
+        game.upload(&save, "Allies GM 5.7z", b"second")?;
+
+        // nothing is left beside the save but the copy it replaced
+        let folder = data.path().join("ww1");
+        let mut files = fs::read_dir(&folder)?
+            .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        files.sort();
+        assert_eq!(files, vec!["Allies GM 5.7z", "versions"]);
+        assert_eq!(fs::read(folder.join("Allies GM 5.7z"))?, b"second");
+        assert_eq!(game.saves()?, vec![(6, "Allies GM 5.7z".to_string())]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn uploads_are_recorded_as_they_are_stored() -> anyhow::Result<()> {
+        let data = tempfile::tempdir()?;
+        let mut game = GameFolder::open(data.path(), "ww1")?;
+        let save = Save::from_parts(Side::Allies, 5).player("GM");
+
+        assert!(game.record_upload(&save, "GM").is_err());
+
+        game.upload(&save, "Allies GM 5.7z", b"archive")?;
+        game.record_upload(&save, "TG")?;
+
+        let ledger = game.ledger()?;
+        assert_eq!(ledger.len(), 1);
+        assert_eq!(ledger[0].uploader, "TG");
+        assert_eq!(ledger[0].size, 7);
+        assert_eq!(ledger[0].digest, FileMetadata::new(b"archive", None).digest);
+
+        Ok(())
+    }
+}
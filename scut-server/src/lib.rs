@@ -0,0 +1,5 @@
+//! The games that `scut-server` serves, and the REST API it serves them with, see [`api`].
+
+pub mod api;
+pub mod config;
+pub mod game;
@@ -0,0 +1,63 @@
+//! # SCUT server
+//!
+//! Serves games to scut over HTTP, so that players don't need a sync client, see
+//! [`HttpRemote`](scut_core::interface::storage::http_remote::HttpRemote).
+//!
+//! ```plaintext
+//! scut-server serve --config scut-server.toml
+//! scut-server token
+//! ```
+
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand, ValueHint};
+use tracing_subscriber::{filter::LevelFilter, EnvFilter};
+
+use scut_server::{api, config::ServerConfig};
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Serve the games listed in the config until stopped
+    Serve {
+        /// Load config from PATH
+        #[arg(short, long, value_name = "PATH", value_hint=ValueHint::FilePath, default_value = "scut-server.toml")]
+        config: PathBuf,
+    },
+
+    /// Print a new token, to give to a player and add to their game's tokens
+    Token,
+}
+
+fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::builder()
+                .with_default_directive(LevelFilter::INFO.into())
+                .from_env_lossy(),
+        )
+        .init();
+
+    match Cli::parse().command {
+        Command::Serve { config } => api::serve(&ServerConfig::load(&config)?),
+        Command::Token => {
+            let mut token = [0; 16];
+            getrandom::getrandom(&mut token)
+                .map_err(|error| anyhow::anyhow!("failed to generate a token: {error}"))?;
+            println!(
+                "{}",
+                token
+                    .iter()
+                    .map(|byte| format!("{byte:02x}"))
+                    .collect::<String>()
+            );
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,165 @@
+//! Drives scut's [`HttpRemote`] against the server's API, served on a random port
+
+use std::{collections::BTreeMap, fs, sync::Arc, thread};
+
+use tempfile::TempDir;
+use tiny_http::Server;
+
+use scut_core::{
+    interface::{
+        compression::copy_compression::CopyCompression, index::Query,
+        storage::http_remote::HttpRemote, RemoteStorage,
+    },
+    ledger::{find_uploads, record_uploads},
+    Roster, Save, Side,
+};
+use scut_server::{
+    api,
+    config::{Game, ServerConfig},
+};
+
+use pretty_assertions::assert_eq;
+
+/// A server for the game `ww1`, with DM playing Axis and GM playing Allies
+struct TestServer {
+    /// The url of the game
+    url: String,
+    server: Arc<Server>,
+    _data: TempDir,
+}
+
+impl TestServer {
+    fn start() -> anyhow::Result<Self> {
+        let data = tempfile::tempdir()?;
+        let game = Game {
+            roster: Roster {
+                axis: vec!["DM".to_string()],
+                allies: vec!["GM".to_string()],
+                ..Roster::default()
+            },
+            tokens: BTreeMap::from([
+                ("DM".to_string(), "dm-token".to_string()),
+                ("GM".to_string(), "gm-token".to_string()),
+            ]),
+        };
+        let config = ServerConfig {
+            address: "127.0.0.1:0".to_string(),
+            data: data.path().to_path_buf(),
+            games: BTreeMap::from([("ww1".to_string(), game)]),
+        };
+
+        let server =
+            Arc::new(Server::http(&config.address).map_err(|error| anyhow::anyhow!(error))?);
+        let port = server
+            .server_addr()
+            .to_ip()
+            .expect("the server listens on an IP address")
+            .port();
+
+        let serving = Arc::clone(&server);
+        thread::spawn(move || api::answer(&serving, &config));
+
+        Ok(TestServer {
+            url: format!("http://127.0.0.1:{port}/games/ww1/"),
+            server,
+            _data: data,
+        })
+    }
+
+    fn connect(&self, token: &str) -> anyhow::Result<HttpRemote> {
+        HttpRemote::new(&self.url, Some(token), Box::new(CopyCompression))
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.server.unblock();
+    }
+}
+
+#[test]
+fn http_upload_and_download() -> anyhow::Result<()> {
+    let server = TestServer::start()?;
+    let local = tempfile::tempdir()?;
+    let teammate = tempfile::tempdir()?;
+
+    let save = Save::from_parts(Side::Axis, 12).player("DM");
+    let path = local.path().join("Axis DM 12.sav");
+    fs::write(&path, "DM's turn")?;
+
+    let mut remote = server.connect("dm-token")?;
+    remote.upload(&save, &path)?;
+
+    // re-uploading keeps the previous copy as a version
+    fs::write(&path, "DM's turn, again")?;
+    remote.upload(&save, &path)?;
+    assert_eq!(remote.versions(&save)?.len(), 1);
+
+    let mut other = server.connect("gm-token")?;
+    assert_eq!(other.index().search(&Query::new())?, vec![save.clone()]);
+    other.download(&save, teammate.path())?;
+    assert_eq!(
+        fs::read_to_string(teammate.path().join("Axis DM 12.sav"))?,
+        "DM's turn, again"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn http_uploads_are_recorded_by_the_server() -> anyhow::Result<()> {
+    let server = TestServer::start()?;
+    let local = tempfile::tempdir()?;
+
+    let save = Save::from_parts(Side::Axis, 12).player("DM");
+    let path = local.path().join("Axis DM 12.sav");
+    fs::write(&path, "DM's turn")?;
+
+    let mut remote = server.connect("dm-token")?;
+    remote.upload(&save, &path)?;
+
+    // recording the upload as GM leaves nothing to record, the server already did it in DM's name
+    let uploads = find_uploads(&mut remote, &[save.clone()])?;
+    record_uploads(&mut remote, &uploads, "GM")?;
+
+    let ledger = server.connect("gm-token")?.ledger()?;
+    assert_eq!(ledger.len(), 1);
+    assert_eq!(ledger[0].save, save);
+    assert_eq!(ledger[0].uploader, "DM");
+    assert_eq!(ledger[0].size, uploads[0].metadata.size);
+    assert_eq!(ledger[0].digest, uploads[0].metadata.digest);
+
+    Ok(())
+}
+
+#[test]
+fn http_refused_requests_explain_why() -> anyhow::Result<()> {
+    let server = TestServer::start()?;
+    let local = tempfile::tempdir()?;
+
+    assert!(server.connect("wrong token").is_err());
+
+    let mut dm = server.connect("dm-token")?;
+
+    // saves must be signed by a player on the roster
+    let unknown = Save::from_parts(Side::Axis, 12).player("XX");
+    let path = local.path().join("Axis XX 12.sav");
+    fs::write(&path, "XX's turn")?;
+    let error = dm.upload(&unknown, &path).unwrap_err();
+    assert!(format!("{error:#}").contains("XX, who isn't on the Axis roster"));
+
+    // and only their side can supersede them
+    let save = Save::from_parts(Side::Axis, 12).player("DM");
+    let path = local.path().join("Axis DM 12.sav");
+    fs::write(&path, "DM's turn")?;
+    dm.upload(&save, &path)?;
+
+    let mut gm = server.connect("gm-token")?;
+    assert!(gm.supersede(&save).is_err());
+    assert_eq!(
+        server.connect("gm-token")?.index().search(&Query::new())?,
+        vec![save]
+    );
+
+    Ok(())
+}
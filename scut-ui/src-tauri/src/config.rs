@@ -4,6 +4,7 @@ use scut_core::{
         config::{toml_file::TomlFileConfig, ConfigService},
         file_system::local_file_system::LocalFileSystem,
     },
+    signature::{load_or_create_signing_key, SigningKey, SIGNING_KEY_FILE},
     Config,
 };
 use std::path::PathBuf;
@@ -31,3 +32,12 @@ pub(crate) fn ready_config(
     let mut config_service = TomlFileConfig::new(config_location, file_system, user_interaction);
     Ok((get_config(&mut config_service)?, Box::new(config_service)))
 }
+
+/// Load your signing key from beside the config, creating a new one if you don't have one yet
+pub(crate) fn ready_signing_key() -> anyhow::Result<SigningKey> {
+    load_or_create_signing_key(
+        &TomlFileConfig::default_location()?.with_file_name(SIGNING_KEY_FILE),
+        &mut LocalFileSystem::new(),
+    )
+    .context("failed to load your signing key")
+}
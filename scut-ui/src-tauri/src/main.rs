@@ -39,9 +39,15 @@ fn upload(autosave: Option<String>, items: Vec<String>) -> Result<String, String
         .collect::<Result<Vec<_>, _>>()
         .map_err(|e| e.to_string())?;
 
-    scut.upload(autosave, saves).map_err(|e| e.to_string())?;
-
-    Ok(format!("Uploaded {}", items.join(", ")))
+    let lagging = scut.upload(autosave, saves).map_err(|e| e.to_string())?;
+
+    let mut message = format!("Uploaded {}", items.join(", "));
+    for mirror in lagging {
+        message.push_str(&format!(
+            "\n⚠️ {mirror} missed the upload, use `scut mirrors --resync` to catch it up"
+        ));
+    }
+    Ok(message)
 }
 
 #[tauri::command(async)]
@@ -54,9 +60,18 @@ fn download(items: Vec<String>) -> Result<String, String> {
         .collect::<Result<Vec<_>, _>>()
         .map_err(|e| e.to_string())?;
 
-    scut.download(saves).map_err(|e| e.to_string())?;
+    let problems = scut.download(saves.clone()).map_err(|e| e.to_string())?;
 
-    Ok(format!("Downloaded {}", items.join(", ")))
+    let downloaded = saves
+        .iter()
+        .filter(|save| problems.iter().all(|problem| problem.save() != *save))
+        .map(|save| save.to_string())
+        .collect::<Vec<_>>();
+    let mut message = format!("Downloaded {}", downloaded.join(", "));
+    for problem in problems {
+        message.push_str(&format!("\n⚠️ {problem}, so it wasn't downloaded"));
+    }
+    Ok(message)
 }
 
 #[tauri::command(async)]
@@ -1,27 +1,46 @@
 use anyhow::{bail, Context};
 use scut_core::{
     interface::{
-        config::ConfigService, predict::simple_predict::SimplePredict, LocalStorage, Predict,
-        Prediction, RemoteStorage,
+        config::ConfigService,
+        predict::{observer_predict::ObserverPredict, simple_predict::SimplePredict},
+        LocalStorage, Predict, Prediction, RemoteStorage,
     },
+    ledger::{find_uploads, record_uploads},
+    record_synced_saves,
+    signature::{sign_uploads, verify_signatures, SignatureProblem, SigningKey},
+    substitute::record_substitute_uploads,
     Config, Save,
 };
 
-use crate::{config::ready_config, storage::ready_storage};
+use crate::{
+    config::{ready_config, ready_signing_key},
+    storage::ready_storage,
+};
 
 pub struct ScutRunner {
     pub local: Box<dyn LocalStorage>,
     pub remote: Box<dyn RemoteStorage>,
     pub config: Config,
     pub config_service: Box<dyn ConfigService>,
-    pub predictor: SimplePredict,
+    pub predictor: Box<dyn Predict>,
+    pub signing_key: SigningKey,
 }
 
 impl ScutRunner {
     pub fn new() -> anyhow::Result<ScutRunner> {
         let (config, config_service) = ready_config(None)?;
         let (local, remote, config) = ready_storage(config)?;
-        let predictor = SimplePredict::default();
+        let signing_key = ready_signing_key()?;
+        let predictor: Box<dyn Predict> = if config.observer.unwrap_or_default() {
+            Box::new(ObserverPredict::new(
+                config.observe_player_saves.unwrap_or_default(),
+            ))
+        } else {
+            Box::new(
+                SimplePredict::new(config.roster.aliases.clone())
+                    .handoff(config.roster.handoff.clone()),
+            )
+        };
 
         Ok(ScutRunner {
             local,
@@ -29,6 +48,7 @@ impl ScutRunner {
             config,
             config_service,
             predictor,
+            signing_key,
         })
     }
 
@@ -38,7 +58,7 @@ impl ScutRunner {
 
         self.predictor.predict(
             self.config.side,
-            &self.config.player,
+            self.config.acting_player()?,
             None,
             self.config.solo.unwrap_or_default(),
             local,
@@ -46,15 +66,23 @@ impl ScutRunner {
         )
     }
 
-    pub fn upload(mut self, autosave: Option<Save>, uploads: Vec<Save>) -> anyhow::Result<()> {
+    /// Upload the saves, returning the names of any mirrors that missed them
+    pub fn upload(
+        mut self,
+        autosave: Option<Save>,
+        uploads: Vec<Save>,
+    ) -> anyhow::Result<Vec<String>> {
         let local = &mut *self.local;
         let remote = &mut *self.remote;
 
-        if let Some(save) = autosave {
+        let mut uploaded = uploads.clone();
+
+        if let Some(save) = autosave.as_ref() {
             let local_path = local
                 .locate_autosave()?
                 .expect("scut predicted need to upload autosave, so it must exist");
-            remote.upload(&save, local_path.as_path())?;
+            remote.upload(save, local_path.as_path())?;
+            uploaded.push(save.clone());
         }
 
         for save in uploads {
@@ -68,20 +96,41 @@ impl ScutRunner {
             remote.upload(&save, local_path.as_path())?;
         }
 
-        Ok(())
+        record_synced_saves(local, remote, &uploaded, &[], autosave.as_ref())?;
+
+        if let Some(substitute) = self.config.substitute() {
+            record_substitute_uploads(remote, &uploaded, substitute)?;
+        }
+
+        // uploads are signed and recorded in the ledger by whoever actually uploaded them, even when playing as someone else
+        let uploads = find_uploads(remote, &uploaded)?;
+        record_uploads(remote, &uploads, &self.config.player)?;
+        sign_uploads(remote, &uploads, &self.config.player, &self.signing_key)?;
+        remote.publish()?;
+
+        Ok(remote.lagging_mirrors())
     }
 
-    pub fn download(mut self, downloads: Vec<Save>) -> anyhow::Result<()> {
+    /// Download the saves whose signatures check out, returning the problems with the signatures of any that weren't downloaded
+    pub fn download(mut self, downloads: Vec<Save>) -> anyhow::Result<Vec<SignatureProblem>> {
         let local = &mut *self.local;
         let remote = &mut *self.remote;
 
+        let problems = verify_signatures(remote, &downloads, &self.config.roster)?;
+        let downloads = downloads
+            .into_iter()
+            .filter(|save| problems.iter().all(|problem| problem.save() != save))
+            .collect::<Vec<_>>();
+
         let local_path = local.location();
 
-        for save in downloads {
-            remote.download(&save, local_path)?;
+        for save in downloads.iter() {
+            remote.download(save, local_path)?;
         }
 
-        Ok(())
+        record_synced_saves(local, remote, &[], &downloads, None)?;
+
+        Ok(problems)
     }
 
     pub fn config(mut self) -> anyhow::Result<()> {
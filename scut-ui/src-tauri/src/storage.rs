@@ -3,9 +3,8 @@ use anyhow::Context;
 use scut_core::{
     error::ErrorSuggestions,
     interface::{
-        compression::SevenZipCompression,
         file_system::local_file_system::LocalFileSystem,
-        storage::{dropbox_folder::DropboxFolder, game_saves_folder::GameSavesFolder},
+        storage::{connect, game_saves_folder::GameSavesFolder},
         LocalStorage, RemoteStorage,
     },
     Config,
@@ -14,20 +13,8 @@ use scut_core::{
 pub type ReadiedStorage = (Box<dyn LocalStorage>, Box<dyn RemoteStorage>, Config);
 
 pub(crate) fn ready_storage(config: Config) -> anyhow::Result<ReadiedStorage> {
-    let compression = SevenZipCompression::new(&config.seven_zip_path);
-
-    let remote_storage = DropboxFolder::new(
-        config.dropbox.clone(),
-        Box::new(LocalFileSystem::new()),
-        Box::new(compression),
-    )
-    .with_context(|| {
-        format!(
-            "failed to load dropbox folder with path '{}'",
-            config.dropbox.display()
-        )
-    })
-    .suggest("Select `Config` from the tray menu to review and update your config")?;
+    let remote_storage = connect::remote_storage(&config)
+        .suggest("Select `Config` from the tray menu to review and update your config")?;
 
     let local_storage =
         GameSavesFolder::new(config.saves.clone(), Box::new(LocalFileSystem::new()))
@@ -39,5 +26,5 @@ pub(crate) fn ready_storage(config: Config) -> anyhow::Result<ReadiedStorage> {
             })
             .suggest("Select `Config` from the tray menu to review and update your config")?;
 
-    Ok((Box::new(local_storage), Box::new(remote_storage), config))
+    Ok((Box::new(local_storage), remote_storage, config))
 }